@@ -1,11 +1,19 @@
+use aim_data::TimeFrame;
+
 use crate::{
-    CandleData, Chart, CompanyInfo, UiData,
+    CandleData, Chart, CompanyInfo, Indicator, OrderFlow, UiData,
     chart::{BackupChartOffset, ChartData},
     draw::DrawObject,
+    history::EditHistory,
+    layer::Layers,
+    layout::{read_str, read_u32, write_str},
+    pane::PaneLayout,
+    template::{read_indicators, write_indicators},
 };
 
 impl Chart {
-    // Manual serialization: write Chart to bytes
+    // Manual serialization: write Chart to bytes, the layout of cache
+    // version 1 that the tagged format replaced
     pub fn write_to_bytes(&self, buf: &mut Vec<u8>) {
         // stock_name: String
         let name_bytes = self.stock_name.as_bytes();
@@ -242,26 +250,30 @@ impl Chart {
             Self {
                 stock_name,
                 company_info,
+                // Stored by the cache file in front of the chart
+                time_frame: TimeFrame::default(),
                 candle_data,
                 chart_data,
                 current_draw_data,
                 moving_object,
                 all_draw_data,
+                // This format predates indicator settings
+                indicators: Indicator::defaults(),
+                panes: PaneLayout::default(),
                 backup_points,
                 backup_chart_offset,
                 is_in_object,
+                dragging_vertex: None,
+                selected: None,
+                history: EditHistory::default(),
+                layers: Layers::default(),
+                // Filled from the tape again, see [`Chart::push_trades`]
+                order_flow: OrderFlow::default(),
                 delta,
             },
             pos,
         ))
     }
-
-    // Helper for hashing: serialize to bytes
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        self.write_to_bytes(&mut buf);
-        buf
-    }
 }
 
 // Manual serialization for ChartData
@@ -322,7 +334,6 @@ impl ChartData {
         pos += 4;
         let width_ui = i32::from_le_bytes(data[pos..pos + 4].try_into().ok()?);
         pos += 4;
-        // Color and type can be added if needed
         let ui_data = UiData {
             position_x,
             position_y,
@@ -348,6 +359,7 @@ impl ChartData {
                 range_y,
                 width,
                 height,
+                image_height: height,
                 candle_distance,
                 zoom_x,
                 zoom_y,
@@ -381,6 +393,487 @@ impl ChartData {
         buf.push(self.ui_data.is_release as u8);
         buf.extend_from_slice(&self.ui_data.height.to_le_bytes());
         buf.extend_from_slice(&self.ui_data.width.to_le_bytes());
-        // Color and type can be added if needed
+    }
+}
+
+/// Magic bytes in front of a tagged cache file, older files start with their version
+pub const CACHE_MAGIC: [u8; 4] = *b"AIMC";
+/// Version of the tagged cache format
+pub const CACHE_VERSION: u32 = 2;
+/// Record holding one chart, see [`Chart::to_tagged_bytes`]
+pub const RECORD_CHART: u8 = 1;
+/// Record holding the chart layout
+pub const RECORD_LAYOUT: u8 = 2;
+/// Record holding one chart template, see [`crate::ChartTemplate::write_to_bytes`]
+pub const RECORD_TEMPLATE: u8 = 3;
+
+// Fields of a chart record
+const FIELD_NAME: u16 = 1;
+const FIELD_TIME_FRAME: u16 = 2;
+const FIELD_COMPANY: u16 = 3;
+const FIELD_CANDLES: u16 = 4;
+const FIELD_VIEW: u16 = 5;
+const FIELD_DRAWING: u16 = 6;
+const FIELD_LAYERS: u16 = 7;
+const FIELD_INDICATORS: u16 = 8;
+const FIELD_PANES: u16 = 9;
+
+// Fields of the view inside a chart record
+const VIEW_OFFSETS: u16 = 1;
+const VIEW_SIZE: u16 = 2;
+const VIEW_ZOOM: u16 = 3;
+
+/// Size of a record header: tag, payload length and checksum
+const RECORD_HEADER_LEN: usize = 1 + 4 + 8;
+
+/// One record of a tagged cache file
+#[derive(Debug, PartialEq)]
+pub enum CacheRecord<'a> {
+    Valid {
+        tag: u8,
+        payload: &'a [u8],
+    },
+    /// The payload does not match its checksum
+    Corrupt {
+        tag: u8,
+    },
+}
+
+/// FNV-1a hash of `bytes`, the checksum of cache records
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Append a record: tag, payload length, payload checksum and the payload
+pub fn write_record(buf: &mut Vec<u8>, tag: u8, payload: &[u8]) {
+    buf.push(tag);
+    buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buf.extend_from_slice(&fnv1a(payload).to_le_bytes());
+    buf.extend_from_slice(payload);
+}
+
+/// Read the record at the start of `data`, with the bytes it takes
+///
+/// A record failing its checksum can still be stepped over. `None` when the
+/// record runs past the end of `data`, the records after it are lost then.
+pub fn read_record(data: &[u8]) -> Option<(CacheRecord<'_>, usize)> {
+    let tag = *data.first()?;
+    let len = u32::from_le_bytes(data.get(1..5)?.try_into().ok()?) as usize;
+    let checksum = u64::from_le_bytes(data.get(5..RECORD_HEADER_LEN)?.try_into().ok()?);
+    let payload = data.get(RECORD_HEADER_LEN..RECORD_HEADER_LEN.checked_add(len)?)?;
+    let record = if fnv1a(payload) == checksum {
+        CacheRecord::Valid { tag, payload }
+    } else {
+        CacheRecord::Corrupt { tag }
+    };
+    Some((record, RECORD_HEADER_LEN + len))
+}
+
+fn write_field(buf: &mut Vec<u8>, tag: u16, payload: &[u8]) {
+    buf.extend_from_slice(&tag.to_le_bytes());
+    buf.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    buf.extend_from_slice(payload);
+}
+
+/// Split `data` into its tagged fields, `None` if one runs past the end
+fn read_fields(data: &[u8]) -> Option<Vec<(u16, &[u8])>> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let tag = u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?);
+        let len = u32::from_le_bytes(data.get(pos + 2..pos + 6)?.try_into().ok()?) as usize;
+        let end = (pos + 6).checked_add(len)?;
+        fields.push((tag, data.get(pos + 6..end)?));
+        pos = end;
+    }
+    Some(fields)
+}
+
+/// Exactly `N` little-endian 4-byte words
+fn read_words<const N: usize>(data: &[u8]) -> Option<[[u8; 4]; N]> {
+    if data.len() != N * 4 {
+        return None;
+    }
+    let mut words = [[0; 4]; N];
+    for (word, bytes) in words.iter_mut().zip(data.chunks_exact(4)) {
+        *word = bytes.try_into().ok()?;
+    }
+    Some(words)
+}
+
+impl Chart {
+    /// Self-describing form of the chart, the payload of a [`RECORD_CHART`]
+    ///
+    /// Each part is a tagged, length-prefixed field: readers skip the fields
+    /// they do not know and use defaults for missing ones. Every drawing has
+    /// its own field carrying its time anchors and layer, so a drawing of an unknown
+    /// kind is dropped alone. Mouse state and the drawing in progress are
+    /// left out, the UI sends them again with every frame.
+    pub fn to_tagged_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_field(&mut buf, FIELD_NAME, self.stock_name.as_bytes());
+        write_field(
+            &mut buf,
+            FIELD_TIME_FRAME,
+            self.time_frame.to_string().as_bytes(),
+        );
+
+        let info = &self.company_info;
+        let company: Vec<u8> = [info.roe, info.roa, info.pe, info.pb, info.eps]
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        write_field(&mut buf, FIELD_COMPANY, &company);
+
+        let mut candles = Vec::new();
+        for candle in &self.candle_data {
+            candle.write_to_bytes(&mut candles);
+        }
+        write_field(&mut buf, FIELD_CANDLES, &candles);
+
+        write_field(&mut buf, FIELD_VIEW, &self.chart_data.to_tagged_bytes());
+
+        for (object, times) in self.all_draw_data.iter().zip(self.drawing_anchors()) {
+            let mut drawing = Vec::new();
+            object.write_to_bytes(&mut drawing);
+            drawing.extend_from_slice(&(times.len() as u32).to_le_bytes());
+            for time in times {
+                drawing.extend_from_slice(&time.to_le_bytes());
+            }
+            write_str(&mut drawing, &object.layer);
+            write_field(&mut buf, FIELD_DRAWING, &drawing);
+        }
+
+        let mut layers = Vec::new();
+        self.layers.write_to_bytes(&mut layers);
+        write_field(&mut buf, FIELD_LAYERS, &layers);
+
+        let mut indicators = Vec::new();
+        write_indicators(&mut indicators, &self.indicators);
+        write_field(&mut buf, FIELD_INDICATORS, &indicators);
+
+        let mut panes = Vec::new();
+        self.panes.write_to_bytes(&mut panes);
+        write_field(&mut buf, FIELD_PANES, &panes);
+        buf
+    }
+
+    /// Read a chart written by [`Chart::to_tagged_bytes`]
+    ///
+    /// `None` when the name, time frame or candles are missing or unreadable.
+    pub fn from_tagged_bytes(data: &[u8]) -> Option<Self> {
+        let mut stock_name = None;
+        let mut time_frame = None;
+        let mut company_info = CompanyInfo::default();
+        let mut candle_data = None;
+        let mut chart_data = None;
+        let mut all_draw_data = Vec::new();
+        let mut anchors = Vec::new();
+        let mut layers = Layers::default();
+        let mut indicators = None;
+        let mut panes = None;
+        for (tag, payload) in read_fields(data)? {
+            match tag {
+                FIELD_NAME => stock_name = Some(String::from_utf8(payload.to_vec()).ok()?),
+                FIELD_TIME_FRAME => {
+                    time_frame = TimeFrame::parse(std::str::from_utf8(payload).ok()?)
+                }
+                FIELD_COMPANY if payload.len() == 8 * 5 => {
+                    let value = |i: usize| {
+                        f64::from_le_bytes(payload[i * 8..i * 8 + 8].try_into().unwrap())
+                    };
+                    company_info = CompanyInfo {
+                        roe: value(0),
+                        roa: value(1),
+                        pe: value(2),
+                        pb: value(3),
+                        eps: value(4),
+                    };
+                }
+                FIELD_CANDLES => {
+                    let mut candles = Vec::new();
+                    let mut pos = 0;
+                    while pos < payload.len() {
+                        let (candle, used) = CandleData::read_from_bytes(&payload[pos..])?;
+                        candles.push(candle);
+                        pos += used;
+                    }
+                    candle_data = Some(candles);
+                }
+                FIELD_VIEW => chart_data = ChartData::from_tagged_bytes(payload),
+                FIELD_DRAWING => {
+                    let Some((mut object, mut pos)) = DrawObject::read_from_bytes(payload) else {
+                        log::warn!("Skipping a drawing of an unknown kind");
+                        continue;
+                    };
+                    let count = read_u32(payload, &mut pos).unwrap_or_default() as usize;
+                    let times: Vec<f64> = payload[pos..]
+                        .chunks_exact(8)
+                        .take(count)
+                        .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+                        .collect();
+                    pos += times.len() * 8;
+                    // Drawings saved before layers existed stay on the default one
+                    if let Some(layer) = read_str(payload, &mut pos) {
+                        object.layer = layer;
+                    }
+                    all_draw_data.push(object);
+                    anchors.push(times);
+                }
+                FIELD_LAYERS => match Layers::read_from_bytes(payload) {
+                    Some((read, _)) => layers = read,
+                    None => log::warn!("Skipping unreadable drawing layers"),
+                },
+                FIELD_INDICATORS => match read_indicators(payload, &mut 0) {
+                    Some(read) => indicators = Some(read),
+                    None => log::warn!("Skipping unreadable indicator settings"),
+                },
+                FIELD_PANES => match PaneLayout::read_from_bytes(payload) {
+                    Some(read) => panes = Some(read),
+                    None => log::warn!("Skipping unreadable panes"),
+                },
+                _ => {}
+            }
+        }
+
+        let candle_data = candle_data?;
+        let mut chart = Self {
+            stock_name: stock_name?,
+            company_info,
+            time_frame: time_frame?,
+            chart_data: chart_data.unwrap_or_else(|| Self::default_view(&candle_data)),
+            candle_data,
+            current_draw_data: DrawObject::default(),
+            moving_object: DrawObject::default(),
+            all_draw_data,
+            // Files written before indicators were saved get the default moving averages
+            indicators: indicators.unwrap_or_else(Indicator::defaults),
+            panes: panes.unwrap_or_default(),
+            backup_points: Vec::new(),
+            backup_chart_offset: None,
+            is_in_object: (0, false, false),
+            dragging_vertex: None,
+            selected: None,
+            history: EditHistory::default(),
+            layers,
+            order_flow: OrderFlow::default(),
+            delta: (0.0, 0.0),
+        };
+        chart.place_drawings(&anchors);
+        chart.sync_indicator_panes();
+        Some(chart)
+    }
+}
+
+impl ChartData {
+    /// Tagged form of the view: offsets, sizes and zoom
+    pub fn to_tagged_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let offsets = [
+            self.y_offset_min,
+            self.y_offset_max,
+            self.x_offset_min,
+            self.x_offset_max,
+        ];
+        let offsets: Vec<u8> = offsets.iter().flat_map(|v| v.to_le_bytes()).collect();
+        write_field(&mut buf, VIEW_OFFSETS, &offsets);
+
+        let mut size = Vec::new();
+        size.extend_from_slice(&self.range_x.to_le_bytes());
+        size.extend_from_slice(&self.range_y.to_le_bytes());
+        size.extend_from_slice(&self.width.to_le_bytes());
+        size.extend_from_slice(&self.height.to_le_bytes());
+        size.extend_from_slice(&self.image_height.to_le_bytes());
+        write_field(&mut buf, VIEW_SIZE, &size);
+
+        let zoom = [self.candle_distance, self.zoom_x, self.zoom_y];
+        let zoom: Vec<u8> = zoom.iter().flat_map(|v| v.to_le_bytes()).collect();
+        write_field(&mut buf, VIEW_ZOOM, &zoom);
+        buf
+    }
+
+    /// Read a view written by [`ChartData::to_tagged_bytes`]
+    pub fn from_tagged_bytes(data: &[u8]) -> Option<Self> {
+        let mut view = Self::default();
+        for (tag, payload) in read_fields(data)? {
+            match tag {
+                VIEW_OFFSETS => {
+                    let [y_min, y_max, x_min, x_max] =
+                        read_words::<4>(payload)?.map(f32::from_le_bytes);
+                    view.y_offset_min = y_min;
+                    view.y_offset_max = y_max;
+                    view.x_offset_min = x_min;
+                    view.x_offset_max = x_max;
+                }
+                VIEW_SIZE => {
+                    let [range_x, range_y, width, height, image_height] = read_words::<5>(payload)?;
+                    view.range_x = i32::from_le_bytes(range_x);
+                    view.range_y = i32::from_le_bytes(range_y);
+                    view.width = u32::from_le_bytes(width);
+                    view.height = u32::from_le_bytes(height);
+                    view.image_height = u32::from_le_bytes(image_height);
+                }
+                VIEW_ZOOM => {
+                    let [candle_distance, zoom_x, zoom_y] =
+                        read_words::<3>(payload)?.map(f32::from_le_bytes);
+                    view.candle_distance = candle_distance;
+                    view.zoom_x = zoom_x;
+                    view.zoom_y = zoom_y;
+                }
+                _ => {}
+            }
+        }
+        Some(view)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use aim_data::OHLCData;
+    use plotters::style::RGBColor;
+
+    use super::*;
+    use crate::{ChartTemplate, DrawingStyle, IndicatorKind, LayerAction, draw::DrawType};
+
+    /// Daily bars at 09:00 local time on the weekdays of `days` calendar days
+    /// from Monday 2024-01-01
+    fn daily_bars(days: std::ops::Range<i64>) -> OHLCData {
+        let t: Vec<String> = days
+            .filter(|day| day % 7 < 5)
+            .map(|day| (1_704_074_400 + day * 86_400).to_string())
+            .collect();
+        let prices = vec![Some(10.0); t.len()];
+        OHLCData {
+            symbol: "FPT".to_string(),
+            o: prices.clone(),
+            h: prices.clone(),
+            l: prices.clone(),
+            c: prices,
+            v: vec![Some(100); t.len()],
+            accumulated_volume: vec![],
+            accumulated_value: vec![],
+            min_batch_trunc_time: String::new(),
+            t,
+        }
+    }
+
+    #[test]
+    fn test_drawings_follow_their_bars() {
+        let mut chart = Chart::with_time_frame(
+            "FPT".to_string(),
+            TimeFrame::Day(1),
+            daily_bars(14..28),
+            CompanyInfo::default(),
+        );
+        let line = DrawType::Line(((2.0, 10.0), (4.5, 11.0)));
+        chart
+            .all_draw_data
+            .push(DrawObject::new(line, RGBColor(0, 0, 0)));
+        let bytes = chart.to_tagged_bytes();
+
+        // Two more weeks of history in front shift every bar by ten
+        let mut reloaded = chart.clone();
+        reloaded.update_candle_data(daily_bars(0..28));
+        let expected = DrawType::Line(((12.0, 10.0), (14.5, 11.0)));
+        assert_eq!(reloaded.all_draw_data[0].draw_type, expected);
+
+        // The cache keeps the anchors, so the drawings move with the bars too
+        let mut cached = Chart::from_tagged_bytes(&bytes).unwrap();
+        cached.update_candle_data(daily_bars(0..28));
+        assert_eq!(cached.all_draw_data[0].draw_type, expected);
+    }
+
+    #[test]
+    fn test_corrupt_chart_record_is_skipped() {
+        let mut chart = Chart::with_time_frame(
+            "FPT".to_string(),
+            TimeFrame::Hour(1),
+            daily_bars(0..14),
+            CompanyInfo::default(),
+        );
+        let line = DrawType::Line(((2.0, 10.0), (4.0, 11.0)));
+        chart
+            .all_draw_data
+            .push(DrawObject::new(line.clone(), RGBColor(0, 0, 0)));
+
+        let mut payload = chart.to_tagged_bytes();
+        // A field from a newer build is skipped
+        write_field(&mut payload, 99, b"later");
+        let mut buf = Vec::new();
+        write_record(&mut buf, RECORD_CHART, &payload);
+        let second = buf.len();
+        write_record(&mut buf, RECORD_CHART, &payload);
+        buf[RECORD_HEADER_LEN + 3] ^= 0xff;
+
+        let (record, used) = read_record(&buf).unwrap();
+        assert_eq!(record, CacheRecord::Corrupt { tag: RECORD_CHART });
+        assert_eq!(used, second);
+        let (record, used) = read_record(&buf[second..]).unwrap();
+        assert_eq!(second + used, buf.len());
+        let CacheRecord::Valid { tag, payload } = record else {
+            panic!("second record should be intact");
+        };
+        assert_eq!(tag, RECORD_CHART);
+        let read = Chart::from_tagged_bytes(payload).unwrap();
+        assert_eq!(read.stock_name, "FPT");
+        assert_eq!(read.time_frame, TimeFrame::Hour(1));
+        assert_eq!(read.candle_data.len(), chart.candle_data.len());
+        assert_eq!(read.chart_data.x_offset_max, chart.chart_data.x_offset_max);
+        assert_eq!(read.all_draw_data[0].draw_type, line);
+
+        // A record cut short cannot be stepped over
+        assert!(read_record(&buf[..buf.len() - 1][second..]).is_none());
+    }
+
+    #[test]
+    fn test_layers_and_templates_carry_over() {
+        let mut chart = Chart::with_time_frame(
+            "FPT".to_string(),
+            TimeFrame::Day(1),
+            daily_bars(0..14),
+            CompanyInfo::default(),
+        );
+        chart.layers.select("Hỗ trợ/kháng cự");
+        chart.layers.apply(LayerAction::ToggleLocked);
+        let mut line = DrawObject::new(DrawType::HorizontalLine(10.0), RGBColor(255, 0, 0));
+        line.layer = chart.layers.active().name.clone();
+        chart.all_draw_data.push(line.clone());
+        assert!(!chart.layers.is_editable(&line.layer));
+        assert!(chart.layers.is_visible(&line.layer));
+
+        let read = Chart::from_tagged_bytes(&chart.to_tagged_bytes()).unwrap();
+        assert_eq!(read.layers, chart.layers);
+        assert_eq!(read.all_draw_data, [line]);
+
+        chart.toggle_indicator(IndicatorKind::Rsi { period: 14 });
+        chart.panes.resize(0, 0.3);
+        let read = Chart::from_tagged_bytes(&chart.to_tagged_bytes()).unwrap();
+        assert_eq!(read.indicators, chart.indicators);
+        assert_eq!(read.panes, chart.panes);
+        let style = DrawingStyle {
+            color: RGBColor(0, 0, 255),
+            text_size: 18,
+            fib_levels: "0.5 1".to_string(),
+        };
+        let template = ChartTemplate::from_chart("Swing", &chart, style);
+        let mut buf = Vec::new();
+        template.write_to_bytes(&mut buf);
+        let (read_template, used) = ChartTemplate::read_from_bytes(&buf).unwrap();
+        assert_eq!(read_template, template);
+        assert_eq!(used, buf.len());
+
+        let mut other = Chart::with_time_frame(
+            "HPG".to_string(),
+            TimeFrame::Day(1),
+            daily_bars(0..14),
+            CompanyInfo::default(),
+        );
+        other.apply_template(&read_template);
+        assert_eq!(other.indicators, chart.indicators);
+        assert_eq!(other.panes.panes().len(), chart.panes.panes().len());
+        assert!(!other.layers.is_editable("Hỗ trợ/kháng cự"));
+        assert!(other.all_draw_data.is_empty());
     }
 }
@@ -1,13 +1,14 @@
-use aim_data::OHLCData;
-use chrono::{DateTime, Duration, Utc};
+use aim_data::calendar::{VN_UTC_OFFSET_SECS, to_vn_time};
+use aim_data::{AimDataError, DataSource, OHLCData, SourceResult, TimeFrame, Trade, resample};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use log::debug;
 
 use plotters::{
     backend::BitMapBackend,
     chart::ChartBuilder,
-    coord::types::RangedCoordf32,
-    drawing::IntoDrawingArea,
-    element::{CandleStick, PathElement, Polygon},
+    coord::{Shift, types::RangedCoordf32},
+    drawing::{DrawingArea, IntoDrawingArea},
+    element::{CandleStick, EmptyElement, PathElement, Polygon},
     prelude::{Cartesian2d, ChartContext, Rectangle, Text},
     series::LineSeries,
     style::{BLUE, Color, IntoFont, RED, RGBColor, ShapeStyle, WHITE, full_palette::GREY},
@@ -17,8 +18,11 @@ use slint::SharedPixelBuffer;
 const UP_COLOR: RGBColor = RGBColor(0x2E, 0xBD, 0x85);
 const DOWN_COLOR: RGBColor = RGBColor(0xF6, 0x46, 0x5D);
 const NO_CHANGE_COLOR: RGBColor = RGBColor(0xFF, 0xEB, 0x3B);
-const THREAD_HOLD: f32 = 2.0; // in pixel
 const DEFAULT_CANDLE_NUMER: usize = 300; // default number of candles to show
+const PROFILE_ROWS: usize = 48; // rows of the volume profile at most
+const PROFILE_WIDTH: f32 = 0.25; // share of the chart width the heaviest row takes
+const FOOTPRINT_ROW_PIXELS: f32 = 12.0; // footprint cells are at least this tall
+const FOOTPRINT_TEXT_PIXELS: f32 = 70.0; // candle distance from which cells show their volumes
 
 use crate::{MouseType, UiData};
 
@@ -26,7 +30,16 @@ use super::{
     CandleData, CandleDataVec,
     company_info::CompanyInfo,
     convert_candlesticks,
-    draw::{DrawObject, DrawType, Point},
+    depth::format_volume,
+    draw::{
+        DrawObject, DrawType, EXTENSION_LEVELS, Point, RETRACEMENT_LEVELS, THREAD_HOLD,
+        parse_levels,
+    },
+    footprint::{OrderFlow, VolumeProfile, row_size},
+    history::{EditCommand, EditHistory},
+    indicator::{Indicator, IndicatorKind, IndicatorLine},
+    layer::{LayerAction, Layers},
+    pane::{PaneContent, PaneLayout, PaneRect},
 };
 
 #[derive(Debug, Clone)]
@@ -46,7 +59,8 @@ pub struct ChartData {
     pub range_x: i32,         // range value of x axis
     pub range_y: i32,         // range value of y axis
     pub width: u32,           // the width of the chart in pixels
-    pub height: u32,          // the height of the chart in pixels
+    pub height: u32,          // the height of the price chart in pixels
+    pub image_height: u32,    // the height of the whole image, panes included
     pub candle_distance: f32, // distance between 2 candle sticks
     pub zoom_x: f32,          // zoom factor in x direction
     pub zoom_y: f32,          // zoom factor in y direction
@@ -64,6 +78,7 @@ impl Default for ChartData {
             range_y: 60,
             width: 1200,
             height: 800,
+            image_height: 800,
             candle_distance: 0.0,
             zoom_x: 0.0,
             zoom_y: 0.0,
@@ -76,14 +91,22 @@ impl Default for ChartData {
 pub struct Chart {
     pub stock_name: String,
     pub company_info: CompanyInfo,
+    pub time_frame: TimeFrame,      // bar size the candles are resampled to
     pub candle_data: CandleDataVec, // Candle sticks data
     pub chart_data: ChartData,
     pub current_draw_data: DrawObject,
     pub moving_object: DrawObject,
     pub all_draw_data: Vec<DrawObject>,
+    pub indicators: Vec<Indicator>, // indicators in drawing order, hidden ones included
+    pub panes: PaneLayout,          // volume and oscillator panes under the price chart
     pub backup_points: Vec<Point>,
     pub backup_chart_offset: Option<BackupChartOffset>,
     pub is_in_object: (usize, bool, bool),
+    pub dragging_vertex: Option<usize>, // handle of the moving object being dragged
+    pub selected: Option<usize>,        // drawing last pressed, for delete and restyle
+    pub history: EditHistory,           // undo and redo of drawing edits
+    pub layers: Layers,                 // layers the drawings are grouped in
+    pub order_flow: OrderFlow,          // trade tape for the volume profile and footprint
     pub delta: (f32, f32),
 }
 
@@ -91,47 +114,297 @@ pub struct Chart {
 impl Chart {
     // new_dafault to set value default for inititalization
     pub fn new_default(stock_name: String, stock: OHLCData, company_info: CompanyInfo) -> Self {
-        let data = convert_candlesticks(stock.symbol.len() == 3, stock.to_candlesticks());
-        let (x_offset_min, data_slice) = if data.len() < DEFAULT_CANDLE_NUMER {
-            (0 as f32, &data[0..(data.len() - 1)])
-        } else {
-            (
-                (data.len() - DEFAULT_CANDLE_NUMER) as f32,
-                &data[(data.len() - DEFAULT_CANDLE_NUMER)..(data.len() - 1)],
-            )
-        };
-        let chart_data = ChartData {
-            x_offset_min,
-            x_offset_max: (data.len() - 1) as f32,
-            y_offset_min: Self::get_y_min(data_slice.to_vec()),
-            y_offset_max: Self::get_y_max(data_slice.to_vec()),
-            ..Default::default()
-        };
+        Self::with_time_frame(stock_name, TimeFrame::default(), stock, company_info)
+    }
 
+    /// Build a chart whose candles are `stock` resampled to `time_frame`
+    pub fn with_time_frame(
+        stock_name: String,
+        time_frame: TimeFrame,
+        stock: OHLCData,
+        company_info: CompanyInfo,
+    ) -> Self {
+        let data = Self::resample_candles(time_frame, stock);
         Self {
             stock_name,
             company_info,
-            candle_data: data.clone(),
-            chart_data,
+            time_frame,
+            chart_data: Self::default_view(&data),
+            candle_data: data,
             moving_object: DrawObject::default(),
             current_draw_data: DrawObject::default(),
             all_draw_data: Vec::default(),
+            indicators: Indicator::defaults(),
+            panes: PaneLayout::default(),
             backup_points: Vec::default(),
             backup_chart_offset: None,
             is_in_object: (0, false, false),
+            dragging_vertex: None,
+            selected: None,
+            history: EditHistory::default(),
+            layers: Layers::default(),
+            order_flow: OrderFlow::default(),
             delta: (0.0, 0.0),
         }
     }
 
+    /// Build a chart for `stock_name` from any data source
+    ///
+    /// Bars are requested at the provider interval of `time_frame` and
+    /// resampled client side. Fails with `EmptyData` when the source has no
+    /// bars for the symbol. Missing company ratios fall back to zeros.
+    pub async fn from_source(
+        source: &dyn DataSource,
+        stock_name: &str,
+        time_frame: TimeFrame,
+    ) -> SourceResult<Self> {
+        let stock_name = stock_name.to_uppercase();
+        let stock = Self::fetch_candles(source, &stock_name, time_frame).await?;
+        let company_info = match source.get_company_ratios(&stock_name, "Y").await {
+            Ok(info) => CompanyInfo::from_ratios(&info),
+            Err(e) => {
+                debug!("No company ratios for {stock_name}: {e}");
+                CompanyInfo::default()
+            }
+        };
+        Ok(Self::with_time_frame(
+            stock_name,
+            time_frame,
+            stock,
+            company_info,
+        ))
+    }
+
+    /// Fetch the raw bars `time_frame` is resampled from
+    pub async fn fetch_candles(
+        source: &dyn DataSource,
+        stock_name: &str,
+        time_frame: TimeFrame,
+    ) -> SourceResult<OHLCData> {
+        let interval = time_frame.source_interval();
+        let quote = source
+            .get_quote(&[stock_name], interval, None, None)
+            .await?;
+        quote
+            .0
+            .into_iter()
+            .find(|data| !data.t.is_empty())
+            .ok_or_else(|| AimDataError::EmptyData {
+                endpoint: format!("{interval} quote for {stock_name}"),
+            })
+    }
+
+    fn resample_candles(time_frame: TimeFrame, stock: OHLCData) -> CandleDataVec {
+        let is_stock = stock.symbol.len() == 3;
+        convert_candlesticks(is_stock, resample(&stock.to_candlesticks(), time_frame))
+    }
+
+    /// Show the last `DEFAULT_CANDLE_NUMER` candles
+    pub(crate) fn default_view(data: &CandleDataVec) -> ChartData {
+        let last = data.len().saturating_sub(1);
+        let first = data.len().saturating_sub(DEFAULT_CANDLE_NUMER);
+        let data_slice = &data[first.min(last)..last];
+        ChartData {
+            x_offset_min: first as f32,
+            x_offset_max: last as f32,
+            y_offset_min: Self::get_y_min(data_slice.to_vec()),
+            y_offset_max: Self::get_y_max(data_slice.to_vec()),
+            ..Default::default()
+        }
+    }
+
+    /// Switch to another bar size, replacing the candles and resetting the view
+    pub fn set_time_frame(&mut self, time_frame: TimeFrame, stock: OHLCData) {
+        let old_step = bar_seconds(self.time_frame);
+        self.time_frame = time_frame;
+        let old = std::mem::replace(
+            &mut self.candle_data,
+            Self::resample_candles(time_frame, stock),
+        );
+        self.reproject_drawings(&old, old_step);
+        self.chart_data = ChartData {
+            width: self.chart_data.width,
+            height: self.chart_data.height,
+            image_height: self.chart_data.image_height,
+            ..Self::default_view(&self.candle_data)
+        };
+        self.backup_chart_offset = None;
+    }
+
+    /// Replace the candles with fresh bars at the provider interval
+    ///
+    /// Drawings keep their times, so they stay on the same bars when older
+    /// history is added in front.
     pub fn update_candle_data(&mut self, stock: OHLCData) {
-        self.candle_data =
-            convert_candlesticks(self.stock_name.len() == 3, stock.to_candlesticks());
+        let old = std::mem::replace(
+            &mut self.candle_data,
+            Self::resample_candles(self.time_frame, stock),
+        );
+        self.reproject_drawings(&old, bar_seconds(self.time_frame));
+    }
+
+    /// Move the drawings, saved ones and the ones kept for undo, from the
+    /// `old` candles onto the loaded ones
+    fn reproject_drawings(&mut self, old: &[CandleData], old_step: f64) {
+        let (candles, step) = (&self.candle_data, bar_seconds(self.time_frame));
+        let reproject = |object: &mut DrawObject| {
+            for x in object.xs_mut() {
+                if let Some(placed) = time_at_position(old, old_step, *x)
+                    .and_then(|time| position_at_time(candles, step, time))
+                {
+                    *x = placed;
+                }
+            }
+        };
+        self.all_draw_data.iter_mut().for_each(reproject);
+        self.history.objects_mut().for_each(reproject);
+    }
+
+    /// Unix time, in seconds, at candle position `x`
+    ///
+    /// Times are interpolated between candles; past either end they step by
+    /// the bar length, so drawings ahead of the last candle keep their place.
+    pub fn anchor_time(&self, x: f32) -> Option<f64> {
+        time_at_position(&self.candle_data, bar_seconds(self.time_frame), x)
+    }
+
+    /// Candle position at Unix time `time`, the inverse of [`Chart::anchor_time`]
+    pub fn anchor_x(&self, time: f64) -> Option<f32> {
+        position_at_time(&self.candle_data, bar_seconds(self.time_frame), time)
+    }
+
+    /// Prices of the horizontal and trend lines drawn on the chart at `time`
+    ///
+    /// Trend lines are extended past their ends.
+    pub fn drawn_line_prices(&self, time: DateTime<Utc>) -> Vec<f64> {
+        let x = self.anchor_x(time.timestamp() as f64);
+        self.all_draw_data
+            .iter()
+            .filter_map(|object| match object.draw_type {
+                DrawType::HorizontalLine(y) => Some(y as f64),
+                DrawType::Line(((x1, y1), (x2, y2))) if x1 != x2 => {
+                    let slope = (y2 - y1) as f64 / (x2 - x1) as f64;
+                    x.map(|x| y1 as f64 + (x - x1) as f64 * slope)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Volumes of the candles of the Vietnamese sessions before the one of
+    /// `time`, oldest first
+    pub fn volumes_before(&self, time: DateTime<Utc>) -> Vec<f64> {
+        let today = to_vn_time(time).date();
+        self.candle_data
+            .iter()
+            .filter(|candle| to_vn_time(candle.time).date() < today)
+            .map(|candle| candle.volume as f64)
+            .collect()
+    }
+
+    /// Times of the x coordinates of every saved drawing, in
+    /// [`DrawObject::xs_mut`] order
+    pub fn drawing_anchors(&self) -> Vec<Vec<f64>> {
+        self.all_draw_data
+            .iter()
+            .map(|object| {
+                let mut object = object.clone();
+                object
+                    .xs_mut()
+                    .into_iter()
+                    .filter_map(|x| self.anchor_time(*x))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Move the saved drawings onto the loaded candles from their `anchors`
+    ///
+    /// Drawings without a full set of anchors are left where they are.
+    pub fn place_drawings(&mut self, anchors: &[Vec<f64>]) {
+        let mut drawings = std::mem::take(&mut self.all_draw_data);
+        for (object, times) in drawings.iter_mut().zip(anchors) {
+            let xs = object.xs_mut();
+            if xs.len() != times.len() {
+                continue;
+            }
+            for (x, time) in xs.into_iter().zip(times) {
+                if let Some(placed) = self.anchor_x(*time) {
+                    *x = placed;
+                }
+            }
+        }
+        self.all_draw_data = drawings;
+    }
+
+    /// Replace the drawings with the ones of `other`, a chart of the same
+    /// stock at another time frame, put on this chart's candles
+    pub fn adopt_drawings(&mut self, other: &Chart) {
+        self.all_draw_data = other.all_draw_data.clone();
+        self.layers = other.layers.clone();
+        self.place_drawings(&other.drawing_anchors());
+        self.history.clear();
+        self.is_in_object = (0, false, false);
+        self.dragging_vertex = None;
+        self.selected = None;
     }
 
     pub fn update_company_info(&mut self, info: CompanyInfo) {
         self.company_info = info;
     }
 
+    /// Show or hide `kind`, adding it with its default colours the first time
+    ///
+    /// Oscillators get their own pane while they are visible.
+    pub fn toggle_indicator(&mut self, kind: IndicatorKind) {
+        match self.indicators.iter_mut().find(|i| i.kind == kind) {
+            Some(indicator) => indicator.visible = !indicator.visible,
+            None => self.indicators.push(Indicator::new(kind)),
+        }
+        self.sync_indicator_panes();
+    }
+
+    /// Move the volume bars between the price chart and their own pane
+    pub fn toggle_volume_pane(&mut self) {
+        self.panes.toggle(PaneContent::Volume);
+    }
+
+    /// Show or hide the volume profile of the visible candles
+    pub fn toggle_volume_profile(&mut self) {
+        self.order_flow.show_profile = !self.order_flow.show_profile;
+    }
+
+    /// Show or hide the footprint cells of the candles
+    pub fn toggle_footprint(&mut self) {
+        self.order_flow.show_footprint = !self.order_flow.show_footprint;
+    }
+
+    /// Add prints of the charted stock's tape, see [`OrderFlow::push`]
+    pub fn push_trades(&mut self, trades: &[Trade], date: NaiveDate) {
+        // Stock prices are charted in thousands of VND
+        let scale = if self.stock_name.len() == 3 {
+            1000.0
+        } else {
+            1.0
+        };
+        self.order_flow.push(trades, date, scale);
+    }
+
+    /// Give every visible oscillator a pane and drop the panes of hidden ones
+    pub(crate) fn sync_indicator_panes(&mut self) {
+        for indicator in &self.indicators {
+            if indicator.kind.is_overlay() {
+                continue;
+            }
+            let content = PaneContent::Indicator(indicator.kind);
+            if indicator.visible {
+                self.panes.add(content);
+            } else {
+                self.panes.remove(&content);
+            }
+        }
+    }
+
     /// main function for rendering the chart with plotter
     pub fn render_plot(&mut self, ui_data: UiData) -> (slint::Image, bool) {
         // Check if candle_data is empty and return empty image
@@ -144,14 +417,38 @@ impl Chart {
         }
 
         self.chart_data.ui_data = ui_data;
-        // clean all object on the chart
-        if self.chart_data.ui_data.is_clean {
-            self.all_draw_data.clear();
-            self.current_draw_data.clear();
+        self.apply_edit_requests();
+        self.apply_layer_requests();
+
+        if self.chart_data.ui_data.is_toggle_indicator {
+            let label = self.chart_data.ui_data.indicator.clone();
+            if label.eq_ignore_ascii_case("volume") {
+                self.toggle_volume_pane();
+            } else if label.eq_ignore_ascii_case("profile") {
+                self.toggle_volume_profile();
+            } else if label.eq_ignore_ascii_case("footprint") {
+                self.toggle_footprint();
+            } else {
+                match IndicatorKind::parse(&label) {
+                    Some(kind) => self.toggle_indicator(kind),
+                    None => log::warn!("Unknown indicator {label:?}"),
+                }
+            }
         }
 
-        if self.chart_data.ui_data.is_undo {
-            self.all_draw_data.pop();
+        // Dragging a pane divider resizes the pane instead of panning the chart
+        let image_height = match self.chart_data.ui_data.height {
+            0 => self.chart_data.image_height,
+            height => height as u32,
+        };
+        if self.panes.drag(
+            image_height,
+            self.chart_data.ui_data.press_y,
+            self.chart_data.ui_data.position_y,
+            self.chart_data.ui_data.is_release,
+        ) {
+            self.chart_data.ui_data.move_x = 0;
+            self.chart_data.ui_data.move_y = 0;
         }
 
         if self.chart_data.candle_distance < 1.5
@@ -194,21 +491,40 @@ impl Chart {
         self.chart_data.zoom_x = zoom_x;
         self.chart_data.zoom_y = zoom_y as f32;
         let (mouse_x, mouse_y) = self.get_mouse_position();
+        // A finished move or resize goes to the history
+        if self.is_in_object.2
+            && self.chart_data.ui_data.is_release
+            && let Some(after) = self.all_draw_data.get(self.is_in_object.0)
+            && *after != self.moving_object
+        {
+            self.history.push(EditCommand::Modify {
+                index: self.is_in_object.0,
+                before: self.moving_object.clone(),
+                after: after.clone(),
+            });
+        }
         if self.is_in_object.2 && !self.chart_data.ui_data.is_release {
             let dx = self.chart_data.ui_data.move_x as f32 / self.chart_data.candle_distance;
             let dy = -(self.chart_data.ui_data.move_y as f32)
                 * (3.0 * (self.chart_data.y_offset_max - self.chart_data.y_offset_min))
                 / (self.chart_data.height - self.chart_data.range_x as u32) as f32;
 
-            // Move the object
+            // Dragging a handle reshapes the object, anywhere else moves it
             debug!("moving - dx: {dx}, dy: {dy}");
             let mut updated_object = self.moving_object.clone();
-            updated_object.translate(dx, dy);
+            match self.dragging_vertex {
+                Some(vertex) => updated_object.translate_vertex(vertex, dx, dy),
+                None => updated_object.translate(dx, dy),
+            }
             self.all_draw_data[self.is_in_object.0] = updated_object;
             self.chart_data.ui_data.move_x = 0;
             self.chart_data.ui_data.move_y = 0;
         } else {
             for (idx, draw_obj) in self.all_draw_data.iter().enumerate() {
+                // Drawings on hidden or locked layers cannot be picked
+                if !self.layers.is_editable(&draw_obj.layer) {
+                    continue;
+                }
                 let x_thresh_hold = (self.chart_data.x_offset_max - self.chart_data.x_offset_min)
                     * THREAD_HOLD
                     / (self.chart_data.width as f32 - 60.0);
@@ -217,7 +533,11 @@ impl Chart {
                     * THREAD_HOLD
                     / (self.chart_data.height as f32 - 60.0);
 
-                if draw_obj.hit_cursor((mouse_x, mouse_y), x_thresh_hold, y_thresh_hold) {
+                // Oval handles sit on the bounding box, off the outline
+                let vertex = draw_obj.vertex_at((mouse_x, mouse_y), x_thresh_hold, y_thresh_hold);
+                if vertex.is_some()
+                    || draw_obj.hit_cursor((mouse_x, mouse_y), x_thresh_hold, y_thresh_hold)
+                {
                     if self.chart_data.ui_data.is_release {
                         // If the object is not moving, set it to be moving
                         self.is_in_object = (idx, true, false);
@@ -225,6 +545,8 @@ impl Chart {
                         self.is_in_object = (idx, true, true);
                         // Calculate the delta in chart coordinates
                         self.moving_object = draw_obj.clone();
+                        self.dragging_vertex = vertex;
+                        self.selected = Some(idx);
                     }
                     self.chart_data.ui_data.move_x = 0;
                     self.chart_data.ui_data.move_y = 0;
@@ -245,25 +567,29 @@ impl Chart {
         // Update width and height of chart when users resize the window
         if self.chart_data.ui_data.height != 0 && self.chart_data.ui_data.width != 0 {
             self.chart_data.width = self.chart_data.ui_data.width as u32;
-            self.chart_data.height = self.chart_data.ui_data.height as u32;
+            self.chart_data.image_height = self.chart_data.ui_data.height as u32;
         }
+        // The price chart gets what the panes leave
+        let (price_height, pane_rects) = self.panes.split(self.chart_data.image_height);
+        self.chart_data.height = price_height;
 
         // Init data for plotters
         let mut pixel_buffer =
-            SharedPixelBuffer::new(self.chart_data.width, self.chart_data.height);
+            SharedPixelBuffer::new(self.chart_data.width, self.chart_data.image_height);
         let size = (pixel_buffer.width(), pixel_buffer.height());
         let backend = BitMapBackend::with_buffer(pixel_buffer.make_mut_bytes(), size);
         let root = backend.into_drawing_area();
         // background: 181c27
         root.fill(&RGBColor(0x19, 0x19, 0x1C))
             .expect("error filling drawing area");
+        let (price_area, panes_area) = root.split_vertically(price_height);
 
         // get candle data to show
         let (space, max_y, min_y) = self.update_y_axis_after_moving();
         let (min_x, max_x) = self.update_x_axis_after_moving();
 
         // Init the first candle chart with x,y range
-        let mut chart = ChartBuilder::on(&root)
+        let mut chart = ChartBuilder::on(&price_area)
             .x_label_area_size(self.chart_data.range_x)
             .right_y_label_area_size(self.chart_data.range_y)
             .build_cartesian_2d(min_x..max_x, min_y..max_y)
@@ -276,15 +602,23 @@ impl Chart {
         // Draw stock name watermark in the background
         self.chart_draw_stock_name_watermark(&mut chart);
 
+        // The volume profile sits behind the candles, the footprint over them
+        self.chart_draw_volume_profile(&mut chart);
+
         // Handle candle sticks on the chart
         self.chart_update_candle_sticks(&mut chart);
 
+        self.chart_draw_footprint(&mut chart);
+
         // Handle drawing on the chart
         // Convert x axis to date time unit
         self.chart_custom_x_axis(&mut chart);
 
+        // Compute the indicators once for the legend and the plot
+        let indicator_lines = self.compute_indicators();
+
         // Show candle information at the position of mouse
-        self.chart_show_candle_info(&mut chart);
+        self.chart_show_candle_info(&mut chart, &indicator_lines);
 
         // show company information
         self.chart_show_company_info(&mut chart);
@@ -295,10 +629,13 @@ impl Chart {
         // Draw all user's objects on the chart
         self.chart_draw_objects(&mut chart);
 
-        self.chart_draw_moving_averages(&mut chart);
+        self.chart_draw_indicators(&mut chart, &indicator_lines);
 
         self.chart_draw_labels(&mut chart, max_x);
 
+        // Volume and oscillator panes share the x range of the price chart
+        self.chart_draw_panes(&panes_area, &pane_rects, &indicator_lines, min_x, max_x);
+
         // update data after a mouse release
         if self.chart_data.ui_data.is_release {
             self.chart_data.y_offset_max = max_y - space;
@@ -306,11 +643,18 @@ impl Chart {
             self.chart_data.x_offset_min = min_x;
             self.chart_data.x_offset_max = max_x;
             self.is_in_object.2 = false;
+            self.dragging_vertex = None;
             if !self.current_draw_data.is_empty() {
                 if let DrawType::Ruler(_) = self.current_draw_data.draw_type {
                     // nothing to do
                 } else {
-                    self.all_draw_data.push(self.current_draw_data.clone());
+                    let mut object = self.current_draw_data.clone();
+                    object.layer = self.layers.active().name.clone();
+                    let command = EditCommand::Add {
+                        index: self.all_draw_data.len(),
+                        object,
+                    };
+                    self.history.execute(command, &mut self.all_draw_data);
                     self.current_draw_data.clear();
                     self.backup_points.clear();
                 }
@@ -329,11 +673,136 @@ impl Chart {
 
         root.present().expect("error presenting");
         drop(chart);
+        drop(price_area);
+        drop(panes_area);
         drop(root);
 
         (slint::Image::from_rgb8(pixel_buffer), self.is_in_object.1)
     }
 
+    /// Apply the toolbar's clean, undo, redo, delete and restyle requests,
+    /// each recorded in [`Chart::history`]
+    fn apply_edit_requests(&mut self) {
+        let ui_data = &self.chart_data.ui_data;
+        let (is_clean, is_undo, is_redo) = (ui_data.is_clean, ui_data.is_undo, ui_data.is_redo);
+        let (is_delete, is_restyle) = (ui_data.is_delete, ui_data.is_restyle);
+        let color = RGBColor(
+            ui_data.color.red(),
+            ui_data.color.green(),
+            ui_data.color.blue(),
+        );
+        let selected = self.selected.filter(|index| {
+            self.all_draw_data
+                .get(*index)
+                .is_some_and(|object| self.layers.is_editable(&object.layer))
+        });
+
+        let command = if is_clean {
+            // Drawings on locked or hidden layers survive a clean
+            self.current_draw_data.clear();
+            let after: Vec<DrawObject> = self
+                .all_draw_data
+                .iter()
+                .filter(|object| !self.layers.is_editable(&object.layer))
+                .cloned()
+                .collect();
+            (after.len() < self.all_draw_data.len()).then(|| EditCommand::Clear {
+                before: self.all_draw_data.clone(),
+                after,
+            })
+        } else if is_delete {
+            selected.map(|index| EditCommand::Delete {
+                index,
+                object: self.all_draw_data[index].clone(),
+            })
+        } else if is_restyle {
+            selected
+                .filter(|index| self.all_draw_data[*index].color != color)
+                .map(|index| {
+                    let before = self.all_draw_data[index].clone();
+                    let after = DrawObject {
+                        color,
+                        ..before.clone()
+                    };
+                    EditCommand::Modify {
+                        index,
+                        before,
+                        after,
+                    }
+                })
+        } else {
+            None
+        };
+
+        let changed = match command {
+            Some(command) => {
+                self.history.execute(command, &mut self.all_draw_data);
+                true
+            }
+            None if is_undo => self.history.undo(&mut self.all_draw_data),
+            None if is_redo => self.history.redo(&mut self.all_draw_data),
+            None => false,
+        };
+        // Indices may point elsewhere now, except after a restyle
+        if changed && !is_restyle {
+            self.selected = None;
+            self.is_in_object = (0, false, false);
+        }
+    }
+
+    /// Pick the layer new drawings go to, or show, hide, lock or unlock it
+    fn apply_layer_requests(&mut self) {
+        let ui_data = &self.chart_data.ui_data;
+        if ui_data.is_select_layer && !ui_data.layer.trim().is_empty() {
+            let name = ui_data.layer.trim().to_string();
+            self.layers.select(&name);
+        }
+        if ui_data.is_layer_action
+            && let Some(action) = LayerAction::from_index(ui_data.layer_action)
+        {
+            self.layers.apply(action);
+        }
+        // A drawing being moved or kept for delete may have just been locked
+        if self
+            .selected
+            .and_then(|index| self.all_draw_data.get(index))
+            .is_some_and(|object| !self.layers.is_editable(&object.layer))
+        {
+            self.selected = None;
+            self.is_in_object = (0, false, false);
+        }
+    }
+
+    /// Time of the candle under pixel column `x` of the last rendered image
+    pub fn time_at(&self, x: i32) -> Option<DateTime<Utc>> {
+        if self.chart_data.candle_distance <= 0.0 {
+            return None;
+        }
+        let index = ((x - self.chart_data.ui_data.move_x) as f32 / self.chart_data.candle_distance
+            + self.chart_data.x_offset_min
+            - self.chart_data.zoom_x)
+            .round();
+        if index < 0.0 {
+            return None;
+        }
+        self.candle_data
+            .get(index as usize)
+            .map(|candle| candle.time)
+    }
+
+    /// Pixel column of the candle open at `time` in the last rendered image
+    pub fn x_of_time(&self, time: DateTime<Utc>) -> Option<i32> {
+        let index = self
+            .candle_data
+            .partition_point(|candle| candle.time <= time)
+            .checked_sub(1)?;
+        let x = (self.candle_data[index].num - self.chart_data.x_offset_min
+            + self.chart_data.zoom_x)
+            * self.chart_data.candle_distance
+            + self.chart_data.ui_data.move_x as f32;
+        Some(x.round() as i32)
+    }
+
     /// Handle drawing all candle sticks on the chart
     fn chart_update_candle_sticks(
         &self,
@@ -378,8 +847,10 @@ impl Chart {
             }))
             .unwrap();
 
-        // Draw volume data
-        self.draw_volume_data(chart, chart_candles, candles_size);
+        // Draw volume data, unless it has a pane of its own
+        if !self.panes.contains(&PaneContent::Volume) {
+            self.draw_volume_data(chart, chart_candles, candles_size);
+        }
     }
 
     /// Draw volume data into the chart
@@ -413,6 +884,136 @@ impl Chart {
             .unwrap();
     }
 
+    /// Volume profile of the trades under the visible candles, along the
+    /// right edge, with the point of control marked and the value area brighter
+    fn chart_draw_volume_profile(
+        &self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    ) {
+        if !self.order_flow.show_profile {
+            return;
+        }
+        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
+        let visible = &self.candle_data[min_candle_x..max_candle_x];
+        let (Some(first), Some(last)) = (visible.first(), visible.last()) else {
+            return;
+        };
+        let (_, max_y, min_y) = self.update_y_axis_after_moving();
+        let (min_x, max_x) = self.update_x_axis_after_moving();
+        let tick = self.order_flow.price_step().unwrap_or(0.0);
+        let end = last.time + Duration::seconds(bar_seconds(self.time_frame) as i64);
+        let profile =
+            self.order_flow
+                .profile(first.time, end, row_size(tick, max_y - min_y, PROFILE_ROWS));
+        let max_volume = profile.max_row_volume();
+        if max_volume <= 0.0 {
+            return;
+        }
+
+        let value_area = profile.value_area();
+        let width = (max_x - min_x) * PROFILE_WIDTH;
+        let half_height = profile.row_size * 0.45;
+        let mut bars = Vec::new();
+        for row in &profile.rows {
+            let alpha = match value_area {
+                Some((low, high)) if row.price >= low && row.price <= high => 0.5,
+                _ => 0.2,
+            };
+            // Buys from the edge, then sells, then the auction volume
+            let mut right = max_x;
+            for (volume, color) in [
+                (row.buy, UP_COLOR),
+                (row.sell, DOWN_COLOR),
+                (row.neutral, GREY),
+            ] {
+                if volume <= 0.0 {
+                    continue;
+                }
+                let left = right - volume / max_volume * width;
+                bars.push(Rectangle::new(
+                    [
+                        (left, row.price - half_height),
+                        (right, row.price + half_height),
+                    ],
+                    color.mix(alpha).filled(),
+                ));
+                right = left;
+            }
+        }
+        chart.draw_series(bars).ok();
+
+        if let Some(poc) = profile.poc() {
+            chart
+                .draw_series(LineSeries::new(
+                    [(max_x - width, poc.price), (max_x, poc.price)],
+                    NO_CHANGE_COLOR.stroke_width(2),
+                ))
+                .ok();
+        }
+    }
+
+    /// Buy and sell volume by price inside each visible candle, coloured by
+    /// their difference and written out once the candles are wide enough
+    fn chart_draw_footprint(
+        &self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    ) {
+        if !self.order_flow.show_footprint {
+            return;
+        }
+        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
+        let visible = &self.candle_data[min_candle_x..max_candle_x];
+        let (_, max_y, min_y) = self.update_y_axis_after_moving();
+        let tick = self.order_flow.price_step().unwrap_or(0.0);
+        let max_rows = (self.chart_data.height as f32 / FOOTPRINT_ROW_PIXELS) as usize;
+        let footprint = self.order_flow.footprint(
+            visible,
+            bar_seconds(self.time_frame) as i64,
+            row_size(tick, max_y - min_y, max_rows),
+        );
+        let max_volume = footprint
+            .iter()
+            .map(VolumeProfile::max_row_volume)
+            .fold(0.0, f32::max);
+        if max_volume <= 0.0 {
+            return;
+        }
+
+        let show_volumes = self.chart_data.candle_distance >= FOOTPRINT_TEXT_PIXELS;
+        let mut cells = Vec::new();
+        let mut labels = Vec::new();
+        for (candle, profile) in visible.iter().zip(&footprint) {
+            let half_height = profile.row_size * 0.5;
+            for cell in profile.rows.iter().filter(|cell| cell.total() > 0.0) {
+                let color = if cell.delta() > 0.0 {
+                    UP_COLOR
+                } else if cell.delta() < 0.0 {
+                    DOWN_COLOR
+                } else {
+                    GREY
+                };
+                let alpha = 0.15 + 0.6 * cell.total() / max_volume;
+                cells.push(Rectangle::new(
+                    [
+                        (candle.num - 0.45, cell.price - half_height),
+                        (candle.num + 0.45, cell.price + half_height),
+                    ],
+                    color.mix(alpha as f64).filled(),
+                ));
+                if show_volumes {
+                    // Sells on the left, buys on the right, as on a ladder
+                    labels.push(Text::new(
+                        format!("{} x {}", format_volume(cell.sell), format_volume(cell.buy)),
+                        (candle.num - 0.42, cell.price + half_height),
+                        ("sans-serif", 10).into_font().color(&WHITE),
+                    ));
+                }
+            }
+        }
+        chart.draw_series(cells).ok();
+        chart.draw_series(labels).ok();
+    }
+
     /// Handle drawing all user's objects on the chart
     fn chart_draw_objects(
         &mut self,
@@ -424,13 +1025,18 @@ impl Chart {
         let (x_position, y_position) = self.get_mouse_position();
 
         // Draw cursor lines following the mouse position
-        self.draw_cursor_lines(chart, x_position, y_position);
+        if !self.chart_data.ui_data.hide_cursor {
+            self.draw_cursor_lines(chart, x_position, y_position);
+        }
         let (_, max_y, min_y) = self.update_y_axis_after_moving();
         let (min_x, max_x) = self.update_x_axis_after_moving();
 
         // Draw all saved lines but don't add new ones
         draw_all_data.push(self.current_draw_data.clone());
         for object in draw_all_data {
+            if !self.layers.is_visible(&object.layer) {
+                continue;
+            }
             let mut new_object = object.clone();
             new_object.clipped(min_x, max_x, min_y, max_y);
             let draw_points = new_object.to_vec((min_x, max_y), (max_x, min_y));
@@ -603,6 +1209,67 @@ impl Chart {
                     // Draw the main line
                     self.draw_arrow(chart, start, end, object.color, max_x, min_x, max_y, min_y);
                 }
+                DrawType::FibRetracement { .. }
+                | DrawType::FibExtension { .. }
+                | DrawType::Channel(_)
+                | DrawType::Pitchfork(_) => {
+                    let segments = object.visible_segments(min_x, max_x, min_y, max_y);
+                    let _ = chart.draw_series(segments.into_iter().map(|(start, end)| {
+                        PathElement::new(vec![start, end], object.color.stroke_width(1))
+                    }));
+                    let labels = object.level_labels().into_iter().filter(|((x, y), _)| {
+                        *x >= min_x && *x <= max_x && *y >= min_y && *y <= max_y
+                    });
+                    for (anchor, label) in labels {
+                        let _ = chart.plotting_area().draw(&Text::new(
+                            label,
+                            anchor,
+                            ("Arial", 12).into_font().color(&object.color),
+                        ));
+                    }
+                }
+                DrawType::Range((start, end)) => {
+                    let change = end.1 - start.1;
+                    let bars = (end.0 - start.0).abs().round() as i64;
+                    let color = if change >= 0.0 { BLUE } else { RED };
+
+                    let _ = chart.draw_series(std::iter::once(Rectangle::new(
+                        [*start, *end],
+                        color.mix(0.2).filled(),
+                    )));
+                    let segments = object.visible_segments(min_x, max_x, min_y, max_y);
+                    let _ = chart.draw_series(segments.into_iter().map(|(start, end)| {
+                        PathElement::new(vec![start, end], color.stroke_width(1))
+                    }));
+
+                    // Label above the top edge, in the middle of the span
+                    let label_height = self.pixels_to_y_distance(18.0, max_y - min_y);
+                    let label_x = (start.0 + end.0) / 2.0;
+                    let label_y = start.1.max(end.1) + label_height;
+                    if label_x >= min_x && label_x <= max_x && label_y <= max_y {
+                        // No percentage from a zero start price
+                        let label = if start.1 != 0.0 {
+                            let percent = change * 100.0 / start.1;
+                            format!("{change:+.02} ({percent:+.02}%) {bars} nến")
+                        } else {
+                            format!("{change:+.02} {bars} nến")
+                        };
+                        let _ = chart.plotting_area().draw(&Text::new(
+                            label,
+                            (label_x, label_y),
+                            ("Arial-Bold", 14).into_font().color(&color),
+                        ));
+                    }
+                }
+                DrawType::Text { anchor, text, size } => {
+                    if !new_object.is_empty() {
+                        let _ = chart.plotting_area().draw(&Text::new(
+                            text.clone(),
+                            *anchor,
+                            ("Arial", *size).into_font().color(&object.color),
+                        ));
+                    }
+                }
                 _ => {
                     chart
                         .draw_series(LineSeries::new(draw_points, object.color.stroke_width(1)))
@@ -611,6 +1278,24 @@ impl Chart {
                 }
             }
         }
+
+        // Handles of the object under the mouse, or else the selected one
+        let handled = if self.is_in_object.1 {
+            Some(self.is_in_object.0)
+        } else {
+            self.selected
+        };
+        if let Some(object) = handled.and_then(|index| self.all_draw_data.get(index)) {
+            let handles = object
+                .vertices()
+                .into_iter()
+                .filter(|(x, y)| *x >= min_x && *x <= max_x && *y >= min_y && *y <= max_y);
+            let _ = chart.draw_series(handles.map(|point| {
+                EmptyElement::at(point)
+                    + Rectangle::new([(-3, -3), (3, 3)], WHITE.filled())
+                    + Rectangle::new([(-3, -3), (3, 3)], object.color)
+            }));
+        }
     }
 
     fn draw_arrow(
@@ -669,19 +1354,7 @@ impl Chart {
         x_position: f32,
         y_position: f32,
     ) {
-        // Check if cursor is inside a candle for vertical line only
-        let mut x_pos = x_position;
-        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
-        let candle_idx = x_position.round() as usize;
-        if candle_idx >= min_candle_x
-            && candle_idx < max_candle_x
-            && candle_idx < self.candle_data.len()
-        {
-            // Cursor is inside a candle, set vertical line to middle of candle
-            let candle = &self.candle_data[candle_idx];
-            x_pos = candle.num;
-        }
-        let x_line = self.cursor_vertical_line(x_pos);
+        let x_line = self.cursor_vertical_line(self.snap_to_candle(x_position));
         let y_line = self.cursor_horizontal_line(y_position);
         // Draw the vertical and horizontal line of mouse position
         chart
@@ -689,16 +1362,35 @@ impl Chart {
             .unwrap()
             .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
 
-        chart
-            .draw_series(LineSeries::new(y_line, GREY.stroke_width(1)))
-            .unwrap()
-            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+        // The horizontal line belongs to the pane under the mouse
+        if self.is_in_price_area() {
+            chart
+                .draw_series(LineSeries::new(y_line, GREY.stroke_width(1)))
+                .unwrap()
+                .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+        }
+    }
+
+    /// Middle of the candle under `x_position`, if the cursor is inside one
+    fn snap_to_candle(&self, x_position: f32) -> f32 {
+        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
+        let candle_idx = x_position.round() as usize;
+        if candle_idx >= min_candle_x
+            && candle_idx < max_candle_x
+            && candle_idx < self.candle_data.len()
+        {
+            self.candle_data[candle_idx].num
+        } else {
+            x_position
+        }
     }
 
     /// Handle different drawing types
     fn update_current_draw(&mut self) {
-        // If cursor is in y-axis field, don't add drawing points
-        if self.is_in_y_field() {
+        // If cursor is in y-axis field or in a pane, don't add drawing points
+        if self.is_in_y_field()
+            || self.chart_data.ui_data.position_y >= self.chart_data.height as i32
+        {
             return;
         }
 
@@ -748,11 +1440,86 @@ impl Chart {
                     self.current_draw_data =
                         DrawObject::new(DrawType::Ruler(((x, y), press_point)), color);
                 }
+                MouseType::FibRetracement => {
+                    let levels =
+                        parse_levels(&self.chart_data.ui_data.fib_levels, &RETRACEMENT_LEVELS);
+                    self.current_draw_data = DrawObject::new(
+                        DrawType::FibRetracement {
+                            swing: (press_point, (x, y)),
+                            levels,
+                        },
+                        color,
+                    );
+                }
+                MouseType::FibExtension => {
+                    // The projection point starts at a 0.618 pullback of the swing
+                    let projection = (
+                        x + (x - press_point.0) / 2.0,
+                        y - 0.618 * (y - press_point.1),
+                    );
+                    let levels =
+                        parse_levels(&self.chart_data.ui_data.fib_levels, &EXTENSION_LEVELS);
+                    self.current_draw_data = DrawObject::new(
+                        DrawType::FibExtension {
+                            points: (press_point, (x, y), projection),
+                            levels,
+                        },
+                        color,
+                    );
+                }
+                MouseType::Channel => {
+                    let through = self.channel_through(press_point, (x, y));
+                    self.current_draw_data =
+                        DrawObject::new(DrawType::Channel((press_point, (x, y), through)), color);
+                }
+                MouseType::Pitchfork => {
+                    // The mouse sets the middle of the two prong anchors
+                    let half = (y - press_point.1).abs() / 2.0;
+                    self.current_draw_data = DrawObject::new(
+                        DrawType::Pitchfork((press_point, (x, y + half), (x, y - half))),
+                        color,
+                    );
+                }
+                MouseType::Range => {
+                    self.current_draw_data =
+                        DrawObject::new(DrawType::Range((press_point, (x, y))), color);
+                }
+                // The text follows the mouse until the button is released
+                MouseType::Text if !self.chart_data.ui_data.text.is_empty() => {
+                    self.current_draw_data = DrawObject::new(
+                        DrawType::Text {
+                            anchor: (x, y),
+                            text: self.chart_data.ui_data.text.clone(),
+                            size: self.chart_data.ui_data.text_size.max(1) as u32,
+                        },
+                        color,
+                    );
+                }
                 _ => {}
             }
         }
     }
 
+    /// High or low between `start` and `end` furthest from the line through
+    /// them, where the parallel side of a new channel goes
+    fn channel_through(&self, start: Point, end: Point) -> Point {
+        let (left, right) = (start.0.min(end.0), start.0.max(end.0));
+        let slope = if end.0 == start.0 {
+            0.0
+        } else {
+            (end.1 - start.1) / (end.0 - start.0)
+        };
+        self.candle_data
+            .iter()
+            .filter(|candle| candle.num >= left && candle.num <= right)
+            .flat_map(|candle| [(candle.num, candle.high), (candle.num, candle.low)])
+            .max_by(|a, b| {
+                let deviation = |p: &Point| (p.1 - start.1 - slope * (p.0 - start.0)).abs();
+                deviation(a).total_cmp(&deviation(b))
+            })
+            .unwrap_or(end)
+    }
+
     /// Draw all labels of the chart:
     ///   - Latest price label
     ///   - latest volume label
@@ -786,13 +1553,23 @@ impl Chart {
         };
 
         // Draw cursor labels
-        self.draw_cursor_labels(chart, max_x, y_position, min, cursor_label_style);
+        if !self.chart_data.ui_data.hide_cursor {
+            self.draw_cursor_labels(chart, max_x, y_position, min, cursor_label_style);
+        }
 
         // Draw latest price label
         self.draw_latest_price_label(chart, max_x, space);
 
-        // Draw latest volume label
-        self.draw_latest_volume_label(chart, max_x, last_volume_map_to_y, space, green_label_style);
+        // Draw latest volume label, the volume pane has its own axis
+        if !self.panes.contains(&PaneContent::Volume) {
+            self.draw_latest_volume_label(
+                chart,
+                max_x,
+                last_volume_map_to_y,
+                space,
+                green_label_style,
+            );
+        }
     }
 
     /// Draw cursor labels
@@ -818,30 +1595,33 @@ impl Chart {
         cursor_rectangle.set_margin(0, 0, 50, 0);
 
         // Show the price of current mouse position
-        let y_position_str = format!("   {y_position:.02}");
-        let _ = chart.plotting_area().draw(&cursor_rectangle);
-        let _ = chart.plotting_area().draw(&Text::new(
-            y_position_str,
-            (
-                max_x,
-                y_position
-                    + (7.0 * (self.chart_data.y_offset_max - self.chart_data.y_offset_min)
-                        / (self.chart_data.height - self.chart_data.range_x as u32) as f32),
-            ),
-            ("Arial-Bold", 14).into_font().color(&WHITE),
-        ));
+        if self.is_in_price_area() {
+            let y_position_str = format!("   {y_position:.02}");
+            let _ = chart.plotting_area().draw(&cursor_rectangle);
+            let _ = chart.plotting_area().draw(&Text::new(
+                y_position_str,
+                (
+                    max_x,
+                    y_position
+                        + (7.0 * (self.chart_data.y_offset_max - self.chart_data.y_offset_min)
+                            / (self.chart_data.height - self.chart_data.range_x as u32) as f32),
+                ),
+                ("Arial-Bold", 14).into_font().color(&WHITE),
+            ));
+        }
 
         // Draw the label for the cursor date
         // Calculate the date based on cursor position, including future dates
         let cursor_index = x_position.round() as i32;
+        let bar = self.time_frame.duration();
         let cursor_date = if cursor_index < 0 {
             // Past dates - go backwards from the first data point
-            let days_back = (-cursor_index) as i64;
-            self.candle_data[0].time - Duration::days(days_back)
+            let bars_back = -cursor_index;
+            self.candle_data[0].time - bar * bars_back
         } else if cursor_index >= self.candle_data.len() as i32 {
             // Future dates - calculate forward from the last data point
-            let days_forward = (cursor_index - (self.candle_data.len() as i32 - 1)) as i64;
-            self.candle_data[self.candle_data.len() - 1].time + Duration::days(days_forward)
+            let bars_forward = cursor_index - (self.candle_data.len() as i32 - 1);
+            self.candle_data[self.candle_data.len() - 1].time + bar * bars_forward
         } else {
             // Within data range - use actual data
             self.candle_data[cursor_index as usize].time
@@ -850,10 +1630,15 @@ impl Chart {
         let mut rectangle_cursor_x =
             Rectangle::new([(x_position, min), (x_position, min)], cursor_label_style);
         rectangle_cursor_x.set_margin(15, 0, 60, 5);
-        let output = &cursor_date.to_string()[0..11];
+        let output = if self.time_frame.is_intraday() {
+            let local = cursor_date + Duration::seconds(VN_UTC_OFFSET_SECS);
+            local.format("%Y-%m-%d %H:%M").to_string()
+        } else {
+            cursor_date.to_string()[0..11].to_string()
+        };
         let _ = chart.plotting_area().draw(&rectangle_cursor_x);
         let _ = chart.plotting_area().draw(&Text::new(
-            output.to_string(),
+            output,
             (x_position, min - 2.0),
             ("Arial-Bold", 14).into_font().color(&WHITE),
         ));
@@ -987,13 +1772,16 @@ impl Chart {
             .x_label_formatter(&|&idx| {
                 if idx > 0.0 && idx < date_to_index.len() as f32 {
                     let date = &(date_to_index[idx as usize].0
-                        + Duration::days(
-                            ((self.chart_data.ui_data.move_x as f32
+                        + self.time_frame.duration()
+                            * ((self.chart_data.ui_data.move_x as f32
                                 / self.chart_data.candle_distance)
-                                as i32)
-                                .into(),
-                        ));
-                    format!("{}", date.format("Tháng %m\n"))
+                                as i32));
+                    if self.time_frame.is_intraday() {
+                        let local = *date + Duration::seconds(VN_UTC_OFFSET_SECS);
+                        format!("{}", local.format("%d/%m\n"))
+                    } else {
+                        format!("{}", date.format("Tháng %m\n"))
+                    }
                 } else {
                     "".to_string()
                 }
@@ -1010,15 +1798,13 @@ impl Chart {
     fn chart_show_candle_info(
         &self,
         chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+        indicator_lines: &[(Indicator, Vec<IndicatorLine>)],
     ) {
         // get candle data to show
         let (_, max_y, min_y) = self.update_y_axis_after_moving();
         let (min_x, _) = self.update_x_axis_after_moving();
         let y_stock_text = max_y - (max_y - min_y) * (20.0 / self.chart_data.height as f32);
         let y_volumn_text = max_y - (max_y - min_y) * (40.0 / self.chart_data.height as f32);
-        let y_ma_20_text = max_y - (max_y - min_y) * (60.0 / self.chart_data.height as f32);
-        let y_ma_50_text = max_y - (max_y - min_y) * (80.0 / self.chart_data.height as f32);
-        let y_ma_200_text = max_y - (max_y - min_y) * (100.0 / self.chart_data.height as f32);
 
         let (x, _) = self.get_mouse_position();
         // If cursor is exactly at the middle of a candle, show info for that candle
@@ -1100,21 +1886,21 @@ impl Chart {
             (min_x, y_volumn_text),
             ("sans-serif", 15).into_font().color(&color),
         ));
-        let _ = chart.plotting_area().draw(&Text::new(
-            "  MA20    _________",
-            (min_x, y_ma_20_text),
-            ("sans-serif", 15).into_font().color(&RED),
-        ));
-        let _ = chart.plotting_area().draw(&Text::new(
-            "  MA50    _________",
-            (min_x, y_ma_50_text),
-            ("sans-serif", 15).into_font().color(&BLUE),
-        ));
-        let _ = chart.plotting_area().draw(&Text::new(
-            "  MA200  _________",
-            (min_x, y_ma_200_text),
-            ("sans-serif", 15).into_font().color(&WHITE),
-        ));
+
+        // One legend row per overlay, oscillators show theirs in their pane
+        let cursor_idx = x.round().max(0.0) as usize;
+        let overlays = indicator_lines
+            .iter()
+            .filter(|(indicator, _)| indicator.kind.is_overlay());
+        for (row, (indicator, lines)) in overlays.enumerate() {
+            let y_text = max_y
+                - (max_y - min_y) * ((60.0 + 20.0 * row as f32) / self.chart_data.height as f32);
+            let _ = chart.plotting_area().draw(&Text::new(
+                indicator_legend(indicator, lines, cursor_idx),
+                (min_x, y_text),
+                ("sans-serif", 15).into_font().color(&indicator.color(0)),
+            ));
+        }
     }
 
     /// Draw stock name as watermark in the background center of the chart
@@ -1189,6 +1975,11 @@ impl Chart {
             .draw(&Text::new(roa_text, (position_x, y_roa), font.clone()));
     }
 
+    /// Whether the cursor is over the price plot rather than the x-axis or a pane
+    fn is_in_price_area(&self) -> bool {
+        self.chart_data.ui_data.position_y < self.chart_data.height as i32 - self.chart_data.range_x
+    }
+
     /// this function is to check if the cursor is in y-axis field
     fn is_in_y_field(&self) -> bool {
         self.chart_data.ui_data.position_x >= self.chart_data.width as i32 - self.chart_data.range_y
@@ -1213,71 +2004,205 @@ impl Chart {
         ]
     }
 
-    pub fn calculate_moving_average(
+    /// Lines of every visible indicator over all candles
+    fn compute_indicators(&self) -> Vec<(Indicator, Vec<IndicatorLine>)> {
+        self.indicators
+            .iter()
+            .filter(|indicator| indicator.visible)
+            .map(|indicator| (indicator.clone(), indicator.kind.compute(&self.candle_data)))
+            .collect()
+    }
+
+    /// Draw the overlay indicators on the price scale
+    fn chart_draw_indicators(
         &self,
-        period: usize,
-        min_candle_x: usize,
-        max_candle_x: usize,
-    ) -> Vec<f32> {
-        let mut ma_values = Vec::new();
-        let mut sum = 0.0;
-
-        for i in (min_candle_x - period)..max_candle_x {
-            sum += self.candle_data[i].close; // Add the closing price
-
-            if i - (min_candle_x - period) >= period {
-                sum -= self.candle_data[i - period].close; // Remove the price outside the window
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+        indicator_lines: &[(Indicator, Vec<IndicatorLine>)],
+    ) {
+        let (min_x, max_x) = self.update_x_axis_after_moving();
+        for (indicator, lines) in indicator_lines {
+            if indicator.kind.is_overlay() {
+                draw_indicator_lines(chart, indicator, lines, min_x, max_x);
             }
-            if i - (min_candle_x - period) >= period - 1 {
-                ma_values.push(sum / period as f32); // Calculate the moving average
+        }
+    }
+
+    /// Draw every pane below the price chart in `area`
+    fn chart_draw_panes(
+        &self,
+        area: &DrawingArea<BitMapBackend, Shift>,
+        rects: &[PaneRect],
+        indicator_lines: &[(Indicator, Vec<IndicatorLine>)],
+        min_x: f32,
+        max_x: f32,
+    ) {
+        let mut rest = area.clone();
+        for (pane, rect) in self.panes.panes().iter().zip(rects) {
+            let (pane_area, below) = rest.split_vertically(rect.height);
+            rest = below;
+            match &pane.content {
+                PaneContent::Volume => self.draw_volume_pane(&pane_area, rect, min_x, max_x),
+                PaneContent::Indicator(kind) => {
+                    if let Some((indicator, lines)) = indicator_lines
+                        .iter()
+                        .find(|(indicator, _)| indicator.kind == *kind)
+                    {
+                        self.draw_indicator_pane(&pane_area, rect, indicator, lines, min_x, max_x);
+                    }
+                }
             }
         }
-        ma_values
     }
 
-    fn chart_draw_moving_averages(
+    /// Build the chart of one pane with its own y-axis and a divider on top
+    fn build_pane<'a, 'b>(
+        &self,
+        area: &'a DrawingArea<BitMapBackend<'b>, Shift>,
+        min_x: f32,
+        max_x: f32,
+        (low, high): (f32, f32),
+        y_formatter: &dyn Fn(&f32) -> String,
+    ) -> ChartContext<'a, BitMapBackend<'b>, Cartesian2d<RangedCoordf32, RangedCoordf32>> {
+        let padding = if high > low { (high - low) * 0.05 } else { 1.0 };
+        let mut chart = ChartBuilder::on(area)
+            .right_y_label_area_size(self.chart_data.range_y)
+            .build_cartesian_2d(min_x..max_x, low - padding..high + padding)
+            .expect("error building pane coordinate system");
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .disable_y_mesh()
+            .x_labels(0)
+            .y_labels(3)
+            .y_label_formatter(y_formatter)
+            .label_style(("Arial-Bold", 12).into_font().color(&WHITE))
+            .axis_style(WHITE.stroke_width(1))
+            .draw()
+            .expect("error drawing pane mesh");
+        let _ = area.draw(&PathElement::new(
+            vec![(0, 0), (self.chart_data.width as i32, 0)],
+            GREY.stroke_width(1),
+        ));
+        chart
+    }
+
+    /// Vertical cursor line across a pane, plus the horizontal one and its value
+    /// when the mouse is inside the pane
+    fn draw_pane_cursor(
         &self,
         chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+        rect: &PaneRect,
+        max_x: f32,
+        y_formatter: &dyn Fn(&f32) -> String,
+    ) {
+        if self.chart_data.ui_data.hide_cursor {
+            return;
+        }
+        let (x_position, _) = self.get_mouse_position();
+        let x_position = self.snap_to_candle(x_position);
+        let y_range = chart.y_range();
+        let _ = chart.draw_series(LineSeries::new(
+            vec![(x_position, y_range.start), (x_position, y_range.end)],
+            GREY.stroke_width(1),
+        ));
+
+        let offset = self.chart_data.ui_data.position_y - rect.top as i32;
+        if offset < 0 || offset >= rect.height as i32 {
+            return;
+        }
+        let value =
+            y_range.end - offset as f32 / rect.height as f32 * (y_range.end - y_range.start);
+        let _ = chart.draw_series(LineSeries::new(
+            self.cursor_horizontal_line(value),
+            GREY.stroke_width(1),
+        ));
+        let _ = chart.plotting_area().draw(&Text::new(
+            format!("   {}", y_formatter(&value)),
+            (max_x, value),
+            ("Arial-Bold", 14).into_font().color(&WHITE),
+        ));
+    }
+
+    fn draw_volume_pane(
+        &self,
+        area: &DrawingArea<BitMapBackend, Shift>,
+        rect: &PaneRect,
+        min_x: f32,
+        max_x: f32,
     ) {
         let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
-        // Calculate MA5, MA20, MA100
-        let ma20 = self.calculate_moving_average(20, min_candle_x, max_candle_x);
-        let ma50 = self.calculate_moving_average(50, min_candle_x, max_candle_x);
-        let ma200 = self.calculate_moving_average(200, min_candle_x, max_candle_x);
+        let chart_candles = &self.candle_data[min_candle_x..max_candle_x];
+        let max_volume = self.get_volume_max(chart_candles.to_vec());
+        let formatter = |volume: &f32| {
+            if *volume >= 1000000.0 {
+                format!("{:.1} M", volume / 1000000.0)
+            } else {
+                format!("{:.0} K", volume / 1000.0)
+            }
+        };
+        let mut chart = self.build_pane(area, min_x, max_x, (0.0, max_volume), &formatter);
 
-        // Draw MA5
-        let ma20_points: Vec<(f32, f32)> = self
+        let half_width = 0.4;
+        let _ = chart.draw_series(chart_candles.iter().map(|x| {
+            let color = if x.open < x.close {
+                UP_COLOR
+            } else {
+                DOWN_COLOR
+            };
+            Rectangle::new(
+                [(x.num - half_width, 0.0), (x.num + half_width, x.volume)],
+                color.filled(),
+            )
+        }));
+        let (x_position, _) = self.get_mouse_position();
+        let volume = self
             .candle_data
-            .iter()
-            .enumerate()
-            .enumerate()
-            .filter_map(|(i, _)| ma20.get(i).map(|&ma| ((i + min_candle_x) as f32, ma)))
-            .collect();
-        chart
-            .draw_series(LineSeries::new(ma20_points, RED.stroke_width(1)))
-            .expect("Error drawing MA20");
+            .get(x_position.round().max(0.0) as usize)
+            .map_or(0.0, |candle| candle.volume);
+        let _ = area.draw(&Text::new(
+            format!("  Volume    {}", formatter(&volume)),
+            (0, 5),
+            ("sans-serif", 15).into_font().color(&WHITE),
+        ));
+        self.draw_pane_cursor(&mut chart, rect, max_x, &formatter);
+    }
 
-        // Draw MA20
-        let ma50_points: Vec<(f32, f32)> = self
-            .candle_data
-            .iter()
-            .enumerate()
-            .filter_map(|(i, _)| ma50.get(i).map(|&ma| ((i + min_candle_x) as f32, ma)))
-            .collect();
-        chart
-            .draw_series(LineSeries::new(ma50_points, BLUE.stroke_width(1)))
-            .expect("Error drawing MA50");
+    fn draw_indicator_pane(
+        &self,
+        area: &DrawingArea<BitMapBackend, Shift>,
+        rect: &PaneRect,
+        indicator: &Indicator,
+        lines: &[IndicatorLine],
+        min_x: f32,
+        max_x: f32,
+    ) {
+        // Bounded oscillators keep their scale, the others fit what is visible
+        let bounds = indicator.kind.bounds().unwrap_or_else(|| {
+            lines
+                .iter()
+                .flat_map(|line| visible_points(line, min_x, max_x))
+                .map(|(_, value)| value)
+                .chain(lines.iter().any(|line| line.histogram).then_some(0.0))
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), value| {
+                    (low.min(value), high.max(value))
+                })
+        });
+        let bounds = if bounds.0.is_finite() {
+            bounds
+        } else {
+            (0.0, 1.0)
+        };
+        let formatter = |value: &f32| format!("{value:.2}");
+        let mut chart = self.build_pane(area, min_x, max_x, bounds, &formatter);
+        draw_indicator_lines(&mut chart, indicator, lines, min_x, max_x);
 
-        // Draw MA100
-        let ma200_points: Vec<(f32, f32)> = self
-            .candle_data
-            .iter()
-            .enumerate()
-            .filter_map(|(i, _)| ma200.get(i).map(|&ma| ((i + min_candle_x) as f32, ma)))
-            .collect();
-        chart
-            .draw_series(LineSeries::new(ma200_points, WHITE.stroke_width(1)))
-            .expect("Error drawing MA200");
+        let (x_position, _) = self.get_mouse_position();
+        let _ = area.draw(&Text::new(
+            indicator_legend(indicator, lines, x_position.round().max(0.0) as usize),
+            (0, 5),
+            ("sans-serif", 15).into_font().color(&indicator.color(0)),
+        ));
+        self.draw_pane_cursor(&mut chart, rect, max_x, &formatter);
     }
 
     fn cursor_horizontal_line(&self, y_position: f32) -> Vec<(f32, f32)> {
@@ -1462,6 +2387,100 @@ impl Chart {
     }
 }
 
+/// Length of one bar of `time_frame` in seconds
+fn bar_seconds(time_frame: TimeFrame) -> f64 {
+    time_frame.duration().num_seconds() as f64
+}
+
+/// See [`Chart::anchor_time`], `step` is the bar length in seconds
+fn time_at_position(candles: &[CandleData], step: f64, x: f32) -> Option<f64> {
+    let first = candles.first()?;
+    let last = candles.last()?;
+    let seconds = |candle: &CandleData| candle.time.timestamp() as f64;
+    let x = x as f64;
+    if x <= first.num as f64 {
+        return Some(seconds(first) + (x - first.num as f64) * step);
+    }
+    if x >= last.num as f64 {
+        return Some(seconds(last) + (x - last.num as f64) * step);
+    }
+    let index = candles
+        .partition_point(|candle| candle.num as f64 <= x)
+        .checked_sub(1)?;
+    let (left, right) = (&candles[index], candles.get(index + 1)?);
+    let fraction = (x - left.num as f64) / (right.num - left.num) as f64;
+    Some(seconds(left) + fraction * (seconds(right) - seconds(left)))
+}
+
+/// See [`Chart::anchor_x`], `step` is the bar length in seconds
+fn position_at_time(candles: &[CandleData], step: f64, time: f64) -> Option<f32> {
+    let first = candles.first()?;
+    let last = candles.last()?;
+    let seconds = |candle: &CandleData| candle.time.timestamp() as f64;
+    if time <= seconds(first) {
+        return Some((first.num as f64 + (time - seconds(first)) / step) as f32);
+    }
+    if time >= seconds(last) {
+        return Some((last.num as f64 + (time - seconds(last)) / step) as f32);
+    }
+    let index = candles
+        .partition_point(|candle| seconds(candle) <= time)
+        .checked_sub(1)?;
+    let (left, right) = (&candles[index], candles.get(index + 1)?);
+    let span = seconds(right) - seconds(left);
+    let fraction = if span > 0.0 {
+        (time - seconds(left)) / span
+    } else {
+        0.0
+    };
+    Some((left.num as f64 + fraction * (right.num - left.num) as f64) as f32)
+}
+
+/// Points of `line` between the x values `min_x` and `max_x`
+///
+/// Shifted lines (Ichimoku) may extend past the last candle.
+fn visible_points(line: &IndicatorLine, min_x: f32, max_x: f32) -> Vec<(f32, f32)> {
+    let first = min_x.floor().max(0.0) as usize;
+    let last = max_x.ceil().max(0.0) as usize;
+    (first..=last)
+        .filter_map(|i| line.value_at(i).map(|value| (i as f32, value)))
+        .collect()
+}
+
+/// Draw the lines of an indicator in the coordinates of `chart`
+fn draw_indicator_lines(
+    chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    indicator: &Indicator,
+    lines: &[IndicatorLine],
+    min_x: f32,
+    max_x: f32,
+) {
+    for (index, line) in lines.iter().enumerate() {
+        let color = indicator.color(index);
+        let points = visible_points(line, min_x, max_x);
+        if line.histogram {
+            let _ = chart.draw_series(points.into_iter().map(|(x, value)| {
+                Rectangle::new([(x - 0.3, 0.0), (x + 0.3, value)], color.filled())
+            }));
+        } else {
+            let _ = chart.draw_series(LineSeries::new(points, color.stroke_width(1)));
+        }
+    }
+}
+
+/// Name of an indicator followed by its values at candle `index`
+fn indicator_legend(indicator: &Indicator, lines: &[IndicatorLine], index: usize) -> String {
+    let values = lines
+        .iter()
+        .map(|line| match line.value_at(index) {
+            Some(value) => format!("{value:.2}"),
+            None => "-".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("  ");
+    format!("  {}    {values}", indicator.kind)
+}
+
 /// Interpolates points so that the distance between consecutive points is at most delta_x or delta_y.
 pub fn interpolate_min_distance(points: &[Point], delta_x: f32, delta_y: f32) -> Vec<Point> {
     if points.is_empty() {
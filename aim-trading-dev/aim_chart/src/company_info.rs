@@ -6,3 +6,19 @@ pub struct CompanyInfo {
     pub pb: f64,
     pub eps: f64,
 }
+
+impl CompanyInfo {
+    /// Take the latest ratios reported by a data source, zero when missing
+    pub fn from_ratios(info: &aim_data::vci::company_info::CompanyInfo) -> Self {
+        let Some(ratio) = info.data.company_financial_ratio.ratio.first() else {
+            return Self::default();
+        };
+        Self {
+            roe: ratio.roe.unwrap_or(0.0),
+            roa: ratio.roa.unwrap_or(0.0),
+            pe: ratio.pe.unwrap_or(0.0),
+            pb: ratio.pb.unwrap_or(0.0),
+            eps: ratio.eps.unwrap_or(0.0),
+        }
+    }
+}
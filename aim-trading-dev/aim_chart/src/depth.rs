@@ -0,0 +1,254 @@
+use aim_data::{BookSide, LevelChange, OrderBook};
+use plotters::{
+    backend::BitMapBackend,
+    drawing::IntoDrawingArea,
+    prelude::Text,
+    style::{IntoFont, RGBColor, WHITE},
+};
+use slint::SharedPixelBuffer;
+
+const BID_COLOR: RGBColor = RGBColor(76, 175, 80);
+const ASK_COLOR: RGBColor = RGBColor(244, 67, 54);
+
+/// One price of the depth-of-market ladder
+#[derive(Debug, Clone, PartialEq)]
+pub struct LadderRow {
+    /// In VND
+    pub price: f64,
+    pub bid_volume: i64,
+    pub ask_volume: i64,
+    /// Volume added since the previous snapshot, negative when orders left
+    pub bid_change: i64,
+    pub ask_change: i64,
+    /// Price of the last match
+    pub is_last: bool,
+}
+
+/// `rows` consecutive prices of `book`, highest first, the asks in the upper
+/// half and the bids in the lower one
+///
+/// Prices follow the tick size of the exchange and stop at the ceiling and
+/// floor, so the ladder can be shorter near them.
+pub fn dom_ladder(book: &OrderBook, changes: &[LevelChange], rows: usize) -> Vec<LadderRow> {
+    let Some(anchor) = book
+        .best_ask()
+        .or(book.best_bid())
+        .map(|level| level.price)
+        .or((book.last_price > 0.0).then_some(book.last_price))
+    else {
+        return Vec::new();
+    };
+    let in_range = |price: f64| {
+        price > 0.0
+            && (book.ceiling <= 0.0 || price <= book.ceiling)
+            && (book.floor <= 0.0 || price >= book.floor)
+    };
+
+    // From the best ask up, then from the tick below it down
+    let mut prices = Vec::with_capacity(rows);
+    let mut price = anchor;
+    while prices.len() < rows / 2 && in_range(price) {
+        prices.push(price);
+        price += book.tick_size(price);
+    }
+    prices.reverse();
+    let mut price = anchor - book.tick_size(anchor - 1.0);
+    while prices.len() < rows && in_range(price) {
+        prices.push(price);
+        price -= book.tick_size(price - 1.0);
+    }
+
+    let change = |side: BookSide, price: f64| {
+        changes
+            .iter()
+            .filter(|change| change.side == side && change.price == price)
+            .map(LevelChange::delta)
+            .sum()
+    };
+    prices
+        .into_iter()
+        .map(|price| LadderRow {
+            price,
+            bid_volume: book.volume_at(BookSide::Bid, price),
+            ask_volume: book.volume_at(BookSide::Ask, price),
+            bid_change: change(BookSide::Bid, price),
+            ask_change: change(BookSide::Ask, price),
+            is_last: price == book.last_price,
+        })
+        .collect()
+}
+
+/// Cumulative bid and ask depth of `book` as a stepped area chart
+pub fn depth_chart_render(book: &OrderBook, width: u32, height: u32) -> slint::Image {
+    let mut pixel_buffer = SharedPixelBuffer::new(width, height);
+
+    // Create a scope where all plotters objects will be dropped
+    {
+        let size = (pixel_buffer.width(), pixel_buffer.height());
+        let backend = BitMapBackend::with_buffer(pixel_buffer.make_mut_bytes(), size);
+        let root = backend.into_drawing_area();
+        root.fill(&RGBColor(0, 0, 0)).ok();
+
+        if book.bids.is_empty() && book.asks.is_empty() {
+            root.draw(&Text::new(
+                "No Depth",
+                (width as i32 / 2 - 30, height as i32 / 2),
+                ("sans-serif", 14).into_font().color(&WHITE),
+            ))
+            .ok();
+        } else {
+            render_depth(&root, book);
+        }
+        root.present().ok();
+    } // All plotters objects (backend, root, chart) are dropped here
+
+    slint::Image::from_rgb8(pixel_buffer)
+}
+
+fn render_depth(
+    root: &plotters::drawing::DrawingArea<plotters::backend::BitMapBackend, plotters::coord::Shift>,
+    book: &OrderBook,
+) {
+    use plotters::prelude::*;
+
+    // Prices in thousands of VND, as everywhere in the UI
+    let bids: Vec<(f32, f32)> = book
+        .cumulative(BookSide::Bid)
+        .into_iter()
+        .map(|(price, volume)| (price as f32 / 1000.0, volume as f32))
+        .collect();
+    let asks: Vec<(f32, f32)> = book
+        .cumulative(BookSide::Ask)
+        .into_iter()
+        .map(|(price, volume)| (price as f32 / 1000.0, volume as f32))
+        .collect();
+
+    let prices = bids.iter().chain(&asks).map(|(price, _)| *price);
+    let low = prices.clone().fold(f32::INFINITY, f32::min);
+    let high = prices.fold(f32::NEG_INFINITY, f32::max);
+    // Room on each side, so the outer levels have a width
+    let pad = ((high - low) / 10.0).max(book.tick_size(high as f64 * 1000.0) as f32 / 1000.0);
+    let (low, high) = (low - pad, high + pad);
+    let max_volume = bids
+        .iter()
+        .chain(&asks)
+        .map(|(_, volume)| *volume)
+        .fold(0.0f32, f32::max)
+        * 1.1;
+
+    let chart_area = root.margin(5, 5, 5, 5);
+    let Ok(mut chart) = ChartBuilder::on(&chart_area)
+        .x_label_area_size(18)
+        .y_label_area_size(40)
+        .build_cartesian_2d(low..high, 0f32..max_volume.max(1.0))
+    else {
+        return;
+    };
+    chart
+        .configure_mesh()
+        .disable_mesh()
+        .axis_style(RGBColor(80, 80, 80))
+        .label_style(("sans-serif", 10).into_font().color(&WHITE))
+        .x_labels(5)
+        .y_labels(4)
+        .x_label_formatter(&|price| format!("{price:.2}"))
+        .y_label_formatter(&|volume| format_volume(*volume))
+        .draw()
+        .ok();
+
+    // Each level holds its cumulative volume until the next one further out
+    let steps = |levels: &[(f32, f32)], edge: f32| -> Vec<(f32, f32)> {
+        let mut points = Vec::with_capacity(levels.len() * 2 + 1);
+        for (i, &(price, volume)) in levels.iter().enumerate() {
+            points.push((price, volume));
+            let next = levels.get(i + 1).map_or(edge, |(price, _)| *price);
+            points.push((next, volume));
+        }
+        points
+    };
+    if !bids.is_empty() {
+        chart
+            .draw_series(
+                AreaSeries::new(steps(&bids, low), 0.0, BID_COLOR.mix(0.3)).border_style(BID_COLOR),
+            )
+            .ok();
+    }
+    if !asks.is_empty() {
+        chart
+            .draw_series(
+                AreaSeries::new(steps(&asks, high), 0.0, ASK_COLOR.mix(0.3))
+                    .border_style(ASK_COLOR),
+            )
+            .ok();
+    }
+
+    // Last match, dashed like the reference line of the mini chart
+    let last = book.last_price as f32 / 1000.0;
+    if last > low && last < high {
+        let dash = max_volume / 20.0;
+        let mut y = 0.0f32;
+        let mut dashes = Vec::new();
+        while y < max_volume {
+            dashes.push(PathElement::new(
+                vec![(last, y), (last, (y + dash * 0.6).min(max_volume))],
+                WHITE.stroke_width(1),
+            ));
+            y += dash;
+        }
+        chart.draw_series(dashes).ok();
+    }
+}
+
+pub(crate) fn format_volume(volume: f32) -> String {
+    if volume >= 1_000_000.0 {
+        format!("{:.1}M", volume / 1_000_000.0)
+    } else if volume >= 1_000.0 {
+        format!("{:.0}K", volume / 1_000.0)
+    } else {
+        format!("{volume:.0}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aim_data::Exchange;
+    use aim_data::explorer::vci::market_watch::PriceVolume;
+    use chrono::Utc;
+
+    #[test]
+    fn test_ladder_centres_on_spread() {
+        let level = |price: f64, volume: i64| PriceVolume { price, volume };
+        let book = OrderBook {
+            symbol: "FPT".to_string(),
+            time: Utc::now(),
+            exchange: Some(Exchange::Hose),
+            bids: vec![level(9_990.0, 300), level(9_970.0, 100)],
+            asks: vec![level(10_000.0, 200), level(10_100.0, 500)],
+            last_price: 9_990.0,
+            ref_price: 10_000.0,
+            ceiling: 10_150.0,
+            floor: 9_300.0,
+        };
+        let changes = [LevelChange {
+            side: BookSide::Ask,
+            price: 10_100.0,
+            before: 200,
+            after: 500,
+        }];
+        let ladder = dom_ladder(&book, &changes, 8);
+        let prices: Vec<f64> = ladder.iter().map(|row| row.price).collect();
+        // Steps of 50 from 10,000 up to the ceiling, of 10 below it
+        assert_eq!(
+            prices,
+            [
+                10_150.0, 10_100.0, 10_050.0, 10_000.0, 9_990.0, 9_980.0, 9_970.0, 9_960.0
+            ]
+        );
+        assert_eq!(ladder[1].ask_volume, 500);
+        assert_eq!(ladder[1].ask_change, 300);
+        assert_eq!(ladder[4].bid_volume, 300);
+        assert!(ladder[4].is_last);
+        assert_eq!(ladder.iter().filter(|row| row.is_last).count(), 1);
+    }
+}
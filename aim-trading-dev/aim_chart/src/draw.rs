@@ -1,6 +1,20 @@
 use plotters::style::RGBColor;
 
+use crate::layer::DEFAULT_LAYER;
+
 pub type Point = (f32, f32);
+
+/// Distance in pixels from an object that still hits it
+pub const THREAD_HOLD: f32 = 2.0;
+/// Handles grab from this many hit distances away
+const HANDLE_GRAB: f32 = 3.0;
+/// Average glyph width relative to the font size, to size text boxes
+const GLYPH_WIDTH_RATIO: f32 = 0.6;
+/// Fibonacci retracement levels, fractions of the swing back from its end
+pub const RETRACEMENT_LEVELS: [f32; 7] = [0.0, 0.236, 0.382, 0.5, 0.618, 0.786, 1.0];
+/// Fibonacci extension levels, multiples of the swing projected from the third point
+pub const EXTENSION_LEVELS: [f32; 7] = [0.0, 0.618, 1.0, 1.272, 1.618, 2.0, 2.618];
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub enum DrawType {
     Line((Point, Point)),
@@ -11,14 +25,103 @@ pub enum DrawType {
     VerticalLine(f32),
     HorizontalLine(f32),
     Pen(Vec<Point>),
+    /// Annotation whose top-left corner sits at `anchor`, `size` in pixels
+    Text {
+        anchor: Point,
+        text: String,
+        size: u32,
+    },
+    /// Fibonacci retracement of the swing `start` to `end`, level 1 at `start`
+    FibRetracement {
+        swing: (Point, Point),
+        levels: Vec<f32>,
+    },
+    /// Trend-based Fibonacci extension: the swing `a` to `b` projected from `c`
+    FibExtension {
+        points: (Point, Point, Point),
+        levels: Vec<f32>,
+    },
+    /// Channel along `start` to `end`, its parallel side passes through the third point
+    Channel((Point, Point, Point)),
+    /// Andrews pitchfork: median line from the pivot through the middle of the
+    /// other two points, prongs parallel to it
+    Pitchfork((Point, Point, Point)),
+    /// Price and time span, labelled with the change and the bar count
+    Range((Point, Point)),
     #[default]
     Empty,
 }
 
-#[derive(Debug, Clone, Default)]
+/// Levels typed in the toolbar, separated by spaces or commas, or `default`
+/// when none can be read
+pub fn parse_levels(text: &str, default: &[f32]) -> Vec<f32> {
+    let levels: Vec<f32> = text
+        .split(|c: char| c.is_whitespace() || c == ',' || c == ';')
+        .filter_map(|level| level.parse().ok())
+        .filter(|level: &f32| level.is_finite())
+        .collect();
+    if levels.is_empty() {
+        default.to_vec()
+    } else {
+        levels
+    }
+}
+
+fn write_points(buf: &mut Vec<u8>, points: &[Point]) {
+    for point in points {
+        buf.extend_from_slice(&point.0.to_le_bytes());
+        buf.extend_from_slice(&point.1.to_le_bytes());
+    }
+}
+
+fn write_levels(buf: &mut Vec<u8>, levels: &[f32]) {
+    buf.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+    for level in levels {
+        buf.extend_from_slice(&level.to_le_bytes());
+    }
+}
+
+fn read_f32(data: &[u8], pos: &mut usize) -> Option<f32> {
+    let value = f32::from_le_bytes(data.get(*pos..*pos + 4)?.try_into().ok()?);
+    *pos += 4;
+    Some(value)
+}
+
+fn read_point(data: &[u8], pos: &mut usize) -> Option<Point> {
+    Some((read_f32(data, pos)?, read_f32(data, pos)?))
+}
+
+fn read_levels(data: &[u8], pos: &mut usize) -> Option<Vec<f32>> {
+    let count = u32::from_le_bytes(data.get(*pos..*pos + 4)?.try_into().ok()?) as usize;
+    *pos += 4;
+    (0..count).map(|_| read_f32(data, pos)).collect()
+}
+
+/// Price of retracement `level` on the swing `start` to `end`
+fn retracement_price(start: Point, end: Point, level: f32) -> f32 {
+    end.1 + (start.1 - end.1) * level
+}
+
+/// Whether `point` is within the thresholds of the segment `a` to `b`
+fn near_segment(point: Point, a: Point, b: Point, x_thresh_hold: f32, y_thresh_hold: f32) -> bool {
+    let (px, py) = point;
+    let dx = b.0 - a.0;
+    let dy = b.1 - a.1;
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((px - a.0) * dx + (py - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    (px - (a.0 + t * dx)).abs() < x_thresh_hold && (py - (a.1 + t * dy)).abs() < y_thresh_hold
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct DrawObject {
     pub draw_type: DrawType,
     pub color: RGBColor,
+    /// Name of the [`crate::DrawingLayer`] the drawing belongs to
+    pub layer: String,
 }
 
 // Manual serialization for DrawType
@@ -76,6 +179,36 @@ pub fn draw_type_write_to_bytes(draw_type: &DrawType, buf: &mut Vec<u8>) {
                 buf.extend_from_slice(&p.1.to_le_bytes());
             }
         }
+        DrawType::Text { anchor, text, size } => {
+            buf.push(9);
+            buf.extend_from_slice(&anchor.0.to_le_bytes());
+            buf.extend_from_slice(&anchor.1.to_le_bytes());
+            buf.extend_from_slice(&size.to_le_bytes());
+            buf.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            buf.extend_from_slice(text.as_bytes());
+        }
+        DrawType::FibRetracement { swing, levels } => {
+            buf.push(10);
+            write_points(buf, &[swing.0, swing.1]);
+            write_levels(buf, levels);
+        }
+        DrawType::FibExtension { points, levels } => {
+            buf.push(11);
+            write_points(buf, &[points.0, points.1, points.2]);
+            write_levels(buf, levels);
+        }
+        DrawType::Channel((a, b, c)) => {
+            buf.push(12);
+            write_points(buf, &[*a, *b, *c]);
+        }
+        DrawType::Pitchfork((a, b, c)) => {
+            buf.push(13);
+            write_points(buf, &[*a, *b, *c]);
+        }
+        DrawType::Range((a, b)) => {
+            buf.push(14);
+            write_points(buf, &[*a, *b]);
+        }
         DrawType::Empty => {
             buf.push(0);
         }
@@ -147,6 +280,60 @@ pub fn draw_type_read_from_bytes(data: &[u8]) -> Option<(DrawType, usize)> {
             }
             Some((DrawType::Pen(points), pos))
         }
+        9 => {
+            if data.len() < pos + 16 {
+                return None;
+            }
+            let x = f32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+            pos += 4;
+            let y = f32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+            pos += 4;
+            let size = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+            pos += 4;
+            let len = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            let text = String::from_utf8(data.get(pos..pos + len)?.to_vec()).ok()?;
+            pos += len;
+            Some((
+                DrawType::Text {
+                    anchor: (x, y),
+                    text,
+                    size,
+                },
+                pos,
+            ))
+        }
+        10 => {
+            let swing = (read_point(data, &mut pos)?, read_point(data, &mut pos)?);
+            let levels = read_levels(data, &mut pos)?;
+            Some((DrawType::FibRetracement { swing, levels }, pos))
+        }
+        11 => {
+            let points = (
+                read_point(data, &mut pos)?,
+                read_point(data, &mut pos)?,
+                read_point(data, &mut pos)?,
+            );
+            let levels = read_levels(data, &mut pos)?;
+            Some((DrawType::FibExtension { points, levels }, pos))
+        }
+        12 | 13 => {
+            let points = (
+                read_point(data, &mut pos)?,
+                read_point(data, &mut pos)?,
+                read_point(data, &mut pos)?,
+            );
+            let draw_type = if tag == 12 {
+                DrawType::Channel(points)
+            } else {
+                DrawType::Pitchfork(points)
+            };
+            Some((draw_type, pos))
+        }
+        14 => {
+            let points = (read_point(data, &mut pos)?, read_point(data, &mut pos)?);
+            Some((DrawType::Range(points), pos))
+        }
         0 => Some((DrawType::Empty, pos)),
         _ => None,
     }
@@ -169,11 +356,16 @@ impl DrawObject {
         let g = data[used1 + 1];
         let b = data[used1 + 2];
         let color = RGBColor(r, g, b);
-        Some((Self { draw_type, color }, used1 + 3))
+        Some((Self::new(draw_type, color), used1 + 3))
     }
 
+    /// A drawing on the default layer
     pub fn new(draw_type: DrawType, color: RGBColor) -> Self {
-        Self { draw_type, color }
+        Self {
+            draw_type,
+            color,
+            layer: DEFAULT_LAYER.to_string(),
+        }
     }
 
     pub fn hit_cursor(&self, point: Point, x_thresh_hold: f32, y_thresh_hold: f32) -> bool {
@@ -279,10 +471,238 @@ impl DrawObject {
                     }
                 })
             }
+            DrawType::Text { anchor, text, size } => {
+                // Thresholds are THREAD_HOLD pixels in chart units
+                let width = text.chars().count() as f32 * *size as f32 * GLYPH_WIDTH_RATIO;
+                let width = width * x_thresh_hold / THREAD_HOLD;
+                let height = *size as f32 * y_thresh_hold / THREAD_HOLD;
+                let (px, py) = point;
+                px >= anchor.0 - x_thresh_hold
+                    && px <= anchor.0 + width + x_thresh_hold
+                    && py <= anchor.1 + y_thresh_hold
+                    && py >= anchor.1 - height - y_thresh_hold
+            }
+            DrawType::FibRetracement { .. }
+            | DrawType::FibExtension { .. }
+            | DrawType::Channel(_)
+            | DrawType::Pitchfork(_)
+            | DrawType::Range(_) => self
+                .segments()
+                .into_iter()
+                .any(|(a, b)| near_segment(point, a, b, x_thresh_hold, y_thresh_hold)),
             _ => false,
         }
     }
 
+    /// Straight pieces the multi-line tools are drawn with
+    pub fn segments(&self) -> Vec<(Point, Point)> {
+        match &self.draw_type {
+            DrawType::FibRetracement {
+                swing: (start, end),
+                levels,
+            } => {
+                let (left, right) = (start.0.min(end.0), start.0.max(end.0));
+                let mut segments: Vec<(Point, Point)> = levels
+                    .iter()
+                    .map(|level| {
+                        let price = retracement_price(*start, *end, *level);
+                        ((left, price), (right, price))
+                    })
+                    .collect();
+                segments.push((*start, *end));
+                segments
+            }
+            DrawType::FibExtension {
+                points: (a, b, c),
+                levels,
+            } => {
+                // Levels run from the third point as far as the swing is long
+                let right = c.0 + (b.0 - a.0).abs();
+                let mut segments: Vec<(Point, Point)> = levels
+                    .iter()
+                    .map(|level| {
+                        let price = c.1 + (b.1 - a.1) * level;
+                        ((c.0, price), (right, price))
+                    })
+                    .collect();
+                segments.push((*a, *b));
+                segments.push((*b, *c));
+                segments
+            }
+            DrawType::Channel((start, end, through)) => {
+                let dx = end.0 - start.0;
+                let base = if dx == 0.0 {
+                    start.1
+                } else {
+                    start.1 + (end.1 - start.1) * (through.0 - start.0) / dx
+                };
+                let offset = through.1 - base;
+                vec![
+                    (*start, *end),
+                    ((start.0, start.1 + offset), (end.0, end.1 + offset)),
+                ]
+            }
+            DrawType::Pitchfork((pivot, b, c)) => {
+                let middle = ((b.0 + c.0) / 2.0, (b.1 + c.1) / 2.0);
+                let (dx, dy) = (middle.0 - pivot.0, middle.1 - pivot.1);
+                vec![
+                    (*pivot, (middle.0 + dx, middle.1 + dy)),
+                    (*b, (b.0 + dx, b.1 + dy)),
+                    (*c, (c.0 + dx, c.1 + dy)),
+                    (*b, *c),
+                ]
+            }
+            DrawType::Range((start, end)) => {
+                let corners = [*start, (end.0, start.1), *end, (start.0, end.1)];
+                (0..4).map(|i| (corners[i], corners[(i + 1) % 4])).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// [`DrawObject::segments`] cut to the chart area, the ones outside dropped
+    pub fn visible_segments(
+        &self,
+        x_min: f32,
+        x_max: f32,
+        y_min: f32,
+        y_max: f32,
+    ) -> Vec<(Point, Point)> {
+        self.segments()
+            .into_iter()
+            .filter_map(|(mut a, mut b)| {
+                cohen_sutherland_clip(&mut a, &mut b, x_min, x_max, y_min, y_max).then_some((a, b))
+            })
+            .collect()
+    }
+
+    /// Left end of every Fibonacci level with its label, e.g. `0.618 (23.45)`
+    pub fn level_labels(&self) -> Vec<(Point, String)> {
+        let levels: Vec<(f32, f32, f32)> = match &self.draw_type {
+            DrawType::FibRetracement {
+                swing: (start, end),
+                levels,
+            } => levels
+                .iter()
+                .map(|level| {
+                    let price = retracement_price(*start, *end, *level);
+                    (*level, start.0.min(end.0), price)
+                })
+                .collect(),
+            DrawType::FibExtension {
+                points: (a, b, c),
+                levels,
+            } => levels
+                .iter()
+                .map(|level| (*level, c.0, c.1 + (b.1 - a.1) * level))
+                .collect(),
+            _ => vec![],
+        };
+        levels
+            .into_iter()
+            .map(|(level, x, price)| ((x, price), format!("{level} ({price:.02})")))
+            .collect()
+    }
+
+    /// Points that can be dragged to reshape the object
+    ///
+    /// Rectangles and ovals have the corners of their bounding box, in the
+    /// order start, (end.x, start.y), end, (start.x, end.y).
+    pub fn vertices(&self) -> Vec<Point> {
+        match &self.draw_type {
+            DrawType::Line((start, end))
+            | DrawType::Arrow((start, end))
+            | DrawType::Ruler((start, end)) => vec![*start, *end],
+            DrawType::Rectangle((start, end)) | DrawType::Oval((start, end)) => {
+                vec![*start, (end.0, start.1), *end, (start.0, end.1)]
+            }
+            DrawType::FibRetracement { swing: (a, b), .. } | DrawType::Range((a, b)) => {
+                vec![*a, *b]
+            }
+            DrawType::FibExtension {
+                points: (a, b, c), ..
+            }
+            | DrawType::Channel((a, b, c))
+            | DrawType::Pitchfork((a, b, c)) => vec![*a, *b, *c],
+            _ => vec![],
+        }
+    }
+
+    /// The points of the multi-line tools, in [`DrawObject::vertices`] order
+    fn tool_points_mut(&mut self) -> Vec<&mut Point> {
+        match &mut self.draw_type {
+            DrawType::FibRetracement { swing: (a, b), .. } | DrawType::Range((a, b)) => {
+                vec![a, b]
+            }
+            DrawType::FibExtension {
+                points: (a, b, c), ..
+            }
+            | DrawType::Channel((a, b, c))
+            | DrawType::Pitchfork((a, b, c)) => vec![a, b, c],
+            _ => vec![],
+        }
+    }
+
+    /// Every x coordinate of the object, always in the same order, so they
+    /// can be anchored to candle times and put back on other candles
+    pub fn xs_mut(&mut self) -> Vec<&mut f32> {
+        if !self.segments().is_empty() {
+            return self
+                .tool_points_mut()
+                .into_iter()
+                .map(|point| &mut point.0)
+                .collect();
+        }
+        match &mut self.draw_type {
+            DrawType::Line((a, b))
+            | DrawType::Arrow((a, b))
+            | DrawType::Rectangle((a, b))
+            | DrawType::Oval((a, b))
+            | DrawType::Ruler((a, b)) => vec![&mut a.0, &mut b.0],
+            DrawType::VerticalLine(x) => vec![x],
+            DrawType::Pen(points) => points.iter_mut().map(|point| &mut point.0).collect(),
+            DrawType::Text { anchor, .. } => vec![&mut anchor.0],
+            _ => vec![],
+        }
+    }
+
+    /// Vertex whose handle is under `point`
+    pub fn vertex_at(&self, point: Point, x_thresh_hold: f32, y_thresh_hold: f32) -> Option<usize> {
+        self.vertices().iter().position(|vertex| {
+            (vertex.0 - point.0).abs() < HANDLE_GRAB * x_thresh_hold
+                && (vertex.1 - point.1).abs() < HANDLE_GRAB * y_thresh_hold
+        })
+    }
+
+    /// Move vertex `index` of [`DrawObject::vertices`], the others stay put
+    pub fn translate_vertex(&mut self, index: usize, dx: f32, dy: f32) {
+        match &mut self.draw_type {
+            DrawType::Line((start, end))
+            | DrawType::Arrow((start, end))
+            | DrawType::Ruler((start, end)) => {
+                let vertex = if index == 0 { start } else { end };
+                vertex.0 += dx;
+                vertex.1 += dy;
+            }
+            DrawType::Rectangle((start, end)) | DrawType::Oval((start, end)) => {
+                let (x, y) = match index {
+                    0 => (&mut start.0, &mut start.1),
+                    1 => (&mut end.0, &mut start.1),
+                    2 => (&mut end.0, &mut end.1),
+                    _ => (&mut start.0, &mut end.1),
+                };
+                *x += dx;
+                *y += dy;
+            }
+            _ => {
+                if let Some(vertex) = self.tool_points_mut().into_iter().nth(index) {
+                    vertex.0 += dx;
+                    vertex.1 += dy;
+                }
+            }
+        }
+    }
+
     pub fn translate(&mut self, dx: f32, dy: f32) {
         match &mut self.draw_type {
             DrawType::Line((start, end)) | DrawType::Arrow((start, end)) => {
@@ -309,11 +729,27 @@ impl DrawObject {
                     p.1 += dy;
                 }
             }
-            _ => {}
+            DrawType::Text { anchor, .. } => {
+                anchor.0 += dx;
+                anchor.1 += dy;
+            }
+            _ => {
+                for point in self.tool_points_mut() {
+                    point.0 += dx;
+                    point.1 += dy;
+                }
+            }
         }
     }
 
     pub fn clipped(&mut self, x_min: f32, x_max: f32, y_min: f32, y_max: f32) {
+        // Multi-line tools keep their points, their segments are cut when drawn
+        if !self.segments().is_empty() {
+            if self.visible_segments(x_min, x_max, y_min, y_max).is_empty() {
+                self.draw_type = DrawType::Empty;
+            }
+            return;
+        }
         match &mut self.draw_type {
             DrawType::Line((start, end)) | DrawType::Arrow((start, end)) => {
                 // Cohen–Sutherland line clipping algorithm (simple version)
@@ -353,15 +789,17 @@ impl DrawObject {
                     *end = (max_x, max_y);
                 }
             }
-            DrawType::VerticalLine(x) => {
-                if *x < x_min || *x > x_max {
-                    self.draw_type = DrawType::Empty;
-                }
+            DrawType::VerticalLine(x) if *x < x_min || *x > x_max => {
+                self.draw_type = DrawType::Empty;
             }
-            DrawType::HorizontalLine(y) => {
-                if *y < y_min || *y > y_max {
-                    self.draw_type = DrawType::Empty;
-                }
+            DrawType::HorizontalLine(y) if *y < y_min || *y > y_max => {
+                self.draw_type = DrawType::Empty;
+            }
+            // Text is hidden as a whole once its anchor leaves the chart
+            DrawType::Text { anchor, .. }
+                if anchor.0 < x_min || anchor.0 > x_max || anchor.1 < y_min || anchor.1 > y_max =>
+            {
+                self.draw_type = DrawType::Empty;
             }
             DrawType::Pen(points) => {
                 // Remove points outside the bounds
@@ -486,3 +924,73 @@ fn cohen_sutherland_clip(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drag_rectangle_corner() {
+        let mut object = DrawObject::new(
+            DrawType::Rectangle(((0.0, 0.0), (10.0, 10.0))),
+            RGBColor(255, 0, 0),
+        );
+        // Grab the (end.x, start.y) corner and pull it out
+        let vertex = object.vertex_at((10.5, 0.5), 0.5, 0.5);
+        assert_eq!(vertex, Some(1));
+        object.translate_vertex(1, 5.0, -2.0);
+        assert_eq!(
+            object.draw_type,
+            DrawType::Rectangle(((0.0, -2.0), (15.0, 10.0)))
+        );
+        assert_eq!(object.vertex_at((5.0, 5.0), 0.5, 0.5), None);
+    }
+
+    #[test]
+    fn test_text_hit_and_bytes() {
+        let object = DrawObject::new(
+            DrawType::Text {
+                anchor: (10.0, 100.0),
+                text: "Đỉnh".to_string(),
+                size: 20,
+            },
+            RGBColor(0, 255, 0),
+        );
+        // One chart unit per pixel: the box is 48 x 20 below and right of the anchor
+        assert!(object.hit_cursor((50.0, 85.0), THREAD_HOLD, THREAD_HOLD));
+        assert!(!object.hit_cursor((70.0, 85.0), THREAD_HOLD, THREAD_HOLD));
+        assert!(!object.hit_cursor((20.0, 110.0), THREAD_HOLD, THREAD_HOLD));
+
+        let mut buf = Vec::new();
+        object.write_to_bytes(&mut buf);
+        let (read, used) = DrawObject::read_from_bytes(&buf).unwrap();
+        assert_eq!(read.draw_type, object.draw_type);
+        assert_eq!(used, buf.len());
+    }
+
+    #[test]
+    fn test_fibonacci_levels_and_clipping() {
+        let mut object = DrawObject::new(
+            DrawType::FibRetracement {
+                swing: ((0.0, 10.0), (10.0, 20.0)),
+                levels: parse_levels("0, 0.5 1 x", &RETRACEMENT_LEVELS),
+            },
+            RGBColor(0, 0, 255),
+        );
+        let labels = object.level_labels();
+        assert_eq!(labels[1], ((0.0, 15.0), "0.5 (15.00)".to_string()));
+        assert!(object.hit_cursor((4.0, 15.2), 0.5, 0.5));
+        assert!(!object.hit_cursor((4.0, 17.0), 0.5, 0.5));
+
+        let mut buf = Vec::new();
+        object.write_to_bytes(&mut buf);
+        let (read, used) = DrawObject::read_from_bytes(&buf).unwrap();
+        assert_eq!(read.draw_type, object.draw_type);
+        assert_eq!(used, buf.len());
+
+        // Only the 0.5 level and the swing cross the view
+        assert_eq!(object.visible_segments(0.0, 10.0, 14.0, 16.0).len(), 2);
+        object.clipped(20.0, 30.0, 0.0, 100.0);
+        assert!(object.is_empty());
+    }
+}
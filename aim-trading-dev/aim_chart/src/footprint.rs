@@ -0,0 +1,396 @@
+//! Order flow of a chart, from the trade tape
+//!
+//! [`OrderFlow`] keeps the prints of the charted stock on the price scale of
+//! its candles. From them the chart draws a [`VolumeProfile`] of the visible
+//! range, with its point of control and value area, and footprint cells per
+//! candle, both split by the side that crossed the spread.
+
+use std::collections::VecDeque;
+
+use aim_data::{Aggressor, Trade};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+use crate::CandleData;
+
+/// Trades a chart keeps, the oldest are dropped first
+pub const ORDER_FLOW_CAPACITY: usize = 50_000;
+
+/// Share of the profile volume inside the value area
+pub const VALUE_AREA_SHARE: f32 = 0.7;
+
+/// One print of the tape in chart units
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlowTrade {
+    pub id: u64,
+    pub time: DateTime<Utc>,
+    /// On the price scale of the candles
+    pub price: f32,
+    pub volume: f32,
+    pub side: Aggressor,
+}
+
+/// Volume traded in one price row
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProfileRow {
+    /// Middle of the row
+    pub price: f32,
+    pub buy: f32,
+    pub sell: f32,
+    /// Auction matches, which have no side
+    pub neutral: f32,
+}
+
+impl ProfileRow {
+    pub fn total(&self) -> f32 {
+        self.buy + self.sell + self.neutral
+    }
+
+    /// Buy minus sell volume
+    pub fn delta(&self) -> f32 {
+        self.buy - self.sell
+    }
+
+    fn add(&mut self, trade: &FlowTrade) {
+        match trade.side {
+            Aggressor::Buy => self.buy += trade.volume,
+            Aggressor::Sell => self.sell += trade.volume,
+            Aggressor::Unknown => self.neutral += trade.volume,
+        }
+    }
+}
+
+/// Volume by price of a set of trades
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VolumeProfile {
+    pub row_size: f32,
+    /// Lowest price first, every row from the lowest trade to the highest
+    pub rows: Vec<ProfileRow>,
+}
+
+impl VolumeProfile {
+    /// Put each trade in the row of `row_size` its price rounds to
+    pub fn build<'a>(trades: impl IntoIterator<Item = &'a FlowTrade>, row_size: f32) -> Self {
+        let trades: Vec<&FlowTrade> = trades.into_iter().collect();
+        let index = |price: f32| (price / row_size).round() as i64;
+        let (Some(low), Some(high)) = (
+            trades.iter().map(|trade| index(trade.price)).min(),
+            trades.iter().map(|trade| index(trade.price)).max(),
+        ) else {
+            return Self {
+                row_size,
+                rows: Vec::new(),
+            };
+        };
+        let mut rows: Vec<ProfileRow> = (low..=high)
+            .map(|i| ProfileRow {
+                price: i as f32 * row_size,
+                ..Default::default()
+            })
+            .collect();
+        for trade in trades {
+            rows[(index(trade.price) - low) as usize].add(trade);
+        }
+        Self { row_size, rows }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn total(&self) -> f32 {
+        self.rows.iter().map(ProfileRow::total).sum()
+    }
+
+    /// Volume of the heaviest row
+    pub fn max_row_volume(&self) -> f32 {
+        self.rows.iter().map(ProfileRow::total).fold(0.0, f32::max)
+    }
+
+    /// Row with the most volume, the point of control
+    pub fn poc(&self) -> Option<&ProfileRow> {
+        self.poc_index().map(|index| &self.rows[index])
+    }
+
+    fn poc_index(&self) -> Option<usize> {
+        self.rows
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total().total_cmp(&b.total()))
+            .map(|(index, _)| index)
+    }
+
+    /// Lowest and highest row price of the value area
+    ///
+    /// The area grows from the point of control towards the heavier
+    /// neighbouring row until it holds [`VALUE_AREA_SHARE`] of the volume.
+    pub fn value_area(&self) -> Option<(f32, f32)> {
+        let poc = self.poc_index()?;
+        let target = self.total() * VALUE_AREA_SHARE;
+        let (mut low, mut high) = (poc, poc);
+        let mut volume = self.rows[poc].total();
+        while volume < target && (low > 0 || high + 1 < self.rows.len()) {
+            let below = low.checked_sub(1).map(|i| self.rows[i].total());
+            let above = self.rows.get(high + 1).map(ProfileRow::total);
+            // Upwards on a tie, or when there is nothing below
+            if above >= below {
+                high += 1;
+                volume += above.unwrap_or(0.0);
+            } else {
+                low -= 1;
+                volume += below.unwrap_or(0.0);
+            }
+        }
+        Some((self.rows[low].price, self.rows[high].price))
+    }
+}
+
+/// Row size splitting `span` of price in at most `max_rows` rows, a whole
+/// number of `tick`s
+pub fn row_size(tick: f32, span: f32, max_rows: usize) -> f32 {
+    let rough = span / max_rows.max(1) as f32;
+    if tick <= 0.0 {
+        return rough;
+    }
+    (rough / tick).ceil().max(1.0) * tick
+}
+
+/// Trades of a chart and which order flow views are shown
+#[derive(Debug, Clone, Default)]
+pub struct OrderFlow {
+    pub show_profile: bool,
+    pub show_footprint: bool,
+    /// Oldest first
+    trades: VecDeque<FlowTrade>,
+    /// Trading day of `trades`
+    date: Option<NaiveDate>,
+}
+
+impl OrderFlow {
+    /// Either view is shown, so the chart needs the tape
+    pub fn is_visible(&self) -> bool {
+        self.show_profile || self.show_footprint
+    }
+
+    pub fn trades(&self) -> impl Iterator<Item = &FlowTrade> {
+        self.trades.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.trades.clear();
+        self.date = None;
+    }
+
+    /// Add the prints of `trades`, oldest first, past the last one kept
+    ///
+    /// `date` is the trading day of the tape, whose times are only times of
+    /// day, and prices are divided by `scale` to match the candles. The
+    /// trades of another day are dropped first, as ids start over with
+    /// every session.
+    pub fn push(&mut self, trades: &[Trade], date: NaiveDate, scale: f32) {
+        if self.date != Some(date) {
+            self.trades.clear();
+            self.date = Some(date);
+        }
+        let last = self.trades.back().map(|trade| trade.id);
+        for trade in trades
+            .iter()
+            .filter(|trade| last.is_none_or(|last| trade.id > last))
+        {
+            let Some(time) = trade.timestamp(date) else {
+                continue;
+            };
+            self.trades.push_back(FlowTrade {
+                id: trade.id,
+                time,
+                price: trade.price as f32 / scale,
+                volume: trade.volume as f32,
+                side: trade.side,
+            });
+        }
+        while self.trades.len() > ORDER_FLOW_CAPACITY {
+            self.trades.pop_front();
+        }
+    }
+
+    /// Smallest gap between two traded prices, the tick size of the tape
+    ///
+    /// Rounded to 0.0001 to drop the noise of subtracting `f32` prices, so
+    /// rows of whole ticks line up with the traded prices.
+    pub fn price_step(&self) -> Option<f32> {
+        let mut prices: Vec<f32> = self.trades.iter().map(|trade| trade.price).collect();
+        prices.sort_by(f32::total_cmp);
+        prices.dedup();
+        prices
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .map(|gap| (gap * 10_000.0).round() / 10_000.0)
+            .filter(|gap| *gap > 0.0)
+            .min_by(f32::total_cmp)
+    }
+
+    /// Profile of the trades from `from` up to `to`
+    pub fn profile(&self, from: DateTime<Utc>, to: DateTime<Utc>, row_size: f32) -> VolumeProfile {
+        VolumeProfile::build(
+            self.trades
+                .iter()
+                .filter(|trade| trade.time >= from && trade.time < to),
+            row_size,
+        )
+    }
+
+    /// Footprint of `candles`, one profile per candle
+    ///
+    /// A candle holds the trades from its time to the next candle's, the last
+    /// one those of the following `step` seconds.
+    pub fn footprint(
+        &self,
+        candles: &[CandleData],
+        step: i64,
+        row_size: f32,
+    ) -> Vec<VolumeProfile> {
+        let mut buckets: Vec<Vec<&FlowTrade>> = vec![Vec::new(); candles.len()];
+        let Some(last) = candles.last() else {
+            return Vec::new();
+        };
+        let end = last.time + Duration::seconds(step);
+        for trade in self.trades.iter().filter(|trade| trade.time < end) {
+            let index = candles.partition_point(|candle| candle.time <= trade.time);
+            if let Some(index) = index.checked_sub(1) {
+                buckets[index].push(trade);
+            }
+        }
+        buckets
+            .into_iter()
+            .map(|trades| VolumeProfile::build(trades, row_size))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aim_data::calendar::from_vn_time;
+
+    fn trade(id: u64, time: &str, price: f64, volume: i64, side: Aggressor) -> Trade {
+        Trade {
+            id,
+            time: time.to_string(),
+            price,
+            volume,
+            side,
+            large: false,
+        }
+    }
+
+    #[test]
+    fn test_profile_poc_and_value_area() {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 4).unwrap();
+        let mut flow = OrderFlow::default();
+        flow.push(
+            &[
+                trade(1, "09:15:00", 25_000.0, 100, Aggressor::Unknown),
+                trade(2, "09:20:00", 25_050.0, 500, Aggressor::Buy),
+                trade(3, "09:21:00", 25_100.0, 300, Aggressor::Sell),
+                trade(4, "09:22:00", 25_150.0, 50, Aggressor::Buy),
+                trade(5, "09:30:00", 25_050.0, 50, Aggressor::Sell),
+            ],
+            date,
+            1000.0,
+        );
+        // Already kept, and unparsable
+        flow.push(
+            &[
+                trade(5, "09:30:00", 25_050.0, 50, Aggressor::Sell),
+                trade(6, "later", 25_050.0, 50, Aggressor::Sell),
+            ],
+            date,
+            1000.0,
+        );
+        assert_eq!(flow.len(), 5);
+        let tick = flow.price_step().unwrap();
+        assert!((tick - 0.05).abs() < 1e-4);
+
+        let profile = VolumeProfile::build(flow.trades(), tick);
+        assert_eq!(profile.rows.len(), 4);
+        let poc = profile.poc().unwrap();
+        assert!((poc.price - 25.05).abs() < 1e-4);
+        assert_eq!((poc.buy, poc.sell, poc.delta()), (500.0, 50.0, 450.0));
+        // 550 + 300 reaches 70% of 1000
+        let (low, high) = profile.value_area().unwrap();
+        assert!((low - 25.05).abs() < 1e-4 && (high - 25.10).abs() < 1e-4);
+
+        let from = from_vn_time(date.and_hms_opt(9, 20, 0).unwrap());
+        let to = from_vn_time(date.and_hms_opt(9, 30, 0).unwrap());
+        assert_eq!(flow.profile(from, to, tick).total(), 850.0);
+        // Rows of two ticks
+        assert!((row_size(tick, 0.5, 5) - 0.1).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_footprint_follows_candles() {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 4).unwrap();
+        let candle = |num: usize, hour: u32, minute: u32| CandleData {
+            num: num as f32,
+            time: from_vn_time(date.and_hms_opt(hour, minute, 0).unwrap()),
+            open: 25.0,
+            high: 25.1,
+            low: 25.0,
+            close: 25.05,
+            volume: 0.0,
+        };
+        // 15 minute bars around the lunch break
+        let candles = [candle(0, 11, 0), candle(1, 11, 15), candle(2, 13, 0)];
+        let mut flow = OrderFlow::default();
+        flow.push(
+            &[
+                trade(1, "10:59:59", 25_000.0, 100, Aggressor::Buy),
+                trade(2, "11:14:59", 25_000.0, 200, Aggressor::Buy),
+                trade(3, "11:29:00", 25_050.0, 300, Aggressor::Sell),
+                trade(4, "13:05:00", 25_100.0, 400, Aggressor::Buy),
+                trade(5, "13:15:00", 25_100.0, 500, Aggressor::Buy),
+            ],
+            date,
+            1000.0,
+        );
+        let footprint = flow.footprint(&candles, 15 * 60, 0.05);
+        let totals: Vec<f32> = footprint.iter().map(VolumeProfile::total).collect();
+        assert_eq!(totals, [200.0, 300.0, 400.0]);
+        assert_eq!(footprint[1].rows[0].sell, 300.0);
+    }
+
+    #[test]
+    fn test_new_session_starts_over() {
+        let monday = NaiveDate::from_ymd_opt(2025, 3, 3).unwrap();
+        let tuesday = NaiveDate::from_ymd_opt(2025, 3, 4).unwrap();
+        let mut flow = OrderFlow::default();
+        flow.push(
+            &[trade(41, "14:45:00", 25_000.0, 100, Aggressor::Unknown)],
+            monday,
+            1000.0,
+        );
+        // Ids start over with the session
+        flow.push(
+            &[
+                trade(1, "09:15:00", 25_100.0, 200, Aggressor::Buy),
+                trade(2, "09:16:00", 25_150.0, 300, Aggressor::Sell),
+            ],
+            tuesday,
+            1000.0,
+        );
+        let ids: Vec<u64> = flow.trades().map(|trade| trade.id).collect();
+        assert_eq!(ids, [1, 2]);
+        let first = flow.trades().next().unwrap();
+        assert_eq!(
+            first.time,
+            from_vn_time(tuesday.and_hms_opt(9, 15, 0).unwrap())
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::VecDeque;
+
+use crate::draw::DrawObject;
+
+/// Edits kept for undo when no other depth is set
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+/// One reversible change to the drawings of a chart
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditCommand {
+    /// `object` was added at `index`
+    Add { index: usize, object: DrawObject },
+    /// The object at `index` was moved, resized or restyled
+    Modify {
+        index: usize,
+        before: DrawObject,
+        after: DrawObject,
+    },
+    /// `object` was deleted from `index`
+    Delete { index: usize, object: DrawObject },
+    /// The drawings were cleared from `before` down to `after`, the ones
+    /// on locked or hidden layers
+    Clear {
+        before: Vec<DrawObject>,
+        after: Vec<DrawObject>,
+    },
+}
+
+impl EditCommand {
+    fn apply(&self, drawings: &mut Vec<DrawObject>) {
+        match self {
+            EditCommand::Add { index, object } => {
+                drawings.insert((*index).min(drawings.len()), object.clone());
+            }
+            EditCommand::Modify { index, after, .. } => {
+                if let Some(object) = drawings.get_mut(*index) {
+                    *object = after.clone();
+                }
+            }
+            EditCommand::Delete { index, .. } => {
+                if *index < drawings.len() {
+                    drawings.remove(*index);
+                }
+            }
+            EditCommand::Clear { after, .. } => *drawings = after.clone(),
+        }
+    }
+
+    fn revert(&self, drawings: &mut Vec<DrawObject>) {
+        match self {
+            EditCommand::Add { index, .. } => {
+                if *index < drawings.len() {
+                    drawings.remove(*index);
+                }
+            }
+            EditCommand::Modify { index, before, .. } => {
+                if let Some(object) = drawings.get_mut(*index) {
+                    *object = before.clone();
+                }
+            }
+            EditCommand::Delete { index, object } => {
+                drawings.insert((*index).min(drawings.len()), object.clone());
+            }
+            EditCommand::Clear { before, .. } => *drawings = before.clone(),
+        }
+    }
+
+    fn objects_mut(&mut self) -> Vec<&mut DrawObject> {
+        match self {
+            EditCommand::Add { object, .. } | EditCommand::Delete { object, .. } => vec![object],
+            EditCommand::Modify { before, after, .. } => vec![before, after],
+            EditCommand::Clear { before, after } => before.iter_mut().chain(after).collect(),
+        }
+    }
+}
+
+/// Undo and redo stacks of the edits made to a chart's drawings
+///
+/// Only the last `depth` edits can be undone; a new edit drops the ones that
+/// were undone.
+#[derive(Debug, Clone)]
+pub struct EditHistory {
+    undo: VecDeque<EditCommand>,
+    redo: Vec<EditCommand>,
+    depth: usize,
+}
+
+impl Default for EditHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_DEPTH)
+    }
+}
+
+impl EditHistory {
+    pub fn new(depth: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            depth,
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Keep at most `depth` edits, forgetting the oldest ones
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.undo.len() > depth {
+            self.undo.pop_front();
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Apply `command` to `drawings` and record it
+    pub fn execute(&mut self, command: EditCommand, drawings: &mut Vec<DrawObject>) {
+        command.apply(drawings);
+        self.push(command);
+    }
+
+    /// Record `command`, already applied to the drawings
+    pub fn push(&mut self, command: EditCommand) {
+        self.redo.clear();
+        if self.depth == 0 {
+            return;
+        }
+        if self.undo.len() == self.depth {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(command);
+    }
+
+    /// Revert the last edit, returns whether there was one
+    pub fn undo(&mut self, drawings: &mut Vec<DrawObject>) -> bool {
+        let Some(command) = self.undo.pop_back() else {
+            return false;
+        };
+        command.revert(drawings);
+        self.redo.push(command);
+        true
+    }
+
+    /// Apply the last undone edit again, returns whether there was one
+    pub fn redo(&mut self, drawings: &mut Vec<DrawObject>) -> bool {
+        let Some(command) = self.redo.pop() else {
+            return false;
+        };
+        command.apply(drawings);
+        self.undo.push_back(command);
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Every drawing the recorded edits hold, to move them with the candles
+    pub fn objects_mut(&mut self) -> impl Iterator<Item = &mut DrawObject> {
+        self.undo
+            .iter_mut()
+            .chain(self.redo.iter_mut())
+            .flat_map(EditCommand::objects_mut)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plotters::style::RGBColor;
+
+    use super::*;
+    use crate::draw::DrawType;
+
+    fn line(x: f32) -> DrawObject {
+        DrawObject::new(
+            DrawType::Line(((x, 0.0), (x + 1.0, 1.0))),
+            RGBColor(0, 0, 0),
+        )
+    }
+
+    #[test]
+    fn test_undo_redo_every_kind_of_edit() {
+        let mut history = EditHistory::default();
+        let mut drawings = Vec::new();
+        history.execute(
+            EditCommand::Add {
+                index: 0,
+                object: line(0.0),
+            },
+            &mut drawings,
+        );
+        history.execute(
+            EditCommand::Add {
+                index: 1,
+                object: line(5.0),
+            },
+            &mut drawings,
+        );
+        let mut recolored = line(5.0);
+        recolored.color = RGBColor(255, 0, 0);
+        let modify = EditCommand::Modify {
+            index: 1,
+            before: line(5.0),
+            after: recolored.clone(),
+        };
+        history.execute(modify, &mut drawings);
+        history.execute(
+            EditCommand::Delete {
+                index: 0,
+                object: line(0.0),
+            },
+            &mut drawings,
+        );
+        let clear = EditCommand::Clear {
+            before: drawings.clone(),
+            after: Vec::new(),
+        };
+        history.execute(clear, &mut drawings);
+        assert!(drawings.is_empty());
+
+        assert!(history.undo(&mut drawings));
+        assert_eq!(drawings, [recolored.clone()]);
+        assert!(history.undo(&mut drawings));
+        assert_eq!(drawings, [line(0.0), recolored.clone()]);
+        assert!(history.undo(&mut drawings));
+        assert_eq!(drawings, [line(0.0), line(5.0)]);
+
+        assert!(history.redo(&mut drawings));
+        assert_eq!(drawings, [line(0.0), recolored.clone()]);
+        // A new edit drops what is left to redo
+        history.execute(
+            EditCommand::Add {
+                index: 2,
+                object: line(9.0),
+            },
+            &mut drawings,
+        );
+        assert!(!history.can_redo());
+        assert!(!history.redo(&mut drawings));
+    }
+
+    #[test]
+    fn test_history_depth_is_bounded() {
+        let mut history = EditHistory::new(2);
+        let mut drawings = Vec::new();
+        for i in 0..4 {
+            let object = line(i as f32);
+            history.execute(EditCommand::Add { index: i, object }, &mut drawings);
+        }
+        assert!(history.undo(&mut drawings));
+        assert!(history.undo(&mut drawings));
+        assert!(!history.undo(&mut drawings));
+        assert_eq!(drawings, [line(0.0), line(1.0)]);
+
+        history.set_depth(0);
+        history.execute(
+            EditCommand::Clear {
+                before: drawings.clone(),
+                after: Vec::new(),
+            },
+            &mut drawings,
+        );
+        assert!(!history.can_undo());
+    }
+}
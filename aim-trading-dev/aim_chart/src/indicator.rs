@@ -0,0 +1,695 @@
+use std::fmt;
+
+use aim_data::calendar::VN_UTC_OFFSET_SECS;
+use plotters::style::{BLUE, RED, RGBColor, WHITE};
+
+use crate::CandleData;
+
+const YELLOW: RGBColor = RGBColor(0xFF, 0xEB, 0x3B);
+const ORANGE: RGBColor = RGBColor(0xFF, 0x98, 0x00);
+const PURPLE: RGBColor = RGBColor(0xAB, 0x47, 0xBC);
+const TEAL: RGBColor = RGBColor(0x26, 0xA6, 0x9A);
+const GREY: RGBColor = RGBColor(0x9E, 0x9E, 0x9E);
+
+/// Values of one indicator line, aligned with the candles (`None` while warming up)
+pub type Series = Vec<Option<f32>>;
+
+/// Technical indicator and its parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndicatorKind {
+    Sma {
+        period: usize,
+    },
+    Ema {
+        period: usize,
+    },
+    Wma {
+        period: usize,
+    },
+    /// Middle band is the SMA, outer bands `width` standard deviations away
+    Bollinger {
+        period: usize,
+        width: f32,
+    },
+    Rsi {
+        period: usize,
+    },
+    Macd {
+        fast: usize,
+        slow: usize,
+        signal: usize,
+    },
+    /// Slow stochastic: %K smoothed over `smooth` bars, %D over `signal`
+    Stochastic {
+        period: usize,
+        smooth: usize,
+        signal: usize,
+    },
+    Atr {
+        period: usize,
+    },
+    /// Session VWAP, restarting every trading day; meant for intraday bars
+    Vwap,
+    Obv,
+    /// Senkou spans are drawn `kijun` bars ahead, the chikou span `kijun` bars behind
+    Ichimoku {
+        tenkan: usize,
+        kijun: usize,
+        senkou: usize,
+    },
+}
+
+/// One line of a computed indicator
+#[derive(Debug, Clone)]
+pub struct IndicatorLine {
+    pub values: Series,
+    /// Bars the line is drawn ahead of (positive) or behind (negative) its candle
+    pub shift: isize,
+    /// Drawn as bars from zero rather than a line
+    pub histogram: bool,
+}
+
+impl IndicatorLine {
+    fn new(values: Series) -> Self {
+        Self {
+            values,
+            shift: 0,
+            histogram: false,
+        }
+    }
+
+    /// Value drawn at candle `index`, taking the shift into account
+    pub fn value_at(&self, index: usize) -> Option<f32> {
+        let source = index as isize - self.shift;
+        usize::try_from(source)
+            .ok()
+            .and_then(|i| self.values.get(i).copied().flatten())
+    }
+}
+
+impl IndicatorKind {
+    /// Every indicator with its usual parameters
+    pub const ALL: [IndicatorKind; 11] = [
+        IndicatorKind::Sma { period: 20 },
+        IndicatorKind::Ema { period: 20 },
+        IndicatorKind::Wma { period: 20 },
+        IndicatorKind::Bollinger {
+            period: 20,
+            width: 2.0,
+        },
+        IndicatorKind::Rsi { period: 14 },
+        IndicatorKind::Macd {
+            fast: 12,
+            slow: 26,
+            signal: 9,
+        },
+        IndicatorKind::Stochastic {
+            period: 14,
+            smooth: 3,
+            signal: 3,
+        },
+        IndicatorKind::Atr { period: 14 },
+        IndicatorKind::Vwap,
+        IndicatorKind::Obv,
+        IndicatorKind::Ichimoku {
+            tenkan: 9,
+            kijun: 26,
+            senkou: 52,
+        },
+    ];
+
+    /// Short name, as accepted by [`IndicatorKind::parse`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            IndicatorKind::Sma { .. } => "SMA",
+            IndicatorKind::Ema { .. } => "EMA",
+            IndicatorKind::Wma { .. } => "WMA",
+            IndicatorKind::Bollinger { .. } => "BB",
+            IndicatorKind::Rsi { .. } => "RSI",
+            IndicatorKind::Macd { .. } => "MACD",
+            IndicatorKind::Stochastic { .. } => "STOCH",
+            IndicatorKind::Atr { .. } => "ATR",
+            IndicatorKind::Vwap => "VWAP",
+            IndicatorKind::Obv => "OBV",
+            IndicatorKind::Ichimoku { .. } => "ICHIMOKU",
+        }
+    }
+
+    /// Parse a name followed by optional parameters, e.g. `EMA 50`, `BB 20 2.5` or `MACD`
+    ///
+    /// Missing parameters take the defaults from [`IndicatorKind::ALL`].
+    pub fn parse(label: &str) -> Option<Self> {
+        let mut parts = label
+            .split(|c: char| c.is_whitespace() || matches!(c, ',' | '(' | ')'))
+            .filter(|part| !part.is_empty());
+        let name = parts.next()?.to_uppercase();
+        let default = Self::ALL.into_iter().find(|kind| kind.name() == name)?;
+        let params = parts
+            .map(|part| part.parse::<f32>().ok().filter(|value| *value > 0.0))
+            .collect::<Option<Vec<f32>>>()?;
+        let param = |index: usize, default: usize| {
+            params.get(index).map_or(default, |&value| value as usize)
+        };
+
+        let kind = match default {
+            IndicatorKind::Sma { period } => IndicatorKind::Sma {
+                period: param(0, period),
+            },
+            IndicatorKind::Ema { period } => IndicatorKind::Ema {
+                period: param(0, period),
+            },
+            IndicatorKind::Wma { period } => IndicatorKind::Wma {
+                period: param(0, period),
+            },
+            IndicatorKind::Bollinger { period, width } => IndicatorKind::Bollinger {
+                period: param(0, period),
+                width: params.get(1).copied().unwrap_or(width),
+            },
+            IndicatorKind::Rsi { period } => IndicatorKind::Rsi {
+                period: param(0, period),
+            },
+            IndicatorKind::Macd { fast, slow, signal } => IndicatorKind::Macd {
+                fast: param(0, fast),
+                slow: param(1, slow),
+                signal: param(2, signal),
+            },
+            IndicatorKind::Stochastic {
+                period,
+                smooth,
+                signal,
+            } => IndicatorKind::Stochastic {
+                period: param(0, period),
+                smooth: param(1, smooth),
+                signal: param(2, signal),
+            },
+            IndicatorKind::Atr { period } => IndicatorKind::Atr {
+                period: param(0, period),
+            },
+            IndicatorKind::Vwap => IndicatorKind::Vwap,
+            IndicatorKind::Obv => IndicatorKind::Obv,
+            IndicatorKind::Ichimoku {
+                tenkan,
+                kijun,
+                senkou,
+            } => IndicatorKind::Ichimoku {
+                tenkan: param(0, tenkan),
+                kijun: param(1, kijun),
+                senkou: param(2, senkou),
+            },
+        };
+        Some(kind)
+    }
+
+    /// Drawn over the candles rather than on its own scale
+    pub fn is_overlay(&self) -> bool {
+        matches!(
+            self,
+            IndicatorKind::Sma { .. }
+                | IndicatorKind::Ema { .. }
+                | IndicatorKind::Wma { .. }
+                | IndicatorKind::Bollinger { .. }
+                | IndicatorKind::Vwap
+                | IndicatorKind::Ichimoku { .. }
+        )
+    }
+
+    /// Fixed value range of bounded oscillators
+    pub fn bounds(&self) -> Option<(f32, f32)> {
+        match self {
+            IndicatorKind::Rsi { .. } | IndicatorKind::Stochastic { .. } => Some((0.0, 100.0)),
+            _ => None,
+        }
+    }
+
+    /// Default colour of each line returned by [`IndicatorKind::compute`]
+    pub fn default_colors(&self) -> &'static [RGBColor] {
+        match self {
+            IndicatorKind::Sma { .. } => &[YELLOW],
+            IndicatorKind::Ema { .. } => &[ORANGE],
+            IndicatorKind::Wma { .. } => &[PURPLE],
+            IndicatorKind::Bollinger { .. } => &[ORANGE, BLUE, BLUE],
+            IndicatorKind::Rsi { .. } => &[PURPLE],
+            IndicatorKind::Macd { .. } => &[BLUE, ORANGE, GREY],
+            IndicatorKind::Stochastic { .. } => &[BLUE, ORANGE],
+            IndicatorKind::Atr { .. } => &[RED],
+            IndicatorKind::Vwap => &[TEAL],
+            IndicatorKind::Obv => &[TEAL],
+            IndicatorKind::Ichimoku { .. } => &[BLUE, RED, TEAL, ORANGE, GREY],
+        }
+    }
+
+    /// Compute every line of the indicator over `candles`
+    pub fn compute(&self, candles: &[CandleData]) -> Vec<IndicatorLine> {
+        let close: Series = candles.iter().map(|c| Some(c.close)).collect();
+        match *self {
+            IndicatorKind::Sma { period } => vec![IndicatorLine::new(sma(&close, period))],
+            IndicatorKind::Ema { period } => vec![IndicatorLine::new(ema(&close, period))],
+            IndicatorKind::Wma { period } => vec![IndicatorLine::new(wma(&close, period))],
+            IndicatorKind::Bollinger { period, width } => {
+                let middle = sma(&close, period);
+                let deviation = std_dev(&close, &middle, period);
+                let band = |sign: f32| {
+                    middle
+                        .iter()
+                        .zip(&deviation)
+                        .map(|(m, d)| Some(m.as_ref()? + sign * width * d.as_ref()?))
+                        .collect()
+                };
+                let (upper, lower) = (band(1.0), band(-1.0));
+                vec![
+                    IndicatorLine::new(middle),
+                    IndicatorLine::new(upper),
+                    IndicatorLine::new(lower),
+                ]
+            }
+            IndicatorKind::Rsi { period } => vec![IndicatorLine::new(rsi(candles, period))],
+            IndicatorKind::Macd { fast, slow, signal } => {
+                let macd = zip_with(&ema(&close, fast), &ema(&close, slow), |f, s| f - s);
+                let signal = ema(&macd, signal);
+                let histogram = zip_with(&macd, &signal, |m, s| m - s);
+                vec![
+                    IndicatorLine::new(macd),
+                    IndicatorLine::new(signal),
+                    IndicatorLine {
+                        histogram: true,
+                        ..IndicatorLine::new(histogram)
+                    },
+                ]
+            }
+            IndicatorKind::Stochastic {
+                period,
+                smooth,
+                signal,
+            } => {
+                let raw = candles
+                    .iter()
+                    .enumerate()
+                    .map(|(i, candle)| {
+                        let (low, high) = low_high(candles, i, period)?;
+                        Some(if high > low {
+                            100.0 * (candle.close - low) / (high - low)
+                        } else {
+                            50.0
+                        })
+                    })
+                    .collect::<Series>();
+                let k = sma(&raw, smooth);
+                let d = sma(&k, signal);
+                vec![IndicatorLine::new(k), IndicatorLine::new(d)]
+            }
+            IndicatorKind::Atr { period } => {
+                let true_range = candles
+                    .iter()
+                    .enumerate()
+                    .map(|(i, candle)| {
+                        let range = candle.high - candle.low;
+                        Some(match i.checked_sub(1).map(|p| candles[p].close) {
+                            Some(prev) => range
+                                .max((candle.high - prev).abs())
+                                .max((candle.low - prev).abs()),
+                            None => range,
+                        })
+                    })
+                    .collect::<Series>();
+                vec![IndicatorLine::new(wilder(&true_range, period))]
+            }
+            IndicatorKind::Vwap => vec![IndicatorLine::new(vwap(candles))],
+            IndicatorKind::Obv => {
+                let mut total = 0.0;
+                let obv = candles
+                    .iter()
+                    .enumerate()
+                    .map(|(i, candle)| {
+                        if let Some(prev) = i.checked_sub(1).map(|p| candles[p].close) {
+                            if candle.close > prev {
+                                total += candle.volume;
+                            } else if candle.close < prev {
+                                total -= candle.volume;
+                            }
+                        }
+                        Some(total)
+                    })
+                    .collect();
+                vec![IndicatorLine::new(obv)]
+            }
+            IndicatorKind::Ichimoku {
+                tenkan,
+                kijun,
+                senkou,
+            } => {
+                let midpoint = |period: usize| -> Series {
+                    (0..candles.len())
+                        .map(|i| low_high(candles, i, period).map(|(low, high)| (low + high) / 2.0))
+                        .collect()
+                };
+                let conversion = midpoint(tenkan);
+                let base = midpoint(kijun);
+                let span_a = zip_with(&conversion, &base, |c, b| (c + b) / 2.0);
+                let displacement = kijun as isize;
+                vec![
+                    IndicatorLine::new(conversion),
+                    IndicatorLine::new(base),
+                    IndicatorLine {
+                        shift: displacement,
+                        ..IndicatorLine::new(span_a)
+                    },
+                    IndicatorLine {
+                        shift: displacement,
+                        ..IndicatorLine::new(midpoint(senkou))
+                    },
+                    IndicatorLine {
+                        shift: -displacement,
+                        ..IndicatorLine::new(close)
+                    },
+                ]
+            }
+        }
+    }
+}
+
+impl fmt::Display for IndicatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name();
+        match self {
+            IndicatorKind::Sma { period }
+            | IndicatorKind::Ema { period }
+            | IndicatorKind::Wma { period }
+            | IndicatorKind::Rsi { period }
+            | IndicatorKind::Atr { period } => write!(f, "{name}({period})"),
+            IndicatorKind::Bollinger { period, width } => write!(f, "{name}({period}, {width})"),
+            IndicatorKind::Macd { fast, slow, signal } => {
+                write!(f, "{name}({fast}, {slow}, {signal})")
+            }
+            IndicatorKind::Stochastic {
+                period,
+                smooth,
+                signal,
+            } => write!(f, "{name}({period}, {smooth}, {signal})"),
+            IndicatorKind::Vwap | IndicatorKind::Obv => write!(f, "{name}"),
+            IndicatorKind::Ichimoku {
+                tenkan,
+                kijun,
+                senkou,
+            } => write!(f, "{name}({tenkan}, {kijun}, {senkou})"),
+        }
+    }
+}
+
+/// An indicator shown on a chart
+#[derive(Debug, Clone, PartialEq)]
+pub struct Indicator {
+    pub kind: IndicatorKind,
+    /// One colour per line, falling back to the kind's defaults
+    pub colors: Vec<RGBColor>,
+    pub visible: bool,
+}
+
+impl Indicator {
+    pub fn new(kind: IndicatorKind) -> Self {
+        Self {
+            kind,
+            colors: kind.default_colors().to_vec(),
+            visible: true,
+        }
+    }
+
+    pub fn with_colors(mut self, colors: &[RGBColor]) -> Self {
+        self.colors = colors.to_vec();
+        self
+    }
+
+    /// Colour of line `index`
+    pub fn color(&self, index: usize) -> RGBColor {
+        self.colors
+            .get(index)
+            .or_else(|| self.kind.default_colors().get(index))
+            .copied()
+            .unwrap_or(WHITE)
+    }
+
+    /// The MA20/50/200 set every chart starts with
+    pub fn defaults() -> Vec<Indicator> {
+        vec![
+            Indicator::new(IndicatorKind::Sma { period: 20 }).with_colors(&[RED]),
+            Indicator::new(IndicatorKind::Sma { period: 50 }).with_colors(&[BLUE]),
+            Indicator::new(IndicatorKind::Sma { period: 200 }).with_colors(&[WHITE]),
+        ]
+    }
+}
+
+/// Combine two series point by point, `None` where either is missing
+fn zip_with(a: &[Option<f32>], b: &[Option<f32>], f: impl Fn(f32, f32) -> f32) -> Series {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| Some(f((*a)?, (*b)?)))
+        .collect()
+}
+
+/// Lowest low and highest high of the `period` candles ending at `index`
+fn low_high(candles: &[CandleData], index: usize, period: usize) -> Option<(f32, f32)> {
+    let start = (index + 1).checked_sub(period.max(1))?;
+    Some(
+        candles[start..=index]
+            .iter()
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(low, high), c| {
+                (low.min(c.low), high.max(c.high))
+            }),
+    )
+}
+
+/// Simple moving average; a gap in `values` restarts the window
+pub fn sma(values: &[Option<f32>], period: usize) -> Series {
+    let mut out = vec![None; values.len()];
+    if period == 0 {
+        return out;
+    }
+    let mut sum = 0.0;
+    let mut count = 0;
+    for (i, value) in values.iter().enumerate() {
+        let Some(value) = value else {
+            sum = 0.0;
+            count = 0;
+            continue;
+        };
+        sum += value;
+        count += 1;
+        if count > period {
+            sum -= values[i - period].unwrap_or_default();
+        }
+        if count >= period {
+            out[i] = Some(sum / period as f32);
+        }
+    }
+    out
+}
+
+/// Exponential smoothing with factor `alpha`, seeded with the SMA of the first `period` values
+fn smooth(values: &[Option<f32>], period: usize, alpha: f32) -> Series {
+    let mut out = vec![None; values.len()];
+    if period == 0 {
+        return out;
+    }
+    let mut previous: Option<f32> = None;
+    let mut seed = 0.0;
+    let mut count = 0;
+    for (i, value) in values.iter().enumerate() {
+        let Some(value) = *value else {
+            previous = None;
+            seed = 0.0;
+            count = 0;
+            continue;
+        };
+        previous = match previous {
+            Some(previous) => Some(previous + alpha * (value - previous)),
+            None => {
+                seed += value;
+                count += 1;
+                (count == period).then(|| seed / period as f32)
+            }
+        };
+        out[i] = previous;
+    }
+    out
+}
+
+pub fn ema(values: &[Option<f32>], period: usize) -> Series {
+    smooth(values, period, 2.0 / (period as f32 + 1.0))
+}
+
+/// Wilder's smoothing, used by RSI and ATR
+fn wilder(values: &[Option<f32>], period: usize) -> Series {
+    smooth(values, period, 1.0 / period.max(1) as f32)
+}
+
+/// Linearly weighted moving average, the latest value weighing `period`
+pub fn wma(values: &[Option<f32>], period: usize) -> Series {
+    if period == 0 {
+        return vec![None; values.len()];
+    }
+    let weight_sum = (period * (period + 1) / 2) as f32;
+    (0..values.len())
+        .map(|i| {
+            let start = (i + 1).checked_sub(period)?;
+            values[start..=i]
+                .iter()
+                .zip(1..)
+                .try_fold(0.0, |acc, (value, weight)| {
+                    Some(acc + (*value)? * weight as f32)
+                })
+                .map(|sum| sum / weight_sum)
+        })
+        .collect()
+}
+
+/// Population standard deviation over the `period` values around `mean`
+fn std_dev(values: &[Option<f32>], mean: &[Option<f32>], period: usize) -> Series {
+    (0..values.len())
+        .map(|i| {
+            let mean = mean[i]?;
+            let start = (i + 1).checked_sub(period)?;
+            let variance = values[start..=i]
+                .iter()
+                .try_fold(0.0, |acc, value| Some(acc + ((*value)? - mean).powi(2)))?
+                / period as f32;
+            Some(variance.sqrt())
+        })
+        .collect()
+}
+
+fn rsi(candles: &[CandleData], period: usize) -> Series {
+    let change = |i: usize, f: fn(f32) -> f32| -> Option<f32> {
+        let prev = candles[i.checked_sub(1)?].close;
+        Some(f(candles[i].close - prev))
+    };
+    let gains: Series = (0..candles.len())
+        .map(|i| change(i, |d| d.max(0.0)))
+        .collect();
+    let losses: Series = (0..candles.len())
+        .map(|i| change(i, |d| (-d).max(0.0)))
+        .collect();
+    zip_with(
+        &wilder(&gains, period),
+        &wilder(&losses, period),
+        |gain, loss| {
+            if loss == 0.0 {
+                100.0
+            } else {
+                100.0 - 100.0 / (1.0 + gain / loss)
+            }
+        },
+    )
+}
+
+/// Volume weighted typical price, restarting at each Vietnam trading day
+fn vwap(candles: &[CandleData]) -> Series {
+    let mut day = None;
+    let mut price_volume = 0.0;
+    let mut volume = 0.0;
+    candles
+        .iter()
+        .map(|candle| {
+            let candle_day = (candle.time.timestamp() + VN_UTC_OFFSET_SECS).div_euclid(86_400);
+            if day != Some(candle_day) {
+                day = Some(candle_day);
+                price_volume = 0.0;
+                volume = 0.0;
+            }
+            let typical = (candle.high + candle.low + candle.close) / 3.0;
+            price_volume += typical * candle.volume;
+            volume += candle.volume;
+            Some(if volume > 0.0 {
+                price_volume / volume
+            } else {
+                typical
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    fn candles(closes: &[f32]) -> Vec<CandleData> {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| CandleData {
+                num: i as f32,
+                time: DateTime::from_timestamp(1_741_050_000 + 60 * i as i64, 0).unwrap(),
+                open: close,
+                high: close + 1.0,
+                low: close - 1.0,
+                close,
+                volume: 100.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_moving_averages_warm_up_without_underflow() {
+        let data = candles(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        let sma = IndicatorKind::Sma { period: 3 }.compute(&data);
+        assert_eq!(
+            sma[0].values,
+            vec![None, None, Some(2.0), Some(3.0), Some(4.0)]
+        );
+        // Longer than the data: nothing to draw, no panic
+        let long = IndicatorKind::Sma { period: 200 }.compute(&data);
+        assert!(long[0].values.iter().all(Option::is_none));
+
+        let wma = IndicatorKind::Wma { period: 3 }.compute(&data);
+        assert_eq!(wma[0].values[2], Some((1.0 + 4.0 + 9.0) / 6.0));
+        let ema = IndicatorKind::Ema { period: 3 }.compute(&data);
+        assert_eq!(ema[0].values[2], Some(2.0));
+        assert_eq!(ema[0].values[3], Some(3.0));
+    }
+
+    #[test]
+    fn test_oscillators_and_shifted_lines() {
+        let rising: Vec<f32> = (1..=40).map(|i| i as f32).collect();
+        let data = candles(&rising);
+        let rsi = IndicatorKind::Rsi { period: 14 }.compute(&data);
+        assert_eq!(rsi[0].values[13], None);
+        assert_eq!(rsi[0].values[14], Some(100.0));
+
+        let macd = IndicatorKind::parse("macd 3 6 2").unwrap().compute(&data);
+        assert_eq!(macd.len(), 3);
+        assert!(macd[2].histogram);
+        assert!(macd[0].values[39].unwrap() > 0.0);
+
+        let ichimoku = IndicatorKind::parse("ICHIMOKU 2 4 8")
+            .unwrap()
+            .compute(&data);
+        // Senkou span A is drawn `kijun` bars ahead of the bar it is computed on
+        assert_eq!(ichimoku[2].value_at(43), ichimoku[2].values[39]);
+        assert_eq!(ichimoku[4].value_at(0), Some(5.0));
+    }
+
+    #[test]
+    fn test_parse_labels() {
+        assert_eq!(
+            IndicatorKind::parse("BB 10 2.5"),
+            Some(IndicatorKind::Bollinger {
+                period: 10,
+                width: 2.5
+            })
+        );
+        assert_eq!(
+            IndicatorKind::parse("ema"),
+            Some(IndicatorKind::Ema { period: 20 })
+        );
+        assert_eq!(IndicatorKind::parse("SMA -5"), None);
+        assert_eq!(IndicatorKind::parse("FOO"), None);
+        assert_eq!(
+            IndicatorKind::Macd {
+                fast: 12,
+                slow: 26,
+                signal: 9
+            }
+            .to_string(),
+            "MACD(12, 26, 9)"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::layout::{read_str, read_u32, write_str};
+
+/// Layer new drawings go to on a fresh chart
+pub const DEFAULT_LAYER: &str = "Mặc định";
+
+/// What the layer picker does to the active layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayerAction {
+    ToggleVisible,
+    ToggleLocked,
+}
+
+impl LayerAction {
+    /// Entry `index` of the picker
+    pub fn from_index(index: i32) -> Option<Self> {
+        match index {
+            0 => Some(LayerAction::ToggleVisible),
+            1 => Some(LayerAction::ToggleLocked),
+            _ => None,
+        }
+    }
+}
+
+/// Named group of drawings shown, hidden and locked together
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawingLayer {
+    pub name: String,
+    pub visible: bool,
+    /// Drawings stay where they are: no moving, reshaping or deleting
+    pub locked: bool,
+}
+
+impl DrawingLayer {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            visible: true,
+            locked: false,
+        }
+    }
+
+    pub fn write_to_bytes(&self, buf: &mut Vec<u8>) {
+        write_str(buf, &self.name);
+        buf.push(self.visible as u8);
+        buf.push(self.locked as u8);
+    }
+
+    pub fn read_from_bytes(data: &[u8]) -> Option<(Self, usize)> {
+        let mut pos = 0;
+        let name = read_str(data, &mut pos)?;
+        let flags = data.get(pos..pos + 2)?;
+        let layer = Self {
+            name,
+            visible: flags[0] != 0,
+            locked: flags[1] != 0,
+        };
+        Some((layer, pos + 2))
+    }
+}
+
+/// The layers of a chart and the one new drawings go to
+///
+/// Drawings name their layer; a name missing here counts as a visible,
+/// unlocked layer so drawings never disappear with their layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layers {
+    layers: Vec<DrawingLayer>,
+    active: usize,
+}
+
+impl Default for Layers {
+    fn default() -> Self {
+        Self {
+            layers: vec![DrawingLayer::new(DEFAULT_LAYER)],
+            active: 0,
+        }
+    }
+}
+
+impl Layers {
+    pub fn all(&self) -> &[DrawingLayer] {
+        &self.layers
+    }
+
+    pub fn active(&self) -> &DrawingLayer {
+        &self.layers[self.active]
+    }
+
+    pub fn get(&self, name: &str) -> Option<&DrawingLayer> {
+        self.layers.iter().find(|layer| layer.name == name)
+    }
+
+    /// The layer called `name`, added at the end when missing
+    pub fn ensure(&mut self, name: &str) -> &mut DrawingLayer {
+        let index = match self.layers.iter().position(|layer| layer.name == name) {
+            Some(index) => index,
+            None => {
+                self.layers.push(DrawingLayer::new(name));
+                self.layers.len() - 1
+            }
+        };
+        &mut self.layers[index]
+    }
+
+    /// Send new drawings to `name`, adding the layer when missing
+    pub fn select(&mut self, name: &str) {
+        self.ensure(name);
+        self.active = self
+            .layers
+            .iter()
+            .position(|layer| layer.name == name)
+            .unwrap_or_default();
+    }
+
+    /// Apply `action` to the active layer
+    pub fn apply(&mut self, action: LayerAction) {
+        let layer = &mut self.layers[self.active];
+        match action {
+            LayerAction::ToggleVisible => layer.visible = !layer.visible,
+            LayerAction::ToggleLocked => layer.locked = !layer.locked,
+        }
+    }
+
+    /// Whether drawings on layer `name` are drawn
+    pub fn is_visible(&self, name: &str) -> bool {
+        self.get(name).is_none_or(|layer| layer.visible)
+    }
+
+    /// Whether drawings on layer `name` can be picked to move, reshape,
+    /// restyle or delete
+    pub fn is_editable(&self, name: &str) -> bool {
+        self.get(name)
+            .is_none_or(|layer| layer.visible && !layer.locked)
+    }
+
+    pub fn write_to_bytes(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&(self.active as u32).to_le_bytes());
+        buf.extend_from_slice(&(self.layers.len() as u32).to_le_bytes());
+        for layer in &self.layers {
+            layer.write_to_bytes(buf);
+        }
+    }
+
+    pub fn read_from_bytes(data: &[u8]) -> Option<(Self, usize)> {
+        let mut pos = 0;
+        let active = read_u32(data, &mut pos)? as usize;
+        let count = read_u32(data, &mut pos)? as usize;
+        let mut layers = Vec::with_capacity(count.min(data.len()));
+        for _ in 0..count {
+            let (layer, used) = DrawingLayer::read_from_bytes(&data[pos..])?;
+            layers.push(layer);
+            pos += used;
+        }
+        if active >= layers.len() {
+            return None;
+        }
+        Some((Self { layers, active }, pos))
+    }
+}
@@ -0,0 +1,598 @@
+use aim_data::TimeFrame;
+use slint::{Image, Rgb8Pixel, SharedPixelBuffer};
+
+use crate::{Chart, UiData};
+
+/// Pixels left between two panels
+const PANEL_GAP_PX: u32 = 2;
+/// Colour of the gaps between panels
+const GAP_COLOR: Rgb8Pixel = Rgb8Pixel::new(0x2A, 0x2E, 0x39);
+/// Outline of the panel the toolbar acts on
+const ACTIVE_COLOR: Rgb8Pixel = Rgb8Pixel::new(0x29, 0x62, 0xFF);
+/// Time frames given to new panels, by panel index
+const PANEL_TIME_FRAMES: [TimeFrame; 4] = [
+    TimeFrame::Day(1),
+    TimeFrame::Hour(1),
+    TimeFrame::Minute(15),
+    TimeFrame::Week(1),
+];
+/// Mouse row given to panels that only show the linked crosshair time,
+/// far below every pane so no horizontal line is drawn
+const NO_CURSOR_ROW: i32 = i32::MAX / 2;
+
+/// How the chart area is split into panels
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LayoutKind {
+    #[default]
+    Single,
+    /// Two panels side by side
+    TwoByOne,
+    TwoByTwo,
+    /// One large panel on the left, three stacked on the right
+    OnePlusThree,
+}
+
+impl LayoutKind {
+    pub const ALL: [LayoutKind; 4] = [
+        LayoutKind::Single,
+        LayoutKind::TwoByOne,
+        LayoutKind::TwoByTwo,
+        LayoutKind::OnePlusThree,
+    ];
+
+    /// Label used by the layout picker and the cache file
+    pub fn label(&self) -> &'static str {
+        match self {
+            LayoutKind::Single => "1",
+            LayoutKind::TwoByOne => "2x1",
+            LayoutKind::TwoByTwo => "2x2",
+            LayoutKind::OnePlusThree => "1+3",
+        }
+    }
+
+    pub fn parse(label: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.label() == label.trim())
+    }
+
+    pub fn panel_count(&self) -> usize {
+        match self {
+            LayoutKind::Single => 1,
+            LayoutKind::TwoByOne => 2,
+            LayoutKind::TwoByTwo => 4,
+            LayoutKind::OnePlusThree => 4,
+        }
+    }
+
+    /// Pixel rectangles of the panels in an image `width` x `height`
+    pub fn rects(&self, width: u32, height: u32) -> Vec<PanelRect> {
+        let half_width = width.saturating_sub(PANEL_GAP_PX) / 2;
+        let half_height = height.saturating_sub(PANEL_GAP_PX) / 2;
+        let right = half_width + PANEL_GAP_PX;
+        let bottom = half_height + PANEL_GAP_PX;
+        match self {
+            LayoutKind::Single => vec![PanelRect::new(0, 0, width, height)],
+            LayoutKind::TwoByOne => vec![
+                PanelRect::new(0, 0, half_width, height),
+                PanelRect::new(right, 0, width.saturating_sub(right), height),
+            ],
+            LayoutKind::TwoByTwo => vec![
+                PanelRect::new(0, 0, half_width, half_height),
+                PanelRect::new(right, 0, width.saturating_sub(right), half_height),
+                PanelRect::new(0, bottom, half_width, height.saturating_sub(bottom)),
+                PanelRect::new(
+                    right,
+                    bottom,
+                    width.saturating_sub(right),
+                    height.saturating_sub(bottom),
+                ),
+            ],
+            LayoutKind::OnePlusThree => {
+                let main_width = width.saturating_sub(PANEL_GAP_PX) * 2 / 3;
+                let side = main_width + PANEL_GAP_PX;
+                let side_width = width.saturating_sub(side);
+                let third = height.saturating_sub(2 * PANEL_GAP_PX) / 3;
+                let step = third + PANEL_GAP_PX;
+                vec![
+                    PanelRect::new(0, 0, main_width, height),
+                    PanelRect::new(side, 0, side_width, third),
+                    PanelRect::new(side, step, side_width, third),
+                    PanelRect::new(side, 2 * step, side_width, height.saturating_sub(2 * step)),
+                ]
+            }
+        }
+    }
+}
+
+/// Pixel rectangle of a panel inside the chart image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PanelRect {
+    pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x as i32
+            && y >= self.y as i32
+            && x < (self.x + self.width) as i32
+            && y < (self.y + self.height) as i32
+    }
+}
+
+/// Settings a panel can share with the other panels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Link {
+    Symbol,
+    TimeFrame,
+    Crosshair,
+}
+
+impl Link {
+    /// Link at `index` in the link picker
+    pub fn from_index(index: i32) -> Option<Self> {
+        match index {
+            0 => Some(Link::Symbol),
+            1 => Some(Link::TimeFrame),
+            2 => Some(Link::Crosshair),
+            _ => None,
+        }
+    }
+}
+
+/// What a panel shares with the other linked panels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PanelLinks {
+    pub symbol: bool,
+    pub time_frame: bool,
+    pub crosshair: bool,
+}
+
+impl Default for PanelLinks {
+    /// Same symbol at different time frames, the usual multi-chart setup
+    fn default() -> Self {
+        Self {
+            symbol: true,
+            time_frame: false,
+            crosshair: true,
+        }
+    }
+}
+
+impl PanelLinks {
+    pub fn toggle(&mut self, link: Link) {
+        match link {
+            Link::Symbol => self.symbol = !self.symbol,
+            Link::TimeFrame => self.time_frame = !self.time_frame,
+            Link::Crosshair => self.crosshair = !self.crosshair,
+        }
+    }
+}
+
+/// One chart of the layout, the `Chart` it shows is found by symbol and time frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartPanel {
+    pub stock_name: String,
+    pub time_frame: TimeFrame,
+    pub links: PanelLinks,
+}
+
+impl ChartPanel {
+    pub fn shows(&self, chart: &Chart) -> bool {
+        chart.stock_name == self.stock_name && chart.time_frame == self.time_frame
+    }
+}
+
+/// Panels shown side by side in the chart area
+///
+/// The active panel follows the symbol search and the time frame picker; the
+/// panels linked to it take the same symbol or time frame and show its
+/// crosshair time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartLayout {
+    kind: LayoutKind,
+    panels: Vec<ChartPanel>,
+    active: usize,
+}
+
+impl Default for ChartLayout {
+    fn default() -> Self {
+        Self::new(String::new(), TimeFrame::default())
+    }
+}
+
+impl ChartLayout {
+    /// A single panel showing `stock_name`
+    pub fn new(stock_name: String, time_frame: TimeFrame) -> Self {
+        Self {
+            kind: LayoutKind::Single,
+            panels: vec![ChartPanel {
+                stock_name,
+                time_frame,
+                links: PanelLinks::default(),
+            }],
+            active: 0,
+        }
+    }
+
+    pub fn kind(&self) -> LayoutKind {
+        self.kind
+    }
+
+    pub fn panels(&self) -> &[ChartPanel] {
+        &self.panels
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn active_panel(&self) -> &ChartPanel {
+        &self.panels[self.active]
+    }
+
+    /// Switch layout, new panels show the active symbol at other time frames
+    pub fn set_kind(&mut self, kind: LayoutKind) {
+        let count = kind.panel_count();
+        let stock_name = self.active_panel().stock_name.clone();
+        while self.panels.len() < count {
+            let time_frame = PANEL_TIME_FRAMES[self.panels.len() % PANEL_TIME_FRAMES.len()];
+            self.panels.push(ChartPanel {
+                stock_name: stock_name.clone(),
+                time_frame,
+                links: PanelLinks::default(),
+            });
+        }
+        self.panels.truncate(count);
+        self.active = self.active.min(count - 1);
+        self.kind = kind;
+    }
+
+    pub fn set_active(&mut self, index: usize) {
+        if index < self.panels.len() {
+            self.active = index;
+        }
+    }
+
+    pub fn toggle_link(&mut self, link: Link) {
+        self.panels[self.active].links.toggle(link);
+    }
+
+    /// Show `stock_name` at `time_frame` in the active panel and in the panels
+    /// linked to it
+    pub fn select(&mut self, stock_name: &str, time_frame: TimeFrame) {
+        let links = self.active_panel().links;
+        for (i, panel) in self.panels.iter_mut().enumerate() {
+            let active = i == self.active;
+            if active || (links.symbol && panel.links.symbol) {
+                panel.stock_name = stock_name.to_string();
+            }
+            if active || (links.time_frame && panel.links.time_frame) {
+                panel.time_frame = time_frame;
+            }
+        }
+    }
+
+    /// Symbols and time frames shown by a panel but missing from `charts`
+    pub fn missing_charts(&self, charts: &[Chart]) -> Vec<(String, TimeFrame)> {
+        let mut missing: Vec<(String, TimeFrame)> = Vec::new();
+        for panel in &self.panels {
+            let key = (panel.stock_name.clone(), panel.time_frame);
+            if !panel.stock_name.is_empty()
+                && !charts.iter().any(|chart| panel.shows(chart))
+                && !missing.contains(&key)
+            {
+                missing.push(key);
+            }
+        }
+        missing
+    }
+
+    /// Render every panel into one image `ui_data.width` x `ui_data.height`,
+    /// returns the image and whether the mouse is over a drawing
+    ///
+    /// The mouse goes to the panel it is over (the one it was pressed in while
+    /// a button is down); pressing in a panel makes it active. The toolbar
+    /// flags (clean, undo, indicators) go to the active panel.
+    pub fn render(&mut self, charts: &mut [Chart], ui_data: UiData) -> (Image, bool) {
+        if ui_data.is_new_layout {
+            match LayoutKind::parse(&ui_data.layout) {
+                Some(kind) => self.set_kind(kind),
+                None => log::warn!("Unknown chart layout {:?}", ui_data.layout),
+            }
+        }
+        if ui_data.is_toggle_link {
+            match Link::from_index(ui_data.link) {
+                Some(link) => self.toggle_link(link),
+                None => log::warn!("Unknown panel link {}", ui_data.link),
+            }
+        }
+
+        let width = ui_data.width.max(0) as u32;
+        let height = ui_data.height.max(0) as u32;
+        let rects = self.kind.rects(width, height);
+        let (mouse_x, mouse_y) = if ui_data.is_release {
+            (ui_data.position_x, ui_data.position_y)
+        } else {
+            (ui_data.press_x, ui_data.press_y)
+        };
+        let hover = rects
+            .iter()
+            .position(|rect| rect.contains(mouse_x, mouse_y));
+        if !ui_data.is_release
+            && let Some(hover) = hover
+        {
+            self.active = hover;
+        }
+
+        let chart_index = |panel: &ChartPanel| charts.iter().position(|chart| panel.shows(chart));
+        let indices: Vec<Option<usize>> = self.panels.iter().map(chart_index).collect();
+        let mut images: Vec<Image> = vec![Image::default(); self.panels.len()];
+        let mut is_in_object = false;
+
+        // The panel under the mouse goes first, the others follow its crosshair time
+        let mut crosshair_time = None;
+        if let Some(hover) = hover
+            && let Some(index) = indices[hover]
+        {
+            let rect = rects[hover];
+            let mut panel_ui_data = self.panel_ui_data(&ui_data, hover, rect);
+            panel_ui_data.position_x -= rect.x as i32;
+            panel_ui_data.position_y -= rect.y as i32;
+            panel_ui_data.press_x -= rect.x as i32;
+            panel_ui_data.press_y -= rect.y as i32;
+            let (image, in_object) = charts[index].render_plot(panel_ui_data);
+            images[hover] = image;
+            is_in_object = in_object;
+            if self.panels[hover].links.crosshair {
+                crosshair_time = charts[index].time_at(ui_data.position_x - rect.x as i32);
+            }
+        }
+
+        for (i, panel) in self.panels.iter().enumerate() {
+            let Some(index) = indices[i] else {
+                continue;
+            };
+            if Some(i) == hover {
+                continue;
+            }
+            let rect = rects[i];
+            let mut panel_ui_data = self.panel_ui_data(&ui_data, i, rect);
+            panel_ui_data.is_release = true;
+            panel_ui_data.move_x = 0;
+            panel_ui_data.move_y = 0;
+            panel_ui_data.zoom = 0;
+            panel_ui_data.position_y = NO_CURSOR_ROW;
+            panel_ui_data.press_y = NO_CURSOR_ROW;
+            match crosshair_time
+                .filter(|_| panel.links.crosshair)
+                .and_then(|time| charts[index].x_of_time(time))
+            {
+                Some(x) => panel_ui_data.position_x = x,
+                None => {
+                    // The legend shows the latest candle
+                    let chart = &charts[index];
+                    panel_ui_data.hide_cursor = true;
+                    panel_ui_data.position_x = chart
+                        .candle_data
+                        .last()
+                        .and_then(|candle| chart.x_of_time(candle.time))
+                        .unwrap_or_default();
+                }
+            }
+            panel_ui_data.press_x = panel_ui_data.position_x;
+            images[i] = charts[index].render_plot(panel_ui_data).0;
+        }
+
+        if self.panels.len() == 1 {
+            return (images.remove(0), is_in_object);
+        }
+        let parts: Vec<(PanelRect, Image)> = rects.into_iter().zip(images).collect();
+        (compose(width, height, &parts, self.active), is_in_object)
+    }
+
+    /// `ui_data` sized to panel `index`, the toolbar flags kept for the active panel only
+    fn panel_ui_data(&self, ui_data: &UiData, index: usize, rect: PanelRect) -> UiData {
+        let mut panel_ui_data = ui_data.clone();
+        panel_ui_data.ticker = self.panels[index].stock_name.clone();
+        panel_ui_data.time_frame = self.panels[index].time_frame.to_string();
+        panel_ui_data.width = rect.width as i32;
+        panel_ui_data.height = rect.height as i32;
+        if index != self.active {
+            panel_ui_data.is_clean = false;
+            panel_ui_data.is_undo = false;
+            panel_ui_data.is_redo = false;
+            panel_ui_data.is_delete = false;
+            panel_ui_data.is_restyle = false;
+            panel_ui_data.is_select_layer = false;
+            panel_ui_data.is_layer_action = false;
+            panel_ui_data.is_toggle_indicator = false;
+        }
+        panel_ui_data
+    }
+
+    pub fn write_to_bytes(&self, buf: &mut Vec<u8>) {
+        write_str(buf, self.kind.label());
+        buf.extend_from_slice(&(self.active as u32).to_le_bytes());
+        buf.extend_from_slice(&(self.panels.len() as u32).to_le_bytes());
+        for panel in &self.panels {
+            write_str(buf, &panel.stock_name);
+            write_str(buf, &panel.time_frame.to_string());
+            buf.push(panel.links.symbol as u8);
+            buf.push(panel.links.time_frame as u8);
+            buf.push(panel.links.crosshair as u8);
+        }
+    }
+
+    pub fn read_from_bytes(data: &[u8]) -> Option<(Self, usize)> {
+        let mut pos = 0;
+        let kind = LayoutKind::parse(&read_str(data, &mut pos)?)?;
+        let active = read_u32(data, &mut pos)? as usize;
+        let count = read_u32(data, &mut pos)? as usize;
+        if count != kind.panel_count() || active >= count {
+            return None;
+        }
+        let mut panels = Vec::with_capacity(count);
+        for _ in 0..count {
+            let stock_name = read_str(data, &mut pos)?;
+            let time_frame = TimeFrame::parse(&read_str(data, &mut pos)?)?;
+            let flags = data.get(pos..pos + 3)?;
+            pos += 3;
+            panels.push(ChartPanel {
+                stock_name,
+                time_frame,
+                links: PanelLinks {
+                    symbol: flags[0] != 0,
+                    time_frame: flags[1] != 0,
+                    crosshair: flags[2] != 0,
+                },
+            });
+        }
+        Some((
+            Self {
+                kind,
+                panels,
+                active,
+            },
+            pos,
+        ))
+    }
+}
+
+pub(crate) fn write_str(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    buf.extend_from_slice(value.as_bytes());
+}
+
+pub(crate) fn read_u32(data: &[u8], pos: &mut usize) -> Option<u32> {
+    let value = u32::from_le_bytes(data.get(*pos..*pos + 4)?.try_into().ok()?);
+    *pos += 4;
+    Some(value)
+}
+
+pub(crate) fn read_str(data: &[u8], pos: &mut usize) -> Option<String> {
+    let len = read_u32(data, pos)? as usize;
+    let value = String::from_utf8(data.get(*pos..*pos + len)?.to_vec()).ok()?;
+    *pos += len;
+    Some(value)
+}
+
+/// Paste panel images into one `width` x `height` image and outline the active panel
+fn compose(width: u32, height: u32, parts: &[(PanelRect, Image)], active: usize) -> Image {
+    let mut buffer = SharedPixelBuffer::<Rgb8Pixel>::new(width, height);
+    let stride = width as usize;
+    let pixels = buffer.make_mut_slice();
+    pixels.fill(GAP_COLOR);
+
+    for (rect, image) in parts {
+        let Some(source) = image.to_rgb8() else {
+            continue;
+        };
+        let copy_width = source.width().min(rect.width) as usize;
+        let copy_height = source.height().min(rect.height) as usize;
+        let source_stride = source.width() as usize;
+        let source_pixels = source.as_slice();
+        for row in 0..copy_height {
+            let from = row * source_stride;
+            let to = (rect.y as usize + row) * stride + rect.x as usize;
+            pixels[to..to + copy_width].copy_from_slice(&source_pixels[from..from + copy_width]);
+        }
+    }
+
+    if let Some((rect, _)) = parts.get(active)
+        && rect.width > 0
+        && rect.height > 0
+    {
+        let (left, top) = (rect.x as usize, rect.y as usize);
+        let (right, bottom) = (
+            left + rect.width as usize - 1,
+            top + rect.height as usize - 1,
+        );
+        for x in left..=right {
+            pixels[top * stride + x] = ACTIVE_COLOR;
+            pixels[bottom * stride + x] = ACTIVE_COLOR;
+        }
+        for y in top..=bottom {
+            pixels[y * stride + left] = ACTIVE_COLOR;
+            pixels[y * stride + right] = ACTIVE_COLOR;
+        }
+    }
+
+    Image::from_rgb8(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_rects_tile_the_image() {
+        for kind in LayoutKind::ALL {
+            let rects = kind.rects(1202, 802);
+            assert_eq!(rects.len(), kind.panel_count());
+            let area: u32 = rects.iter().map(|rect| rect.width * rect.height).sum();
+            // Only the gaps are left uncovered
+            assert!(area <= 1202 * 802);
+            assert!(area >= 1200 * 796);
+            for rect in &rects {
+                assert!(rect.x + rect.width <= 1202 && rect.y + rect.height <= 802);
+            }
+        }
+        let rects = LayoutKind::TwoByTwo.rects(1202, 802);
+        assert_eq!(rects[3], PanelRect::new(602, 402, 600, 400));
+        assert!(rects[3].contains(700, 500));
+        assert!(!rects[0].contains(601, 10));
+    }
+
+    #[test]
+    fn test_linked_panels_follow_the_active_one() {
+        let mut layout = ChartLayout::new("FPT".to_string(), TimeFrame::Day(1));
+        layout.set_kind(LayoutKind::OnePlusThree);
+        assert_eq!(layout.panels()[1].stock_name, "FPT");
+        assert_eq!(layout.panels()[2].time_frame, TimeFrame::Minute(15));
+
+        layout.set_active(2);
+        layout.toggle_link(Link::Symbol);
+        layout.set_active(0);
+        layout.select("HPG", TimeFrame::Week(1));
+        let names: Vec<&str> = layout
+            .panels()
+            .iter()
+            .map(|panel| panel.stock_name.as_str())
+            .collect();
+        assert_eq!(names, ["HPG", "HPG", "FPT", "HPG"]);
+        // Time frames are not linked by default
+        assert_eq!(layout.panels()[0].time_frame, TimeFrame::Week(1));
+        assert_eq!(layout.panels()[1].time_frame, TimeFrame::Hour(1));
+
+        layout.set_kind(LayoutKind::TwoByOne);
+        assert_eq!(layout.panels().len(), 2);
+        assert_eq!(layout.missing_charts(&[]).len(), 2);
+    }
+
+    #[test]
+    fn test_layout_bytes_round_trip() {
+        let mut layout = ChartLayout::new("VNM".to_string(), TimeFrame::Hour(4));
+        layout.set_kind(LayoutKind::TwoByTwo);
+        layout.set_active(3);
+        layout.toggle_link(Link::Crosshair);
+        let mut buf = Vec::new();
+        layout.write_to_bytes(&mut buf);
+        let (read, used) = ChartLayout::read_from_bytes(&buf).unwrap();
+        assert_eq!(read, layout);
+        assert_eq!(used, buf.len());
+        assert!(ChartLayout::read_from_bytes(&buf[..buf.len() - 1]).is_none());
+    }
+}
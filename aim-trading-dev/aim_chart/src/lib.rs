@@ -1,11 +1,32 @@
 mod cache;
 mod chart;
 mod company_info;
+pub mod depth;
 mod draw;
+mod footprint;
+mod history;
+mod indicator;
+mod layer;
+mod layout;
 pub mod mini_chart;
+mod pane;
+mod template;
+pub use cache::{
+    CACHE_MAGIC, CACHE_VERSION, CacheRecord, RECORD_CHART, RECORD_LAYOUT, RECORD_TEMPLATE, fnv1a,
+    read_record, write_record,
+};
 pub use chart::Chart;
 use chrono::{DateTime, Utc};
 pub use company_info::CompanyInfo;
+pub use footprint::{
+    FlowTrade, ORDER_FLOW_CAPACITY, OrderFlow, ProfileRow, VALUE_AREA_SHARE, VolumeProfile,
+};
+pub use history::{DEFAULT_HISTORY_DEPTH, EditCommand, EditHistory};
+pub use indicator::{Indicator, IndicatorKind, IndicatorLine};
+pub use layer::{DEFAULT_LAYER, DrawingLayer, LayerAction, Layers};
+pub use layout::{ChartLayout, ChartPanel, LayoutKind, Link, PanelLinks, PanelRect};
+pub use pane::{Pane, PaneContent, PaneLayout, PaneRect};
+pub use template::{ChartTemplate, DrawingStyle};
 
 use aim_data::Candlestick;
 use slint::Color;
@@ -22,6 +43,11 @@ pub enum MouseType {
     Text,
     HorizontalLine,
     VerticalLine,
+    FibRetracement,
+    FibExtension,
+    Channel,
+    Pitchfork,
+    Range,
 }
 
 #[derive(Debug, Clone)]
@@ -44,8 +70,32 @@ pub struct UiData {
     pub is_new_stock: bool,
     pub is_in_object: bool,
     pub is_undo: bool,
+    pub is_redo: bool,
+    /// Delete the selected drawing
+    pub is_delete: bool,
+    /// Give the selected drawing the current colour
+    pub is_restyle: bool,
     pub is_in_update: bool,
     pub color: Color,
+    pub indicator: String,
+    pub is_toggle_indicator: bool,
+    pub layout: String,
+    pub is_new_layout: bool,
+    pub link: i32,
+    pub is_toggle_link: bool,
+    /// Draw no crosshair, for panels the mouse is not over
+    pub hide_cursor: bool,
+    /// Annotation placed by the text tool
+    pub text: String,
+    pub text_size: i32,
+    /// Fibonacci levels typed in the toolbar, the defaults when empty
+    pub fib_levels: String,
+    /// Layer new drawings go to
+    pub layer: String,
+    pub is_select_layer: bool,
+    /// Entry of the layer picker, see [`LayerAction::from_index`]
+    pub layer_action: i32,
+    pub is_layer_action: bool,
 }
 
 impl Default for UiData {
@@ -69,8 +119,25 @@ impl Default for UiData {
             is_new_stock: false,
             is_in_object: false,
             is_undo: false,
+            is_redo: false,
+            is_delete: false,
+            is_restyle: false,
             is_in_update: false,
             color: Color::default(),
+            indicator: String::new(),
+            is_toggle_indicator: false,
+            layout: String::new(),
+            is_new_layout: false,
+            link: 0,
+            is_toggle_link: false,
+            hide_cursor: false,
+            text: String::new(),
+            text_size: 14,
+            fib_levels: String::new(),
+            layer: String::new(),
+            is_select_layer: false,
+            layer_action: 0,
+            is_layer_action: false,
         }
     }
 }
@@ -107,7 +174,7 @@ impl CandleData {
     }
 
     pub fn read_from_bytes(data: &[u8]) -> Option<(Self, usize)> {
-        if data.len() < 4 + 8 + 4 * 6 {
+        if data.len() < 4 + 8 + 4 * 5 {
             return None;
         }
         let mut pos = 0;
@@ -0,0 +1,259 @@
+use crate::{
+    indicator::IndicatorKind,
+    layout::{read_str, read_u32, write_str},
+};
+
+/// Share of the image a new pane takes
+const DEFAULT_PANE_RATIO: f32 = 0.2;
+/// Smallest share a pane can be resized to
+const MIN_PANE_RATIO: f32 = 0.05;
+/// Share of the image always left to the price pane
+const MIN_PRICE_RATIO: f32 = 0.4;
+/// Distance in pixels from a divider that still grabs it
+const DIVIDER_GRAB_PX: i32 = 4;
+
+/// What a pane below the price chart shows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaneContent {
+    Volume,
+    /// An oscillator on its own y-axis
+    Indicator(IndicatorKind),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pane {
+    pub content: PaneContent,
+    /// Share of the whole chart height
+    pub ratio: f32,
+}
+
+/// Pixel rows of a pane inside the chart image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaneRect {
+    pub top: u32,
+    pub height: u32,
+}
+
+/// Panes stacked under the price chart, top to bottom
+///
+/// All panes share the price chart's x range. Without a volume pane the
+/// volume bars stay at the bottom of the price chart.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PaneLayout {
+    panes: Vec<Pane>,
+    /// Pane whose top divider is being dragged
+    dragging: Option<usize>,
+}
+
+impl PaneLayout {
+    pub fn panes(&self) -> &[Pane] {
+        &self.panes
+    }
+
+    pub fn contains(&self, content: &PaneContent) -> bool {
+        self.panes.iter().any(|pane| pane.content == *content)
+    }
+
+    /// Append a pane, shrinking the others if the price chart would get too small
+    pub fn add(&mut self, content: PaneContent) {
+        if self.contains(&content) {
+            return;
+        }
+        self.panes.push(Pane {
+            content,
+            ratio: DEFAULT_PANE_RATIO,
+        });
+        let total: f32 = self.panes.iter().map(|pane| pane.ratio).sum();
+        let budget = 1.0 - MIN_PRICE_RATIO;
+        if total > budget {
+            for pane in &mut self.panes {
+                pane.ratio *= budget / total;
+            }
+        }
+    }
+
+    pub fn remove(&mut self, content: &PaneContent) {
+        self.panes.retain(|pane| pane.content != *content);
+        self.dragging = None;
+    }
+
+    pub fn toggle(&mut self, content: PaneContent) {
+        if self.contains(&content) {
+            self.remove(&content);
+        } else {
+            self.add(content);
+        }
+    }
+
+    /// Set the share of pane `index`, taking the difference from the price chart
+    pub fn resize(&mut self, index: usize, ratio: f32) {
+        let others: f32 = self
+            .panes
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, pane)| pane.ratio)
+            .sum();
+        let max = (1.0 - MIN_PRICE_RATIO - others).max(MIN_PANE_RATIO);
+        if let Some(pane) = self.panes.get_mut(index) {
+            pane.ratio = ratio.clamp(MIN_PANE_RATIO, max);
+        }
+    }
+
+    /// Height of the price chart and rows of every pane in an image `height` pixels tall
+    pub fn split(&self, height: u32) -> (u32, Vec<PaneRect>) {
+        let heights: Vec<u32> = self
+            .panes
+            .iter()
+            .map(|pane| (pane.ratio * height as f32) as u32)
+            .collect();
+        let price_height = height.saturating_sub(heights.iter().sum());
+        let mut top = price_height;
+        let rects = heights
+            .into_iter()
+            .map(|height| {
+                let rect = PaneRect { top, height };
+                top += height;
+                rect
+            })
+            .collect();
+        (price_height, rects)
+    }
+
+    /// Pane whose top divider is within reach of pixel row `y`
+    fn divider_at(&self, height: u32, y: i32) -> Option<usize> {
+        let (_, rects) = self.split(height);
+        rects
+            .iter()
+            .position(|rect| (rect.top as i32 - y).abs() <= DIVIDER_GRAB_PX)
+    }
+
+    /// Follow a mouse drag on a divider, returns whether one is being dragged
+    /// or was just released
+    ///
+    /// `press_y` is where the button went down and `position_y` where the mouse
+    /// is now, both in pixels from the top of an image `height` pixels tall.
+    pub fn drag(&mut self, height: u32, press_y: i32, position_y: i32, released: bool) -> bool {
+        if released {
+            // Still report the release so it does not turn into a pan
+            return self.dragging.take().is_some();
+        }
+        if self.dragging.is_none() {
+            self.dragging = self.divider_at(height, press_y);
+        }
+        let Some(index) = self.dragging else {
+            return false;
+        };
+        // The bottom of the pane stays put, its divider follows the mouse
+        let (_, rects) = self.split(height);
+        let bottom = rects[index].top + rects[index].height;
+        let new_height = bottom as i32 - position_y;
+        self.resize(index, new_height as f32 / height.max(1) as f32);
+        true
+    }
+
+    pub fn write_to_bytes(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&(self.panes.len() as u32).to_le_bytes());
+        for pane in &self.panes {
+            match pane.content {
+                PaneContent::Volume => buf.push(0),
+                PaneContent::Indicator(kind) => {
+                    buf.push(1);
+                    write_str(buf, &kind.to_string());
+                }
+            }
+            buf.extend_from_slice(&pane.ratio.to_le_bytes());
+        }
+    }
+
+    /// Read panes written by [`PaneLayout::write_to_bytes`], `None` if they
+    /// are cut short or name an unknown indicator
+    pub fn read_from_bytes(data: &[u8]) -> Option<Self> {
+        let mut pos = 0;
+        let count = read_u32(data, &mut pos)? as usize;
+        let mut panes = Vec::with_capacity(count.min(data.len()));
+        for _ in 0..count {
+            let content = match *data.get(pos)? {
+                0 => {
+                    pos += 1;
+                    PaneContent::Volume
+                }
+                1 => {
+                    pos += 1;
+                    PaneContent::Indicator(IndicatorKind::parse(&read_str(data, &mut pos)?)?)
+                }
+                _ => return None,
+            };
+            let ratio = f32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?);
+            pos += 4;
+            panes.push(Pane { content, ratio });
+        }
+        Some(Self {
+            panes,
+            dragging: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSI: PaneContent = PaneContent::Indicator(IndicatorKind::Rsi { period: 14 });
+
+    #[test]
+    fn test_panes_leave_room_for_price_chart() {
+        let mut layout = PaneLayout::default();
+        layout.add(PaneContent::Volume);
+        layout.add(RSI);
+        layout.add(RSI);
+        assert_eq!(layout.panes().len(), 2);
+        assert_eq!(
+            layout.split(1000),
+            (
+                600,
+                vec![
+                    PaneRect {
+                        top: 600,
+                        height: 200
+                    },
+                    PaneRect {
+                        top: 800,
+                        height: 200
+                    }
+                ]
+            )
+        );
+
+        layout.add(PaneContent::Indicator(IndicatorKind::Obv));
+        let (price_height, rects) = layout.split(1000);
+        assert!(price_height >= 400);
+        assert_eq!(rects.len(), 3);
+
+        layout.remove(&PaneContent::Volume);
+        assert!(!layout.contains(&PaneContent::Volume));
+    }
+
+    #[test]
+    fn test_drag_divider_resizes_pane() {
+        let mut layout = PaneLayout::default();
+        layout.add(PaneContent::Volume);
+        layout.add(RSI);
+        // Grab the divider above the RSI pane at 800px and pull it up 100px
+        assert!(layout.drag(1000, 802, 700, false));
+        assert_eq!(
+            layout.split(1000).1[1],
+            PaneRect {
+                top: 700,
+                height: 300
+            }
+        );
+        // The grab holds while the mouse moves away from the old divider
+        assert!(layout.drag(1000, 802, 750, false));
+        assert_eq!(layout.split(1000).1[1].height, 250);
+        assert!(layout.drag(1000, 802, 750, true));
+        assert!(!layout.drag(1000, 802, 750, true));
+        // Pressing away from any divider does nothing
+        assert!(!layout.drag(1000, 300, 200, false));
+    }
+}
@@ -0,0 +1,143 @@
+use plotters::style::RGBColor;
+
+use crate::{
+    Chart, Indicator, IndicatorKind,
+    layer::DrawingLayer,
+    layout::{read_str, read_u32, write_str},
+    pane::PaneContent,
+};
+
+/// Colour and sizes the drawing tools start with
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawingStyle {
+    pub color: RGBColor,
+    pub text_size: u32,
+    /// Fibonacci levels as typed in the toolbar, empty for the defaults
+    pub fib_levels: String,
+}
+
+/// Drawing style, indicators and layers saved under a name, to set up
+/// other charts the same way
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartTemplate {
+    pub name: String,
+    pub style: DrawingStyle,
+    pub indicators: Vec<Indicator>,
+    /// Layer names with their visibility and lock, not their drawings
+    pub layers: Vec<DrawingLayer>,
+}
+
+impl ChartTemplate {
+    /// Capture the indicators and layers of `chart` with the tools' `style`
+    pub fn from_chart(name: &str, chart: &Chart, style: DrawingStyle) -> Self {
+        Self {
+            name: name.to_string(),
+            style,
+            indicators: chart.indicators.clone(),
+            layers: chart.layers.all().to_vec(),
+        }
+    }
+
+    pub fn write_to_bytes(&self, buf: &mut Vec<u8>) {
+        write_str(buf, &self.name);
+        let RGBColor(r, g, b) = self.style.color;
+        buf.extend_from_slice(&[r, g, b]);
+        buf.extend_from_slice(&self.style.text_size.to_le_bytes());
+        write_str(buf, &self.style.fib_levels);
+
+        write_indicators(buf, &self.indicators);
+
+        buf.extend_from_slice(&(self.layers.len() as u32).to_le_bytes());
+        for layer in &self.layers {
+            layer.write_to_bytes(buf);
+        }
+    }
+
+    /// Read a template, `None` if it is cut short or names an unknown indicator
+    pub fn read_from_bytes(data: &[u8]) -> Option<(Self, usize)> {
+        let mut pos = 0;
+        let name = read_str(data, &mut pos)?;
+        let color = read_color(data, &mut pos)?;
+        let text_size = read_u32(data, &mut pos)?;
+        let fib_levels = read_str(data, &mut pos)?;
+
+        let indicators = read_indicators(data, &mut pos)?;
+
+        let count = read_u32(data, &mut pos)? as usize;
+        let mut layers = Vec::with_capacity(count.min(data.len()));
+        for _ in 0..count {
+            let (layer, used) = DrawingLayer::read_from_bytes(&data[pos..])?;
+            layers.push(layer);
+            pos += used;
+        }
+
+        let template = Self {
+            name,
+            style: DrawingStyle {
+                color,
+                text_size,
+                fib_levels,
+            },
+            indicators,
+            layers,
+        };
+        Some((template, pos))
+    }
+}
+
+fn read_color(data: &[u8], pos: &mut usize) -> Option<RGBColor> {
+    let rgb = data.get(*pos..*pos + 3)?;
+    *pos += 3;
+    Some(RGBColor(rgb[0], rgb[1], rgb[2]))
+}
+
+/// Indicator kinds with their colours and visibility, as templates and the
+/// chart cache store them
+pub(crate) fn write_indicators(buf: &mut Vec<u8>, indicators: &[Indicator]) {
+    buf.extend_from_slice(&(indicators.len() as u32).to_le_bytes());
+    for indicator in indicators {
+        write_str(buf, &indicator.kind.to_string());
+        buf.push(indicator.visible as u8);
+        buf.extend_from_slice(&(indicator.colors.len() as u32).to_le_bytes());
+        for RGBColor(r, g, b) in &indicator.colors {
+            buf.extend_from_slice(&[*r, *g, *b]);
+        }
+    }
+}
+
+/// Read indicators written by [`write_indicators`], `None` if they are cut
+/// short or name an unknown kind
+pub(crate) fn read_indicators(data: &[u8], pos: &mut usize) -> Option<Vec<Indicator>> {
+    let count = read_u32(data, pos)? as usize;
+    let mut indicators = Vec::with_capacity(count.min(data.len()));
+    for _ in 0..count {
+        let kind = IndicatorKind::parse(&read_str(data, pos)?)?;
+        let visible = *data.get(*pos)? != 0;
+        *pos += 1;
+        let colors = (0..read_u32(data, pos)?)
+            .map(|_| read_color(data, pos))
+            .collect::<Option<Vec<RGBColor>>>()?;
+        let mut indicator = Indicator::new(kind).with_colors(&colors);
+        indicator.visible = visible;
+        indicators.push(indicator);
+    }
+    Some(indicators)
+}
+
+impl Chart {
+    /// Take the indicators and layer settings of `template`
+    ///
+    /// Drawings stay; layers the template does not know are kept as they are.
+    pub fn apply_template(&mut self, template: &ChartTemplate) {
+        for indicator in &self.indicators {
+            self.panes.remove(&PaneContent::Indicator(indicator.kind));
+        }
+        self.indicators = template.indicators.clone();
+        self.sync_indicator_panes();
+        for layer in &template.layers {
+            let own = self.layers.ensure(&layer.name);
+            own.visible = layer.visible;
+            own.locked = layer.locked;
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::feed_alert::{FeedAlert, SeenIds};
+
+/// Alert log entries kept, oldest dropped first
+pub const ALERT_LOG_CAPACITY: usize = 500;
+
+/// What an alert watches for on its symbol
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum AlertCondition {
+    /// The price crosses `price`, either way
+    PriceCross { price: f64 },
+    /// The change from the reference price reaches `percent`, either way
+    ChangeBeyond { percent: f64 },
+    /// The volume so far today reaches `ratio` times the average of the
+    /// last `days` sessions
+    VolumeSpike { ratio: f64, days: usize },
+    /// The price hits the ceiling
+    Ceiling,
+    /// The price hits the floor
+    Floor,
+    /// The price crosses a horizontal or trend line drawn on the symbol
+    DrawnLine,
+}
+
+impl AlertCondition {
+    /// Parse a name followed by its parameters, e.g. `PRICE 25.5`,
+    /// `CHANGE 5`, `VOLUME 2 20` or `CEILING`
+    pub fn parse(label: &str) -> Option<Self> {
+        let mut parts = label.split_whitespace();
+        let name = parts.next()?.to_uppercase();
+        let params = parts
+            .map(|part| part.replace(',', ".").parse::<f64>().ok())
+            .collect::<Option<Vec<f64>>>()?;
+        let positive = |index: usize| params.get(index).copied().filter(|value| *value > 0.0);
+
+        let condition = match name.as_str() {
+            "PRICE" => AlertCondition::PriceCross {
+                price: positive(0)?,
+            },
+            "CHANGE" => AlertCondition::ChangeBeyond {
+                percent: positive(0)?,
+            },
+            "VOLUME" => AlertCondition::VolumeSpike {
+                ratio: positive(0)?,
+                days: positive(1).map_or(20, |days| days as usize),
+            },
+            "CEILING" => AlertCondition::Ceiling,
+            "FLOOR" => AlertCondition::Floor,
+            "LINES" => AlertCondition::DrawnLine,
+            _ => return None,
+        };
+        Some(condition)
+    }
+
+    /// Whether the alert fires on a move through a level rather than on
+    /// a state it enters
+    fn is_crossing(&self) -> bool {
+        matches!(
+            self,
+            AlertCondition::PriceCross { .. } | AlertCondition::DrawnLine
+        )
+    }
+}
+
+impl fmt::Display for AlertCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertCondition::PriceCross { price } => write!(f, "PRICE {price}"),
+            AlertCondition::ChangeBeyond { percent } => write!(f, "CHANGE {percent}"),
+            AlertCondition::VolumeSpike { ratio, days } => write!(f, "VOLUME {ratio} {days}"),
+            AlertCondition::Ceiling => write!(f, "CEILING"),
+            AlertCondition::Floor => write!(f, "FLOOR"),
+            AlertCondition::DrawnLine => write!(f, "LINES"),
+        }
+    }
+}
+
+/// Latest market-watch figures of one symbol, prices in thousands of VND
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub symbol: String,
+    pub price: f64,
+    pub ref_price: f64,
+    pub ceiling: f64,
+    pub floor: f64,
+    /// Volume matched so far today
+    pub volume: f64,
+    pub time: DateTime<Utc>,
+}
+
+impl Quote {
+    /// Change from the reference price in percent
+    pub fn change_percent(&self) -> f64 {
+        if self.ref_price > 0.0 {
+            (self.price - self.ref_price) / self.ref_price * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// What the charts know about a symbol, for the conditions a quote alone
+/// cannot decide
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlertContext {
+    /// Volumes of the sessions before today, oldest first
+    pub session_volumes: Vec<f64>,
+    /// Prices of the drawn horizontal and trend lines at the quote time
+    pub line_prices: Vec<f64>,
+}
+
+/// A condition set on a symbol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: u32,
+    pub symbol: String,
+    pub condition: AlertCondition,
+    pub enabled: bool,
+    /// Whether the condition held on the last quote, so a state fires once
+    /// when it is entered
+    #[serde(skip)]
+    met: bool,
+}
+
+/// A fired alert, as kept in the log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertEvent {
+    pub alert_id: u32,
+    pub symbol: String,
+    pub message: String,
+    pub price: f64,
+    pub time: DateTime<Utc>,
+}
+
+/// The alerts of the user and the log of the ones that fired
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertBook {
+    alerts: Vec<Alert>,
+    log: VecDeque<AlertEvent>,
+    next_id: u32,
+    /// Price of the previous quote of each symbol, for crossings
+    #[serde(skip)]
+    last_prices: HashMap<String, f64>,
+    /// Rules on the abnormal trade and insider transaction feeds
+    #[serde(default)]
+    pub(crate) rules: Vec<FeedAlert>,
+    /// Ids of the abnormal trades already checked
+    #[serde(default)]
+    pub(crate) seen_trades: SeenIds,
+    /// Ids of the insider transactions already checked
+    #[serde(default)]
+    pub(crate) seen_insider: SeenIds,
+    /// Feeds fetched before, whose new ids are new events
+    #[serde(default)]
+    pub(crate) primed_feeds: BTreeSet<String>,
+}
+
+impl AlertBook {
+    pub fn alerts(&self) -> &[Alert] {
+        &self.alerts
+    }
+
+    /// Fired alerts, newest last
+    pub fn log(&self) -> &VecDeque<AlertEvent> {
+        &self.log
+    }
+
+    /// Watch `symbol` for `condition`, returns the id of the new alert
+    pub fn add(&mut self, symbol: &str, condition: AlertCondition) -> u32 {
+        let id = self.take_id();
+        self.alerts.push(Alert {
+            id,
+            symbol: symbol.to_uppercase(),
+            condition,
+            enabled: true,
+            met: false,
+        });
+        id
+    }
+
+    /// Drop alert or feed rule `id`, returns whether it existed
+    pub fn remove(&mut self, id: u32) -> bool {
+        let len = self.alerts.len() + self.rules.len();
+        self.alerts.retain(|alert| alert.id != id);
+        self.rules.retain(|rule| rule.id != id);
+        self.alerts.len() + self.rules.len() != len
+    }
+
+    /// Id for a new alert or feed rule, shared so one list can show both
+    pub(crate) fn take_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Append fired alerts to the log, dropping the oldest past capacity
+    pub(crate) fn record(&mut self, events: &[AlertEvent]) {
+        for event in events {
+            if self.log.len() == ALERT_LOG_CAPACITY {
+                self.log.pop_front();
+            }
+            self.log.push_back(event.clone());
+        }
+    }
+
+    /// Symbols with an enabled alert, to be polled
+    pub fn symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self
+            .alerts
+            .iter()
+            .filter(|alert| alert.enabled)
+            .map(|alert| alert.symbol.clone())
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+
+    /// Check the alerts of `quote.symbol` against it, log and return the
+    /// ones that fire
+    pub fn evaluate(&mut self, quote: &Quote, context: &AlertContext) -> Vec<AlertEvent> {
+        if quote.price <= 0.0 {
+            return Vec::new();
+        }
+        let previous = self.last_prices.insert(quote.symbol.clone(), quote.price);
+        let mut events = Vec::new();
+        for alert in self
+            .alerts
+            .iter_mut()
+            .filter(|alert| alert.enabled && alert.symbol == quote.symbol)
+        {
+            let messages = if alert.condition.is_crossing() {
+                previous
+                    .map(|previous| crossings(&alert.condition, previous, quote, context))
+                    .unwrap_or_default()
+            } else {
+                let state = state_message(&alert.condition, quote, context);
+                let was_met = std::mem::replace(&mut alert.met, state.is_some());
+                state.filter(|_| !was_met).into_iter().collect()
+            };
+            events.extend(messages.into_iter().map(|message| AlertEvent {
+                alert_id: alert.id,
+                symbol: quote.symbol.clone(),
+                message,
+                price: quote.price,
+                time: quote.time,
+            }));
+        }
+
+        self.record(&events);
+        events
+    }
+}
+
+/// Messages for every level of `condition` the price went through since `previous`
+fn crossings(
+    condition: &AlertCondition,
+    previous: f64,
+    quote: &Quote,
+    context: &AlertContext,
+) -> Vec<String> {
+    let crossed = |level: f64| {
+        if previous < level && quote.price >= level {
+            Some("lên")
+        } else if previous > level && quote.price <= level {
+            Some("xuống")
+        } else {
+            None
+        }
+    };
+    let symbol = &quote.symbol;
+    match condition {
+        AlertCondition::PriceCross { price } => crossed(*price)
+            .map(|way| format!("{symbol} cắt {way} {price:.2}"))
+            .into_iter()
+            .collect(),
+        AlertCondition::DrawnLine => context
+            .line_prices
+            .iter()
+            .filter_map(|level| {
+                crossed(*level).map(|way| format!("{symbol} cắt {way} đường vẽ tại {level:.2}"))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Message of a state condition while it holds
+fn state_message(
+    condition: &AlertCondition,
+    quote: &Quote,
+    context: &AlertContext,
+) -> Option<String> {
+    let symbol = &quote.symbol;
+    let price = quote.price;
+    match *condition {
+        AlertCondition::ChangeBeyond { percent } => {
+            let change = quote.change_percent();
+            (change.abs() >= percent).then(|| format!("{symbol} thay đổi {change:+.2}%"))
+        }
+        AlertCondition::VolumeSpike { ratio, days } => {
+            let recent =
+                &context.session_volumes[context.session_volumes.len().saturating_sub(days)..];
+            if recent.is_empty() {
+                return None;
+            }
+            let average = recent.iter().sum::<f64>() / recent.len() as f64;
+            (average > 0.0 && quote.volume >= ratio * average).then(|| {
+                let times = quote.volume / average;
+                format!(
+                    "{symbol} khối lượng gấp {times:.1} lần TB {} phiên",
+                    recent.len()
+                )
+            })
+        }
+        AlertCondition::Ceiling => (quote.ceiling > 0.0 && price >= quote.ceiling)
+            .then(|| format!("{symbol} chạm giá trần {price:.2}")),
+        AlertCondition::Floor => (quote.floor > 0.0 && price <= quote.floor)
+            .then(|| format!("{symbol} chạm giá sàn {price:.2}")),
+        AlertCondition::PriceCross { .. } | AlertCondition::DrawnLine => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn quote(price: f64, volume: f64) -> Quote {
+        Quote {
+            symbol: "FPT".to_string(),
+            price,
+            ref_price: 100.0,
+            ceiling: 107.0,
+            floor: 93.0,
+            volume,
+            time: Utc.with_ymd_and_hms(2024, 1, 2, 3, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_alerts_fire_once_per_crossing_or_state() {
+        let mut book = AlertBook::default();
+        book.add("fpt", AlertCondition::parse("PRICE 101.5").unwrap());
+        book.add("FPT", AlertCondition::parse("change 3").unwrap());
+        book.add("FPT", AlertCondition::parse("VOLUME 2 2").unwrap());
+        book.add("FPT", AlertCondition::Ceiling);
+        let lines = book.add("FPT", AlertCondition::DrawnLine);
+        assert_eq!(AlertCondition::parse("PRICE x"), None);
+        assert_eq!(
+            AlertCondition::parse(
+                &AlertCondition::VolumeSpike {
+                    ratio: 2.5,
+                    days: 10
+                }
+                .to_string()
+            ),
+            Some(AlertCondition::VolumeSpike {
+                ratio: 2.5,
+                days: 10
+            })
+        );
+        let context = AlertContext {
+            session_volumes: vec![9_000.0, 100.0, 300.0],
+            line_prices: vec![102.0],
+        };
+
+        // Nothing to cross from on the first quote
+        assert!(book.evaluate(&quote(101.0, 100.0), &context).is_empty());
+        let events = book.evaluate(&quote(102.5, 100.0), &context);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].message, "FPT cắt lên 101.50");
+        assert_eq!(events[1].alert_id, lines);
+
+        let events = book.evaluate(&quote(103.5, 460.0), &context);
+        let messages: Vec<&str> = events.iter().map(|event| event.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "FPT thay đổi +3.50%",
+                "FPT khối lượng gấp 2.3 lần TB 2 phiên"
+            ]
+        );
+        // Still beyond 3% and still heavy: no repeat
+        assert!(book.evaluate(&quote(104.0, 500.0), &context).is_empty());
+        let events = book.evaluate(&quote(107.0, 500.0), &context);
+        assert_eq!(events[0].message, "FPT chạm giá trần 107.00");
+
+        let events = book.evaluate(&quote(101.0, 500.0), &context);
+        assert_eq!(events.len(), 2);
+        assert!(events[0].message.contains("xuống"));
+        assert_eq!(book.log().len(), 7);
+
+        let json = serde_json::to_string(&book).unwrap();
+        let read: AlertBook = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.alerts().len(), 5);
+        assert_eq!(read.log(), book.log());
+        assert!(book.remove(lines));
+        assert_eq!(book.symbols(), ["FPT"]);
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+
+/// Offset of Vietnam time (ICT) from UTC in seconds
+pub const VN_UTC_OFFSET_SECS: i64 = 7 * 3600;
+
+/// Days the exchanges are closed on top of weekends
+///
+/// Lunar holidays (Tết, Hùng Kings) and the compensatory days move every year
+/// and are announced by the State Securities Commission; extend the table with
+/// [`TradingCalendar::add_holidays`] until it is updated here.
+const HOLIDAYS: &[(i32, u32, u32)] = &[
+    // 2024
+    (2024, 1, 1),
+    (2024, 2, 8),
+    (2024, 2, 9),
+    (2024, 2, 12),
+    (2024, 2, 13),
+    (2024, 2, 14),
+    (2024, 4, 18),
+    (2024, 4, 29),
+    (2024, 4, 30),
+    (2024, 5, 1),
+    (2024, 9, 2),
+    (2024, 9, 3),
+    // 2025
+    (2025, 1, 1),
+    (2025, 1, 27),
+    (2025, 1, 28),
+    (2025, 1, 29),
+    (2025, 1, 30),
+    (2025, 1, 31),
+    (2025, 4, 7),
+    (2025, 4, 30),
+    (2025, 5, 1),
+    (2025, 5, 2),
+    (2025, 9, 1),
+    (2025, 9, 2),
+    // 2026
+    (2026, 1, 1),
+    (2026, 2, 16),
+    (2026, 2, 17),
+    (2026, 2, 18),
+    (2026, 2, 19),
+    (2026, 2, 20),
+    (2026, 4, 27),
+    (2026, 4, 30),
+    (2026, 5, 1),
+    (2026, 9, 2),
+];
+
+/// Vietnamese stock exchanges
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Exchange {
+    Hose,
+    Hnx,
+    Upcom,
+}
+
+impl Exchange {
+    /// Exchange of a VCI listing `board` ("HSX", "HNX", "UPCOM")
+    pub fn from_board(board: &str) -> Option<Self> {
+        match board.to_uppercase().as_str() {
+            "HSX" | "HOSE" => Some(Exchange::Hose),
+            "HNX" => Some(Exchange::Hnx),
+            "UPCOM" => Some(Exchange::Upcom),
+            _ => None,
+        }
+    }
+
+    /// Intraday sessions in Vietnam time, in order
+    pub fn sessions(&self) -> &'static [Session] {
+        match self {
+            Exchange::Hose => HOSE_SESSIONS,
+            Exchange::Hnx => HNX_SESSIONS,
+            Exchange::Upcom => UPCOM_SESSIONS,
+        }
+    }
+
+    /// Price step of a stock quoted at `price` VND
+    pub fn tick_size(&self, price: f64) -> f64 {
+        match self {
+            Exchange::Hose if price < 10_000.0 => 10.0,
+            Exchange::Hose if price < 50_000.0 => 50.0,
+            _ => 100.0,
+        }
+    }
+}
+
+/// Part of the trading day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SessionPhase {
+    /// Opening call auction (ATO)
+    OpeningAuction,
+    /// Continuous order matching
+    Continuous,
+    LunchBreak,
+    /// Closing call auction (ATC)
+    ClosingAuction,
+    /// Put-through and post-close trading, no order matching
+    PutThrough,
+    Closed,
+}
+
+impl SessionPhase {
+    /// Prices can still move in this phase
+    pub fn is_matching(&self) -> bool {
+        matches!(
+            self,
+            SessionPhase::OpeningAuction | SessionPhase::Continuous | SessionPhase::ClosingAuction
+        )
+    }
+}
+
+/// One phase of the trading day, `[start, end)` in Vietnam time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub phase: SessionPhase,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+const fn session(phase: SessionPhase, start: (u32, u32), end: (u32, u32)) -> Session {
+    Session {
+        phase,
+        start: NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(),
+        end: NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap(),
+    }
+}
+
+const HOSE_SESSIONS: &[Session] = &[
+    session(SessionPhase::OpeningAuction, (9, 0), (9, 15)),
+    session(SessionPhase::Continuous, (9, 15), (11, 30)),
+    session(SessionPhase::LunchBreak, (11, 30), (13, 0)),
+    session(SessionPhase::Continuous, (13, 0), (14, 30)),
+    session(SessionPhase::ClosingAuction, (14, 30), (14, 45)),
+    session(SessionPhase::PutThrough, (14, 45), (15, 0)),
+];
+
+const HNX_SESSIONS: &[Session] = &[
+    session(SessionPhase::Continuous, (9, 0), (11, 30)),
+    session(SessionPhase::LunchBreak, (11, 30), (13, 0)),
+    session(SessionPhase::Continuous, (13, 0), (14, 30)),
+    session(SessionPhase::ClosingAuction, (14, 30), (14, 45)),
+    session(SessionPhase::PutThrough, (14, 45), (15, 0)),
+];
+
+const UPCOM_SESSIONS: &[Session] = &[
+    session(SessionPhase::Continuous, (9, 0), (11, 30)),
+    session(SessionPhase::LunchBreak, (11, 30), (13, 0)),
+    session(SessionPhase::Continuous, (13, 0), (15, 0)),
+];
+
+/// Convert a UTC instant to Vietnam wall-clock time
+pub fn to_vn_time(time: DateTime<Utc>) -> NaiveDateTime {
+    time.naive_utc() + Duration::seconds(VN_UTC_OFFSET_SECS)
+}
+
+/// Convert a Vietnam wall-clock time to UTC
+pub fn from_vn_time(time: NaiveDateTime) -> DateTime<Utc> {
+    (time - Duration::seconds(VN_UTC_OFFSET_SECS)).and_utc()
+}
+
+/// Trading days and sessions of the Vietnamese exchanges
+#[derive(Debug, Clone)]
+pub struct TradingCalendar {
+    holidays: BTreeSet<NaiveDate>,
+}
+
+impl Default for TradingCalendar {
+    fn default() -> Self {
+        Self::new(
+            HOLIDAYS
+                .iter()
+                .filter_map(|&(y, m, d)| NaiveDate::from_ymd_opt(y, m, d)),
+        )
+    }
+}
+
+static CALENDAR: OnceLock<TradingCalendar> = OnceLock::new();
+
+/// The calendar with the built-in holiday table
+pub fn calendar() -> &'static TradingCalendar {
+    CALENDAR.get_or_init(TradingCalendar::default)
+}
+
+impl TradingCalendar {
+    pub fn new(holidays: impl IntoIterator<Item = NaiveDate>) -> Self {
+        Self {
+            holidays: holidays.into_iter().collect(),
+        }
+    }
+
+    pub fn add_holidays(&mut self, holidays: impl IntoIterator<Item = NaiveDate>) {
+        self.holidays.extend(holidays);
+    }
+
+    pub fn is_holiday(&self, date: NaiveDate) -> bool {
+        self.holidays.contains(&date)
+    }
+
+    pub fn is_trading_day(&self, date: NaiveDate) -> bool {
+        !matches!(date.weekday(), Weekday::Sat | Weekday::Sun) && !self.is_holiday(date)
+    }
+
+    /// Last trading day strictly before `date`
+    pub fn previous_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut day = date - Duration::days(1);
+        while !self.is_trading_day(day) {
+            day -= Duration::days(1);
+        }
+        day
+    }
+
+    /// First trading day strictly after `date`
+    pub fn next_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        let mut day = date + Duration::days(1);
+        while !self.is_trading_day(day) {
+            day += Duration::days(1);
+        }
+        day
+    }
+
+    /// Number of the trading day `date`, consecutive trading days having
+    /// consecutive numbers across weekends and holidays
+    ///
+    /// A day off gets the number of the next trading day.
+    pub fn trading_day_number(&self, date: NaiveDate) -> i64 {
+        // 1970-01-05 is a Monday, weekends take the number of the Monday
+        let days = (date - NaiveDate::from_ymd_opt(1970, 1, 5).unwrap()).num_days();
+        let weekday = days.rem_euclid(7);
+        let weekdays = days.div_euclid(7) * 5 + weekday.min(5);
+        let holidays = self
+            .holidays
+            .range(..date)
+            .filter(|day| !matches!(day.weekday(), Weekday::Sat | Weekday::Sun))
+            .count();
+        weekdays - holidays as i64
+    }
+
+    /// `date` itself if it is a trading day, otherwise the one before it
+    pub fn latest_trading_day(&self, date: NaiveDate) -> NaiveDate {
+        if self.is_trading_day(date) {
+            date
+        } else {
+            self.previous_trading_day(date)
+        }
+    }
+
+    /// Trading day of the latest session opened by `time`, the day whose
+    /// matched orders are served until the next session opens
+    pub fn session_date(&self, time: DateTime<Utc>) -> NaiveDate {
+        let local = to_vn_time(time);
+        // Every exchange opens at the same time
+        let open = Exchange::Hose.sessions()[0].start;
+        if self.is_trading_day(local.date()) && local.time() >= open {
+            local.date()
+        } else {
+            self.previous_trading_day(local.date())
+        }
+    }
+
+    /// Phase of `exchange` at `time`
+    pub fn phase(&self, exchange: Exchange, time: DateTime<Utc>) -> SessionPhase {
+        let local = to_vn_time(time);
+        if !self.is_trading_day(local.date()) {
+            return SessionPhase::Closed;
+        }
+        exchange
+            .sessions()
+            .iter()
+            .find(|s| local.time() >= s.start && local.time() < s.end)
+            .map_or(SessionPhase::Closed, |s| s.phase)
+    }
+
+    /// Orders are being matched on `exchange` at `time`
+    pub fn is_open(&self, exchange: Exchange, time: DateTime<Utc>) -> bool {
+        self.phase(exchange, time).is_matching()
+    }
+
+    /// First and last instant of the trading day `date` on `exchange`, in UTC
+    pub fn session_bounds(
+        &self,
+        exchange: Exchange,
+        date: NaiveDate,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        let sessions = exchange.sessions();
+        let open = sessions.first().map_or(NaiveTime::MIN, |s| s.start);
+        let close = sessions.last().map_or(NaiveTime::MIN, |s| s.end);
+        (
+            from_vn_time(date.and_time(open)),
+            from_vn_time(date.and_time(close)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn vn(y: i32, m: u32, d: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        from_vn_time(date(y, m, d).and_hms_opt(hour, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn test_previous_and_next_trading_day_skip_tet() {
+        let calendar = TradingCalendar::default();
+        // Tết 2025 closed the market from Monday 27 to Friday 31 January
+        assert_eq!(
+            calendar.previous_trading_day(date(2025, 2, 3)),
+            date(2025, 1, 24)
+        );
+        assert_eq!(
+            calendar.next_trading_day(date(2025, 1, 24)),
+            date(2025, 2, 3)
+        );
+        assert_eq!(
+            calendar.latest_trading_day(date(2025, 2, 1)),
+            date(2025, 1, 24)
+        );
+        assert!(calendar.is_trading_day(date(2025, 2, 3)));
+    }
+
+    #[test]
+    fn test_session_phases_per_exchange() {
+        let calendar = TradingCalendar::default();
+        let tuesday = |h, m| vn(2025, 3, 4, h, m);
+        assert_eq!(
+            calendar.phase(Exchange::Hose, tuesday(9, 5)),
+            SessionPhase::OpeningAuction
+        );
+        assert_eq!(
+            calendar.phase(Exchange::Hnx, tuesday(9, 5)),
+            SessionPhase::Continuous
+        );
+        assert_eq!(
+            calendar.phase(Exchange::Hose, tuesday(12, 0)),
+            SessionPhase::LunchBreak
+        );
+        assert!(!calendar.is_open(Exchange::Hose, tuesday(12, 0)));
+        assert_eq!(
+            calendar.phase(Exchange::Hnx, tuesday(14, 40)),
+            SessionPhase::ClosingAuction
+        );
+        assert_eq!(
+            calendar.phase(Exchange::Upcom, tuesday(14, 40)),
+            SessionPhase::Continuous
+        );
+        assert_eq!(
+            calendar.phase(Exchange::Hose, tuesday(15, 0)),
+            SessionPhase::Closed
+        );
+        assert_eq!(
+            calendar.phase(Exchange::Hose, vn(2025, 4, 30, 10, 0)),
+            SessionPhase::Closed
+        );
+
+        let (open, close) = calendar.session_bounds(Exchange::Hose, date(2025, 3, 4));
+        assert_eq!(open, tuesday(9, 0));
+        assert_eq!(close, tuesday(15, 0));
+
+        // Before the open the tape is still the one of the previous session
+        assert_eq!(calendar.session_date(tuesday(8, 59)), date(2025, 3, 3));
+        assert_eq!(calendar.session_date(tuesday(9, 0)), date(2025, 3, 4));
+        assert_eq!(
+            calendar.session_date(vn(2025, 2, 3, 8, 0)),
+            date(2025, 1, 24)
+        );
+    }
+}
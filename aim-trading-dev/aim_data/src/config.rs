@@ -0,0 +1,237 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::AimDataError;
+use crate::http::RetryPolicy;
+use crate::source::SourceResult;
+
+/// File name of the backend config inside the app config directory
+pub const CONFIG_FILE_NAME: &str = "aim_backend.json";
+
+const DEFAULT_SERVER_URL: &str = "https://103.48.84.52:4443";
+
+/// Connection settings for the AIM backend
+///
+/// Loaded from `aim_backend.json` in the `Aim` config directory (or the file
+/// named by `AIM_CONFIG`), then overridden by environment variables:
+/// `AIM_SERVER_URL`, `AIM_TOKEN`, `AIM_CA_BUNDLE`, `AIM_CERT_SHA256`,
+/// `AIM_CONNECT_TIMEOUT_SECS`, `AIM_REQUEST_TIMEOUT_SECS`, `AIM_MAX_RETRIES`
+/// and `AIM_STREAM_ENDPOINT`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AimConfig {
+    /// Base URL of the backend, without a trailing slash
+    pub base_url: String,
+    /// Bearer token sent with every request, requests fail while it is empty
+    pub token: String,
+    /// PEM bundle of CA certificates trusted in addition to the system roots
+    pub ca_bundle: Option<PathBuf>,
+    /// SHA-256 fingerprint (hex, colons optional) of the server certificate.
+    /// When set, the certificate chain is not validated and the server is
+    /// trusted only if its leaf certificate matches.
+    pub pinned_cert_sha256: Option<String>,
+    pub connect_timeout_secs: u64,
+    pub request_timeout_secs: u64,
+    /// Retries after a 5xx, 429 or timed out request
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every attempt
+    pub retry_base_delay_ms: u64,
+    pub retry_max_delay_ms: u64,
+    /// Server-sent events endpoint of the market stream, empty to only poll;
+    /// the stream is only opened with a `token`
+    pub stream_endpoint: String,
+}
+
+impl Default for AimConfig {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_SERVER_URL.to_string(),
+            token: String::new(),
+            ca_bundle: None,
+            pinned_cert_sha256: None,
+            connect_timeout_secs: 10,
+            request_timeout_secs: 30,
+            max_retries: 3,
+            retry_base_delay_ms: 250,
+            retry_max_delay_ms: 10_000,
+            stream_endpoint: "stream".to_string(),
+        }
+    }
+}
+
+impl AimConfig {
+    /// Default location of the config file, `<config dir>/Aim/aim_backend.json`
+    ///
+    /// Falls back to the cache directory used for the rest of the app data.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs_next::config_dir()
+            .or_else(dirs_next::cache_dir)
+            .map(|dir| dir.join("Aim").join(CONFIG_FILE_NAME))
+    }
+
+    /// Load the config from the default location and apply env overrides
+    ///
+    /// A missing file is not an error: defaults plus env overrides are used.
+    pub fn load() -> SourceResult<Self> {
+        let path = std::env::var_os("AIM_CONFIG")
+            .map(PathBuf::from)
+            .or_else(Self::default_path);
+        let mut config = match path {
+            Some(path) if path.exists() => Self::from_file(&path)?,
+            _ => Self::default(),
+        };
+        config.apply_env(|key| std::env::var(key).ok())?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> SourceResult<Self> {
+        let data = fs::read_to_string(path).map_err(|e| AimDataError::io(path, e))?;
+        serde_json::from_str(&data)
+            .map_err(|e| AimDataError::decode(&path.display().to_string(), &data, e))
+    }
+
+    /// Write the config as pretty JSON, creating the parent directory
+    pub fn save(&self, path: &Path) -> SourceResult<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| AimDataError::io(dir, e))?;
+        }
+        let data = serde_json::to_string_pretty(self)
+            .map_err(|e| AimDataError::Config(e.to_string()))?;
+        fs::write(path, data).map_err(|e| AimDataError::io(path, e))
+    }
+
+    /// Override fields from `AIM_*` variables looked up through `var`
+    fn apply_env(&mut self, var: impl Fn(&str) -> Option<String>) -> SourceResult<()> {
+        if let Some(url) = var("AIM_SERVER_URL") {
+            self.base_url = url;
+        }
+        if let Some(token) = var("AIM_TOKEN") {
+            self.token = token;
+        }
+        if let Some(path) = var("AIM_CA_BUNDLE") {
+            self.ca_bundle = Some(PathBuf::from(path));
+        }
+        if let Some(fingerprint) = var("AIM_CERT_SHA256") {
+            self.pinned_cert_sha256 = Some(fingerprint);
+        }
+        if let Some(secs) = var("AIM_CONNECT_TIMEOUT_SECS") {
+            self.connect_timeout_secs = parse_number("AIM_CONNECT_TIMEOUT_SECS", &secs)?;
+        }
+        if let Some(secs) = var("AIM_REQUEST_TIMEOUT_SECS") {
+            self.request_timeout_secs = parse_number("AIM_REQUEST_TIMEOUT_SECS", &secs)?;
+        }
+        if let Some(retries) = var("AIM_MAX_RETRIES") {
+            self.max_retries = parse_number("AIM_MAX_RETRIES", &retries)?;
+        }
+        if let Some(endpoint) = var("AIM_STREAM_ENDPOINT") {
+            self.stream_endpoint = endpoint;
+        }
+        self.base_url = self.base_url.trim_end_matches('/').to_string();
+        Ok(())
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_secs(self.connect_timeout_secs)
+    }
+
+    pub fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs)
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_retries: self.max_retries,
+            base_delay: Duration::from_millis(self.retry_base_delay_ms),
+            max_delay: Duration::from_millis(self.retry_max_delay_ms),
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Full URL of an endpoint relative to the base URL
+    pub fn url(&self, endpoint: &str) -> String {
+        format!("{}/{}", self.base_url, endpoint.trim_start_matches('/'))
+    }
+
+    /// The token to authenticate with, an error when none is configured
+    pub fn bearer_token(&self) -> SourceResult<&str> {
+        if self.token.is_empty() {
+            return Err(AimDataError::Config(
+                "no AIM backend token, set AIM_TOKEN or token in the config file".to_string(),
+            ));
+        }
+        Ok(&self.token)
+    }
+
+    /// The pinned fingerprint as raw bytes, if one is configured
+    pub fn pinned_fingerprint(&self) -> SourceResult<Option<[u8; 32]>> {
+        let Some(hex) = &self.pinned_cert_sha256 else {
+            return Ok(None);
+        };
+        let digits: Vec<u8> = hex
+            .bytes()
+            .filter(|b| !matches!(b, b':' | b' '))
+            .collect();
+        let invalid = || AimDataError::Config(format!("invalid SHA-256 fingerprint: {hex}"));
+        if digits.len() != 64 {
+            return Err(invalid());
+        }
+        let mut fingerprint = [0u8; 32];
+        for (byte, pair) in fingerprint.iter_mut().zip(digits.chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| invalid())?;
+        }
+        Ok(Some(fingerprint))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(key: &str, value: &str) -> SourceResult<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| AimDataError::Config(format!("{key} must be a number, got {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_overrides_file_values() {
+        let mut config: AimConfig =
+            serde_json::from_str(r#"{ "base_url": "https://staging.example/", "token": "file" }"#)
+                .unwrap();
+        assert_eq!(config.request_timeout_secs, 30);
+
+        config
+            .apply_env(|key| match key {
+                "AIM_TOKEN" => Some("env".to_string()),
+                "AIM_REQUEST_TIMEOUT_SECS" => Some("5".to_string()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(config.token, "env");
+        assert_eq!(config.request_timeout(), Duration::from_secs(5));
+        assert_eq!(config.url("reports"), "https://staging.example/reports");
+
+        let bad = config.apply_env(|key| (key == "AIM_CONNECT_TIMEOUT_SECS").then(|| "x".into()));
+        assert!(bad.is_err());
+    }
+
+    #[test]
+    fn test_pinned_fingerprint_parsing() {
+        let mut config = AimConfig::default();
+        assert_eq!(config.pinned_fingerprint().unwrap(), None);
+
+        let hex = "AB:".repeat(31) + "CD";
+        config.pinned_cert_sha256 = Some(hex);
+        let fingerprint = config.pinned_fingerprint().unwrap().unwrap();
+        assert_eq!(fingerprint[0], 0xAB);
+        assert_eq!(fingerprint[31], 0xCD);
+
+        config.pinned_cert_sha256 = Some("abcd".to_string());
+        assert!(config.pinned_fingerprint().is_err());
+    }
+}
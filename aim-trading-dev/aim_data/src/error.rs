@@ -0,0 +1,147 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use reqwest::StatusCode;
+
+/// Maximum number of characters of a response body kept in an error
+const BODY_SNIPPET_LEN: usize = 200;
+
+/// Errors returned by every data source in this crate
+#[derive(Debug)]
+pub enum AimDataError {
+    /// Connection, TLS or timeout failure before a response was read
+    Transport(reqwest::Error),
+    /// The server answered with a non-success status
+    HttpStatus {
+        endpoint: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// The response body did not match the expected schema
+    Decode {
+        endpoint: String,
+        body: String,
+        source: serde_json::Error,
+    },
+    /// The request succeeded but carried no data (unknown symbol, empty payload)
+    EmptyData { endpoint: String },
+    /// Reading or writing a local file failed
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The backend configuration is invalid (bad fingerprint, CA bundle, timeout)
+    Config(String),
+    /// The server certificate does not match the pinned fingerprint
+    PinMismatch { endpoint: String },
+    /// The provider does not serve this kind of data
+    Unsupported {
+        source: String,
+        operation: &'static str,
+    },
+}
+
+impl AimDataError {
+    pub(crate) fn http_status(endpoint: &str, status: StatusCode, body: &str) -> Self {
+        Self::HttpStatus {
+            endpoint: endpoint.to_string(),
+            status,
+            body: snippet(body),
+        }
+    }
+
+    pub(crate) fn decode(endpoint: &str, body: &str, source: serde_json::Error) -> Self {
+        Self::Decode {
+            endpoint: endpoint.to_string(),
+            body: snippet(body),
+            source,
+        }
+    }
+
+    pub(crate) fn empty(endpoint: &str) -> Self {
+        Self::EmptyData {
+            endpoint: endpoint.to_string(),
+        }
+    }
+
+    pub(crate) fn io(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Self::Io {
+            path: path.into(),
+            source,
+        }
+    }
+
+    /// HTTP status of the failed request, if the server answered at all
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::HttpStatus { status, .. } => Some(*status),
+            Self::Transport(e) => e.status(),
+            _ => None,
+        }
+    }
+
+    /// The server rejected our credentials (HTTP 401/403)
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(
+            self.status(),
+            Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+        )
+    }
+
+    /// The symbol or resource does not exist, or has no data
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Self::EmptyData { .. }) || self.status() == Some(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Cut a response body down to a loggable size on a char boundary
+fn snippet(body: &str) -> String {
+    match body.char_indices().nth(BODY_SNIPPET_LEN) {
+        Some((index, _)) => format!("{}...", &body[..index]),
+        None => body.to_string(),
+    }
+}
+
+impl fmt::Display for AimDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transport(e) => write!(f, "transport error: {e}"),
+            Self::HttpStatus {
+                endpoint,
+                status,
+                body,
+            } => write!(f, "HTTP {status} from {endpoint}: {body}"),
+            Self::Decode {
+                endpoint,
+                body,
+                source,
+            } => write!(f, "failed to decode {endpoint}: {source} (body: {body})"),
+            Self::EmptyData { endpoint } => write!(f, "no data returned by {endpoint}"),
+            Self::Io { path, source } => write!(f, "{}: {source}", path.display()),
+            Self::Config(message) => write!(f, "invalid configuration: {message}"),
+            Self::PinMismatch { endpoint } => {
+                write!(f, "certificate of {endpoint} does not match the pinned fingerprint")
+            }
+            Self::Unsupported { source, operation } => {
+                write!(f, "{source} does not provide {operation}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AimDataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Transport(e) => Some(e),
+            Self::Decode { source, .. } => Some(source),
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for AimDataError {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Transport(e)
+    }
+}
@@ -1,4 +1,5 @@
 mod api;
+mod pin;
 pub use api::*;
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
@@ -10,28 +11,208 @@ pub struct SjcPriceData {
     pub sell_price: String,
     pub updated_at: i64,
 }
-use reqwest::ClientBuilder;
+use reqwest::Certificate;
+use crate::config::AimConfig;
+use crate::error::AimDataError;
+use crate::explorer::read_json;
+use crate::file::write_atomic;
+use crate::http::{self, HttpClient};
+use crate::source::{BoxFuture, DataSource, FinanceSheet, SourceResult};
 use serde::{Deserialize, Serialize};
-const TOKEN: &str = "SUPER_SECRET_ADMIN_TOKEN";
-const SERVER_URL: &str = "https://103.48.84.52:4443";
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+/// Longest a market stream request may stay open
+const STREAM_MAX_DURATION: Duration = Duration::from_secs(24 * 3600);
+
+static EXPLORER: OnceLock<RwLock<Arc<AimExplorer>>> = OnceLock::new();
+
+fn explorer_slot() -> &'static RwLock<Arc<AimExplorer>> {
+    EXPLORER.get_or_init(|| {
+        // Fall back to defaults so a broken config file surfaces as request errors
+        let explorer = AimConfig::load()
+            .and_then(AimExplorer::new)
+            .or_else(|_| AimExplorer::new(AimConfig::default()))
+            .expect("default AIM backend config must be valid");
+        RwLock::new(Arc::new(explorer))
+    })
+}
+
+/// Use `config` for every AIM backend request made by the `fetch_*` functions
+///
+/// Call this before the first request: data sources created earlier keep
+/// the client they were built with.
+pub fn configure(config: AimConfig) -> SourceResult<()> {
+    let explorer = AimExplorer::new(config)?;
+    *explorer_slot().write().unwrap_or_else(|e| e.into_inner()) = Arc::new(explorer);
+    Ok(())
+}
+
+/// The AIM backend client shared by the `fetch_*` functions
+pub fn explorer() -> Arc<AimExplorer> {
+    explorer_slot()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
 
 // Generic function to fetch data from API endpoints
-async fn fetch_api_data<T>(endpoint: &str) -> Result<T, reqwest::Error>
+async fn fetch_api_data<T>(endpoint: &str) -> SourceResult<T>
 where
     T: for<'de> serde::Deserialize<'de>,
 {
-    let url = format!("{SERVER_URL}/{endpoint}");
-    let client = ClientBuilder::new()
-        .danger_accept_invalid_certs(true)
-        .build()?;
-    let resp = client
-        .get(url)
-        .header("Authorization", format!("Bearer {TOKEN}"))
-        .send()
-        .await?;
+    explorer().fetch(endpoint).await
+}
+
+/// Client for the AIM backend, serving company fundamentals and market statistics
+#[derive(Debug, Clone)]
+pub struct AimExplorer {
+    config: AimConfig,
+    http: HttpClient,
+}
+
+impl AimExplorer {
+    pub fn new(config: AimConfig) -> SourceResult<Self> {
+        let mut builder = http::client_builder()
+            .connect_timeout(config.connect_timeout())
+            .timeout(config.request_timeout());
+
+        if let Some(path) = &config.ca_bundle {
+            let pem = fs::read(path).map_err(|e| AimDataError::io(path, e))?;
+            let certs = Certificate::from_pem_bundle(&pem).map_err(|e| {
+                AimDataError::Config(format!("invalid CA bundle {}: {e}", path.display()))
+            })?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        if let Some(fingerprint) = config.pinned_fingerprint()? {
+            // The pin replaces chain validation and is checked in the handshake
+            builder = builder.use_preconfigured_tls(pin::pinned_tls_config(fingerprint)?);
+        }
+
+        let http = HttpClient::new(builder.build()?, config.retry_policy());
+        Ok(Self { config, http })
+    }
+
+    pub fn config(&self) -> &AimConfig {
+        &self.config
+    }
 
-    let data: T = resp.json().await?;
-    Ok(data)
+    /// Send the request built by `build` with the bearer token
+    ///
+    /// Fails without sending anything when no token is configured, and
+    /// reports a handshake the pinned fingerprint failed as such.
+    async fn send(
+        &self,
+        endpoint: &str,
+        build: impl Fn(&reqwest::Client) -> reqwest::RequestBuilder,
+    ) -> SourceResult<reqwest::Response> {
+        let token = self.config.bearer_token()?;
+        self.http
+            .send(|client| build(client).bearer_auth(token))
+            .await
+            .map_err(|e| match e {
+                AimDataError::Transport(e) if pin::is_pin_mismatch(&e) => {
+                    AimDataError::PinMismatch {
+                        endpoint: endpoint.to_string(),
+                    }
+                }
+                e => e,
+            })
+    }
+
+    async fn get(&self, endpoint: &str, accept: Option<&str>) -> SourceResult<reqwest::Response> {
+        let url = self.config.url(endpoint);
+        self.send(endpoint, |client| {
+            let request = client.get(&url);
+            match accept {
+                Some(accept) => request.header("Accept", accept),
+                None => request,
+            }
+        })
+        .await
+    }
+
+    /// GET a JSON endpoint relative to the base URL
+    pub async fn fetch<T>(&self, endpoint: &str) -> SourceResult<T>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        let response = self.get(endpoint, None).await?;
+        read_json(endpoint, response).await
+    }
+
+    /// Open the server-sent events stream at `endpoint` with `query`
+    ///
+    /// The response is returned as soon as the headers are in, with a
+    /// success status, and its body is read as events arrive.
+    pub async fn open_stream(
+        &self,
+        endpoint: &str,
+        query: &[(&str, String)],
+    ) -> SourceResult<reqwest::Response> {
+        let url = self.config.url(endpoint);
+        let response = self
+            .send(endpoint, |client| {
+                client
+                    .get(&url)
+                    .query(query)
+                    .header("Accept", "text/event-stream")
+                    // A stream lasts a trading day, stalls are caught by the reader
+                    .timeout(STREAM_MAX_DURATION)
+            })
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(AimDataError::http_status(endpoint, status, &body));
+        }
+        Ok(response)
+    }
+
+    /// GET a binary endpoint relative to the base URL
+    pub async fn fetch_bytes(&self, endpoint: &str, accept: &str) -> SourceResult<Vec<u8>> {
+        let response = self.get(endpoint, Some(accept)).await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(AimDataError::http_status(endpoint, status, &body));
+        }
+        let bytes = response.bytes().await?;
+        if bytes.is_empty() {
+            return Err(AimDataError::empty(endpoint));
+        }
+        Ok(bytes.to_vec())
+    }
+}
+
+impl DataSource for AimExplorer {
+    fn name(&self) -> &str {
+        "AIM"
+    }
+
+    fn get_finance_sheet<'a>(
+        &'a self,
+        symbol: &'a str,
+        sheet: FinanceSheet,
+        period: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<FinanceSheetData>>> {
+        Box::pin(async move {
+            let endpoint = format!("{}/{symbol}/{period}", sheet.endpoint());
+            self.fetch(&endpoint).await
+        })
+    }
+
+    fn get_financial_data<'a>(
+        &'a self,
+        symbol: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<FinancialData>>> {
+        Box::pin(async move {
+            let endpoint = format!("financial-data/{symbol}");
+            self.fetch(&endpoint).await
+        })
+    }
 }
 
 
@@ -989,7 +1170,7 @@ pub struct ApiReport {
 }
 
 
-use std::{clone, fs};
+use std::fs;
 use std::path::Path;
 
 #[derive(Debug, Clone)]
@@ -998,7 +1179,7 @@ pub struct PdfReport {
     pub file_path: String,
 }
 
-pub async fn fetch_api_finance_report_pdf(symbol: &str) -> Result<PdfReport, reqwest::Error> {
+pub async fn fetch_api_finance_report_pdf(symbol: &str) -> SourceResult<PdfReport> {
 
     let cache_dir = "cache";
     let file_path = format!("{cache_dir}/{symbol}.pdf");
@@ -1010,24 +1191,11 @@ pub async fn fetch_api_finance_report_pdf(symbol: &str) -> Result<PdfReport, req
     }
 
     let endpoint = format!("report-file/{symbol}");
-    let url = format!("{SERVER_URL}/{endpoint}");
-
-    let client = ClientBuilder::new()
-        .danger_accept_invalid_certs(true)
-        .build()?; // <- tự động trả reqwest::Error nếu lỗi
-
-    let resp = client
-        .get(&url)
-        .header("Authorization", format!("Bearer {TOKEN}"))
-        .header("Accept", "application/pdf")
-        .send()
-        .await?; // <- reqwest::Error nếu lỗi mạng
-
-    let bytes = resp.bytes().await?; // <- reqwest::Error nếu lỗi đọc
+    let bytes = explorer().fetch_bytes(&endpoint, "application/pdf").await?;
 
-    fs::create_dir_all("cache").ok(); // không cần xử lý lỗi tạo thư mục
-    let file_path = format!("cache/{}.pdf", symbol);
-    fs::write(&file_path, &bytes).ok(); // lỗi ghi file bỏ qua
+    fs::create_dir_all(cache_dir).map_err(|e| AimDataError::io(cache_dir, e))?;
+    // Ghi ra file tạm rồi đổi tên để không để lại PDF hỏng khi ghi lỗi giữa chừng
+    write_atomic(Path::new(&file_path), &bytes).map_err(|e| AimDataError::io(&file_path, e))?;
 
     Ok(PdfReport {
         symbol: symbol.to_string(),
@@ -0,0 +1,157 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, ClientConfig, DigitallySignedStruct, OtherError, SignatureScheme};
+use sha2::{Digest, Sha256};
+
+use crate::error::AimDataError;
+use crate::source::SourceResult;
+
+/// The leaf certificate of the server does not hash to the pin
+#[derive(Debug)]
+struct PinError;
+
+impl fmt::Display for PinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "certificate does not match the pinned fingerprint")
+    }
+}
+
+impl StdError for PinError {}
+
+/// Accepts the server only if its leaf certificate hashes to the pin
+///
+/// The chain is not validated, so self-signed certificates work, but the
+/// handshake signatures still are: the server must hold the pinned key.
+#[derive(Debug)]
+struct PinVerifier {
+    fingerprint: [u8; 32],
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        if Sha256::digest(end_entity.as_ref()).as_slice() == self.fingerprint {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(CertificateError::Other(
+                OtherError(Arc::new(PinError)),
+            )))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// TLS settings checking the pin during the handshake, before any request
+/// (and its bearer token) goes out on the connection
+pub(super) fn pinned_tls_config(fingerprint: [u8; 32]) -> SourceResult<ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = PinVerifier {
+        fingerprint,
+        provider: Arc::clone(&provider),
+    };
+    let config = ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| AimDataError::Config(format!("TLS setup failed: {e}")))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Ok(config)
+}
+
+/// `error` comes from a handshake the pin failed
+///
+/// `io::Error` hides the error it wraps from `source()`, so it is unwrapped
+/// by hand on the way down.
+pub(super) fn is_pin_mismatch(error: &(dyn StdError + 'static)) -> bool {
+    let mut next = Some(error);
+    while let Some(error) = next {
+        if error.is::<PinError>() {
+            return true;
+        }
+        if let Some(rustls::Error::InvalidCertificate(CertificateError::Other(other))) =
+            error.downcast_ref::<rustls::Error>()
+        {
+            return other.0.is::<PinError>();
+        }
+        next = match error.downcast_ref::<std::io::Error>() {
+            Some(io) => io.get_ref().map(|inner| inner as &(dyn StdError + 'static)),
+            None => error.source(),
+        };
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pin_checked_in_handshake() {
+        let der = CertificateDer::from(b"leaf certificate".to_vec());
+        let verifier = PinVerifier {
+            fingerprint: Sha256::digest(der.as_ref()).into(),
+            provider: Arc::new(rustls::crypto::ring::default_provider()),
+        };
+        let name = ServerName::try_from("aim.example").unwrap();
+        assert!(
+            verifier
+                .verify_server_cert(&der, &[], &name, &[], UnixTime::now())
+                .is_ok()
+        );
+
+        let other = CertificateDer::from(b"another certificate".to_vec());
+        let error = verifier
+            .verify_server_cert(&other, &[], &name, &[], UnixTime::now())
+            .unwrap_err();
+        // As the TLS stream reports it
+        let io = std::io::Error::new(std::io::ErrorKind::InvalidData, error);
+        assert!(is_pin_mismatch(&io));
+        let unrelated = std::io::Error::new(std::io::ErrorKind::InvalidData, "reset");
+        assert!(!is_pin_mismatch(&unrelated));
+    }
+}
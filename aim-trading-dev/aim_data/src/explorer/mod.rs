@@ -1,9 +1,15 @@
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use std::str::FromStr;
 
+use crate::error::AimDataError;
+
 pub mod aim;
+pub mod offline;
 pub mod vci;
+pub use aim::AimExplorer;
+pub use offline::OfflineExplorer;
 pub use vci::VCIExplorer;
 
 // Default headers that are common across all data sources
@@ -61,3 +67,22 @@ pub fn get_headers(data_source: &str) -> HeaderMap {
 
     headers
 }
+
+/// Check the status of a response and decode its JSON body
+///
+/// `endpoint` only labels errors, so it must not carry credentials.
+pub(crate) async fn read_json<T: DeserializeOwned>(
+    endpoint: &str,
+    response: reqwest::Response,
+) -> Result<T, AimDataError> {
+    let status = response.status();
+    let body = response.text().await?;
+    if !status.is_success() {
+        return Err(AimDataError::http_status(endpoint, status, &body));
+    }
+    let trimmed = body.trim();
+    if trimmed.is_empty() || trimmed == "null" {
+        return Err(AimDataError::empty(endpoint));
+    }
+    serde_json::from_str(&body).map_err(|e| AimDataError::decode(endpoint, &body, e))
+}
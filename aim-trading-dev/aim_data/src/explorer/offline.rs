@@ -0,0 +1,271 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::error::AimDataError;
+use crate::explorer::aim::{FinanceSheetData, FinancialData};
+use crate::explorer::vci::OrderList;
+use crate::explorer::vci::company_info::CompanyInfo;
+use crate::explorer::vci::listing::VCISymbol;
+use crate::explorer::vci::market_watch::{MarketWatchResponse, VCIMarketWatch};
+use crate::explorer::vci::ohlc::{OHLCData, OHLCResponse};
+use crate::source::{BoxFuture, DataSource, FinanceSheet, SourceResult};
+
+/// File-backed data source for offline work and tests
+///
+/// Data is read from JSON files laid out under `root`:
+/// - `quote/{SYMBOL}_{TIME_FRAME}.json` - `OHLCData`
+/// - `market_watch/{SYMBOL}.json` - `VCIMarketWatch`
+/// - `order_list/{SYMBOL}.json` - `Vec<VCIOderBook>`
+/// - `company_info/{SYMBOL}_{PERIOD}.json` - `CompanyInfo`
+/// - `finance_sheet/{SYMBOL}_{SHEET}_{PERIOD}.json` - `Vec<FinanceSheetData>`
+/// - `financial_data/{SYMBOL}.json` - `Vec<FinancialData>`
+/// - `listings/ALL.json` - `Vec<VCISymbol>`
+/// - `index/{INDEX}.json` - `Vec<String>`
+///
+/// The `save_*` methods write the same layout, so live responses can be
+/// captured once and replayed later.
+#[derive(Debug, Clone)]
+pub struct OfflineExplorer {
+    root: PathBuf,
+}
+
+impl OfflineExplorer {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path(&self, kind: &str, key: &str) -> PathBuf {
+        self.root.join(kind).join(format!("{}.json", key.to_uppercase()))
+    }
+
+    fn read<T: DeserializeOwned>(&self, kind: &str, key: &str) -> SourceResult<T> {
+        let path = self.path(kind, key);
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(AimDataError::empty(&path.display().to_string()));
+            }
+            Err(e) => return Err(AimDataError::io(path, e)),
+        };
+        serde_json::from_str(&data)
+            .map_err(|e| AimDataError::decode(&path.display().to_string(), &data, e))
+    }
+
+    fn write<T: Serialize>(&self, kind: &str, key: &str, value: &T) -> SourceResult<()> {
+        let path = self.path(kind, key);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| AimDataError::io(dir, e))?;
+        }
+        let data = serde_json::to_vec_pretty(value)
+            .map_err(|e| AimDataError::decode(&path.display().to_string(), "", e))?;
+        fs::write(&path, data).map_err(|e| AimDataError::io(path, e))
+    }
+
+    pub fn save_quote(&self, time_frame: &str, data: &OHLCData) -> SourceResult<()> {
+        self.write("quote", &format!("{}_{time_frame}", data.symbol), data)
+    }
+
+    pub fn save_market_watch(&self, data: &MarketWatchResponse) -> SourceResult<()> {
+        for item in &data.0 {
+            self.write("market_watch", &item.listing_info.symbol, item)?;
+        }
+        Ok(())
+    }
+
+    pub fn save_order_list(&self, symbol: &str, data: &OrderList) -> SourceResult<()> {
+        self.write("order_list", symbol, data)
+    }
+
+    pub fn save_company_ratios(
+        &self,
+        symbol: &str,
+        period: &str,
+        data: &CompanyInfo,
+    ) -> SourceResult<()> {
+        self.write("company_info", &format!("{symbol}_{period}"), data)
+    }
+
+    pub fn save_finance_sheet(
+        &self,
+        symbol: &str,
+        sheet: FinanceSheet,
+        period: &str,
+        data: &[FinanceSheetData],
+    ) -> SourceResult<()> {
+        let key = format!("{symbol}_{}_{period}", sheet.endpoint());
+        self.write("finance_sheet", &key, &data)
+    }
+
+    pub fn save_financial_data(&self, symbol: &str, data: &[FinancialData]) -> SourceResult<()> {
+        self.write("financial_data", symbol, &data)
+    }
+
+    pub fn save_listings(&self, data: &[VCISymbol]) -> SourceResult<()> {
+        self.write("listings", "ALL", &data)
+    }
+
+    pub fn save_index_constituents(&self, index: &str, data: &[String]) -> SourceResult<()> {
+        self.write("index", index, &data)
+    }
+}
+
+impl DataSource for OfflineExplorer {
+    fn name(&self) -> &str {
+        "offline"
+    }
+
+    fn get_quote<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+        time_frame: &'a str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, SourceResult<OHLCResponse>> {
+        Box::pin(async move {
+            let mut data = Vec::new();
+            for symbol in symbols {
+                match self.read::<OHLCData>("quote", &format!("{symbol}_{time_frame}")) {
+                    Ok(quote) => data.push(quote.range(
+                        start_time.map(|t| t.timestamp()),
+                        end_time.map(|t| t.timestamp()),
+                    )),
+                    // Symbols without a file are skipped, like unknown symbols on VCI
+                    Err(AimDataError::EmptyData { .. }) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(OHLCResponse(data))
+        })
+    }
+
+    fn get_market_watch<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+    ) -> BoxFuture<'a, SourceResult<MarketWatchResponse>> {
+        Box::pin(async move {
+            let mut data = Vec::new();
+            for symbol in symbols {
+                match self.read::<VCIMarketWatch>("market_watch", symbol) {
+                    Ok(item) => data.push(item),
+                    Err(AimDataError::EmptyData { .. }) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok(MarketWatchResponse(data))
+        })
+    }
+
+    fn get_order_list<'a>(
+        &'a self,
+        symbol: &'a str,
+        limit: u32,
+    ) -> BoxFuture<'a, SourceResult<OrderList>> {
+        Box::pin(async move {
+            let mut data: OrderList = self.read("order_list", symbol)?;
+            data.truncate(limit as usize);
+            Ok(data)
+        })
+    }
+
+    fn get_company_ratios<'a>(
+        &'a self,
+        symbol: &'a str,
+        period: &'a str,
+    ) -> BoxFuture<'a, SourceResult<CompanyInfo>> {
+        Box::pin(async move { self.read("company_info", &format!("{symbol}_{period}")) })
+    }
+
+    fn get_finance_sheet<'a>(
+        &'a self,
+        symbol: &'a str,
+        sheet: FinanceSheet,
+        period: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<FinanceSheetData>>> {
+        Box::pin(async move {
+            self.read(
+                "finance_sheet",
+                &format!("{symbol}_{}_{period}", sheet.endpoint()),
+            )
+        })
+    }
+
+    fn get_financial_data<'a>(
+        &'a self,
+        symbol: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<FinancialData>>> {
+        Box::pin(async move { self.read("financial_data", symbol) })
+    }
+
+    fn get_listings(&self) -> BoxFuture<'_, SourceResult<Vec<VCISymbol>>> {
+        Box::pin(async move { self.read("listings", "ALL") })
+    }
+
+    fn get_index_constituents<'a>(
+        &'a self,
+        index: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<String>>> {
+        Box::pin(async move { self.read("index", index) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explorer::vci::VCIOderBook;
+
+    fn sample_quote() -> OHLCData {
+        OHLCData {
+            symbol: "AAA".to_string(),
+            o: vec![Some(10.0), Some(11.0), Some(12.0)],
+            h: vec![Some(11.0), Some(12.0), Some(13.0)],
+            l: vec![Some(9.0), Some(10.0), Some(11.0)],
+            c: vec![Some(10.5), Some(11.5), Some(12.5)],
+            v: vec![Some(100), Some(200), Some(300)],
+            t: vec!["1000".to_string(), "2000".to_string(), "3000".to_string()],
+            accumulated_volume: vec![None, None, None],
+            accumulated_value: vec![None, None, None],
+            min_batch_trunc_time: String::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_offline_round_trip() {
+        let root = std::env::temp_dir().join(format!("aim_offline_{}", std::process::id()));
+        let explorer = OfflineExplorer::new(&root);
+
+        explorer.save_quote("ONE_DAY", &sample_quote()).unwrap();
+        let orders = vec![VCIOderBook {
+            id: 1,
+            timestamp: "09:15:00".to_string(),
+            price: 10.5,
+            volume: 100,
+            match_type: "b".to_string(),
+        }];
+        explorer.save_order_list("AAA", &orders).unwrap();
+
+        let source: &dyn DataSource = &explorer;
+        let start = DateTime::from_timestamp(1500, 0);
+        let quote = source
+            .get_quote(&["AAA", "BBB"], "ONE_DAY", start, None)
+            .await
+            .unwrap();
+        assert_eq!(quote.0.len(), 1);
+        assert_eq!(quote.0[0].t, vec!["2000", "3000"]);
+        assert_eq!(quote.0[0].c, vec![Some(11.5), Some(12.5)]);
+
+        let orders = source.get_order_list("aaa", 10).await.unwrap();
+        assert_eq!(orders.len(), 1);
+        let missing = source.get_financial_data("AAA").await.unwrap_err();
+        assert!(missing.is_not_found());
+
+        fs::remove_dir_all(&root).ok();
+    }
+}
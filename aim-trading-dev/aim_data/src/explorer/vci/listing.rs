@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// One row of the VCI symbol directory (`price/symbols/getAll`, `getByGroup`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VCISymbol {
+    pub symbol: String,
+    /// "STOCK", "FUND", "ETF", "BOND", "COVERED_WARRANT"
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// "HSX", "HNX", "UPCOM"
+    pub board: String,
+    #[serde(rename = "organName")]
+    pub organ_name: String,
+    #[serde(rename = "organShortName")]
+    pub organ_short_name: String,
+    #[serde(rename = "enOrganName")]
+    pub en_organ_name: String,
+}
+
+impl VCISymbol {
+    pub fn is_stock(&self) -> bool {
+        self.kind.eq_ignore_ascii_case("STOCK")
+    }
+}
@@ -1,9 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct MarketWatchResponse(pub Vec<VCIMarketWatch>);
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VCIMarketWatch {
     #[serde(rename = "listingInfo")]
     pub listing_info: ListingInfo,
@@ -13,7 +13,7 @@ pub struct VCIMarketWatch {
     pub match_price: MatchPrice,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct BidAsk {
     pub code: String,
     pub symbol: String,
@@ -24,13 +24,13 @@ pub struct BidAsk {
     // pub time: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PriceVolume {
     pub price: f64,
     pub volume: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ListingInfo {
     pub code: String,
     pub symbol: String,
@@ -52,7 +52,7 @@ pub struct ListingInfo {
     pub trading_date: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MatchPrice {
     pub code: String,
     pub symbol: String,
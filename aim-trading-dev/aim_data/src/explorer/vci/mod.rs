@@ -1,25 +1,36 @@
 use chrono::{DateTime, Utc};
 use company_info::CompanyInfo;
+use listing::VCISymbol;
 use market_watch::{MarketWatchResponse, VCIMarketWatch};
 use ohlc::OHLCResponse;
 pub use order_data::{OrderData, OrderList, VCIOderBook};
-use reqwest::Error;
 use serde_json::json;
 
-use super::get_headers;
+use super::{get_headers, read_json};
+use crate::http::{HttpClient, RetryPolicy};
+use crate::source::{BoxFuture, DataSource, SourceResult};
 
 pub mod company_info;
+pub mod listing;
 pub mod market_watch;
 pub mod ohlc;
 mod order_data;
 
+/// Data source for the VCI (Vietcap) trading platform
+///
+/// Clones share the same connection pool.
+#[derive(Debug, Clone, Default)]
 pub struct VCIExplorer {
-    client: reqwest::Client,
+    http: HttpClient,
 }
 
 impl VCIExplorer {
     pub fn new(client: reqwest::Client) -> Self {
-        Self { client }
+        Self::with_http(HttpClient::new(client, RetryPolicy::default()))
+    }
+
+    pub fn with_http(http: HttpClient) -> Self {
+        Self { http }
     }
 
     pub async fn get_quote(
@@ -28,7 +39,7 @@ impl VCIExplorer {
         time_frame: &str,
         start_time: Option<DateTime<Utc>>,
         end_time: Option<DateTime<Utc>>,
-    ) -> Result<OHLCResponse, Error> {
+    ) -> SourceResult<OHLCResponse> {
         let url = "https://trading.vietcap.com.vn/api/chart/OHLCChart/gap";
         let headers = get_headers("VCI");
 
@@ -48,19 +59,14 @@ impl VCIExplorer {
         });
 
         let response = self
-            .client
-            .request(reqwest::Method::POST, url)
-            .headers(headers)
-            .json(&payload)
-            .send()
+            .http
+            .send(|client| client.post(url).headers(headers.clone()).json(&payload))
             .await?;
 
-        let res = response.json::<OHLCResponse>().await?;
-
-        Ok(res)
+        read_json(url, response).await
     }
 
-    pub async fn get_market_watch(&self, symbols: &[&str]) -> Result<MarketWatchResponse, Error> {
+    pub async fn get_market_watch(&self, symbols: &[&str]) -> SourceResult<MarketWatchResponse> {
         let url = "https://trading.vietcap.com.vn/api/price/symbols/getList";
         let headers = get_headers("VCI");
 
@@ -69,18 +75,19 @@ impl VCIExplorer {
             "symbols": symbols,
         });
         let response = self
-            .client
-            .request(reqwest::Method::POST, url)
-            .headers(headers)
-            .json(&payload)
-            .send()
+            .http
+            .send(|client| client.post(url).headers(headers.clone()).json(&payload))
             .await?;
 
-        let data: Vec<VCIMarketWatch> = response.json().await?;
+        let data: Vec<VCIMarketWatch> = read_json(url, response).await?;
         Ok(MarketWatchResponse(data))
     }
 
-    pub async fn get_company_info(&self, symbol: &str, period: &str) -> Result<CompanyInfo, Error> {
+    pub async fn get_company_info(
+        &self,
+        symbol: &str,
+        period: &str,
+    ) -> SourceResult<CompanyInfo> {
         let url = "https://trading.vietcap.com.vn/data-mt/graphql";
         let headers = get_headers("VCI");
 
@@ -94,18 +101,14 @@ impl VCIExplorer {
         });
 
         let response = self
-            .client
-            .request(reqwest::Method::POST, url)
-            .headers(headers)
-            .json(&payload)
-            .send()
+            .http
+            .send(|client| client.post(url).headers(headers.clone()).json(&payload))
             .await?;
 
-        let data: CompanyInfo = response.json().await?;
-        Ok(data)
+        read_json(url, response).await
     }
 
-    pub async fn get_order_list(&self, symbol: &str, limit: u32) -> Result<OrderList, Error> {
+    pub async fn get_order_list(&self, symbol: &str, limit: u32) -> SourceResult<OrderList> {
         let url = "https://trading.vietcap.com.vn/api/market-watch/LEData/getAll";
         let headers = get_headers("VCI");
 
@@ -116,14 +119,11 @@ impl VCIExplorer {
         });
 
         let response = self
-            .client
-            .request(reqwest::Method::POST, url)
-            .headers(headers)
-            .json(&payload)
-            .send()
+            .http
+            .send(|client| client.post(url).headers(headers.clone()).json(&payload))
             .await?;
 
-        let data: Vec<OrderData> = response.json().await?;
+        let data: Vec<OrderData> = read_json(url, response).await?;
         let converted_data = data
             .into_iter()
             .map(|od| VCIOderBook {
@@ -136,6 +136,90 @@ impl VCIExplorer {
             .collect();
         Ok(converted_data)
     }
+
+    /// Every symbol listed on HOSE, HNX and UPCOM, stocks and other products
+    pub async fn get_listings(&self) -> SourceResult<Vec<VCISymbol>> {
+        let url = "https://trading.vietcap.com.vn/api/price/symbols/getAll";
+        let headers = get_headers("VCI");
+
+        let response = self
+            .http
+            .send(|client| client.get(url).headers(headers.clone()))
+            .await?;
+
+        read_json(url, response).await
+    }
+
+    /// Constituents of an index or group ("VN30", "VN100", "HNX30")
+    pub async fn get_index_constituents(&self, index: &str) -> SourceResult<Vec<String>> {
+        let url = "https://trading.vietcap.com.vn/api/price/symbols/getByGroup";
+        let headers = get_headers("VCI");
+
+        let response = self
+            .http
+            .send(|client| {
+                client
+                    .get(url)
+                    .headers(headers.clone())
+                    .query(&[("group", index)])
+            })
+            .await?;
+
+        let data: Vec<VCISymbol> = read_json(url, response).await?;
+        Ok(data.into_iter().map(|item| item.symbol).collect())
+    }
+}
+
+impl DataSource for VCIExplorer {
+    fn name(&self) -> &str {
+        "VCI"
+    }
+
+    fn get_quote<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+        time_frame: &'a str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, SourceResult<OHLCResponse>> {
+        Box::pin(async move {
+            VCIExplorer::get_quote(self, symbols, time_frame, start_time, end_time).await
+        })
+    }
+
+    fn get_market_watch<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+    ) -> BoxFuture<'a, SourceResult<MarketWatchResponse>> {
+        Box::pin(async move { VCIExplorer::get_market_watch(self, symbols).await })
+    }
+
+    fn get_order_list<'a>(
+        &'a self,
+        symbol: &'a str,
+        limit: u32,
+    ) -> BoxFuture<'a, SourceResult<OrderList>> {
+        Box::pin(async move { VCIExplorer::get_order_list(self, symbol, limit).await })
+    }
+
+    fn get_company_ratios<'a>(
+        &'a self,
+        symbol: &'a str,
+        period: &'a str,
+    ) -> BoxFuture<'a, SourceResult<CompanyInfo>> {
+        Box::pin(async move { VCIExplorer::get_company_info(self, symbol, period).await })
+    }
+
+    fn get_listings(&self) -> BoxFuture<'_, SourceResult<Vec<VCISymbol>>> {
+        Box::pin(async move { VCIExplorer::get_listings(self).await })
+    }
+
+    fn get_index_constituents<'a>(
+        &'a self,
+        index: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<String>>> {
+        Box::pin(async move { VCIExplorer::get_index_constituents(self, index).await })
+    }
 }
 
 #[cfg(test)]
@@ -122,6 +122,107 @@ pub struct Candlestick {
 }
 
 impl OHLCData {
+    /// An empty series for `symbol`
+    pub fn empty(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            o: Vec::new(),
+            h: Vec::new(),
+            l: Vec::new(),
+            c: Vec::new(),
+            v: Vec::new(),
+            t: Vec::new(),
+            accumulated_volume: Vec::new(),
+            accumulated_value: Vec::new(),
+            min_batch_trunc_time: String::new(),
+        }
+    }
+
+    /// Number of bars
+    pub fn len(&self) -> usize {
+        self.t.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.t.is_empty()
+    }
+
+    /// Bar timestamps as Unix seconds, unparsable ones as 0
+    pub fn timestamps(&self) -> Vec<i64> {
+        self.t.iter().map(|t| t.parse().unwrap_or(0)).collect()
+    }
+
+    pub fn last_timestamp(&self) -> Option<i64> {
+        self.t.last().and_then(|t| t.parse().ok())
+    }
+
+    /// Copy of the bars whose timestamp falls within `[start, end]` (Unix seconds)
+    pub fn range(&self, start: Option<i64>, end: Option<i64>) -> Self {
+        let from = start.unwrap_or(i64::MIN);
+        let to = end.unwrap_or(i64::MAX);
+        let timestamps = self.timestamps();
+        let begin = timestamps.partition_point(|&t| t < from);
+        let end = timestamps.partition_point(|&t| t <= to).max(begin);
+
+        fn slice<T: Clone>(values: &[T], begin: usize, end: usize) -> Vec<T> {
+            values[begin.min(values.len())..end.min(values.len())].to_vec()
+        }
+
+        Self {
+            symbol: self.symbol.clone(),
+            o: slice(&self.o, begin, end),
+            h: slice(&self.h, begin, end),
+            l: slice(&self.l, begin, end),
+            c: slice(&self.c, begin, end),
+            v: slice(&self.v, begin, end),
+            t: slice(&self.t, begin, end),
+            accumulated_volume: slice(&self.accumulated_volume, begin, end),
+            accumulated_value: slice(&self.accumulated_value, begin, end),
+            min_batch_trunc_time: self.min_batch_trunc_time.clone(),
+        }
+    }
+
+    /// Merge newer bars into this series
+    ///
+    /// Bars of `fresh` replace stored bars in the time span they cover, so a
+    /// still-forming last bar is overwritten by its updated version.
+    pub fn merge(&mut self, fresh: OHLCData) {
+        let (Some(first), Some(last)) = (
+            fresh.t.first().and_then(|t| t.parse::<i64>().ok()),
+            fresh.last_timestamp(),
+        ) else {
+            return;
+        };
+        let timestamps = self.timestamps();
+        let begin = timestamps.partition_point(|&t| t < first);
+        let end = timestamps.partition_point(|&t| t <= last).max(begin);
+
+        fn splice<T>(values: &mut Vec<T>, begin: usize, end: usize, fresh: Vec<T>) {
+            let len = values.len();
+            values.splice(begin.min(len)..end.min(len), fresh);
+        }
+
+        splice(&mut self.o, begin, end, fresh.o);
+        splice(&mut self.h, begin, end, fresh.h);
+        splice(&mut self.l, begin, end, fresh.l);
+        splice(&mut self.c, begin, end, fresh.c);
+        splice(&mut self.v, begin, end, fresh.v);
+        splice(&mut self.t, begin, end, fresh.t);
+        splice(
+            &mut self.accumulated_volume,
+            begin,
+            end,
+            fresh.accumulated_volume,
+        );
+        splice(
+            &mut self.accumulated_value,
+            begin,
+            end,
+            fresh.accumulated_value,
+        );
+        self.min_batch_trunc_time = fresh.min_batch_trunc_time;
+    }
+
     pub fn to_candlesticks(&self) -> Vec<Candlestick> {
         let mut candlesticks = Vec::new();
 
@@ -0,0 +1,528 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::alert::{AlertBook, AlertEvent};
+use crate::explorer::aim::{AbnormalTrade, InsiderTransaction};
+use crate::side::TradeSide;
+
+/// Seen ids kept per feed, the first seen dropped first
+pub const FEED_SEEN_CAPACITY: usize = 5_000;
+
+/// Key of the abnormal trade feed among the primed feeds
+const ABNORMAL_TRADE_FEED: &str = "trade";
+
+/// Key of the insider feed of `symbol` among the primed feeds
+fn insider_feed(symbol: &str) -> String {
+    format!("insider {}", symbol.to_uppercase())
+}
+
+/// Tickers a feed rule applies to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuleScope {
+    /// Every ticker of the feed
+    All,
+    /// The tickers of the user's list
+    Watchlist,
+    /// One ticker
+    Symbol(String),
+}
+
+impl RuleScope {
+    fn parse(token: &str) -> Self {
+        match token.to_uppercase().as_str() {
+            "ALL" => RuleScope::All,
+            "MYLIST" => RuleScope::Watchlist,
+            symbol => RuleScope::Symbol(symbol.to_string()),
+        }
+    }
+
+    fn contains(&self, symbol: &str, watchlist: &[String]) -> bool {
+        match self {
+            RuleScope::All => true,
+            RuleScope::Watchlist => watchlist
+                .iter()
+                .any(|watched| watched.eq_ignore_ascii_case(symbol)),
+            RuleScope::Symbol(scoped) => scoped.eq_ignore_ascii_case(symbol),
+        }
+    }
+}
+
+impl fmt::Display for RuleScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleScope::All => write!(f, "ALL"),
+            RuleScope::Watchlist => write!(f, "MYLIST"),
+            RuleScope::Symbol(symbol) => write!(f, "{symbol}"),
+        }
+    }
+}
+
+/// What a rule watches for on the news feeds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FeedRule {
+    /// An abnormal trade worth at least `min_value` billion VND
+    AbnormalTrade { min_value: f64, scope: RuleScope },
+    /// A new insider transaction or registration, of one side or both
+    Insider {
+        side: Option<TradeSide>,
+        scope: RuleScope,
+    },
+}
+
+impl FeedRule {
+    /// Parse a name, its parameters and an optional scope, e.g.
+    /// `TRADE 10`, `TRADE 10 ALL`, `INSIDER BUY` or `INSIDER FPT`
+    ///
+    /// The scope is `ALL`, `MYLIST` (the default) or a ticker.
+    pub fn parse(label: &str) -> Option<Self> {
+        let mut parts = label.split_whitespace().peekable();
+        let name = parts.next()?.to_uppercase();
+        let rule = match name.as_str() {
+            "TRADE" => FeedRule::AbnormalTrade {
+                min_value: parts
+                    .next()?
+                    .replace(',', ".")
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|value| *value > 0.0)?,
+                scope: RuleScope::Watchlist,
+            },
+            "INSIDER" => {
+                let side = match parts.peek().map(|part| part.to_uppercase()).as_deref() {
+                    Some("BUY") => Some(TradeSide::Buy),
+                    Some("SELL") => Some(TradeSide::Sell),
+                    _ => None,
+                };
+                if side.is_some() {
+                    parts.next();
+                }
+                FeedRule::Insider {
+                    side,
+                    scope: RuleScope::Watchlist,
+                }
+            }
+            _ => return None,
+        };
+        let scope = parts.next().map(RuleScope::parse);
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(match (rule, scope) {
+            (FeedRule::AbnormalTrade { min_value, .. }, Some(scope)) => {
+                FeedRule::AbnormalTrade { min_value, scope }
+            }
+            (FeedRule::Insider { side, .. }, Some(scope)) => FeedRule::Insider { side, scope },
+            (rule, None) => rule,
+        })
+    }
+
+    pub fn scope(&self) -> &RuleScope {
+        match self {
+            FeedRule::AbnormalTrade { scope, .. } | FeedRule::Insider { scope, .. } => scope,
+        }
+    }
+}
+
+impl fmt::Display for FeedRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FeedRule::AbnormalTrade { min_value, scope } => {
+                write!(f, "TRADE {min_value} {scope}")
+            }
+            FeedRule::Insider { side, scope } => match side {
+                Some(TradeSide::Buy) => write!(f, "INSIDER BUY {scope}"),
+                Some(TradeSide::Sell) => write!(f, "INSIDER SELL {scope}"),
+                None => write!(f, "INSIDER {scope}"),
+            },
+        }
+    }
+}
+
+/// A rule set on a feed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedAlert {
+    pub id: u32,
+    pub rule: FeedRule,
+    pub enabled: bool,
+}
+
+impl AlertBook {
+    pub fn rules(&self) -> &[FeedAlert] {
+        &self.rules
+    }
+
+    /// Watch a feed for `rule`, returns the id of the new rule
+    ///
+    /// A feed no other rule fetches may not have been fetched for a while,
+    /// so its next fetch only records what is there. The feeds already
+    /// fetched for the rules on `watchlist` carry on.
+    pub fn add_rule(&mut self, rule: FeedRule, watchlist: &[String]) -> u32 {
+        let watched_trades = self.watches_abnormal_trades();
+        let insider_symbols = self.insider_symbols(watchlist);
+        let id = self.take_id();
+        self.rules.push(FeedAlert {
+            id,
+            rule,
+            enabled: true,
+        });
+        if !watched_trades {
+            self.primed_feeds.remove(ABNORMAL_TRADE_FEED);
+        }
+        for symbol in self.insider_symbols(watchlist) {
+            if !insider_symbols.contains(&symbol) {
+                self.primed_feeds.remove(&insider_feed(&symbol));
+            }
+        }
+        id
+    }
+
+    /// Whether an enabled rule watches the abnormal trade feed
+    pub fn watches_abnormal_trades(&self) -> bool {
+        self.enabled_rules()
+            .any(|rule| matches!(rule, FeedRule::AbnormalTrade { .. }))
+    }
+
+    /// Tickers to fetch the insider feed of, the feed being per ticker
+    ///
+    /// A rule on all tickers falls back to the watchlist and the symbols
+    /// price alerts are set on.
+    pub fn insider_symbols(&self, watchlist: &[String]) -> Vec<String> {
+        let mut symbols = Vec::new();
+        for rule in self.enabled_rules() {
+            let FeedRule::Insider { scope, .. } = rule else {
+                continue;
+            };
+            match scope {
+                RuleScope::All => {
+                    symbols.extend(watchlist.iter().cloned());
+                    symbols.extend(self.symbols());
+                }
+                RuleScope::Watchlist => symbols.extend(watchlist.iter().cloned()),
+                RuleScope::Symbol(symbol) => symbols.push(symbol.clone()),
+            }
+        }
+        let mut symbols: Vec<String> = symbols.iter().map(|symbol| symbol.to_uppercase()).collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+
+    /// Check the trades not seen before against the rules, log and return
+    /// the ones that fire
+    ///
+    /// The first fetch of the feed only marks its trades as seen, they
+    /// happened before the rules watched it.
+    pub fn check_abnormal_trades(
+        &mut self,
+        trades: &[AbnormalTrade],
+        watchlist: &[String],
+    ) -> Vec<AlertEvent> {
+        let primed = !self.primed_feeds.insert(ABNORMAL_TRADE_FEED.to_string());
+        let mut events = Vec::new();
+        for trade in trades {
+            if !self.seen_trades.insert(trade.id) || !primed {
+                continue;
+            }
+            // Prices of the feed are in VND
+            let value = trade.price * trade.volume as f64 / 1e9;
+            let side = match trade.match_type.as_str() {
+                "b" => TradeSide::Buy.label(),
+                "s" => TradeSide::Sell.label(),
+                _ => "khớp",
+            };
+            for alert in self.rules.iter().filter(|alert| alert.enabled) {
+                let FeedRule::AbnormalTrade { min_value, scope } = &alert.rule else {
+                    continue;
+                };
+                if value < *min_value || !scope.contains(&trade.ticker, watchlist) {
+                    continue;
+                }
+                events.push(AlertEvent {
+                    alert_id: alert.id,
+                    symbol: trade.ticker.clone(),
+                    message: format!(
+                        "{} GD bất thường {side} {} cp giá {:.2}, {value:.1} tỷ",
+                        trade.ticker,
+                        trade.volume,
+                        trade.price / 1000.0
+                    ),
+                    price: trade.price / 1000.0,
+                    time: DateTime::from_timestamp(trade.timestamp, 0).unwrap_or_else(Utc::now),
+                });
+            }
+        }
+        self.record(&events);
+        events
+    }
+
+    /// Check the insider transactions of `symbol` not seen before against
+    /// the rules, log and return the ones that fire
+    ///
+    /// The feed returns the whole history of the ticker, so its first fetch
+    /// only marks the transactions as seen.
+    pub fn check_insider_transactions(
+        &mut self,
+        symbol: &str,
+        transactions: &[InsiderTransaction],
+        watchlist: &[String],
+    ) -> Vec<AlertEvent> {
+        let primed = !self.primed_feeds.insert(insider_feed(symbol));
+        let mut events = Vec::new();
+        for transaction in transactions {
+            if !self.seen_insider.insert(transaction.transaction_id) || !primed {
+                continue;
+            }
+            let side = if transaction.transaction_type == 1 {
+                TradeSide::Buy
+            } else {
+                TradeSide::Sell
+            };
+            for alert in self.rules.iter().filter(|alert| alert.enabled) {
+                let FeedRule::Insider {
+                    side: wanted,
+                    scope,
+                } = &alert.rule
+                else {
+                    continue;
+                };
+                if wanted.is_some_and(|wanted| wanted != side)
+                    || !scope.contains(&transaction.symbol, watchlist)
+                {
+                    continue;
+                }
+                events.push(AlertEvent {
+                    alert_id: alert.id,
+                    symbol: transaction.symbol.clone(),
+                    message: insider_message(transaction, side),
+                    price: 0.0,
+                    time: Utc::now(),
+                });
+            }
+        }
+        self.record(&events);
+        events
+    }
+
+    fn enabled_rules(&self) -> impl Iterator<Item = &FeedRule> {
+        self.rules
+            .iter()
+            .filter(|alert| alert.enabled)
+            .map(|alert| &alert.rule)
+    }
+}
+
+/// Ids already checked on a feed, in the order they were seen
+///
+/// Ids are not in time order on every feed, so the first seen is dropped
+/// past [`FEED_SEEN_CAPACITY`], not the lowest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<i64>", into = "Vec<i64>")]
+pub(crate) struct SeenIds {
+    order: VecDeque<i64>,
+    ids: HashSet<i64>,
+}
+
+impl SeenIds {
+    /// Remember `id`, returns whether it was new
+    pub(crate) fn insert(&mut self, id: i64) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        while self.order.len() > FEED_SEEN_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+impl From<Vec<i64>> for SeenIds {
+    fn from(ids: Vec<i64>) -> Self {
+        let mut seen = Self::default();
+        for id in ids {
+            seen.insert(id);
+        }
+        seen
+    }
+}
+
+impl From<SeenIds> for Vec<i64> {
+    fn from(seen: SeenIds) -> Self {
+        seen.order.into()
+    }
+}
+
+/// Message of an insider transaction, executed or only registered
+fn insider_message(transaction: &InsiderTransaction, side: TradeSide) -> String {
+    let holder = match &transaction.position {
+        Some(position) => format!("{} ({position})", transaction.name),
+        None => transaction.name.clone(),
+    };
+    let side = side.label();
+    match (transaction.execution_volume, transaction.registered_volume) {
+        (Some(executed), _) if executed > 0.0 => {
+            format!(
+                "{}: {holder} đã {side} {executed:.0} cp",
+                transaction.symbol
+            )
+        }
+        (_, Some(registered)) => format!(
+            "{}: {holder} đăng ký {side} {registered:.0} cp",
+            transaction.symbol
+        ),
+        _ => format!("{}: {holder} đăng ký {side}", transaction.symbol),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(id: i64, ticker: &str, volume: i64) -> AbnormalTrade {
+        AbnormalTrade {
+            id,
+            ticker: ticker.to_string(),
+            price: 50_000.0,
+            volume,
+            timestamp: 1_704_164_400,
+            match_type: "b".to_string(),
+        }
+    }
+
+    fn insider(transaction_id: i64, transaction_type: i32) -> InsiderTransaction {
+        InsiderTransaction {
+            transaction_id,
+            major_holder_id: 1,
+            individual_holder_id: None,
+            institution_holder_id: None,
+            institution_holder_symbol: None,
+            institution_holder_exchange: None,
+            name: "Nguyễn Văn A".to_string(),
+            position: Some("Chủ tịch HĐQT".to_string()),
+            symbol: "FPT".to_string(),
+            transaction_type,
+            execution_volume: None,
+            execution_date: 0,
+            start_date: 0,
+            end_date: 0,
+            registered_volume: Some(1_000_000.0),
+        }
+    }
+
+    #[test]
+    fn test_feed_rules_fire_once_per_event() {
+        let watchlist = vec!["FPT".to_string()];
+        let mut book = AlertBook::default();
+        let trades = book.add_rule(FeedRule::parse("trade 10").unwrap(), &watchlist);
+        book.add_rule(FeedRule::parse("INSIDER BUY").unwrap(), &watchlist);
+        assert_eq!(FeedRule::parse("TRADE"), None);
+        assert_eq!(
+            FeedRule::parse("INSIDER SELL HPG"),
+            Some(FeedRule::Insider {
+                side: Some(TradeSide::Sell),
+                scope: RuleScope::Symbol("HPG".to_string()),
+            })
+        );
+        let rule = FeedRule::parse("TRADE 2,5 ALL").unwrap();
+        assert_eq!(FeedRule::parse(&rule.to_string()), Some(rule));
+        assert!(book.watches_abnormal_trades());
+        assert_eq!(book.insider_symbols(&watchlist), ["FPT"]);
+
+        // The first fetch is history, not news
+        assert!(
+            book.check_abnormal_trades(&[trade(0, "FPT", 300_000)], &watchlist)
+                .is_empty()
+        );
+        assert!(
+            book.check_insider_transactions("FPT", &[insider(6, 1)], &watchlist)
+                .is_empty()
+        );
+
+        // 50,000 VND x 300,000 = 15 bn, 50,000 x 100,000 = 5 bn
+        let feed = [
+            trade(1, "FPT", 300_000),
+            trade(2, "FPT", 100_000),
+            trade(3, "HPG", 300_000),
+        ];
+        let events = book.check_abnormal_trades(&feed, &watchlist);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].alert_id, trades);
+        assert_eq!(
+            events[0].message,
+            "FPT GD bất thường mua 300000 cp giá 50.00, 15.0 tỷ"
+        );
+        assert!(book.check_abnormal_trades(&feed, &watchlist).is_empty());
+
+        let events =
+            book.check_insider_transactions("FPT", &[insider(7, 0), insider(8, 1)], &watchlist);
+        let messages: Vec<&str> = events.iter().map(|event| event.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            ["FPT: Nguyễn Văn A (Chủ tịch HĐQT) đăng ký mua 1000000 cp"]
+        );
+        assert_eq!(book.log().len(), 2);
+
+        // Seen ids survive a restart
+        let json = serde_json::to_string(&book).unwrap();
+        let mut read: AlertBook = serde_json::from_str(&json).unwrap();
+        assert_eq!(read.rules(), book.rules());
+        assert!(read.check_abnormal_trades(&feed, &watchlist).is_empty());
+        assert!(
+            read.check_insider_transactions("FPT", &[insider(8, 1)], &watchlist)
+                .is_empty()
+        );
+        assert!(read.remove(trades));
+        assert!(!read.watches_abnormal_trades());
+        // Watching again starts from what the feed holds then
+        read.add_rule(FeedRule::parse("TRADE 10").unwrap(), &watchlist);
+        assert!(
+            read.check_abnormal_trades(&[trade(4, "FPT", 300_000)], &watchlist)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_adding_a_rule_keeps_the_others_firing() {
+        let watchlist = vec!["FPT".to_string()];
+        let mut book = AlertBook::default();
+        let trades = book.add_rule(FeedRule::parse("TRADE 10").unwrap(), &watchlist);
+        let insiders = book.add_rule(FeedRule::parse("INSIDER").unwrap(), &watchlist);
+        book.check_abnormal_trades(&[trade(1, "FPT", 300_000)], &watchlist);
+        book.check_insider_transactions("FPT", &[insider(1, 1)], &watchlist);
+
+        // Both feeds are already fetched for the rules above
+        book.add_rule(FeedRule::parse("TRADE 20 ALL").unwrap(), &watchlist);
+        book.add_rule(FeedRule::parse("INSIDER SELL HPG").unwrap(), &watchlist);
+        let events = book.check_abnormal_trades(&[trade(2, "FPT", 300_000)], &watchlist);
+        let ids: Vec<u32> = events.iter().map(|event| event.alert_id).collect();
+        assert_eq!(ids, [trades]);
+        let events = book.check_insider_transactions("FPT", &[insider(2, 1)], &watchlist);
+        let ids: Vec<u32> = events.iter().map(|event| event.alert_id).collect();
+        assert_eq!(ids, [insiders]);
+
+        // The feed of HPG is new, its first fetch is history
+        assert!(
+            book.check_insider_transactions("HPG", &[insider(3, 0)], &watchlist)
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_seen_ids_drop_the_first_seen() {
+        let mut seen = SeenIds::default();
+        // Newest first, as some feeds list them
+        for id in (0..=FEED_SEEN_CAPACITY as i64).rev() {
+            assert!(seen.insert(id));
+        }
+        assert!(!seen.insert(0));
+        // The highest id went first, so it reads as new again
+        assert!(seen.insert(FEED_SEEN_CAPACITY as i64));
+        let json = serde_json::to_string(&seen).unwrap();
+        assert_eq!(serde_json::from_str::<SeenIds>(&json).unwrap(), seen);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, ClientBuilder, RequestBuilder, Response, StatusCode};
+
+use crate::error::AimDataError;
+use crate::source::SourceResult;
+
+/// Longest `Retry-After` we are willing to wait before giving up
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Exponential backoff with jitter for 5xx, 429 and timed out requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on every attempt
+    pub base_delay: Duration,
+    /// Upper bound of the computed delay
+    pub max_delay: Duration,
+    /// Fraction of the delay that is randomised, from 0.0 (none) to 1.0 (full jitter)
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(10),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Never retry
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry number `attempt` (starting at 0)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        // Keep (1 - jitter) of the delay and randomise the rest
+        exp.mul_f64(1.0 - jitter + jitter * random_unit())
+    }
+}
+
+/// Random number in `[0, 1)` without pulling in a RNG crate
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Parse a `Retry-After` header, either delay-seconds or an HTTP date
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or_default())
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// A pooled client builder tuned for many small polling requests to one host
+pub fn client_builder() -> ClientBuilder {
+    ClientBuilder::new()
+        .pool_idle_timeout(Duration::from_secs(90))
+        .pool_max_idle_per_host(16)
+        .tcp_keepalive(Duration::from_secs(60))
+        .connect_timeout(Duration::from_secs(10))
+        .timeout(Duration::from_secs(30))
+}
+
+/// HTTP client shared by every request of one data source
+///
+/// Cloning is cheap and shares the connection pool.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    client: Client,
+    retry: RetryPolicy,
+}
+
+impl HttpClient {
+    pub fn new(client: Client, retry: RetryPolicy) -> Self {
+        Self { client, retry }
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry
+    }
+
+    /// Send the request built by `build`, retrying 5xx, 429 and timeouts
+    ///
+    /// `build` is called once per attempt. The last response is returned as
+    /// is once retries are exhausted, so callers still see the real status.
+    pub async fn send(&self, build: impl Fn(&Client) -> RequestBuilder) -> SourceResult<Response> {
+        let mut attempt = 0;
+        loop {
+            let retries_left = attempt < self.retry.max_retries;
+            let delay = match build(&self.client).send().await {
+                Ok(response) if retries_left && is_retryable_status(response.status()) => {
+                    match retry_after(&response) {
+                        Some(wait) if wait > MAX_RETRY_AFTER => return Ok(response),
+                        Some(wait) => wait,
+                        None => self.retry.backoff(attempt),
+                    }
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if retries_left && (e.is_timeout() || e.is_connect()) => {
+                    self.retry.backoff(attempt)
+                }
+                Err(e) => return Err(AimDataError::Transport(e)),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        let client = client_builder().build().unwrap_or_default();
+        Self::new(client, RetryPolicy::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_backoff_grows_and_is_capped() {
+        let policy = RetryPolicy {
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.backoff(0), Duration::from_millis(250));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(20), policy.max_delay);
+
+        let jittered = RetryPolicy::default();
+        for attempt in 0..5 {
+            let delay = jittered.backoff(attempt);
+            assert!(delay >= policy.backoff(attempt) / 2 && delay <= policy.backoff(attempt));
+        }
+    }
+
+    /// Serve the given raw responses, one per connection
+    async fn serve(responses: Vec<&'static str>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+        });
+        format!("http://{addr}/")
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors_honouring_retry_after() {
+        let url = serve(vec![
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 503 Service Unavailable\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 2\r\n\r\nok",
+        ])
+        .await;
+        let client = HttpClient::new(
+            Client::new(),
+            RetryPolicy {
+                base_delay: Duration::from_millis(1),
+                ..RetryPolicy::default()
+            },
+        );
+        let response = client.send(|c| c.get(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.text().await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_returns_last_response_when_retries_exhausted() {
+        let url = serve(vec![
+            "HTTP/1.1 502 Bad Gateway\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ])
+        .await;
+        let client = HttpClient::new(Client::new(), RetryPolicy::none());
+        let response = client.send(|c| c.get(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+}
@@ -1,10 +1,51 @@
 // pub mod btc;
+pub mod alert;
+pub mod calendar;
+pub mod config;
+pub mod error;
 pub mod explorer;
+pub mod feed_alert;
+pub mod file;
+pub mod http;
+pub mod orderbook;
+pub mod portfolio;
+pub mod resample;
+pub mod side;
+pub mod source;
+pub mod store;
+pub mod stream;
+pub mod symbols;
+pub mod tape;
+pub mod watchlist;
 
 // pub use btc::get_btc_price;
 use chrono::{DateTime, Utc};
 use explorer::vci::VCIOderBook;
-use reqwest::Client;
+use source::CombinedSource;
+use std::sync::{Arc, OnceLock, RwLock};
+
+static DATA_SOURCE: OnceLock<RwLock<Arc<dyn DataSource>>> = OnceLock::new();
+
+fn data_source_slot() -> &'static RwLock<Arc<dyn DataSource>> {
+    DATA_SOURCE.get_or_init(|| RwLock::new(Arc::new(CombinedSource::default())))
+}
+
+/// Replace the provider used by the free functions in this crate
+///
+/// Defaults to [`CombinedSource`] (VCI for market data, AIM for fundamentals).
+pub fn set_data_source(source: Arc<dyn DataSource>) {
+    *data_source_slot()
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = source;
+}
+
+/// The provider currently used by the free functions in this crate
+pub fn data_source() -> Arc<dyn DataSource> {
+    data_source_slot()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
 
 /// Get stock quote data for a given symbol
 ///
@@ -14,7 +55,7 @@ use reqwest::Client;
 /// * `end_time` - Optional end time for the data range
 ///
 /// # Returns
-/// * `Result<OHLCResponse, reqwest::Error>` - The OHLC data or an error
+/// * `SourceResult<OHLCResponse>` - The OHLC data or an error
 ///
 /// # Example
 /// ```
@@ -42,40 +83,76 @@ pub async fn get_quote(
     time_frame: &str,
     start_time: Option<DateTime<Utc>>,
     end_time: Option<DateTime<Utc>>,
-) -> Result<explorer::vci::ohlc::OHLCResponse, reqwest::Error> {
-    let client = Client::new();
-    let explorer = explorer::VCIExplorer::new(client);
-    explorer
+) -> SourceResult<explorer::vci::ohlc::OHLCResponse> {
+    data_source()
         .get_quote(symbol, time_frame, start_time, end_time)
         .await
 }
 
 pub async fn get_market_watch(
     symbols: &[&str],
-) -> Result<explorer::vci::market_watch::MarketWatchResponse, reqwest::Error> {
-    let client = Client::new();
-    let explorer = explorer::VCIExplorer::new(client);
-    explorer.get_market_watch(symbols).await
+) -> SourceResult<explorer::vci::market_watch::MarketWatchResponse> {
+    data_source().get_market_watch(symbols).await
 }
 
 pub async fn get_company_info(
     symbols: &str,
-) -> Result<explorer::vci::company_info::CompanyInfo, reqwest::Error> {
-    let client = Client::new();
-    let explorer = explorer::VCIExplorer::new(client);
-    explorer.get_company_info(symbols, "Y").await
+) -> SourceResult<explorer::vci::company_info::CompanyInfo> {
+    data_source().get_company_ratios(symbols, "Y").await
+}
+
+static MARKET_STREAM: OnceLock<MarketStream> = OnceLock::new();
+
+/// The market stream shared by the app, built on first use from the
+/// configured AIM backend and [`data_source`]
+pub fn market_stream() -> MarketStream {
+    MARKET_STREAM.get_or_init(MarketStream::default).clone()
+}
+
+/// Push updates of `symbols` from the AIM feed, polling the data source
+/// while the feed is unreachable
+pub fn subscribe(symbols: &[&str], channels: &[Channel]) -> Subscription {
+    market_stream().subscribe(symbols, channels)
 }
 
 #[allow(dead_code)]
-pub async fn get_order_list(symbol: &str) -> Result<Vec<VCIOderBook>, reqwest::Error> {
-    let client = Client::new();
-    let explorer = explorer::VCIExplorer::new(client);
-    explorer.get_order_list(symbol, 30000).await
+pub async fn get_order_list(symbol: &str) -> SourceResult<Vec<VCIOderBook>> {
+    data_source().get_order_list(symbol, tape::MAX_PAGE).await
+}
+
+/// Follow the time & sales of `symbol`, starting with the trades of the day
+pub fn watch_tape(symbol: &str) -> TapeStream {
+    TapeStream::open(&market_stream(), symbol, TapeConfig::default())
 }
 
 /// Re-export types for direct usage
 pub use explorer::vci::ohlc::{Candlestick, OHLCData};
 pub use explorer::*;
+pub use alert::{
+    ALERT_LOG_CAPACITY, Alert, AlertBook, AlertCondition, AlertContext, AlertEvent, Quote,
+};
+pub use calendar::{Exchange, SessionPhase, TradingCalendar, calendar};
+pub use config::AimConfig;
+pub use error::AimDataError;
+pub use feed_alert::{FEED_SEEN_CAPACITY, FeedAlert, FeedRule, RuleScope};
+pub use file::write_atomic;
+pub use orderbook::{BookSide, DepthHistory, LevelChange, OrderBook};
+pub use portfolio::{
+    Book, CashFlow, DEFAULT_BROKER_FEE_RATE, FeeSchedule, Lot, NavPoint, Portfolio, PortfolioError,
+    Position, SELL_TAX_RATE, SETTLEMENT_DAYS, StatementError, Transaction, parse_statement,
+    settlement_date,
+};
+pub use resample::{TimeFrame, resample};
+pub use side::TradeSide;
+pub use source::{DataSource, FinanceSheet, SourceResult};
+pub use store::CandleStore;
+pub use stream::{BarUpdate, Channel, MarketStream, StreamEvent, Subscription, Tick};
+pub use tape::{Aggressor, Tape, TapeConfig, TapeStats, TapeStream, TapeUpdate, Trade};
+pub use symbols::{SymbolMaster, refresh_symbol_master, set_symbol_master, symbol_master};
+pub use watchlist::{
+    DEFAULT_WATCHLIST, Watchlist, WatchlistAction, WatchlistColumns, WatchlistSort, Watchlists,
+    import_watchlists,
+};
 
 // mod test {
 //     #[cfg(test)]
@@ -0,0 +1,329 @@
+//! Level-2 depth of one symbol
+//!
+//! VCI publishes the best bid and ask levels with every market watch row.
+//! [`OrderBook`] is one such snapshot with the usual measures on it, and
+//! [`DepthHistory`] keeps the recent snapshots of a symbol with what changed
+//! from one to the next.
+
+use std::collections::{BTreeSet, VecDeque};
+
+use chrono::{DateTime, Utc};
+
+use crate::calendar::Exchange;
+use crate::explorer::vci::market_watch::{PriceVolume, VCIMarketWatch};
+
+/// Snapshots kept by [`DepthHistory::default`]
+pub const DEFAULT_DEPTH_HISTORY: usize = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+/// Depth of one symbol at one time, prices in VND
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderBook {
+    pub symbol: String,
+    pub time: DateTime<Utc>,
+    pub exchange: Option<Exchange>,
+    /// Best first, so highest price first
+    pub bids: Vec<PriceVolume>,
+    /// Best first, so lowest price first
+    pub asks: Vec<PriceVolume>,
+    pub last_price: f64,
+    pub ref_price: f64,
+    pub ceiling: f64,
+    pub floor: f64,
+}
+
+impl OrderBook {
+    /// Depth of a market watch row, without the empty levels VCI pads with
+    pub fn from_market_watch(row: &VCIMarketWatch, time: DateTime<Utc>) -> Self {
+        let levels = |prices: &[PriceVolume]| -> Vec<PriceVolume> {
+            prices
+                .iter()
+                .filter(|level| level.price > 0.0 && level.volume > 0)
+                .cloned()
+                .collect()
+        };
+        let mut bids = levels(&row.bid_ask.bid_prices);
+        let mut asks = levels(&row.bid_ask.ask_prices);
+        bids.sort_by(|a, b| b.price.total_cmp(&a.price));
+        asks.sort_by(|a, b| a.price.total_cmp(&b.price));
+        Self {
+            symbol: row.listing_info.symbol.clone(),
+            time,
+            exchange: Exchange::from_board(&row.listing_info.board),
+            bids,
+            asks,
+            last_price: row.match_price.match_price,
+            ref_price: row.listing_info.ref_price,
+            ceiling: row.listing_info.ceiling,
+            floor: row.listing_info.floor,
+        }
+    }
+
+    pub fn levels(&self, side: BookSide) -> &[PriceVolume] {
+        match side {
+            BookSide::Bid => &self.bids,
+            BookSide::Ask => &self.asks,
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<&PriceVolume> {
+        self.bids.first()
+    }
+
+    pub fn best_ask(&self) -> Option<&PriceVolume> {
+        self.asks.first()
+    }
+
+    /// Best ask minus best bid, `None` while a side is empty
+    pub fn spread(&self) -> Option<f64> {
+        Some(self.best_ask()?.price - self.best_bid()?.price)
+    }
+
+    pub fn mid(&self) -> Option<f64> {
+        Some((self.best_ask()?.price + self.best_bid()?.price) / 2.0)
+    }
+
+    /// Volume resting on `side`
+    pub fn depth(&self, side: BookSide) -> i64 {
+        self.levels(side).iter().map(|level| level.volume).sum()
+    }
+
+    /// Bid minus ask depth over the whole depth, from -1 (all asks) to 1
+    /// (all bids), `None` on an empty book
+    pub fn imbalance(&self) -> Option<f64> {
+        let bids = self.depth(BookSide::Bid) as f64;
+        let asks = self.depth(BookSide::Ask) as f64;
+        (bids + asks > 0.0).then(|| (bids - asks) / (bids + asks))
+    }
+
+    /// Each level of `side` with the volume up to and including it, best first
+    pub fn cumulative(&self, side: BookSide) -> Vec<(f64, i64)> {
+        let mut total = 0;
+        self.levels(side)
+            .iter()
+            .map(|level| {
+                total += level.volume;
+                (level.price, total)
+            })
+            .collect()
+    }
+
+    /// Volume resting at `price` on `side`, 0 when there is no such level
+    pub fn volume_at(&self, side: BookSide, price: f64) -> i64 {
+        self.levels(side)
+            .iter()
+            .find(|level| level.price == price)
+            .map_or(0, |level| level.volume)
+    }
+
+    /// Price step at `price`, 100 VND when the exchange is not known
+    pub fn tick_size(&self, price: f64) -> f64 {
+        self.exchange
+            .map_or(100.0, |exchange| exchange.tick_size(price))
+    }
+
+    /// Levels whose volume differs from `previous`, bids then asks, each
+    /// highest price first
+    pub fn diff(&self, previous: &OrderBook) -> Vec<LevelChange> {
+        let mut changes = Vec::new();
+        for side in [BookSide::Bid, BookSide::Ask] {
+            let prices: BTreeSet<i64> = self
+                .levels(side)
+                .iter()
+                .chain(previous.levels(side))
+                .map(|level| level.price.round() as i64)
+                .collect();
+            for price in prices.into_iter().rev() {
+                let price = price as f64;
+                let before = previous.volume_at(side, price);
+                let after = self.volume_at(side, price);
+                if before != after {
+                    changes.push(LevelChange {
+                        side,
+                        price,
+                        before,
+                        after,
+                    });
+                }
+            }
+        }
+        changes
+    }
+
+    fn same_depth(&self, other: &OrderBook) -> bool {
+        self.symbol == other.symbol
+            && self.bids == other.bids
+            && self.asks == other.asks
+            && self.last_price == other.last_price
+    }
+}
+
+/// Volume of one price level before and after an update
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LevelChange {
+    pub side: BookSide,
+    pub price: f64,
+    pub before: i64,
+    pub after: i64,
+}
+
+impl LevelChange {
+    /// Volume added to the level, negative when orders left it
+    pub fn delta(&self) -> i64 {
+        self.after - self.before
+    }
+}
+
+/// Latest depth snapshots of one symbol, oldest first
+#[derive(Debug, Clone)]
+pub struct DepthHistory {
+    capacity: usize,
+    snapshots: VecDeque<OrderBook>,
+}
+
+impl Default for DepthHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEPTH_HISTORY)
+    }
+}
+
+impl DepthHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            snapshots: VecDeque::new(),
+        }
+    }
+
+    /// Keep `book` unless the depth did not move, returns the levels that
+    /// changed since the last snapshot
+    ///
+    /// The first snapshot, and the first of another symbol, which starts the
+    /// history over, count every level as changed.
+    pub fn push(&mut self, book: OrderBook) -> Vec<LevelChange> {
+        if self
+            .latest()
+            .is_some_and(|latest| latest.symbol != book.symbol)
+        {
+            self.snapshots.clear();
+        }
+        let changes = match self.latest() {
+            Some(latest) if latest.same_depth(&book) => return Vec::new(),
+            Some(latest) => book.diff(latest),
+            None => {
+                let empty = OrderBook {
+                    bids: Vec::new(),
+                    asks: Vec::new(),
+                    ..book.clone()
+                };
+                book.diff(&empty)
+            }
+        };
+        self.snapshots.push_back(book);
+        while self.snapshots.len() > self.capacity {
+            self.snapshots.pop_front();
+        }
+        changes
+    }
+
+    pub fn latest(&self) -> Option<&OrderBook> {
+        self.snapshots.back()
+    }
+
+    pub fn snapshots(&self) -> impl Iterator<Item = &OrderBook> {
+        self.snapshots.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(bids: &[(f64, i64)], asks: &[(f64, i64)]) -> VCIMarketWatch {
+        let levels = |levels: &[(f64, i64)]| -> Vec<serde_json::Value> {
+            levels
+                .iter()
+                .map(|(price, volume)| serde_json::json!({ "price": price, "volume": volume }))
+                .collect()
+        };
+        let listing = serde_json::json!({
+            "code": "FPT", "symbol": "FPT", "ceiling": 26750.0, "floor": 23250.0,
+            "refPrice": 25000.0, "board": "HSX", "enOrganName": "", "enOrganShortName": "",
+            "organName": "", "organShortName": "", "ticker": "FPT", "tradingDate": "",
+        });
+        let matched = serde_json::json!({
+            "code": "FPT", "symbol": "FPT", "matchPrice": 25050.0, "matchVol": 100,
+            "accumulatedVolume": 1000, "accumulatedValue": 25100.0, "lowest": 24900.0,
+            "referencePrice": 25000.0,
+        });
+        serde_json::from_value(serde_json::json!({
+            "listingInfo": listing,
+            "bidAsk": {
+                "code": "FPT", "symbol": "FPT",
+                "bidPrices": levels(bids), "askPrices": levels(asks),
+            },
+            "matchPrice": matched,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_depth_measures_and_history() {
+        let time = Utc::now();
+        let first = OrderBook::from_market_watch(
+            &row(
+                &[(24950.0, 300), (25000.0, 100), (0.0, 0)],
+                &[(25100.0, 200), (25050.0, 200)],
+            ),
+            time,
+        );
+        assert_eq!(first.best_bid().unwrap().price, 25000.0);
+        assert_eq!(first.best_ask().unwrap().price, 25050.0);
+        assert_eq!(first.spread(), Some(50.0));
+        assert_eq!(first.tick_size(first.mid().unwrap()), 50.0);
+        assert_eq!(first.imbalance(), Some(0.0));
+        assert_eq!(
+            first.cumulative(BookSide::Bid),
+            [(25000.0, 100), (24950.0, 400)]
+        );
+
+        let mut history = DepthHistory::new(2);
+        assert_eq!(history.push(first.clone()).len(), 4);
+        assert!(history.push(first.clone()).is_empty());
+
+        // Best bid lifted, a new bid level below
+        let second = OrderBook::from_market_watch(
+            &row(
+                &[(25000.0, 500), (24950.0, 300), (24900.0, 50)],
+                &[(25050.0, 200), (25100.0, 200)],
+            ),
+            time,
+        );
+        let changes = history.push(second.clone());
+        let deltas: Vec<(BookSide, f64, i64)> = changes
+            .iter()
+            .map(|change| (change.side, change.price, change.delta()))
+            .collect();
+        assert_eq!(
+            deltas,
+            [(BookSide::Bid, 25000.0, 400), (BookSide::Bid, 24900.0, 50)]
+        );
+        // 850 bid against 400 ask
+        assert!((second.imbalance().unwrap() - 0.36).abs() < 1e-9);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.latest(), Some(&second));
+    }
+}
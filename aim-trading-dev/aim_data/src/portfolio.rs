@@ -0,0 +1,918 @@
+//! Holdings of the user, kept as a ledger of trades
+//!
+//! [`Portfolio`] records the trades and cash flows and replays them into a
+//! [`Book`]: open lots per symbol, average cost, realised and unrealised P&L
+//! after the broker fee and the sell tax. Shares bought settle T+2 and only
+//! settled shares can be sold; sell proceeds reach the cash T+2 as well.
+//! Prices are in VND, marked from the market watch.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::calendar::calendar;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::side::TradeSide;
+
+/// Personal income tax withheld on the value of every sale
+pub const SELL_TAX_RATE: f64 = 0.001;
+
+/// Broker fee on the value of an order when the statement gives none
+pub const DEFAULT_BROKER_FEE_RATE: f64 = 0.0015;
+
+/// Trading days from the trade to the delivery of shares and cash
+pub const SETTLEMENT_DAYS: usize = 2;
+
+/// Trading day the shares and cash of a trade on `trade_date` arrive
+pub fn settlement_date(trade_date: NaiveDate) -> NaiveDate {
+    (0..SETTLEMENT_DAYS).fold(trade_date, |date, _| calendar().next_trading_day(date))
+}
+
+/// Fees and taxes charged on trades without their own
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    /// Share of the order value charged by the broker
+    pub broker_rate: f64,
+    /// Least fee of an order, in VND
+    pub min_fee: f64,
+    /// Share of the sale value withheld as tax
+    pub sell_tax_rate: f64,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> Self {
+        Self {
+            broker_rate: DEFAULT_BROKER_FEE_RATE,
+            min_fee: 0.0,
+            sell_tax_rate: SELL_TAX_RATE,
+        }
+    }
+}
+
+impl FeeSchedule {
+    pub fn broker_fee(&self, value: f64) -> f64 {
+        (value * self.broker_rate).max(self.min_fee)
+    }
+
+    pub fn sell_tax(&self, value: f64) -> f64 {
+        value * self.sell_tax_rate
+    }
+}
+
+/// A matched order of the user
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub date: NaiveDate,
+    pub symbol: String,
+    pub side: TradeSide,
+    pub quantity: i64,
+    /// Per share, in VND
+    pub price: f64,
+    /// Charged by the broker, from the [`FeeSchedule`] when `None`
+    #[serde(default)]
+    pub fee: Option<f64>,
+    /// Withheld on a sale, from the [`FeeSchedule`] when `None`
+    #[serde(default)]
+    pub tax: Option<f64>,
+}
+
+impl Transaction {
+    pub fn new(date: NaiveDate, symbol: &str, side: TradeSide, quantity: i64, price: f64) -> Self {
+        Self {
+            date,
+            symbol: symbol.trim().to_uppercase(),
+            side,
+            quantity,
+            price,
+            fee: None,
+            tax: None,
+        }
+    }
+
+    pub fn value(&self) -> f64 {
+        self.quantity as f64 * self.price
+    }
+
+    fn validate(&self) -> Result<(), PortfolioError> {
+        if self.symbol.is_empty() || self.quantity <= 0 || self.price <= 0.0 {
+            return Err(PortfolioError::InvalidTrade(format!(
+                "{} {} x {} on {}",
+                self.symbol, self.quantity, self.price, self.date
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Money put in (positive) or taken out (negative) of the account
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CashFlow {
+    pub date: NaiveDate,
+    pub amount: f64,
+}
+
+/// Shares of one buy still held
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lot {
+    pub quantity: i64,
+    /// Per share, fees excluded
+    pub price: f64,
+    pub trade_date: NaiveDate,
+    /// From this day on the shares can be sold
+    pub settle_date: NaiveDate,
+}
+
+/// What is held of one symbol, closed positions keeping their realised P&L
+#[derive(Debug, Clone, PartialEq)]
+pub struct Position {
+    pub symbol: String,
+    /// Oldest first, sales take from the front
+    pub lots: Vec<Lot>,
+    /// Of the shares held, buy fees included
+    pub cost: f64,
+    /// Gains of the sales after fees and tax, against the average cost
+    pub realised: f64,
+    pub fees: f64,
+    pub taxes: f64,
+    /// Last price from the market, `None` until marked
+    pub market_price: Option<f64>,
+}
+
+impl Position {
+    fn new(symbol: &str) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            lots: Vec::new(),
+            cost: 0.0,
+            realised: 0.0,
+            fees: 0.0,
+            taxes: 0.0,
+            market_price: None,
+        }
+    }
+
+    pub fn quantity(&self) -> i64 {
+        self.lots.iter().map(|lot| lot.quantity).sum()
+    }
+
+    /// Shares that can be sold on `date`
+    pub fn settled_quantity(&self, date: NaiveDate) -> i64 {
+        self.lots
+            .iter()
+            .filter(|lot| lot.settle_date <= date)
+            .map(|lot| lot.quantity)
+            .sum()
+    }
+
+    /// Shares bought but not delivered yet on `date`
+    pub fn pending_quantity(&self, date: NaiveDate) -> i64 {
+        self.quantity() - self.settled_quantity(date)
+    }
+
+    /// Cost per share held, buy fees included
+    pub fn average_cost(&self) -> f64 {
+        match self.quantity() {
+            0 => 0.0,
+            quantity => self.cost / quantity as f64,
+        }
+    }
+
+    /// At the market price, at cost while not marked
+    pub fn market_value(&self) -> f64 {
+        match self.market_price {
+            Some(price) => price * self.quantity() as f64,
+            None => self.cost,
+        }
+    }
+
+    /// Gain if the shares held were sold at the market price, before the
+    /// fee and tax of that sale
+    pub fn unrealised(&self) -> f64 {
+        self.market_value() - self.cost
+    }
+
+    fn buy(&mut self, transaction: &Transaction, fee: f64) {
+        self.lots.push(Lot {
+            quantity: transaction.quantity,
+            price: transaction.price,
+            trade_date: transaction.date,
+            settle_date: settlement_date(transaction.date),
+        });
+        self.cost += transaction.value() + fee;
+        self.fees += fee;
+    }
+
+    fn sell(
+        &mut self,
+        transaction: &Transaction,
+        fee: f64,
+        tax: f64,
+    ) -> Result<(), PortfolioError> {
+        let available = self.settled_quantity(transaction.date);
+        if available < transaction.quantity {
+            return Err(PortfolioError::NotEnoughShares {
+                symbol: self.symbol.clone(),
+                date: transaction.date,
+                available,
+                wanted: transaction.quantity,
+            });
+        }
+        let sold_cost = self.average_cost() * transaction.quantity as f64;
+        self.realised += transaction.value() - fee - tax - sold_cost;
+        self.cost -= sold_cost;
+        self.fees += fee;
+        self.taxes += tax;
+
+        let mut left = transaction.quantity;
+        for lot in self
+            .lots
+            .iter_mut()
+            .filter(|lot| lot.settle_date <= transaction.date)
+        {
+            let taken = lot.quantity.min(left);
+            lot.quantity -= taken;
+            left -= taken;
+        }
+        self.lots.retain(|lot| lot.quantity > 0);
+        if self.lots.is_empty() {
+            self.cost = 0.0;
+        }
+        Ok(())
+    }
+}
+
+/// The portfolio replayed up to a day
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Book {
+    /// By symbol, closed ones included
+    pub positions: Vec<Position>,
+    /// Settled cash, negative when buys are not covered by deposits
+    pub cash: f64,
+    /// Sale proceeds not settled yet
+    pub receivable: f64,
+}
+
+impl Book {
+    pub fn open_positions(&self) -> impl Iterator<Item = &Position> {
+        self.positions
+            .iter()
+            .filter(|position| position.quantity() > 0)
+    }
+
+    pub fn market_value(&self) -> f64 {
+        self.open_positions().map(Position::market_value).sum()
+    }
+
+    /// Net asset value: cash, receivables and the shares at market
+    pub fn nav(&self) -> f64 {
+        self.cash + self.receivable + self.market_value()
+    }
+
+    pub fn realised(&self) -> f64 {
+        self.positions
+            .iter()
+            .map(|position| position.realised)
+            .sum()
+    }
+
+    pub fn unrealised(&self) -> f64 {
+        self.open_positions().map(Position::unrealised).sum()
+    }
+}
+
+/// Value of the portfolio at the end of a day
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NavPoint {
+    pub date: NaiveDate,
+    pub nav: f64,
+    pub cash: f64,
+    pub market_value: f64,
+}
+
+/// Why a trade was not recorded
+#[derive(Debug, Clone, PartialEq)]
+pub enum PortfolioError {
+    /// Selling more than the shares settled on the day of the sale
+    NotEnoughShares {
+        symbol: String,
+        date: NaiveDate,
+        available: i64,
+        wanted: i64,
+    },
+    /// No symbol, or a quantity or price that is not positive
+    InvalidTrade(String),
+}
+
+impl fmt::Display for PortfolioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PortfolioError::NotEnoughShares {
+                symbol,
+                date,
+                available,
+                wanted,
+            } => write!(
+                f,
+                "cannot sell {wanted} {symbol} on {date}, {available} settled"
+            ),
+            PortfolioError::InvalidTrade(trade) => write!(f, "invalid trade {trade}"),
+        }
+    }
+}
+
+impl std::error::Error for PortfolioError {}
+
+/// Why a broker statement was not read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementError {
+    /// No header naming the date, symbol, side, quantity and price columns
+    NoHeader,
+    /// Neither the header nor the trades tell whether prices are in VND or
+    /// in thousands
+    UnknownPriceUnit,
+}
+
+impl fmt::Display for StatementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatementError::NoHeader => write!(f, "no header naming the trade columns"),
+            StatementError::UnknownPriceUnit => {
+                write!(f, "cannot tell whether prices are in VND or in thousands")
+            }
+        }
+    }
+}
+
+impl std::error::Error for StatementError {}
+
+/// Trades, cash flows and daily values of the user's account
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Portfolio {
+    #[serde(default)]
+    pub fees: FeeSchedule,
+    /// In date order, trades of one day in the order they were recorded
+    #[serde(default)]
+    transactions: Vec<Transaction>,
+    #[serde(default)]
+    cash_flows: Vec<CashFlow>,
+    /// Last market price per symbol
+    #[serde(default)]
+    marks: BTreeMap<String, f64>,
+    /// One point per day, oldest first
+    #[serde(default)]
+    nav_history: Vec<NavPoint>,
+}
+
+impl Portfolio {
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    pub fn cash_flows(&self) -> &[CashFlow] {
+        &self.cash_flows
+    }
+
+    pub fn nav_history(&self) -> &[NavPoint] {
+        &self.nav_history
+    }
+
+    /// Add `transaction` after the others of its day
+    ///
+    /// Fails, leaving the portfolio as it was, when the trade is invalid or
+    /// leaves a sale, this one or a later one, without settled shares.
+    pub fn record(&mut self, transaction: Transaction) -> Result<(), PortfolioError> {
+        transaction.validate()?;
+        let mut transactions = self.transactions.clone();
+        let index = transactions.partition_point(|recorded| recorded.date <= transaction.date);
+        transactions.insert(index, transaction);
+        self.replay(&transactions, NaiveDate::MAX)?;
+        self.transactions = transactions;
+        Ok(())
+    }
+
+    /// Record the trades of a statement, skipping the ones already recorded,
+    /// returns how many were added and why the others were not
+    ///
+    /// A trade appearing twice in the statement is recorded twice, so
+    /// importing overlapping statements does not count a fill again.
+    pub fn import(&mut self, transactions: &[Transaction]) -> (usize, Vec<PortfolioError>) {
+        let mut fresh: Vec<Transaction> = transactions
+            .iter()
+            .enumerate()
+            .filter(|(index, transaction)| {
+                let in_statement = transactions[..*index]
+                    .iter()
+                    .filter(|earlier| earlier == transaction)
+                    .count();
+                let recorded = self
+                    .transactions
+                    .iter()
+                    .filter(|recorded| recorded == transaction)
+                    .count();
+                in_statement >= recorded
+            })
+            .map(|(_, transaction)| transaction.clone())
+            .collect();
+        fresh.sort_by_key(|transaction| transaction.date);
+
+        let mut added = 0;
+        let mut errors = Vec::new();
+        for transaction in fresh {
+            match self.record(transaction) {
+                Ok(()) => added += 1,
+                Err(e) => errors.push(e),
+            }
+        }
+        (added, errors)
+    }
+
+    /// Put in (positive) or take out (negative) `amount` VND on `date`
+    pub fn deposit(&mut self, date: NaiveDate, amount: f64) {
+        let index = self.cash_flows.partition_point(|flow| flow.date <= date);
+        self.cash_flows.insert(index, CashFlow { date, amount });
+    }
+
+    /// Take `price` VND as the market price of `symbol`
+    pub fn mark(&mut self, symbol: &str, price: f64) {
+        if price > 0.0 {
+            self.marks.insert(symbol.to_uppercase(), price);
+        }
+    }
+
+    /// Symbols with shares held on `date`
+    pub fn held_symbols(&self, date: NaiveDate) -> Vec<String> {
+        self.book(date)
+            .open_positions()
+            .map(|position| position.symbol.clone())
+            .collect()
+    }
+
+    /// Positions and cash at the end of `date`, marked with the last prices
+    pub fn book(&self, date: NaiveDate) -> Book {
+        // The ledger is checked by every record, so it always replays
+        self.replay(&self.transactions, date).unwrap_or_else(|e| {
+            eprintln!("Portfolio ledger does not replay: {e}");
+            Book::default()
+        })
+    }
+
+    /// Store the value of the portfolio on `date`, replacing the one stored
+    /// earlier that day
+    pub fn record_nav(&mut self, date: NaiveDate) -> NavPoint {
+        let book = self.book(date);
+        let point = NavPoint {
+            date,
+            nav: book.nav(),
+            cash: book.cash + book.receivable,
+            market_value: book.market_value(),
+        };
+        let index = self.nav_history.partition_point(|point| point.date < date);
+        match self.nav_history.get_mut(index) {
+            Some(stored) if stored.date == date => *stored = point,
+            _ => self.nav_history.insert(index, point),
+        }
+        point
+    }
+
+    fn replay(
+        &self,
+        transactions: &[Transaction],
+        date: NaiveDate,
+    ) -> Result<Book, PortfolioError> {
+        let mut positions: BTreeMap<String, Position> = BTreeMap::new();
+        let mut cash: f64 = self
+            .cash_flows
+            .iter()
+            .filter(|flow| flow.date <= date)
+            .map(|flow| flow.amount)
+            .sum();
+        let mut receivable = 0.0;
+        for transaction in transactions
+            .iter()
+            .filter(|transaction| transaction.date <= date)
+        {
+            let position = positions
+                .entry(transaction.symbol.clone())
+                .or_insert_with(|| Position::new(&transaction.symbol));
+            let value = transaction.value();
+            let fee = transaction
+                .fee
+                .unwrap_or_else(|| self.fees.broker_fee(value));
+            match transaction.side {
+                TradeSide::Buy => {
+                    position.buy(transaction, fee);
+                    cash -= value + fee;
+                }
+                TradeSide::Sell => {
+                    let tax = transaction.tax.unwrap_or_else(|| self.fees.sell_tax(value));
+                    position.sell(transaction, fee, tax)?;
+                    if settlement_date(transaction.date) <= date {
+                        cash += value - fee - tax;
+                    } else {
+                        receivable += value - fee - tax;
+                    }
+                }
+            }
+        }
+        for position in positions.values_mut() {
+            position.market_price = self.marks.get(&position.symbol).copied();
+        }
+        Ok(Book {
+            positions: positions.into_values().collect(),
+            cash,
+            receivable,
+        })
+    }
+}
+
+/// Column names of broker statements, lower case
+const DATE_COLUMNS: &[&str] = &["ngày gd", "ngày giao dịch", "ngày", "date", "trade date"];
+const SYMBOL_COLUMNS: &[&str] = &["mã ck", "mã chứng khoán", "mã", "symbol", "ticker"];
+const SIDE_COLUMNS: &[&str] = &["loại gd", "loại lệnh", "mua/bán", "lệnh", "side", "type"];
+const QUANTITY_COLUMNS: &[&str] = &[
+    "kl khớp",
+    "khối lượng khớp",
+    "khối lượng",
+    "số lượng",
+    "quantity",
+    "qty",
+    "volume",
+];
+const PRICE_COLUMNS: &[&str] = &["giá khớp", "giá", "price", "matched price"];
+const VALUE_COLUMNS: &[&str] = &[
+    "giá trị khớp",
+    "giá trị",
+    "thành tiền",
+    "value",
+    "matched value",
+    "amount",
+];
+const FEE_COLUMNS: &[&str] = &["phí", "phí gd", "phí giao dịch", "fee", "commission"];
+const TAX_COLUMNS: &[&str] = &["thuế", "thuế tncn", "tax"];
+/// Notes after the price column name for prices in thousands of VND,
+/// `Giá khớp (nghìn đồng)`
+const THOUSAND_UNITS: &[&str] = &["nghìn", "ngàn", "1000", "1.000", "1,000", "thousand"];
+
+/// Positions of the statement columns in a header row
+struct StatementColumns {
+    date: usize,
+    symbol: usize,
+    side: usize,
+    quantity: usize,
+    price: usize,
+    value: Option<usize>,
+    fee: Option<usize>,
+    tax: Option<usize>,
+    /// VND per unit of the price column when the header tells it
+    price_scale: Option<f64>,
+}
+
+impl StatementColumns {
+    fn find(header: &[String]) -> Option<Self> {
+        // A unit in brackets after the name does not change the column
+        let names: Vec<(String, String)> = header
+            .iter()
+            .map(|field| {
+                let field = field.to_lowercase();
+                match field.split_once('(') {
+                    Some((name, unit)) => (name.trim().to_string(), unit.to_string()),
+                    None => (field, String::new()),
+                }
+            })
+            .collect();
+        let column = |names_of: &[&str]| {
+            names_of
+                .iter()
+                .find_map(|name| names.iter().position(|(field, _)| field == name))
+        };
+        let price = column(PRICE_COLUMNS)?;
+        let unit = &names[price].1;
+        let price_scale = if THOUSAND_UNITS.iter().any(|note| unit.contains(note)) {
+            Some(1000.0)
+        } else if !unit.is_empty() {
+            Some(1.0)
+        } else {
+            None
+        };
+        Some(Self {
+            date: column(DATE_COLUMNS)?,
+            symbol: column(SYMBOL_COLUMNS)?,
+            side: column(SIDE_COLUMNS)?,
+            quantity: column(QUANTITY_COLUMNS)?,
+            price,
+            value: column(VALUE_COLUMNS),
+            fee: column(FEE_COLUMNS),
+            tax: column(TAX_COLUMNS),
+            price_scale,
+        })
+    }
+
+    /// VND per unit of the price column of `rows`, `None` when nothing
+    /// tells
+    ///
+    /// Without a unit in the header, prices are checked against the value of
+    /// a trade, then against its fee, both being in VND. Failing that, a
+    /// decimal part means thousands and a price of 1000 or more means VND,
+    /// no share trading at a million VND.
+    fn price_scale(&self, rows: &[Vec<String>]) -> Option<f64> {
+        if self.price_scale.is_some() {
+            return self.price_scale;
+        }
+        let amount = |fields: &[String], column: Option<usize>| {
+            column
+                .and_then(|column| fields.get(column))
+                .and_then(|field| parse_amount(field))
+                .filter(|amount| *amount > 0.0)
+        };
+        // Price, price times quantity and the row, for the rows of trades
+        let trades: Vec<(f64, f64, &[String])> = rows
+            .iter()
+            .filter_map(|fields| {
+                let price = amount(fields, Some(self.price))?;
+                let quantity = amount(fields, Some(self.quantity))?;
+                Some((price, price * quantity, fields.as_slice()))
+            })
+            .collect();
+
+        let near = |ratio: f64, scale: f64| (ratio / scale - 1.0).abs() < 0.05;
+        for &(_, gross, fields) in &trades {
+            let Some(value) = amount(fields, self.value) else {
+                continue;
+            };
+            if near(value / gross, 1000.0) {
+                return Some(1000.0);
+            }
+            if near(value / gross, 1.0) {
+                return Some(1.0);
+            }
+        }
+        // Broker fees are well under 5% of the value of a trade
+        if let Some((gross, fee)) = trades
+            .iter()
+            .find_map(|&(_, gross, fields)| Some((gross, amount(fields, self.fee)?)))
+        {
+            return Some(if fee / gross > 0.05 { 1000.0 } else { 1.0 });
+        }
+        if trades.iter().any(|(price, ..)| price.fract() != 0.0) {
+            return Some(1000.0);
+        }
+        if trades.iter().any(|(price, ..)| *price >= 1000.0) {
+            return Some(1.0);
+        }
+        None
+    }
+
+    /// Trade of a row, prices multiplied by `price_scale` into VND
+    fn read(&self, fields: &[String], price_scale: f64) -> Option<Transaction> {
+        let field = |index: usize| fields.get(index).map(String::as_str);
+        let date = parse_date(field(self.date)?)?;
+        let side = match field(self.side)?.to_lowercase().as_str() {
+            "mua" | "m" | "buy" | "b" => TradeSide::Buy,
+            "bán" | "ban" | "sell" | "s" => TradeSide::Sell,
+            _ => return None,
+        };
+        // Prices in VND are whole numbers
+        let price = (parse_amount(field(self.price)?)? * price_scale).round();
+        let mut transaction = Transaction::new(
+            date,
+            field(self.symbol)?,
+            side,
+            parse_amount(field(self.quantity)?)? as i64,
+            price,
+        );
+        transaction.fee = self.fee.and_then(field).and_then(parse_amount);
+        transaction.tax = self.tax.and_then(field).and_then(parse_amount);
+        transaction.validate().ok()?;
+        Some(transaction)
+    }
+}
+
+/// Trades of a broker statement exported as CSV
+///
+/// Lines above the header and rows that are not trades (totals, blank lines)
+/// are skipped. Fields may be separated by commas, semicolons or tabs.
+/// Prices are in VND, or in thousands when the price column says so, as in
+/// `Giá (nghìn đồng)`. Without a unit in the header it is told from the
+/// value or fee columns or from the prices themselves, and the statement is
+/// refused when they do not tell.
+pub fn parse_statement(csv: &str) -> Result<Vec<Transaction>, StatementError> {
+    let lines: Vec<&str> = csv.lines().collect();
+    for (index, line) in lines.iter().enumerate() {
+        for delimiter in [',', ';', '\t'] {
+            let Some(columns) = StatementColumns::find(&split_fields(line, delimiter)) else {
+                continue;
+            };
+            let rows: Vec<Vec<String>> = lines[index + 1..]
+                .iter()
+                .map(|line| split_fields(line, delimiter))
+                .collect();
+            let price_scale = columns
+                .price_scale(&rows)
+                .ok_or(StatementError::UnknownPriceUnit)?;
+            let transactions = rows
+                .iter()
+                .filter_map(|fields| columns.read(fields, price_scale))
+                .collect();
+            return Ok(transactions);
+        }
+    }
+    Err(StatementError::NoHeader)
+}
+
+/// Fields of a CSV line, double quotes around a field being dropped
+fn split_fields(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            _ if c == delimiter && !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+        .iter()
+        .map(|field| field.trim().to_string())
+        .collect()
+}
+
+/// `dd/mm/yyyy`, `dd-mm-yyyy` or `yyyy-mm-dd`
+fn parse_date(text: &str) -> Option<NaiveDate> {
+    ["%d/%m/%Y", "%d-%m-%Y", "%Y-%m-%d"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(text.trim(), format).ok())
+}
+
+/// Number written with thousands separators, `,` or `.`, and maybe a
+/// decimal part: `25,500`, `25.500`, `1,234.5`, `25.5`
+fn parse_amount(text: &str) -> Option<f64> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if text.is_empty() {
+        return None;
+    }
+    // With both separators the last one is the decimal point
+    let decimal = match (text.rfind(','), text.rfind('.')) {
+        (Some(comma), Some(dot)) => Some(if comma > dot { ',' } else { '.' }),
+        (Some(_), None) => (!is_grouped(&text, ',')).then_some(','),
+        (None, Some(_)) => (!is_grouped(&text, '.')).then_some('.'),
+        (None, None) => None,
+    };
+    let number: String = text
+        .chars()
+        .filter_map(|c| match c {
+            ',' | '.' if Some(c) == decimal => Some('.'),
+            ',' | '.' => None,
+            _ => Some(c),
+        })
+        .collect();
+    number.parse().ok()
+}
+
+/// Every group after a `separator` has three digits, as in `1,234,567`
+fn is_grouped(text: &str, separator: char) -> bool {
+    text.split(separator)
+        .skip(1)
+        .all(|group| group.len() == 3 && group.chars().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_settlement_fees_and_pnl() {
+        let mut portfolio = Portfolio::default();
+        portfolio.deposit(date(2025, 3, 3), 100_000_000.0);
+        // Monday buy, settles Wednesday
+        let buy = Transaction::new(date(2025, 3, 3), "fpt", TradeSide::Buy, 1000, 25_000.0);
+        portfolio.record(buy.clone()).unwrap();
+        let early = Transaction::new(date(2025, 3, 4), "FPT", TradeSide::Sell, 500, 26_000.0);
+        assert!(matches!(
+            portfolio.record(early),
+            Err(PortfolioError::NotEnoughShares { available: 0, .. })
+        ));
+        let book = portfolio.book(date(2025, 3, 4));
+        let fpt = &book.positions[0];
+        assert_eq!(fpt.pending_quantity(date(2025, 3, 4)), 1000);
+        assert_eq!(fpt.settled_quantity(date(2025, 3, 5)), 1000);
+        // 25,000,000 plus the 0.15% fee
+        assert_eq!(fpt.average_cost(), 25_037.5);
+
+        let sell = Transaction::new(date(2025, 3, 5), "FPT", TradeSide::Sell, 400, 27_000.0);
+        portfolio.record(sell).unwrap();
+        portfolio.mark("FPT", 26_000.0);
+        let book = portfolio.book(date(2025, 3, 5));
+        let fpt = &book.positions[0];
+        assert_eq!(fpt.quantity(), 600);
+        // 10,800,000 less 16,200 fee, 10,800 tax and 400 x 25,037.5 cost
+        assert!((fpt.realised - 758_000.0).abs() < 1e-6);
+        assert!((fpt.unrealised() - 600.0 * (26_000.0 - 25_037.5)).abs() < 1e-6);
+        // Proceeds of Wednesday's sale arrive on Friday
+        assert!((book.receivable - 10_773_000.0).abs() < 1e-6);
+        assert!((book.cash - (100_000_000.0 - 25_037_500.0)).abs() < 1e-6);
+        let cash_on_friday = portfolio.book(date(2025, 3, 7)).cash;
+        assert!((cash_on_friday - (74_962_500.0 + 10_773_000.0)).abs() < 1e-6);
+
+        let point = portfolio.record_nav(date(2025, 3, 5));
+        assert!((point.nav - (book.cash + book.receivable + 600.0 * 26_000.0)).abs() < 1e-6);
+        portfolio.record_nav(date(2025, 3, 5));
+        assert_eq!(portfolio.nav_history().len(), 1);
+        assert_eq!(portfolio.held_symbols(date(2025, 3, 5)), ["FPT"]);
+
+        // Saved and loaded with the trades in order
+        let json = serde_json::to_string(&portfolio).unwrap();
+        let read: Portfolio = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, portfolio);
+        assert_eq!(read.transactions()[0], buy.clone());
+    }
+
+    #[test]
+    fn test_broker_statement_import() {
+        let csv = "\
+Sao kê lệnh khớp
+Tài khoản;058C123456
+Ngày GD;Mã CK;Loại GD;KL khớp;Giá khớp;Phí;Thuế
+03/03/2025;FPT;Mua;1.000;25;37.500;0
+05/03/2025;FPT;Bán;400;27,5;16.500;11.000
+05/03/2025;HPG;Bán;100;28;;
+Tổng;;;;;;
+";
+        let transactions = parse_statement(csv).unwrap();
+        assert_eq!(transactions.len(), 3);
+        assert_eq!(transactions[0].quantity, 1000);
+        assert_eq!(transactions[0].price, 25_000.0);
+        assert_eq!(transactions[1].price, 27_500.0);
+        assert_eq!(transactions[1].fee, Some(16_500.0));
+        assert_eq!(transactions[2].fee, None);
+
+        let mut portfolio = Portfolio::default();
+        let (added, errors) = portfolio.import(&transactions);
+        // No HPG to sell
+        assert_eq!(added, 2);
+        assert!(matches!(
+            &errors[..],
+            [PortfolioError::NotEnoughShares { symbol, .. }] if symbol == "HPG"
+        ));
+        // Importing it again adds nothing
+        assert_eq!(portfolio.import(&transactions).0, 0);
+        let book = portfolio.book(date(2025, 3, 10));
+        assert!(
+            (book.positions[0].realised - (11_000_000.0 - 16_500.0 - 11_000.0 - 400.0 * 25_037.5))
+                .abs()
+                < 1e-6
+        );
+
+        let english = "Date,Symbol,Side,Quantity,Price\n2025-03-03,vnm,BUY,\"1,200\",\"61,500\"\n";
+        let transactions = parse_statement(english).unwrap();
+        assert_eq!(
+            transactions,
+            [Transaction::new(
+                date(2025, 3, 3),
+                "VNM",
+                TradeSide::Buy,
+                1200,
+                61_500.0
+            )]
+        );
+        // Sub-1000 VND prices of UPCOM stocks stay as they are, the fee
+        // being far below the value
+        let upcom = "Date,Symbol,Side,Quantity,Price,Fee\n2025-03-03,KSH,BUY,5000,800,6000\n";
+        assert_eq!(parse_statement(upcom).unwrap()[0].price, 800.0);
+        let header = "Date,Symbol,Side,Quantity,Price (x1000 VND)\n2025-03-03,FPT,BUY,100,25\n";
+        assert_eq!(parse_statement(header).unwrap()[0].price, 25_000.0);
+        assert_eq!(
+            parse_statement("symbol\nFPT\n"),
+            Err(StatementError::NoHeader)
+        );
+    }
+
+    #[test]
+    fn test_statement_in_thousands_with_whole_prices() {
+        // The value in VND shows the whole prices are thousands
+        let csv = "\
+Ngày GD;Mã CK;Loại GD;KL khớp;Giá khớp;Giá trị
+03/03/2025;FPT;Mua;1.000;25;25.000.000
+05/03/2025;HPG;Mua;200;28;5.600.000
+";
+        let prices: Vec<f64> = parse_statement(csv)
+            .unwrap()
+            .iter()
+            .map(|transaction| transaction.price)
+            .collect();
+        assert_eq!(prices, [25_000.0, 28_000.0]);
+
+        // Nothing tells 25 and 28 from VND prices of penny stocks
+        let unknown = "Ngày GD;Mã CK;Loại GD;KL khớp;Giá khớp\n03/03/2025;FPT;Mua;1.000;25\n";
+        assert_eq!(
+            parse_statement(unknown),
+            Err(StatementError::UnknownPriceUnit)
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+
+use crate::calendar::{VN_UTC_OFFSET_SECS, calendar};
+use crate::explorer::vci::ohlc::Candlestick;
+
+/// Opening time of the HOSE/HNX continuous session, in minutes after local midnight
+const SESSION_OPEN_MINUTES: i64 = 9 * 60;
+
+/// Bar size of a chart, e.g. `5m`, `2H`, `3D`, `1W` or `1M`
+///
+/// Intraday bars are aligned to the 09:00 session open, multi-day bars count
+/// trading days only so a `2D` bar always spans two trading days, weekends
+/// and holidays of [`calendar`] left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeFrame {
+    Minute(u32),
+    Hour(u32),
+    Day(u32),
+    Week(u32),
+    Month(u32),
+}
+
+impl Default for TimeFrame {
+    fn default() -> Self {
+        TimeFrame::Day(1)
+    }
+}
+
+impl TimeFrame {
+    /// Parse a picker label: `m` minutes, `H` hours, `D` days, `W` weeks, `M` months
+    ///
+    /// The VCI interval constants (`ONE_MINUTE`, `ONE_HOUR`, `ONE_DAY`) are accepted too.
+    pub fn parse(label: &str) -> Option<Self> {
+        match label {
+            "ONE_MINUTE" => return Some(TimeFrame::Minute(1)),
+            "ONE_HOUR" => return Some(TimeFrame::Hour(1)),
+            "ONE_DAY" => return Some(TimeFrame::Day(1)),
+            "ONE_WEEK" => return Some(TimeFrame::Week(1)),
+            _ => {}
+        }
+        let label = label.trim();
+        let unit = label.chars().last()?;
+        let count: u32 = label[..label.len() - unit.len_utf8()].parse().ok()?;
+        if count == 0 {
+            return None;
+        }
+        match unit {
+            'm' => Some(TimeFrame::Minute(count)),
+            'H' | 'h' => Some(TimeFrame::Hour(count)),
+            'D' | 'd' => Some(TimeFrame::Day(count)),
+            'W' | 'w' => Some(TimeFrame::Week(count)),
+            'M' => Some(TimeFrame::Month(count)),
+            _ => None,
+        }
+    }
+
+    /// Interval to request from the provider before resampling
+    pub fn source_interval(&self) -> &'static str {
+        match self {
+            TimeFrame::Minute(_) => "ONE_MINUTE",
+            TimeFrame::Hour(_) => "ONE_HOUR",
+            TimeFrame::Day(_) | TimeFrame::Week(_) | TimeFrame::Month(_) => "ONE_DAY",
+        }
+    }
+
+    pub fn is_intraday(&self) -> bool {
+        matches!(self, TimeFrame::Minute(_) | TimeFrame::Hour(_))
+    }
+
+    /// Nominal length of one bar, months counted as 30 days
+    pub fn duration(&self) -> Duration {
+        match *self {
+            TimeFrame::Minute(n) => Duration::minutes(n as i64),
+            TimeFrame::Hour(n) => Duration::hours(n as i64),
+            TimeFrame::Day(n) => Duration::days(n as i64),
+            TimeFrame::Week(n) => Duration::weeks(n as i64),
+            TimeFrame::Month(n) => Duration::days(30 * n as i64),
+        }
+    }
+
+    /// Bucket a bar starting at `timestamp` (Unix seconds) falls into
+    fn bucket(&self, timestamp: i64) -> i64 {
+        let local = timestamp + VN_UTC_OFFSET_SECS;
+        let day = local.div_euclid(86_400);
+        match *self {
+            TimeFrame::Minute(_) | TimeFrame::Hour(_) => {
+                let step = self.duration().num_seconds();
+                // Start of the bucket in UTC, counted from the session open
+                let open = day * 86_400 + SESSION_OPEN_MINUTES * 60 - VN_UTC_OFFSET_SECS;
+                open + (timestamp - open).div_euclid(step) * step
+            }
+            // One bar per date, even a date the calendar has as a day off
+            TimeFrame::Day(1) => day,
+            TimeFrame::Day(n) => {
+                let date = NaiveDate::default() + Duration::days(day);
+                calendar().trading_day_number(date).div_euclid(n as i64)
+            }
+            TimeFrame::Week(n) => (day - 4).div_euclid(7).div_euclid(n as i64),
+            TimeFrame::Month(n) => {
+                let date = NaiveDate::default() + Duration::days(day);
+                (date.year() as i64 * 12 + date.month0() as i64).div_euclid(n as i64)
+            }
+        }
+    }
+}
+
+impl fmt::Display for TimeFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeFrame::Minute(n) => write!(f, "{n}m"),
+            TimeFrame::Hour(n) => write!(f, "{n}H"),
+            TimeFrame::Day(n) => write!(f, "{n}D"),
+            TimeFrame::Week(n) => write!(f, "{n}W"),
+            TimeFrame::Month(n) => write!(f, "{n}M"),
+        }
+    }
+}
+
+/// Aggregate time-ordered bars into `time_frame` bars
+///
+/// Intraday bars are stamped with the start of their bucket, longer bars
+/// with the first bar they contain.
+pub fn resample(candles: &[Candlestick], time_frame: TimeFrame) -> Vec<Candlestick> {
+    let mut bars: Vec<Candlestick> = Vec::new();
+    let mut current_bucket = None;
+    for candle in candles {
+        let bucket = time_frame.bucket(candle.timestamp.timestamp());
+        match bars.last_mut() {
+            Some(bar) if current_bucket == Some(bucket) => {
+                bar.high = bar.high.max(candle.high);
+                bar.low = bar.low.min(candle.low);
+                bar.close = candle.close;
+                bar.volume += candle.volume;
+            }
+            _ => {
+                let mut bar = candle.clone();
+                if time_frame.is_intraday() {
+                    bar.timestamp =
+                        DateTime::<Utc>::from_timestamp(bucket, 0).unwrap_or(candle.timestamp);
+                }
+                bars.push(bar);
+                current_bucket = Some(bucket);
+            }
+        }
+    }
+    bars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A bar at `hh:mm` Vietnam time on 2025-03-`day`
+    fn bar(day: u32, hour: u32, minute: u32, close: f64) -> Candlestick {
+        let local = NaiveDate::from_ymd_opt(2025, 3, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap();
+        Candlestick {
+            timestamp: (local - Duration::seconds(VN_UTC_OFFSET_SECS)).and_utc(),
+            open: close,
+            high: close + 1.0,
+            low: close - 1.0,
+            close,
+            volume: 10,
+        }
+    }
+
+    #[test]
+    fn test_parse_labels() {
+        assert_eq!(TimeFrame::parse("15m"), Some(TimeFrame::Minute(15)));
+        assert_eq!(TimeFrame::parse("4H"), Some(TimeFrame::Hour(4)));
+        assert_eq!(TimeFrame::parse("1M"), Some(TimeFrame::Month(1)));
+        assert_eq!(TimeFrame::parse("ONE_DAY"), Some(TimeFrame::Day(1)));
+        assert_eq!(TimeFrame::parse("0D"), None);
+        assert_eq!(TimeFrame::Week(2).to_string(), "2W");
+        assert_eq!(TimeFrame::Hour(2).source_interval(), "ONE_HOUR");
+    }
+
+    #[test]
+    fn test_intraday_buckets_start_at_session_open() {
+        let minutes = [
+            bar(3, 9, 0, 1.0),
+            bar(3, 9, 14, 2.0),
+            bar(3, 9, 15, 3.0),
+            bar(3, 11, 29, 4.0),
+            bar(3, 13, 0, 5.0),
+        ];
+        let bars = resample(&minutes, TimeFrame::Minute(15));
+        assert_eq!(bars.len(), 4);
+        assert_eq!(bars[0].close, 2.0);
+        assert_eq!(bars[0].high, 3.0);
+        assert_eq!(bars[0].volume, 20);
+        assert_eq!(bars[2].timestamp, bar(3, 11, 15, 0.0).timestamp);
+
+        let bars = resample(&minutes, TimeFrame::Hour(2));
+        assert_eq!(bars.len(), 3);
+        assert_eq!(bars[1].timestamp, bar(3, 11, 0, 0.0).timestamp);
+        assert_eq!(bars[2].timestamp, bar(3, 13, 0, 0.0).timestamp);
+    }
+
+    #[test]
+    fn test_daily_buckets_skip_weekends() {
+        // Thu 6, Fri 7, Mon 10, Tue 11 March 2025
+        let days = [
+            bar(6, 0, 0, 1.0),
+            bar(7, 0, 0, 2.0),
+            bar(10, 0, 0, 3.0),
+            bar(11, 0, 0, 4.0),
+        ];
+        // Friday and Monday are consecutive trading days and share a bar
+        let two_days = resample(&days, TimeFrame::Day(2));
+        assert_eq!(two_days.len(), 3);
+        assert_eq!(two_days[1].timestamp, days[1].timestamp);
+        assert_eq!(two_days[1].open, 2.0);
+        assert_eq!(two_days[1].close, 3.0);
+
+        let weeks = resample(&days, TimeFrame::Week(1));
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(weeks[1].open, 3.0);
+        assert_eq!(resample(&days, TimeFrame::Month(1)).len(), 1);
+    }
+
+    #[test]
+    fn test_daily_buckets_skip_holidays() {
+        // Tết 2025 closed the market from Monday 27 to Friday 31 January
+        let day = |month: u32, day: u32, close: f64| Candlestick {
+            timestamp: crate::calendar::from_vn_time(
+                NaiveDate::from_ymd_opt(2025, month, day)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+            ),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 10,
+        };
+        let days = [
+            day(1, 22, 1.0),
+            day(1, 23, 2.0),
+            day(1, 24, 3.0),
+            day(2, 3, 4.0),
+            day(2, 4, 5.0),
+            day(2, 5, 6.0),
+        ];
+        // The second bar holds five sessions, on both sides of the holiday
+        let five_days = resample(&days, TimeFrame::Day(5));
+        assert_eq!(five_days.len(), 2);
+        assert_eq!(five_days[1].timestamp, days[1].timestamp);
+        assert_eq!((five_days[1].open, five_days[1].close), (2.0, 6.0));
+        assert_eq!(five_days[1].volume, 50);
+    }
+}
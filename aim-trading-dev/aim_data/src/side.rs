@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// Side of a trade or insider transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+impl TradeSide {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            TradeSide::Buy => "mua",
+            TradeSide::Sell => "bán",
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+use crate::error::AimDataError;
+use crate::explorer::aim::{self, FinanceSheetData, FinancialData};
+use crate::explorer::vci::company_info::CompanyInfo;
+use crate::explorer::vci::listing::VCISymbol;
+use crate::explorer::vci::market_watch::MarketWatchResponse;
+use crate::explorer::vci::ohlc::OHLCResponse;
+use crate::explorer::vci::{OrderList, VCIExplorer};
+
+/// Boxed future returned by [`DataSource`] methods, so the trait stays object safe
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Result type shared by every data source
+pub type SourceResult<T> = Result<T, AimDataError>;
+
+/// Financial statements served by the fundamentals endpoints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FinanceSheet {
+    BalanceSheet,
+    IncomeStatement,
+    CashFlowDirect,
+    CashFlowIndirect,
+}
+
+impl FinanceSheet {
+    /// Name of the statement as used in AIM backend endpoints and offline file names
+    pub fn endpoint(&self) -> &'static str {
+        match self {
+            FinanceSheet::BalanceSheet => "balance-sheet",
+            FinanceSheet::IncomeStatement => "income-statement",
+            FinanceSheet::CashFlowDirect => "cash-flow-direct",
+            FinanceSheet::CashFlowIndirect => "cash-flow-indirect",
+        }
+    }
+}
+
+/// A provider of market data and company fundamentals
+///
+/// Every method has a default implementation that fails with an "unsupported"
+/// error, so a provider only implements what its backend actually serves.
+pub trait DataSource: Send + Sync {
+    /// Short provider name used in logs and errors
+    fn name(&self) -> &str;
+
+    /// OHLC bars for one or more symbols
+    fn get_quote<'a>(
+        &'a self,
+        _symbols: &'a [&'a str],
+        _time_frame: &'a str,
+        _start_time: Option<DateTime<Utc>>,
+        _end_time: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, SourceResult<OHLCResponse>> {
+        unsupported(self.name(), "quotes")
+    }
+
+    /// Listing info, best bid/ask and last match for a set of symbols
+    fn get_market_watch<'a>(
+        &'a self,
+        _symbols: &'a [&'a str],
+    ) -> BoxFuture<'a, SourceResult<MarketWatchResponse>> {
+        unsupported(self.name(), "market watch")
+    }
+
+    /// Latest matched orders for a symbol, newest first
+    fn get_order_list<'a>(
+        &'a self,
+        _symbol: &'a str,
+        _limit: u32,
+    ) -> BoxFuture<'a, SourceResult<OrderList>> {
+        unsupported(self.name(), "order flow")
+    }
+
+    /// Financial ratios (ROE, ROA, P/E, P/B, EPS) for a symbol
+    fn get_company_ratios<'a>(
+        &'a self,
+        _symbol: &'a str,
+        _period: &'a str,
+    ) -> BoxFuture<'a, SourceResult<CompanyInfo>> {
+        unsupported(self.name(), "company ratios")
+    }
+
+    /// One financial statement for a symbol and period (e.g. "Q12025")
+    fn get_finance_sheet<'a>(
+        &'a self,
+        _symbol: &'a str,
+        _sheet: FinanceSheet,
+        _period: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<FinanceSheetData>>> {
+        unsupported(self.name(), "finance sheets")
+    }
+
+    /// Financial overview for a symbol
+    fn get_financial_data<'a>(
+        &'a self,
+        _symbol: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<FinancialData>>> {
+        unsupported(self.name(), "financial data")
+    }
+
+    /// Every listed symbol with its board and company name
+    fn get_listings(&self) -> BoxFuture<'_, SourceResult<Vec<VCISymbol>>> {
+        unsupported(self.name(), "listings")
+    }
+
+    /// Symbols of an index basket such as "VN30"
+    fn get_index_constituents<'a>(
+        &'a self,
+        _index: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<String>>> {
+        unsupported(self.name(), "index constituents")
+    }
+}
+
+fn unsupported<'a, T: Send + 'a>(
+    source: &str,
+    operation: &'static str,
+) -> BoxFuture<'a, SourceResult<T>> {
+    let err = AimDataError::Unsupported {
+        source: source.to_string(),
+        operation,
+    };
+    Box::pin(async move { Err(err) })
+}
+
+/// Routes market data to one provider and fundamentals to another
+///
+/// The default routes quotes, market watch, order flow, ratios and listings to
+/// VCI and financial statements to the AIM backend.
+pub struct CombinedSource {
+    market: Arc<dyn DataSource>,
+    fundamentals: Arc<dyn DataSource>,
+}
+
+impl CombinedSource {
+    pub fn new(market: Arc<dyn DataSource>, fundamentals: Arc<dyn DataSource>) -> Self {
+        Self {
+            market,
+            fundamentals,
+        }
+    }
+}
+
+impl Default for CombinedSource {
+    fn default() -> Self {
+        Self::new(
+            Arc::new(VCIExplorer::default()),
+            aim::explorer(),
+        )
+    }
+}
+
+impl DataSource for CombinedSource {
+    fn name(&self) -> &str {
+        "combined"
+    }
+
+    fn get_quote<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+        time_frame: &'a str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, SourceResult<OHLCResponse>> {
+        self.market
+            .get_quote(symbols, time_frame, start_time, end_time)
+    }
+
+    fn get_market_watch<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+    ) -> BoxFuture<'a, SourceResult<MarketWatchResponse>> {
+        self.market.get_market_watch(symbols)
+    }
+
+    fn get_order_list<'a>(
+        &'a self,
+        symbol: &'a str,
+        limit: u32,
+    ) -> BoxFuture<'a, SourceResult<OrderList>> {
+        self.market.get_order_list(symbol, limit)
+    }
+
+    fn get_company_ratios<'a>(
+        &'a self,
+        symbol: &'a str,
+        period: &'a str,
+    ) -> BoxFuture<'a, SourceResult<CompanyInfo>> {
+        self.market.get_company_ratios(symbol, period)
+    }
+
+    fn get_finance_sheet<'a>(
+        &'a self,
+        symbol: &'a str,
+        sheet: FinanceSheet,
+        period: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<FinanceSheetData>>> {
+        self.fundamentals.get_finance_sheet(symbol, sheet, period)
+    }
+
+    fn get_financial_data<'a>(
+        &'a self,
+        symbol: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<FinancialData>>> {
+        self.fundamentals.get_financial_data(symbol)
+    }
+
+    fn get_listings(&self) -> BoxFuture<'_, SourceResult<Vec<VCISymbol>>> {
+        self.market.get_listings()
+    }
+
+    fn get_index_constituents<'a>(
+        &'a self,
+        index: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<String>>> {
+        self.market.get_index_constituents(index)
+    }
+}
@@ -0,0 +1,505 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AimDataError;
+use crate::explorer::aim::{FinanceSheetData, FinancialData};
+use crate::explorer::vci::OrderList;
+use crate::explorer::vci::company_info::CompanyInfo;
+use crate::explorer::vci::listing::VCISymbol;
+use crate::explorer::vci::market_watch::MarketWatchResponse;
+use crate::explorer::vci::ohlc::{OHLCData, OHLCResponse};
+use crate::file::write_atomic;
+use crate::source::{BoxFuture, DataSource, FinanceSheet, SourceResult};
+
+/// Minimum time between two writes of a series whose bar count did not change
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// What is kept on disk for one (symbol, time frame)
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredSeries {
+    /// Start of the range already requested from the provider (Unix seconds).
+    /// Every bar from here up to the last stored bar is known.
+    covered_from: i64,
+    data: OHLCData,
+}
+
+/// Series of one (symbol, time frame), locked while it is being updated
+type SeriesSlot = Arc<tokio::sync::Mutex<Series>>;
+
+struct Series {
+    stored: Option<StoredSeries>,
+    loaded: bool,
+    saved_len: usize,
+    saved_at: Option<Instant>,
+}
+
+impl Series {
+    fn new() -> Self {
+        Self {
+            stored: None,
+            loaded: false,
+            saved_len: 0,
+            saved_at: None,
+        }
+    }
+
+    /// Where the provider must be queried from to answer `[start, end]`,
+    /// or `None` when the stored bars already cover it
+    fn fetch_from(&self, start: i64, end: Option<i64>) -> Option<i64> {
+        let Some(stored) = &self.stored else {
+            return Some(start);
+        };
+        let Some(last) = stored.data.last_timestamp() else {
+            return Some(start);
+        };
+        if start < stored.covered_from {
+            return Some(start);
+        }
+        match end {
+            Some(end) if end < last => None,
+            // Re-fetch the last bar, it may still be forming
+            _ => Some(last),
+        }
+    }
+}
+
+/// On-disk OHLC history in front of another provider
+///
+/// Bars are kept per (symbol, time frame) under `{root}/{TIME_FRAME}/{SYMBOL}.json`.
+/// A quote request only asks `inner` for the range that is not stored yet,
+/// starting at the last stored bar so a still-forming bar gets replaced.
+/// Every other request is passed through to `inner`.
+pub struct CandleStore {
+    root: PathBuf,
+    inner: Arc<dyn DataSource>,
+    series: Mutex<HashMap<(String, String), SeriesSlot>>,
+}
+
+impl CandleStore {
+    pub fn new(root: impl Into<PathBuf>, inner: Arc<dyn DataSource>) -> Self {
+        Self {
+            root: root.into(),
+            inner,
+            series: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn inner(&self) -> &Arc<dyn DataSource> {
+        &self.inner
+    }
+
+    fn path(&self, symbol: &str, time_frame: &str) -> PathBuf {
+        self.root.join(time_frame).join(format!("{symbol}.json"))
+    }
+
+    fn entry(&self, symbol: &str, time_frame: &str) -> SeriesSlot {
+        self.series
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry((symbol.to_string(), time_frame.to_string()))
+            .or_insert_with(|| Arc::new(tokio::sync::Mutex::new(Series::new())))
+            .clone()
+    }
+
+    /// Read a stored series
+    ///
+    /// A corrupt file is removed and its error returned, so the next request
+    /// fetches the series again.
+    fn load(&self, symbol: &str, time_frame: &str) -> SourceResult<Option<StoredSeries>> {
+        let path = self.path(symbol, time_frame);
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(AimDataError::io(path, e)),
+        };
+        serde_json::from_str(&data).map(Some).map_err(|e| {
+            let _ = fs::remove_file(&path);
+            AimDataError::decode(&path.display().to_string(), &data, e)
+        })
+    }
+
+    /// Write through [`write_atomic`] so a crash never leaves a half-written series
+    fn save(&self, symbol: &str, time_frame: &str, stored: &StoredSeries) -> SourceResult<()> {
+        let path = self.path(symbol, time_frame);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| AimDataError::io(dir, e))?;
+        }
+        let data = serde_json::to_vec(stored)
+            .map_err(|e| AimDataError::decode(&path.display().to_string(), "", e))?;
+        write_atomic(&path, &data).map_err(|e| AimDataError::io(path, e))
+    }
+
+    /// Drop the stored bars of one series, in memory and on disk
+    pub async fn clear(&self, symbol: &str, time_frame: &str) -> SourceResult<()> {
+        let symbol = symbol.to_uppercase();
+        let entry = self.entry(&symbol, time_frame);
+        let mut series = entry.lock().await;
+        *series = Series::new();
+        series.loaded = true;
+        match fs::remove_file(self.path(&symbol, time_frame)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(AimDataError::io(self.path(&symbol, time_frame), e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn get_quote_stored(
+        &self,
+        symbols: &[&str],
+        time_frame: &str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> SourceResult<OHLCResponse> {
+        let start = start_time.map(|t| t.timestamp()).unwrap_or(0);
+        let end = end_time.map(|t| t.timestamp());
+
+        // Lock every series in sorted order so concurrent requests cannot deadlock
+        let mut keys: Vec<String> = symbols.iter().map(|s| s.to_uppercase()).collect();
+        keys.sort();
+        keys.dedup();
+        let entries: Vec<_> = keys.iter().map(|k| self.entry(k, time_frame)).collect();
+        let mut guards = Vec::with_capacity(entries.len());
+        for entry in &entries {
+            guards.push(entry.lock().await);
+        }
+
+        // Group the symbols that need the same range into one request
+        let mut gaps: BTreeMap<i64, Vec<usize>> = BTreeMap::new();
+        for (index, series) in guards.iter_mut().enumerate() {
+            if !series.loaded {
+                series.stored = self.load(&keys[index], time_frame)?;
+                series.saved_len = series.stored.as_ref().map_or(0, |s| s.data.len());
+                series.loaded = true;
+            }
+            if let Some(from) = series.fetch_from(start, end) {
+                gaps.entry(from).or_default().push(index);
+            }
+        }
+
+        // A group the provider fails on is left out, the others still answer
+        let mut failure = None;
+        for (from, indices) in gaps {
+            let group: Vec<&str> = indices.iter().map(|&i| keys[i].as_str()).collect();
+            let from_time = DateTime::from_timestamp(from, 0);
+            let fresh = match self
+                .inner
+                .get_quote(&group, time_frame, from_time, None)
+                .await
+            {
+                Ok(fresh) => fresh,
+                Err(e) => {
+                    eprintln!(
+                        "Failed to fetch {time_frame} bars of {}: {e}",
+                        group.join(",")
+                    );
+                    failure.get_or_insert(e);
+                    continue;
+                }
+            };
+
+            let mut fresh: HashMap<String, OHLCData> = fresh
+                .0
+                .into_iter()
+                .map(|data| (data.symbol.to_uppercase(), data))
+                .collect();
+            for &index in &indices {
+                let series = &mut *guards[index];
+                let data = fresh.remove(&keys[index]).filter(|data| !data.is_empty());
+                // Nothing known about the symbol, keep it off the disk
+                if series.stored.is_none() && data.is_none() {
+                    continue;
+                }
+                let stored = series.stored.get_or_insert_with(|| StoredSeries {
+                    covered_from: from,
+                    data: OHLCData::empty(&keys[index]),
+                });
+                stored.covered_from = stored.covered_from.min(from);
+                if let Some(data) = data {
+                    stored.data.merge(data);
+                }
+
+                let len = stored.data.len();
+                let due = series
+                    .saved_at
+                    .is_none_or(|at| at.elapsed() >= SAVE_INTERVAL);
+                if len != series.saved_len || due {
+                    // Retried on the next request, the bars are served from memory meanwhile
+                    if let Err(e) = self.save(&keys[index], time_frame, stored) {
+                        eprintln!("Failed to save {time_frame} bars of {}: {e}", keys[index]);
+                        continue;
+                    }
+                    series.saved_len = len;
+                    series.saved_at = Some(Instant::now());
+                }
+            }
+        }
+
+        // Answer in the order the symbols were requested, skipping unknown ones
+        let mut data = Vec::new();
+        for symbol in symbols {
+            let Ok(index) = keys.binary_search(&symbol.to_uppercase()) else {
+                continue;
+            };
+            if let Some(stored) = &guards[index].stored {
+                let bars = stored.data.range(Some(start), end);
+                if !bars.is_empty() {
+                    data.push(bars);
+                }
+            }
+        }
+        match failure {
+            Some(e) if data.is_empty() => Err(e),
+            _ => Ok(OHLCResponse(data)),
+        }
+    }
+}
+
+impl DataSource for CandleStore {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn get_quote<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+        time_frame: &'a str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> BoxFuture<'a, SourceResult<OHLCResponse>> {
+        Box::pin(self.get_quote_stored(symbols, time_frame, start_time, end_time))
+    }
+
+    fn get_market_watch<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+    ) -> BoxFuture<'a, SourceResult<MarketWatchResponse>> {
+        self.inner.get_market_watch(symbols)
+    }
+
+    fn get_order_list<'a>(
+        &'a self,
+        symbol: &'a str,
+        limit: u32,
+    ) -> BoxFuture<'a, SourceResult<OrderList>> {
+        self.inner.get_order_list(symbol, limit)
+    }
+
+    fn get_company_ratios<'a>(
+        &'a self,
+        symbol: &'a str,
+        period: &'a str,
+    ) -> BoxFuture<'a, SourceResult<CompanyInfo>> {
+        self.inner.get_company_ratios(symbol, period)
+    }
+
+    fn get_finance_sheet<'a>(
+        &'a self,
+        symbol: &'a str,
+        sheet: FinanceSheet,
+        period: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<FinanceSheetData>>> {
+        self.inner.get_finance_sheet(symbol, sheet, period)
+    }
+
+    fn get_financial_data<'a>(
+        &'a self,
+        symbol: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<FinancialData>>> {
+        self.inner.get_financial_data(symbol)
+    }
+
+    fn get_listings(&self) -> BoxFuture<'_, SourceResult<Vec<VCISymbol>>> {
+        self.inner.get_listings()
+    }
+
+    fn get_index_constituents<'a>(
+        &'a self,
+        index: &'a str,
+    ) -> BoxFuture<'a, SourceResult<Vec<String>>> {
+        self.inner.get_index_constituents(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn bars(symbol: &str, bars: &[(i64, f64)]) -> OHLCData {
+        let mut data = OHLCData::empty(symbol);
+        for &(t, close) in bars {
+            data.o.push(Some(close));
+            data.h.push(Some(close));
+            data.l.push(Some(close));
+            data.c.push(Some(close));
+            data.v.push(Some(100));
+            data.t.push(t.to_string());
+            data.accumulated_volume.push(None);
+            data.accumulated_value.push(None);
+        }
+        data
+    }
+
+    /// Serves a fixed history and records the `from` of every request
+    struct History {
+        data: Mutex<OHLCData>,
+        requests: Mutex<Vec<i64>>,
+        calls: AtomicUsize,
+        /// Symbols whose requests fail
+        down: Mutex<Vec<&'static str>>,
+    }
+
+    impl History {
+        fn new(data: OHLCData) -> Arc<Self> {
+            Arc::new(Self {
+                data: Mutex::new(data),
+                requests: Mutex::new(Vec::new()),
+                calls: AtomicUsize::new(0),
+                down: Mutex::new(Vec::new()),
+            })
+        }
+    }
+
+    impl DataSource for History {
+        fn name(&self) -> &str {
+            "history"
+        }
+
+        fn get_quote<'a>(
+            &'a self,
+            symbols: &'a [&'a str],
+            _time_frame: &'a str,
+            start_time: Option<DateTime<Utc>>,
+            end_time: Option<DateTime<Utc>>,
+        ) -> BoxFuture<'a, SourceResult<OHLCResponse>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let start = start_time.map(|t| t.timestamp());
+            self.requests.lock().unwrap().push(start.unwrap_or(0));
+            let down = self.down.lock().unwrap();
+            if let Some(symbol) = symbols.iter().find(|symbol| down.contains(symbol)) {
+                let error = AimDataError::EmptyData {
+                    endpoint: format!("history/{symbol}"),
+                };
+                return Box::pin(async move { Err(error) });
+            }
+            let data = self
+                .data
+                .lock()
+                .unwrap()
+                .range(start, end_time.map(|t| t.timestamp()));
+            Box::pin(async move { Ok(OHLCResponse(vec![data])) })
+        }
+    }
+
+    #[test]
+    fn test_merge_replaces_forming_bar() {
+        let mut stored = bars("AAA", &[(100, 1.0), (200, 2.0), (300, 3.0)]);
+        stored.merge(bars("AAA", &[(300, 3.5), (400, 4.0)]));
+        assert_eq!(stored.t, vec!["100", "200", "300", "400"]);
+        assert_eq!(stored.c, vec![Some(1.0), Some(2.0), Some(3.5), Some(4.0)]);
+
+        // Older bars are spliced in front without touching newer ones
+        stored.merge(bars("AAA", &[(50, 0.5)]));
+        assert_eq!(stored.t.first().unwrap(), "50");
+        assert_eq!(stored.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_only_missing_range_is_fetched() {
+        let root = std::env::temp_dir().join(format!("aim_candles_{}", std::process::id()));
+        let history = History::new(bars("AAA", &[(100, 1.0), (200, 2.0), (300, 3.0)]));
+        let store = CandleStore::new(&root, history.clone());
+
+        let quote = store
+            .get_quote(&["aaa"], "ONE_DAY", None, None)
+            .await
+            .unwrap();
+        assert_eq!(quote.0[0].len(), 3);
+
+        // The forming bar moves and a new one appears
+        *history.data.lock().unwrap() =
+            bars("AAA", &[(100, 1.0), (200, 2.0), (300, 3.3), (400, 4.0)]);
+        let quote = store
+            .get_quote(&["AAA"], "ONE_DAY", None, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            quote.0[0].c,
+            vec![Some(1.0), Some(2.0), Some(3.3), Some(4.0)]
+        );
+        assert_eq!(*history.requests.lock().unwrap(), vec![0, 300]);
+
+        // A closed range is served from the store, even after a restart
+        let store = CandleStore::new(&root, history.clone());
+        let end = DateTime::from_timestamp(250, 0);
+        let quote = store
+            .get_quote(&["AAA"], "ONE_DAY", None, end)
+            .await
+            .unwrap();
+        assert_eq!(quote.0[0].t, vec!["100", "200"]);
+        assert_eq!(history.calls.load(Ordering::SeqCst), 2);
+
+        // A corrupt file is reported, then the series is fetched again
+        fs::write(store.path("AAA", "ONE_DAY"), "{").unwrap();
+        let store = CandleStore::new(&root, history.clone());
+        let error = store
+            .get_quote(&["AAA"], "ONE_DAY", None, end)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AimDataError::Decode { .. }));
+        let quote = store
+            .get_quote(&["AAA"], "ONE_DAY", None, end)
+            .await
+            .unwrap();
+        assert_eq!(quote.0[0].t, vec!["100", "200"]);
+
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[tokio::test]
+    async fn test_missing_and_failing_symbols_are_left_out() {
+        let root = std::env::temp_dir().join(format!("aim_candles_partial_{}", std::process::id()));
+        let history = History::new(bars("AAA", &[(100, 1.0), (200, 2.0), (300, 3.0)]));
+        let store = CandleStore::new(&root, history.clone());
+
+        // The provider knows nothing of ZZZ, no empty series is written for it
+        let quote = store
+            .get_quote(&["AAA", "ZZZ"], "ONE_DAY", None, None)
+            .await
+            .unwrap();
+        assert_eq!(quote.0.len(), 1);
+        assert!(store.path("AAA", "ONE_DAY").exists());
+        assert!(!store.path("ZZZ", "ONE_DAY").exists());
+
+        // ZZZ is asked for apart from AAA, whose bars are already stored, and
+        // its failure does not take AAA down with it
+        history.down.lock().unwrap().push("ZZZ");
+        let quote = store
+            .get_quote(&["AAA", "ZZZ"], "ONE_DAY", None, None)
+            .await
+            .unwrap();
+        assert_eq!(quote.0.len(), 1);
+        assert_eq!(quote.0[0].symbol, "AAA");
+        assert_eq!(*history.requests.lock().unwrap(), vec![0, 0, 300]);
+
+        // With nothing to show, the failure is reported
+        let error = store
+            .get_quote(&["ZZZ"], "ONE_DAY", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AimDataError::EmptyData { .. }));
+
+        fs::remove_dir_all(&root).ok();
+    }
+}
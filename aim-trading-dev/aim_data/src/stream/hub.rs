@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+
+use super::poll::{PollFailures, PollState};
+use super::{Channel, MarketStream, Request, StreamEvent};
+
+/// The one upstream of a channel, shared by every subscription to it
+struct Upstream {
+    /// Subscriptions asking for each symbol
+    interest: BTreeMap<String, usize>,
+    events: broadcast::Sender<StreamEvent>,
+    /// Last quote or bar of each symbol, handed to new subscriptions
+    latest: HashMap<String, StreamEvent>,
+    /// Fed by the runner, read by `fan`
+    tx: mpsc::Sender<StreamEvent>,
+    fan: JoinHandle<()>,
+    /// Streams or polls the symbols of `interest`, restarted when they change
+    runner: Option<JoinHandle<()>>,
+    failures: Arc<PollFailures>,
+    /// Kept across restarts so symbols already followed are not sent again
+    state: Arc<tokio::sync::Mutex<PollState>>,
+}
+
+/// Upstreams of a [`MarketStream`] and of its clones
+#[derive(Default)]
+pub(super) struct Hub {
+    upstreams: Mutex<HashMap<Channel, Upstream>>,
+}
+
+impl Hub {
+    /// Add `request` to the upstreams of its channels and forward their
+    /// events to `tx`, returns the forwarding tasks and the failure counters
+    pub(super) fn join(
+        self: &Arc<Self>,
+        stream: &MarketStream,
+        request: &Request,
+        tx: &mpsc::Sender<StreamEvent>,
+    ) -> (Vec<JoinHandle<()>>, Vec<Arc<PollFailures>>) {
+        let mut upstreams = self.upstreams.lock().unwrap_or_else(|e| e.into_inner());
+        let mut forwards = Vec::new();
+        let mut failures = Vec::new();
+        for &channel in &request.channels {
+            let upstream = upstreams
+                .entry(channel)
+                .or_insert_with(|| self.open(stream, channel));
+            let mut added = false;
+            for symbol in &request.symbols {
+                let count = upstream.interest.entry(symbol.clone()).or_default();
+                added |= *count == 0;
+                *count += 1;
+            }
+            if added || upstream.runner.is_none() {
+                upstream.restart(stream, channel);
+            }
+            failures.push(Arc::clone(&upstream.failures));
+
+            // Both under the lock, so no event falls between the two
+            let mut events = upstream.events.subscribe();
+            let replay: Vec<StreamEvent> = request
+                .symbols
+                .iter()
+                .filter_map(|symbol| upstream.latest.get(symbol).cloned())
+                .collect();
+            let request = request.clone();
+            let tx = tx.clone();
+            forwards.push(tokio::spawn(async move {
+                for event in replay {
+                    if tx.send(event).await.is_err() {
+                        return;
+                    }
+                }
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            if request.wants(&event) && tx.send(event).await.is_err() {
+                                return;
+                            }
+                        }
+                        Err(RecvError::Lagged(missed)) => {
+                            eprintln!(
+                                "Market stream subscriber fell behind, {missed} updates dropped"
+                            )
+                        }
+                        Err(RecvError::Closed) => return,
+                    }
+                }
+            }));
+        }
+        (forwards, failures)
+    }
+
+    /// Take `request` out of its upstreams, stopping the ones nobody uses
+    pub(super) fn leave(&self, stream: &MarketStream, request: &Request) {
+        let mut upstreams = self.upstreams.lock().unwrap_or_else(|e| e.into_inner());
+        for channel in &request.channels {
+            let Some(upstream) = upstreams.get_mut(channel) else {
+                continue;
+            };
+            let mut removed = false;
+            for symbol in &request.symbols {
+                if let Some(count) = upstream.interest.get_mut(symbol) {
+                    *count -= 1;
+                    if *count == 0 {
+                        upstream.interest.remove(symbol);
+                        removed = true;
+                    }
+                }
+            }
+            if upstream.interest.is_empty() {
+                if let Some(upstream) = upstreams.remove(channel) {
+                    upstream.stop();
+                }
+            } else if removed {
+                upstream.restart(stream, *channel);
+            }
+        }
+    }
+
+    fn open(self: &Arc<Self>, stream: &MarketStream, channel: Channel) -> Upstream {
+        let capacity = stream.config.capacity.max(1);
+        let (events, _) = broadcast::channel(capacity);
+        let (tx, mut rx) = mpsc::channel(capacity);
+        // Weak, the hub owns this task
+        let hub = Arc::downgrade(self);
+        let fan = tokio::spawn(async move {
+            while let Some(event) = rx.recv().await {
+                let Some(hub) = hub.upgrade() else {
+                    return;
+                };
+                let mut upstreams = hub.upstreams.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(upstream) = upstreams.get_mut(&channel) {
+                    upstream.publish(event);
+                }
+            }
+        });
+        Upstream {
+            interest: BTreeMap::new(),
+            events,
+            latest: HashMap::new(),
+            tx,
+            fan,
+            runner: None,
+            failures: Arc::default(),
+            state: Arc::default(),
+        }
+    }
+}
+
+impl Upstream {
+    fn publish(&mut self, event: StreamEvent) {
+        if !matches!(event, StreamEvent::Tick(_)) {
+            self.latest
+                .insert(event.symbol().to_string(), event.clone());
+        }
+        // No receiver only means every subscription is being dropped
+        let _ = self.events.send(event);
+    }
+
+    /// Follow the symbols of `interest` from now on
+    fn restart(&mut self, stream: &MarketStream, channel: Channel) {
+        if let Some(runner) = self.runner.take() {
+            runner.abort();
+        }
+        self.latest
+            .retain(|symbol, _| self.interest.contains_key(symbol));
+        let request = Request {
+            symbols: self.interest.keys().cloned().collect(),
+            channels: vec![channel],
+        };
+        let stream = stream.clone();
+        let tx = self.tx.clone();
+        let failures = Arc::clone(&self.failures);
+        let state = Arc::clone(&self.state);
+        self.runner = Some(tokio::spawn(async move {
+            // Waits for the runner being replaced to let go of it
+            let mut state = state.lock().await;
+            stream.run(request, tx, &failures, &mut state).await;
+        }));
+    }
+
+    fn stop(self) {
+        if let Some(runner) = self.runner {
+            runner.abort();
+        }
+        self.fan.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explorer::OfflineExplorer;
+
+    /// Symbols followed on `channel` with their subscription count
+    fn interest(stream: &MarketStream, channel: Channel) -> Option<String> {
+        let upstreams = stream.hub.upstreams.lock().unwrap();
+        let interest = upstreams.get(&channel)?.interest.iter();
+        let counts: Vec<String> = interest
+            .map(|(symbol, count)| format!("{symbol}:{count}"))
+            .collect();
+        Some(counts.join(" "))
+    }
+
+    #[tokio::test]
+    async fn test_subscriptions_share_one_upstream_per_channel() {
+        let fallback = Arc::new(OfflineExplorer::new(
+            std::env::temp_dir().join("aim_hub_none"),
+        ));
+        let stream = MarketStream::new(None, fallback);
+        let ticks = stream.subscribe(&["FPT"], &[Channel::Ticks]);
+        // Clones share the upstreams too
+        let both = stream
+            .clone()
+            .subscribe(&["fpt", "VCB"], &[Channel::Ticks, Channel::Quotes]);
+        assert_eq!(
+            interest(&stream, Channel::Ticks),
+            Some("FPT:2 VCB:1".to_string())
+        );
+        assert_eq!(
+            interest(&stream, Channel::Quotes),
+            Some("FPT:1 VCB:1".to_string())
+        );
+
+        drop(both);
+        assert_eq!(interest(&stream, Channel::Ticks), Some("FPT:1".to_string()));
+        assert_eq!(interest(&stream, Channel::Quotes), None);
+        drop(ticks);
+        assert_eq!(interest(&stream, Channel::Ticks), None);
+    }
+}
@@ -0,0 +1,571 @@
+//! Push-based market data for the polling tasks
+//!
+//! [`MarketStream::subscribe`] opens the server-sent events feed of the AIM
+//! backend for a set of symbols and forwards ticks, bid/ask updates and bar
+//! updates as [`StreamEvent`]s. While the feed is unreachable the same events
+//! are made by polling the REST data source, and the feed is tried again
+//! every [`StreamConfig::retry_interval`].
+//!
+//! Subscriptions of one stream share a single upstream per channel, asking
+//! for every symbol any of them follows.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::explorer::aim::{self, AimExplorer};
+use crate::explorer::vci::market_watch::VCIMarketWatch;
+use crate::explorer::vci::{OrderList, VCIOderBook};
+use crate::source::DataSource;
+
+mod hub;
+mod poll;
+pub mod replay;
+mod sse;
+
+pub use replay::{RecordedEvent, ReplayServer, read_session, record_session, write_session};
+
+/// Kind of update a subscription asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// Matched orders
+    Ticks,
+    /// Best bid/ask and last match
+    Quotes,
+    /// One-minute bars
+    Bars,
+}
+
+impl Channel {
+    /// Name used in the stream query and the event names
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Ticks => "ticks",
+            Channel::Quotes => "quotes",
+            Channel::Bars => "bars",
+        }
+    }
+}
+
+/// One matched order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tick {
+    pub symbol: String,
+    /// Increases with every match of the symbol
+    pub id: u64,
+    /// Time of day in Vietnam, hh:mm:ss
+    pub time: String,
+    pub price: f64,
+    pub volume: i64,
+    /// "b" for a buy-initiated match, "s" for a sell, empty in auctions
+    pub side: String,
+}
+
+impl Tick {
+    pub fn from_order(symbol: &str, order: &VCIOderBook) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            id: order.id,
+            time: order.timestamp.clone(),
+            price: order.price,
+            volume: order.volume,
+            side: order.match_type.clone(),
+        }
+    }
+
+    pub fn to_order(&self) -> VCIOderBook {
+        VCIOderBook {
+            id: self.id,
+            timestamp: self.time.clone(),
+            price: self.price,
+            volume: self.volume,
+            match_type: self.side.clone(),
+        }
+    }
+}
+
+/// Latest state of a bar that is still forming, or just closed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BarUpdate {
+    pub symbol: String,
+    /// VCI time frame name, "ONE_MINUTE"
+    pub time_frame: String,
+    /// Bar start, Unix seconds
+    pub time: i64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: i64,
+}
+
+/// An update pushed to a subscription
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Tick(Tick),
+    /// Best bid/ask and last match, the row polled from the market watch
+    Quote(Box<VCIMarketWatch>),
+    Bar(BarUpdate),
+}
+
+impl StreamEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            StreamEvent::Tick(tick) => &tick.symbol,
+            StreamEvent::Quote(quote) => &quote.listing_info.symbol,
+            StreamEvent::Bar(bar) => &bar.symbol,
+        }
+    }
+
+    pub fn channel(&self) -> Channel {
+        match self {
+            StreamEvent::Tick(_) => Channel::Ticks,
+            StreamEvent::Quote(_) => Channel::Quotes,
+            StreamEvent::Bar(_) => Channel::Bars,
+        }
+    }
+
+    /// SSE event name
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StreamEvent::Tick(_) => "tick",
+            StreamEvent::Quote(_) => "quote",
+            StreamEvent::Bar(_) => "bar",
+        }
+    }
+
+    /// Event of SSE name `kind` carrying `data`, `None` for unknown names
+    pub fn decode(kind: &str, data: &str) -> Option<serde_json::Result<Self>> {
+        Some(match kind {
+            "tick" => serde_json::from_str(data).map(StreamEvent::Tick),
+            "quote" => serde_json::from_str(data).map(StreamEvent::Quote),
+            "bar" => serde_json::from_str(data).map(StreamEvent::Bar),
+            _ => return None,
+        })
+    }
+
+    /// JSON of the payload, the SSE `data` field
+    pub fn payload(&self) -> serde_json::Result<String> {
+        match self {
+            StreamEvent::Tick(tick) => serde_json::to_string(tick),
+            StreamEvent::Quote(quote) => serde_json::to_string(quote),
+            StreamEvent::Bar(bar) => serde_json::to_string(bar),
+        }
+    }
+}
+
+/// Add the ticks not in `orders` yet, keeping it newest first, returns
+/// whether any was added
+pub fn merge_ticks(orders: &mut OrderList, ticks: &[Tick]) -> bool {
+    let known: HashSet<u64> = orders.iter().map(|order| order.id).collect();
+    let fresh: Vec<VCIOderBook> = ticks
+        .iter()
+        .filter(|tick| !known.contains(&tick.id))
+        .map(Tick::to_order)
+        .collect();
+    if fresh.is_empty() {
+        return false;
+    }
+    orders.extend(fresh);
+    orders.sort_by_key(|order| std::cmp::Reverse(order.id));
+    true
+}
+
+/// Timings of the feed and of the polling fallback
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamConfig {
+    /// Delay between two polls of ticks and quotes
+    pub poll_interval: Duration,
+    /// Delay between two polls of bars
+    pub bar_poll_interval: Duration,
+    /// Matched orders asked on the first poll of a symbol, later polls only
+    /// fetch past the last one seen
+    pub tick_limit: u32,
+    /// Time spent polling before the feed is tried again
+    pub retry_interval: Duration,
+    /// Silence after which the feed is taken as dead, the server sends
+    /// keep-alive comments more often than this
+    pub idle_timeout: Duration,
+    /// Events buffered per subscription and per channel upstream
+    pub capacity: usize,
+    /// Poll only once while no exchange is matching orders
+    pub pause_when_closed: bool,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            bar_poll_interval: Duration::from_secs(5),
+            tick_limit: 500,
+            retry_interval: Duration::from_secs(60),
+            idle_timeout: Duration::from_secs(30),
+            capacity: 4096,
+            pause_when_closed: true,
+        }
+    }
+}
+
+/// Opens subscriptions on the AIM feed, falling back to a REST source
+///
+/// Clones share their upstreams, see [`crate::market_stream`] for the one
+/// of the app.
+#[derive(Clone)]
+pub struct MarketStream {
+    feed: Option<Arc<AimExplorer>>,
+    fallback: Arc<dyn DataSource>,
+    config: StreamConfig,
+    hub: Arc<hub::Hub>,
+}
+
+impl Default for MarketStream {
+    /// The configured AIM backend in front of [`crate::data_source`], only
+    /// polling when no stream endpoint or token is set
+    fn default() -> Self {
+        let feed = aim::explorer();
+        let config = feed.config();
+        let streams = !config.stream_endpoint.is_empty() && !config.token.is_empty();
+        Self::new(streams.then_some(feed), crate::data_source())
+    }
+}
+
+impl MarketStream {
+    /// Stream from `feed`, or only poll `fallback` when there is none
+    pub fn new(feed: Option<Arc<AimExplorer>>, fallback: Arc<dyn DataSource>) -> Self {
+        Self {
+            feed,
+            fallback,
+            config: StreamConfig::default(),
+            hub: Arc::default(),
+        }
+    }
+
+    pub fn with_config(mut self, config: StreamConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// The REST source polled while the feed is unreachable
+    pub fn fallback(&self) -> Arc<dyn DataSource> {
+        Arc::clone(&self.fallback)
+    }
+
+    /// Push `channels` updates of `symbols` until the subscription is dropped
+    ///
+    /// A new subscription starts with the last quote and bar already
+    /// received for its symbols.
+    pub fn subscribe(&self, symbols: &[&str], channels: &[Channel]) -> Subscription {
+        let (tx, rx) = mpsc::channel(self.config.capacity.max(1));
+        let mut request = Request {
+            symbols: symbols.iter().map(|symbol| symbol.to_uppercase()).collect(),
+            channels: Vec::new(),
+        };
+        request.symbols.sort();
+        request.symbols.dedup();
+        for &channel in channels {
+            if !request.channels.contains(&channel) {
+                request.channels.push(channel);
+            }
+        }
+        let (forwards, failures) = self.hub.join(self, &request, &tx);
+        Subscription {
+            events: rx,
+            failures,
+            forwards,
+            stream: self.clone(),
+            request,
+        }
+    }
+
+    async fn run(
+        self,
+        request: Request,
+        tx: mpsc::Sender<StreamEvent>,
+        failures: &poll::PollFailures,
+        state: &mut poll::PollState,
+    ) {
+        loop {
+            if let Some(feed) = &self.feed {
+                match sse::open(feed, &request).await {
+                    Ok(response) => {
+                        let result = sse::pump(response, &request, &tx, self.config.idle_timeout);
+                        match result.await {
+                            _ if tx.is_closed() => return,
+                            Ok(()) => eprintln!("Market stream closed by the server, reconnecting"),
+                            Err(e) => eprintln!("Market stream interrupted: {e}"),
+                        }
+                        // Anything missed while reconnecting comes from one poll round
+                        let deadline = Some(Instant::now());
+                        if !poll::run(
+                            &*self.fallback,
+                            &request,
+                            &self.config,
+                            state,
+                            &tx,
+                            failures,
+                            deadline,
+                        )
+                        .await
+                        {
+                            return;
+                        }
+                        continue;
+                    }
+                    Err(e) => eprintln!("Market stream unavailable, polling instead: {e}"),
+                }
+            }
+            let deadline = self
+                .feed
+                .is_some()
+                .then(|| Instant::now() + self.config.retry_interval);
+            if !poll::run(
+                &*self.fallback,
+                &request,
+                &self.config,
+                state,
+                &tx,
+                failures,
+                deadline,
+            )
+            .await
+            {
+                return;
+            }
+        }
+    }
+}
+
+/// Symbols and channels of one subscription
+#[derive(Debug, Clone)]
+struct Request {
+    symbols: Vec<String>,
+    channels: Vec<Channel>,
+}
+
+impl Request {
+    fn wants(&self, event: &StreamEvent) -> bool {
+        self.channels.contains(&event.channel())
+            && self.symbols.iter().any(|symbol| symbol == event.symbol())
+    }
+}
+
+/// Updates of one [`MarketStream::subscribe`] call, stopped when dropped
+pub struct Subscription {
+    events: mpsc::Receiver<StreamEvent>,
+    /// One per channel
+    failures: Vec<Arc<poll::PollFailures>>,
+    forwards: Vec<JoinHandle<()>>,
+    stream: MarketStream,
+    request: Request,
+}
+
+impl Subscription {
+    /// Next update, `None` once the stream stopped
+    pub async fn recv(&mut self) -> Option<StreamEvent> {
+        self.events.recv().await
+    }
+
+    /// Every update already received, without waiting
+    pub fn drain(&mut self) -> Vec<StreamEvent> {
+        let mut events = Vec::new();
+        while let Ok(event) = self.events.try_recv() {
+            events.push(event);
+        }
+        events
+    }
+
+    /// Requests to the REST fallback that failed so far, with the error of
+    /// the last one
+    ///
+    /// The upstreams are shared, so this counts the failed requests for the
+    /// other subscriptions to the same channels too.
+    pub fn failed_polls(&self) -> (u64, Option<String>) {
+        self.failures
+            .iter()
+            .map(|failures| failures.get())
+            .fold((0, None), |(count, last), (more, other)| {
+                (count + more, other.or(last))
+            })
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        for forward in &self.forwards {
+            forward.abort();
+        }
+        self.stream.hub.leave(&self.stream, &self.request);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AimConfig;
+    use crate::error::AimDataError;
+    use crate::explorer::vci::market_watch::{BidAsk, ListingInfo, MatchPrice};
+    use crate::source::{BoxFuture, SourceResult};
+    use std::sync::Mutex;
+
+    pub(crate) fn quote(symbol: &str, price: f64) -> VCIMarketWatch {
+        VCIMarketWatch {
+            listing_info: ListingInfo {
+                code: symbol.to_string(),
+                symbol: symbol.to_string(),
+                ceiling: 0.0,
+                floor: 0.0,
+                ref_price: 0.0,
+                board: "HSX".to_string(),
+                en_organ_name: String::new(),
+                en_organ_short_name: String::new(),
+                organ_name: String::new(),
+                organ_short_name: String::new(),
+                ticker: symbol.to_string(),
+                trading_date: String::new(),
+            },
+            bid_ask: BidAsk {
+                code: symbol.to_string(),
+                symbol: symbol.to_string(),
+                bid_prices: Vec::new(),
+                ask_prices: Vec::new(),
+            },
+            match_price: MatchPrice {
+                code: symbol.to_string(),
+                symbol: symbol.to_string(),
+                match_price: price,
+                match_vol: 0,
+                accumulated_volume: 0,
+                highest: 0.0,
+                lowest: 0.0,
+                reference_price: 0.0,
+            },
+        }
+    }
+
+    pub(crate) fn tick(symbol: &str, id: u64) -> Tick {
+        Tick {
+            symbol: symbol.to_string(),
+            id,
+            time: "09:15:00".to_string(),
+            price: 10.0 + id as f64,
+            volume: 100,
+            side: "b".to_string(),
+        }
+    }
+
+    /// REST source whose order book grows by one match per poll, after
+    /// failing the first `failing` polls
+    struct Market {
+        polls: Mutex<u64>,
+        failing: Mutex<u32>,
+    }
+
+    impl DataSource for Market {
+        fn name(&self) -> &str {
+            "market"
+        }
+
+        fn get_order_list<'a>(
+            &'a self,
+            symbol: &'a str,
+            limit: u32,
+        ) -> BoxFuture<'a, SourceResult<OrderList>> {
+            let mut failing = self.failing.lock().unwrap();
+            if *failing > 0 {
+                *failing -= 1;
+                let endpoint = format!("order-list/{symbol}");
+                return Box::pin(async move { Err(AimDataError::EmptyData { endpoint }) });
+            }
+            let mut polls = self.polls.lock().unwrap();
+            *polls += 1;
+            let last = *polls + 1;
+            let orders = (1..=last)
+                .rev()
+                .take(limit as usize)
+                .map(|id| tick(symbol, id).to_order())
+                .collect();
+            Box::pin(async move { Ok(orders) })
+        }
+    }
+
+    #[test]
+    fn test_merge_ticks_keeps_newest_first() {
+        let mut orders = vec![tick("FPT", 2).to_order(), tick("FPT", 1).to_order()];
+        assert!(!merge_ticks(&mut orders, &[tick("FPT", 2)]));
+        assert!(merge_ticks(
+            &mut orders,
+            &[tick("FPT", 4), tick("FPT", 3), tick("FPT", 2)]
+        ));
+        let ids: Vec<u64> = orders.iter().map(|order| order.id).collect();
+        assert_eq!(ids, [4, 3, 2, 1]);
+
+        let event = StreamEvent::Quote(Box::new(quote("FPT", 100.0)));
+        let json = serde_json::to_string(&event).unwrap();
+        assert_eq!(serde_json::from_str::<StreamEvent>(&json).unwrap(), event);
+        let decoded = StreamEvent::decode(event.kind(), &event.payload().unwrap());
+        assert_eq!(decoded.unwrap().unwrap(), event);
+        assert!(StreamEvent::decode("heartbeat", "{}").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_polling_without_feed() {
+        // Nothing listens on this port, so the feed fails at once
+        let config = AimConfig {
+            base_url: "http://127.0.0.1:9".to_string(),
+            token: "test".to_string(),
+            max_retries: 0,
+            ..AimConfig::default()
+        };
+        let feed = Arc::new(AimExplorer::new(config).unwrap());
+        let market = Arc::new(Market {
+            polls: Mutex::new(0),
+            failing: Mutex::new(0),
+        });
+        let stream = MarketStream::new(Some(feed), market).with_config(StreamConfig {
+            poll_interval: Duration::from_millis(5),
+            pause_when_closed: false,
+            ..StreamConfig::default()
+        });
+        let mut subscription = stream.subscribe(&["fpt"], &[Channel::Ticks]);
+
+        let mut ids = Vec::new();
+        while ids.len() < 4 {
+            match subscription.recv().await {
+                Some(StreamEvent::Tick(tick)) => {
+                    assert_eq!(tick.symbol, "FPT");
+                    ids.push(tick.id);
+                }
+                other => panic!("unexpected event {other:?}"),
+            }
+        }
+        // Each match is pushed once, oldest first
+        assert_eq!(ids, [1, 2, 3, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_first_poll_is_retried() {
+        let market = Arc::new(Market {
+            polls: Mutex::new(0),
+            failing: Mutex::new(1),
+        });
+        // Pausing while the market is closed waits for a poll that went through
+        let stream = MarketStream::new(None, market).with_config(StreamConfig {
+            poll_interval: Duration::from_millis(5),
+            pause_when_closed: true,
+            ..StreamConfig::default()
+        });
+        let mut subscription = stream.subscribe(&["FPT"], &[Channel::Ticks]);
+
+        let event = tokio::time::timeout(Duration::from_secs(5), subscription.recv()).await;
+        assert!(matches!(event, Ok(Some(StreamEvent::Tick(_)))));
+        let (failed, last) = subscription.failed_polls();
+        assert_eq!(failed, 1);
+        assert!(last.unwrap().starts_with("ticks of FPT"));
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::{NaiveDate, Utc};
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+use super::{BarUpdate, Channel, Request, StreamConfig, StreamEvent, Tick};
+use crate::calendar::{Exchange, calendar};
+use crate::error::AimDataError;
+use crate::explorer::vci::market_watch::VCIMarketWatch;
+use crate::source::DataSource;
+use crate::tape;
+
+/// Bars asked per poll, enough to see the last one close
+const BAR_LOOKBACK_SECS: i64 = 30 * 60;
+
+/// Requests of the polls that failed, shared with the subscription
+#[derive(Debug, Default)]
+pub(super) struct PollFailures {
+    count: AtomicU64,
+    last: Mutex<Option<String>>,
+}
+
+impl PollFailures {
+    fn record(&self, what: &str, error: AimDataError) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        *self.last.lock().unwrap() = Some(format!("{what}: {error}"));
+    }
+
+    /// Failed requests so far and the error of the last one
+    pub(super) fn get(&self) -> (u64, Option<String>) {
+        let last = self.last.lock().unwrap().clone();
+        (self.count.load(Ordering::Relaxed), last)
+    }
+}
+
+/// What was already pushed, so polls only send changes
+#[derive(Debug, Default)]
+pub(super) struct PollState {
+    last_tick: HashMap<String, u64>,
+    quotes: HashMap<String, VCIMarketWatch>,
+    bars: HashMap<String, BarUpdate>,
+    /// A first poll went through, later ones may pause while the market is closed
+    polled: bool,
+    /// Session of `last_tick`, whose ids start over with the next one
+    session: Option<NaiveDate>,
+    bars_polled_at: Option<Instant>,
+}
+
+fn market_open() -> bool {
+    let now = Utc::now();
+    [Exchange::Hose, Exchange::Hnx, Exchange::Upcom]
+        .into_iter()
+        .any(|exchange| calendar().phase(exchange, now).is_matching())
+}
+
+/// Poll `source` until `deadline`, or forever without one, pushing what
+/// changed and counting failed requests in `failures`; returns `false` once
+/// the subscription was dropped
+pub(super) async fn run(
+    source: &dyn DataSource,
+    request: &Request,
+    config: &StreamConfig,
+    state: &mut PollState,
+    tx: &mpsc::Sender<StreamEvent>,
+    failures: &PollFailures,
+    deadline: Option<Instant>,
+) -> bool {
+    loop {
+        if tx.is_closed() {
+            return false;
+        }
+        if !state.polled || !config.pause_when_closed || market_open() {
+            let (events, errors) = poll_once(source, request, config, state).await;
+            // Keep polling until a round gets through, even with the market closed
+            state.polled |= errors.is_empty();
+            for (what, error) in errors {
+                failures.record(&what, error);
+            }
+            for event in events {
+                if tx.send(event).await.is_err() {
+                    return false;
+                }
+            }
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return true;
+        }
+        tokio::time::sleep(config.poll_interval).await;
+    }
+}
+
+/// Changes since the last poll, with what failed to be fetched
+async fn poll_once(
+    source: &dyn DataSource,
+    request: &Request,
+    config: &StreamConfig,
+    state: &mut PollState,
+) -> (Vec<StreamEvent>, Vec<(String, AimDataError)>) {
+    let mut events = Vec::new();
+    let mut errors = Vec::new();
+    let symbols: Vec<&str> = request.symbols.iter().map(String::as_str).collect();
+
+    if request.channels.contains(&Channel::Ticks) {
+        let session = calendar().session_date(Utc::now());
+        if state.session.replace(session) != Some(session) {
+            state.last_tick.clear();
+        }
+        for &symbol in &symbols {
+            let last = state.last_tick.get(symbol).copied();
+            match tape::fetch_since(source, symbol, last, config.tick_limit).await {
+                Ok(orders) => {
+                    // Orders come newest first, ticks are pushed oldest first
+                    let fresh: Vec<Tick> = orders
+                        .iter()
+                        .rev()
+                        .map(|order| Tick::from_order(symbol, order))
+                        .collect();
+                    if let Some(tick) = fresh.last() {
+                        state.last_tick.insert(symbol.to_string(), tick.id);
+                    }
+                    events.extend(fresh.into_iter().map(StreamEvent::Tick));
+                }
+                Err(e) => errors.push((format!("ticks of {symbol}"), e)),
+            }
+        }
+    }
+
+    if request.channels.contains(&Channel::Quotes) && !symbols.is_empty() {
+        match source.get_market_watch(&symbols).await {
+            Ok(rows) => {
+                for row in rows.0 {
+                    let symbol = &row.listing_info.symbol;
+                    if state.quotes.get(symbol) != Some(&row) {
+                        state.quotes.insert(symbol.clone(), row.clone());
+                        events.push(StreamEvent::Quote(Box::new(row)));
+                    }
+                }
+            }
+            Err(e) => errors.push(("quotes".to_string(), e)),
+        }
+    }
+
+    let bars_due = state
+        .bars_polled_at
+        .is_none_or(|at| at.elapsed() >= config.bar_poll_interval);
+    if request.channels.contains(&Channel::Bars) && !symbols.is_empty() && bars_due {
+        state.bars_polled_at = Some(Instant::now());
+        let start = chrono::DateTime::from_timestamp(Utc::now().timestamp() - BAR_LOOKBACK_SECS, 0);
+        match source.get_quote(&symbols, "ONE_MINUTE", start, None).await {
+            Ok(series) => {
+                for data in series.0 {
+                    let Some(bar) = data.to_candlesticks().pop() else {
+                        continue;
+                    };
+                    let update = BarUpdate {
+                        symbol: data.symbol.clone(),
+                        time_frame: "ONE_MINUTE".to_string(),
+                        time: bar.timestamp.timestamp(),
+                        open: bar.open,
+                        high: bar.high,
+                        low: bar.low,
+                        close: bar.close,
+                        volume: bar.volume,
+                    };
+                    if state.bars.get(&data.symbol) != Some(&update) {
+                        state.bars.insert(data.symbol, update.clone());
+                        events.push(StreamEvent::Bar(update));
+                    }
+                }
+            }
+            Err(e) => errors.push(("bars".to_string(), e)),
+        }
+    }
+
+    (events, errors)
+}
@@ -0,0 +1,268 @@
+//! Recording a stream and serving it back as a local SSE feed
+//!
+//! A session is JSON lines of [`RecordedEvent`]. [`ReplayServer`] plays one
+//! to every client with the original timing, so the streaming tasks can be
+//! tested against a real HTTP connection without the backend.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use super::{Channel, Request, StreamEvent, Subscription};
+use crate::error::AimDataError;
+use crate::source::SourceResult;
+
+/// Delay between keep-alive comments once a session is played
+const KEEP_ALIVE: Duration = Duration::from_secs(1);
+
+/// An event and when it came, relative to the start of the session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    pub offset_ms: u64,
+    pub event: StreamEvent,
+}
+
+/// Parse a session, one [`RecordedEvent`] per non-blank line
+pub fn read_session(text: &str) -> SourceResult<Vec<RecordedEvent>> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            serde_json::from_str(line).map_err(|e| AimDataError::decode("session", line, e))
+        })
+        .collect()
+}
+
+pub fn write_session(events: &[RecordedEvent]) -> String {
+    events
+        .iter()
+        .filter_map(|event| serde_json::to_string(event).ok())
+        .map(|line| line + "\n")
+        .collect()
+}
+
+/// Record the updates of `subscription` for `duration`
+pub async fn record_session(
+    subscription: &mut Subscription,
+    duration: Duration,
+) -> Vec<RecordedEvent> {
+    let start = Instant::now();
+    let mut events = Vec::new();
+    while let Ok(Some(event)) = tokio::time::timeout_at(start + duration, subscription.recv()).await
+    {
+        events.push(RecordedEvent {
+            offset_ms: start.elapsed().as_millis() as u64,
+            event,
+        });
+    }
+    events
+}
+
+/// Local SSE server replaying a session to each client, stopped when dropped
+///
+/// Clients connect like to the backend feed, `GET /{endpoint}?symbols=&channels=`,
+/// and only get the events of their symbols and channels. Once the session is
+/// played the connection is kept open with keep-alive comments.
+pub struct ReplayServer {
+    addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl ReplayServer {
+    /// Serve `session` on a free local port, `speed` times faster than recorded
+    pub async fn start(session: Vec<RecordedEvent>, speed: f64) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let session = Arc::new(session);
+        let speed = if speed > 0.0 { speed } else { 1.0 };
+        let task = tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let session = Arc::clone(&session);
+                tokio::spawn(async move {
+                    if let Err(e) = serve(socket, &session, speed).await {
+                        eprintln!("Replay client left: {e}");
+                    }
+                });
+            }
+        });
+        Ok(Self { addr, task })
+    }
+
+    /// Base URL to use as the backend URL
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl Drop for ReplayServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(
+    mut socket: TcpStream,
+    session: &[RecordedEvent],
+    speed: f64,
+) -> std::io::Result<()> {
+    let request = read_request(&mut socket).await?;
+    socket
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+    let start = Instant::now();
+    for recorded in session
+        .iter()
+        .filter(|recorded| request.wants(&recorded.event))
+    {
+        let at = Duration::from_millis(recorded.offset_ms).div_f64(speed);
+        tokio::time::sleep_until(start + at).await;
+        let payload = recorded.event.payload().unwrap_or_default();
+        let frame = format!("event: {}\ndata: {payload}\n\n", recorded.event.kind());
+        socket.write_all(frame.as_bytes()).await?;
+    }
+    loop {
+        socket.write_all(b": keep-alive\n\n").await?;
+        tokio::time::sleep(KEEP_ALIVE).await;
+    }
+}
+
+/// Symbols and channels asked in the request line, every channel by default
+async fn read_request(socket: &mut TcpStream) -> std::io::Result<Request> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = socket.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let target = head.split_whitespace().nth(1).unwrap_or("/");
+    let query = target.split_once('?').map(|(_, query)| query).unwrap_or("");
+
+    let mut request = Request {
+        symbols: Vec::new(),
+        channels: vec![Channel::Ticks, Channel::Quotes, Channel::Bars],
+    };
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        let values = decode_component(value);
+        let values = values.split(',').filter(|value| !value.is_empty());
+        match key {
+            "symbols" => request.symbols = values.map(str::to_uppercase).collect(),
+            "channels" => {
+                request.channels = values
+                    .filter_map(|name| {
+                        [Channel::Ticks, Channel::Quotes, Channel::Bars]
+                            .into_iter()
+                            .find(|channel| channel.name() == name)
+                    })
+                    .collect()
+            }
+            _ => {}
+        }
+    }
+    Ok(request)
+}
+
+/// Undo the percent-encoding of a query value
+fn decode_component(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::{quote, tick};
+    use super::super::{MarketStream, StreamConfig};
+    use super::*;
+    use crate::config::AimConfig;
+    use crate::explorer::OfflineExplorer;
+    use crate::explorer::aim::AimExplorer;
+
+    #[tokio::test]
+    async fn test_replayed_session_reaches_subscribers() {
+        let session = vec![
+            RecordedEvent {
+                offset_ms: 0,
+                event: StreamEvent::Quote(Box::new(quote("FPT", 100_000.0))),
+            },
+            RecordedEvent {
+                offset_ms: 10,
+                event: StreamEvent::Tick(tick("VCB", 1)),
+            },
+            RecordedEvent {
+                offset_ms: 20,
+                event: StreamEvent::Tick(tick("FPT", 7)),
+            },
+            RecordedEvent {
+                offset_ms: 30,
+                event: StreamEvent::Quote(Box::new(quote("FPT", 101_000.0))),
+            },
+        ];
+        let text = write_session(&session);
+        assert_eq!(read_session(&text).unwrap(), session);
+
+        let server = ReplayServer::start(session.clone(), 10.0).await.unwrap();
+        let config = AimConfig {
+            base_url: server.url(),
+            token: "replay".to_string(),
+            ..AimConfig::default()
+        };
+        let feed = Arc::new(AimExplorer::new(config).unwrap());
+        // The fallback has no data, so every event comes from the feed
+        let fallback = Arc::new(OfflineExplorer::new(
+            std::env::temp_dir().join("aim_replay_none"),
+        ));
+        let stream = MarketStream::new(Some(feed), fallback).with_config(StreamConfig {
+            pause_when_closed: false,
+            ..StreamConfig::default()
+        });
+
+        let mut subscription = stream.subscribe(&["FPT"], &[Channel::Ticks, Channel::Quotes]);
+        let recorded = record_session(&mut subscription, Duration::from_millis(500)).await;
+        // Each channel has its own connection, so only the order within one holds
+        let of = |channel: Channel| -> Vec<StreamEvent> {
+            let events = recorded.iter().map(|r| r.event.clone());
+            events.filter(|event| event.channel() == channel).collect()
+        };
+        assert_eq!(
+            of(Channel::Quotes),
+            [&session[0], &session[3]].map(|r| r.event.clone())
+        );
+        assert_eq!(of(Channel::Ticks), [session[2].event.clone()]);
+
+        // A second subscription shares the connection and starts from the last quote
+        let mut quotes = stream.subscribe(&["FPT"], &[Channel::Quotes]);
+        assert_eq!(quotes.recv().await, Some(session[3].event.clone()));
+    }
+}
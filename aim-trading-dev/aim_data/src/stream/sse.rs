@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+use super::{Request, StreamEvent};
+use crate::error::AimDataError;
+use crate::explorer::aim::AimExplorer;
+use crate::source::SourceResult;
+
+/// Connect to the feed of `explorer` for `request`
+pub(super) async fn open(
+    explorer: &AimExplorer,
+    request: &Request,
+) -> SourceResult<reqwest::Response> {
+    let channels: Vec<&str> = request.channels.iter().map(|c| c.name()).collect();
+    let query = [
+        ("symbols", request.symbols.join(",")),
+        ("channels", channels.join(",")),
+    ];
+    explorer
+        .open_stream(&explorer.config().stream_endpoint, &query)
+        .await
+}
+
+/// Forward the events of `response` until it ends, stalls for
+/// `idle_timeout` or the subscription is dropped
+pub(super) async fn pump(
+    mut response: reqwest::Response,
+    request: &Request,
+    tx: &mpsc::Sender<StreamEvent>,
+    idle_timeout: Duration,
+) -> SourceResult<()> {
+    let endpoint = response.url().path().to_string();
+    let mut parser = SseParser::default();
+    loop {
+        let chunk = match tokio::time::timeout(idle_timeout, response.chunk()).await {
+            Ok(chunk) => chunk?,
+            Err(_) => return Err(AimDataError::empty(&endpoint)),
+        };
+        let Some(chunk) = chunk else {
+            return Ok(());
+        };
+        for (kind, data) in parser.push(&chunk) {
+            let event = match StreamEvent::decode(&kind, &data) {
+                Some(Ok(event)) => event,
+                Some(Err(e)) => return Err(AimDataError::decode(&endpoint, &data, e)),
+                // Keep-alive and events of newer servers
+                None => continue,
+            };
+            if request.wants(&event) && tx.send(event).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Splits a `text/event-stream` body into (event name, data) pairs
+#[derive(Debug, Default)]
+pub(super) struct SseParser {
+    buffer: String,
+    pending: Vec<u8>,
+    event: String,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    /// Feed the next bytes of the body, returns the events they complete
+    pub(super) fn push(&mut self, bytes: &[u8]) -> Vec<(String, String)> {
+        // A chunk may end inside a multi-byte character
+        self.pending.extend_from_slice(bytes);
+        let valid = match std::str::from_utf8(&self.pending) {
+            Ok(text) => text.len(),
+            Err(e) => e.valid_up_to(),
+        };
+        self.buffer
+            .push_str(std::str::from_utf8(&self.pending[..valid]).unwrap_or_default());
+        self.pending.drain(..valid);
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.find('\n') {
+            let line: String = self.buffer.drain(..=end).collect();
+            let line = line.trim_end_matches(['\n', '\r']);
+            if line.is_empty() {
+                if !self.data.is_empty() {
+                    let kind = std::mem::take(&mut self.event);
+                    let kind = if kind.is_empty() {
+                        "message".to_string()
+                    } else {
+                        kind
+                    };
+                    events.push((kind, self.data.join("\n")));
+                }
+                self.event.clear();
+                self.data.clear();
+                continue;
+            }
+            if line.starts_with(':') {
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = value.to_string(),
+                "data" => self.data.push(value.to_string()),
+                // `id` and `retry` are not used, reconnects resume from a poll
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parser_handles_split_chunks() {
+        let body = ": keep-alive\r\n\r\nevent: tick\ndata: {\"a\":\ndata: \"á\"}\n\nevent: bar\ndata: 1\n\n";
+        let bytes = body.as_bytes();
+        let mut parser = SseParser::default();
+        let mut events = Vec::new();
+        // One byte at a time, splitting the two-byte "á" as well
+        for byte in bytes {
+            events.extend(parser.push(std::slice::from_ref(byte)));
+        }
+        assert_eq!(
+            events,
+            [
+                ("tick".to_string(), "{\"a\":\n\"á\"}".to_string()),
+                ("bar".to_string(), "1".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::Exchange;
+use crate::error::AimDataError;
+use crate::explorer::aim::{self, IcbIndex, StockByGics};
+use crate::explorer::vci::listing::VCISymbol;
+use crate::file::write_atomic;
+use crate::source::{DataSource, SourceResult};
+
+/// Index baskets kept in the symbol master
+pub const TRACKED_INDICES: &[&str] = &["VN30", "VN100", "HNX30"];
+
+/// Age after which a snapshot is fetched again
+pub const SNAPSHOT_MAX_AGE: Duration = Duration::hours(24);
+
+/// A stock listed on one of the exchanges
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Listing {
+    pub symbol: String,
+    pub name: String,
+    /// VCI board code ("HSX", "HNX", "UPCOM")
+    pub board: String,
+    /// ICB industry, empty until the classification was loaded
+    #[serde(default)]
+    pub industry: String,
+    /// ICB sub-industry, the level sectors are grouped by
+    #[serde(default)]
+    pub sub_industry: String,
+}
+
+impl Listing {
+    pub fn exchange(&self) -> Option<Exchange> {
+        Exchange::from_board(&self.board)
+    }
+}
+
+/// ICB classification of one stock
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub symbol: String,
+    pub industry: String,
+    pub sub_industry: String,
+    /// Orders the stocks of a sector
+    pub market_cap: f64,
+}
+
+impl From<&StockByGics> for Classification {
+    fn from(stock: &StockByGics) -> Self {
+        Self {
+            symbol: stock.stock_code.trim().to_uppercase(),
+            industry: stock.industry_name.trim().to_string(),
+            sub_industry: stock.sub_industry_name.trim().to_string(),
+            market_cap: stock.vhtt,
+        }
+    }
+}
+
+/// ICB code and name of the indices, largest market cap first
+fn icb_ranking(icb: &[IcbIndex]) -> Vec<(String, String)> {
+    let mut icb: Vec<&IcbIndex> = icb.iter().collect();
+    icb.sort_by_key(|index| std::cmp::Reverse(index.market_cap));
+    icb.into_iter()
+        .map(|index| (index.icb_code.clone(), index.icb_name.trim().to_string()))
+        .collect()
+}
+
+/// Stocks of one ICB sub-industry, largest market cap first
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sector {
+    pub name: String,
+    pub industry: String,
+    /// Code of the matching ICB index, empty when the backend has none
+    pub icb_code: String,
+    pub symbols: Vec<String>,
+}
+
+/// Listings, ICB sectors and index constituents of the Vietnamese market
+///
+/// Built from the listing directory of a [`DataSource`] and the AIM
+/// `stock-by-gics` and `icb-index` endpoints, and kept on disk as a JSON
+/// snapshot so the app starts with the last known membership when offline.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SymbolMaster {
+    updated_at: Option<DateTime<Utc>>,
+    listings: BTreeMap<String, Listing>,
+    sectors: Vec<Sector>,
+    indices: BTreeMap<String, Vec<String>>,
+}
+
+impl SymbolMaster {
+    /// Master of the stocks in `listings`, classified by `classes`, with sectors
+    /// ordered like `icb` (ICB code and name, largest index first)
+    pub fn build(
+        listings: &[VCISymbol],
+        classes: &[Classification],
+        icb: &[(String, String)],
+        indices: BTreeMap<String, Vec<String>>,
+        updated_at: DateTime<Utc>,
+    ) -> Self {
+        let mut master = Self {
+            updated_at: Some(updated_at),
+            listings: listings
+                .iter()
+                .filter(|item| item.is_stock() && Exchange::from_board(&item.board).is_some())
+                .map(|item| {
+                    let listing = Listing {
+                        symbol: item.symbol.to_uppercase(),
+                        name: item.organ_name.clone(),
+                        board: item.board.to_uppercase(),
+                        industry: String::new(),
+                        sub_industry: String::new(),
+                    };
+                    (listing.symbol.clone(), listing)
+                })
+                .collect(),
+            sectors: Vec::new(),
+            indices,
+        };
+        master.classify(classes, icb);
+        master
+    }
+
+    /// Set the ICB sector of every listed stock from `classes`
+    fn classify(&mut self, classes: &[Classification], icb: &[(String, String)]) {
+        let mut classes: Vec<&Classification> = classes
+            .iter()
+            .filter(|class| !class.sub_industry.is_empty())
+            .collect();
+        classes.sort_by(|a, b| b.market_cap.total_cmp(&a.market_cap));
+
+        let mut sectors: Vec<Sector> = Vec::new();
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        for class in classes {
+            let Some(listing) = self.listings.get_mut(&class.symbol) else {
+                continue;
+            };
+            listing.industry = class.industry.clone();
+            listing.sub_industry = class.sub_industry.clone();
+            let index = *positions
+                .entry(class.sub_industry.as_str())
+                .or_insert_with(|| {
+                    sectors.push(Sector {
+                        name: class.sub_industry.clone(),
+                        industry: class.industry.clone(),
+                        icb_code: String::new(),
+                        symbols: Vec::new(),
+                    });
+                    sectors.len() - 1
+                });
+            sectors[index].symbols.push(listing.symbol.clone());
+        }
+
+        let rank = |name: &str| {
+            icb.iter()
+                .position(|(_, icb_name)| icb_name.to_lowercase() == name.to_lowercase())
+        };
+        for sector in &mut sectors {
+            if let Some(position) = rank(&sector.name) {
+                sector.icb_code = icb[position].0.clone();
+            }
+        }
+        // Sectors with an ICB index first, by its market cap, then by name
+        sectors.sort_by_cached_key(|sector| {
+            (
+                rank(&sector.name).unwrap_or(usize::MAX),
+                sector.name.clone(),
+            )
+        });
+        self.sectors = sectors;
+    }
+
+    /// Fetch a new master, keeping the classification and index baskets of
+    /// `self` for the parts the backends fail to serve
+    pub async fn refresh(&self, source: &dyn DataSource) -> SourceResult<Self> {
+        let listings = source.get_listings().await?;
+
+        let mut indices = BTreeMap::new();
+        for &index in TRACKED_INDICES {
+            let fetched = match source.get_index_constituents(index).await {
+                Ok(symbols) if !symbols.is_empty() => Some(symbols),
+                Ok(_) => {
+                    eprintln!("No constituents for index {index}, keeping the snapshot");
+                    None
+                }
+                Err(e) => {
+                    eprintln!("Failed to fetch constituents of {index}: {e}");
+                    None
+                }
+            };
+            if let Some(symbols) = fetched.or_else(|| self.indices.get(index).cloned()) {
+                indices.insert(index.to_string(), symbols);
+            }
+        }
+
+        let mut master = Self::build(&listings, &[], &[], indices, Utc::now());
+        match aim::fetch_stock_by_gics_data().await {
+            Ok(stocks) => {
+                let classes: Vec<Classification> = stocks.iter().map(Into::into).collect();
+                let icb = aim::fetch_icb_index_data().await.unwrap_or_else(|e| {
+                    eprintln!("Failed to fetch ICB indices: {e}");
+                    Vec::new()
+                });
+                master.classify(&classes, &icb_ranking(&icb));
+            }
+            Err(e) => {
+                eprintln!("Failed to fetch ICB classification, keeping the snapshot: {e}");
+                master.keep_sectors(self);
+            }
+        }
+        Ok(master)
+    }
+
+    /// Carry the sectors of `previous` over to the stocks still listed
+    fn keep_sectors(&mut self, previous: &SymbolMaster) {
+        for listing in self.listings.values_mut() {
+            if let Some(old) = previous.listings.get(&listing.symbol) {
+                listing.industry = old.industry.clone();
+                listing.sub_industry = old.sub_industry.clone();
+            }
+        }
+        self.sectors = previous
+            .sectors
+            .iter()
+            .map(|sector| Sector {
+                symbols: sector
+                    .symbols
+                    .iter()
+                    .filter(|symbol| self.listings.contains_key(*symbol))
+                    .cloned()
+                    .collect(),
+                ..sector.clone()
+            })
+            .filter(|sector| !sector.symbols.is_empty())
+            .collect();
+    }
+
+    /// Read a snapshot written by [`SymbolMaster::save`]
+    pub fn load(path: &Path) -> SourceResult<Self> {
+        let data = fs::read_to_string(path).map_err(|e| AimDataError::io(path, e))?;
+        serde_json::from_str(&data)
+            .map_err(|e| AimDataError::decode(&path.display().to_string(), &data, e))
+    }
+
+    /// Write the snapshot to `path`, replacing the previous one in one step
+    pub fn save(&self, path: &Path) -> SourceResult<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| AimDataError::io(dir, e))?;
+        }
+        let data = serde_json::to_vec(self)
+            .map_err(|e| AimDataError::decode(&path.display().to_string(), "", e))?;
+        write_atomic(path, &data).map_err(|e| AimDataError::io(path, e))
+    }
+
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.updated_at
+    }
+
+    /// Whether the snapshot was never fetched or is older than `max_age`
+    pub fn is_stale(&self, now: DateTime<Utc>, max_age: Duration) -> bool {
+        self.updated_at.is_none_or(|time| now - time > max_age)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.listings.is_empty()
+    }
+
+    pub fn contains(&self, symbol: &str) -> bool {
+        self.listings.contains_key(&symbol.to_uppercase())
+    }
+
+    pub fn listing(&self, symbol: &str) -> Option<&Listing> {
+        self.listings.get(&symbol.to_uppercase())
+    }
+
+    /// Every listed stock, in alphabetical order
+    pub fn symbols(&self) -> impl Iterator<Item = &str> {
+        self.listings.keys().map(String::as_str)
+    }
+
+    pub fn sectors(&self) -> &[Sector] {
+        &self.sectors
+    }
+
+    /// Constituents of `index`, empty when it is not tracked or not loaded
+    pub fn constituents(&self, index: &str) -> &[String] {
+        self.indices
+            .get(&index.to_uppercase())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+static SYMBOL_MASTER: OnceLock<RwLock<Arc<SymbolMaster>>> = OnceLock::new();
+
+fn symbol_master_slot() -> &'static RwLock<Arc<SymbolMaster>> {
+    SYMBOL_MASTER.get_or_init(|| RwLock::new(Arc::new(SymbolMaster::default())))
+}
+
+/// The symbol master shared by the app, empty until one is loaded
+pub fn symbol_master() -> Arc<SymbolMaster> {
+    symbol_master_slot()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+pub fn set_symbol_master(master: SymbolMaster) {
+    *symbol_master_slot()
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = Arc::new(master);
+}
+
+/// Load the snapshot at `path` if none is loaded yet, then fetch a new one
+/// through [`crate::data_source`] when it is older than `max_age`
+///
+/// Returns whether the shared master changed. A failed fetch keeps the
+/// snapshot, which stays stale so the next call tries again.
+pub async fn refresh_symbol_master(path: &Path, max_age: Duration) -> SourceResult<bool> {
+    let mut current = symbol_master();
+    let mut changed = false;
+    if current.is_empty() {
+        match SymbolMaster::load(path) {
+            Ok(snapshot) => {
+                set_symbol_master(snapshot);
+                current = symbol_master();
+                changed = true;
+            }
+            Err(e) if !e.is_not_found() => eprintln!("Failed to read symbol snapshot: {e}"),
+            Err(_) => {}
+        }
+    }
+    if !current.is_stale(Utc::now(), max_age) {
+        return Ok(changed);
+    }
+    let master = current.refresh(&*crate::data_source()).await?;
+    master.save(path)?;
+    set_symbol_master(master);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(symbol: &str, kind: &str, board: &str) -> VCISymbol {
+        VCISymbol {
+            symbol: symbol.to_string(),
+            kind: kind.to_string(),
+            board: board.to_string(),
+            organ_name: format!("Công ty {symbol}"),
+            ..Default::default()
+        }
+    }
+
+    fn class(symbol: &str, industry: &str, sub_industry: &str, market_cap: f64) -> Classification {
+        Classification {
+            symbol: symbol.to_string(),
+            industry: industry.to_string(),
+            sub_industry: sub_industry.to_string(),
+            market_cap,
+        }
+    }
+
+    #[test]
+    fn test_symbol_master_build_and_snapshot() {
+        let listings = [
+            listing("vcb", "STOCK", "HSX"),
+            listing("ACB", "STOCK", "HSX"),
+            listing("SHS", "STOCK", "HNX"),
+            listing("E1VFVN30", "ETF", "HSX"),
+            listing("HPG", "STOCK", "HSX"),
+        ];
+        let classes = [
+            class("ACB", "Tài chính", "Ngân hàng", 100.0),
+            class("VCB", "Tài chính", "Ngân hàng", 500.0),
+            class("SHS", "Tài chính", "Chứng khoán", 20.0),
+            class("XYZ", "Tài chính", "Chứng khoán", 10.0),
+            class("HPG", "Nguyên vật liệu", "Thép", 150.0),
+        ];
+        let icb = [
+            ("8350".to_string(), "Ngân hàng".to_string()),
+            ("8777".to_string(), "Chứng khoán".to_string()),
+        ];
+        let indices = BTreeMap::from([("VN30".to_string(), vec!["ACB".into(), "VCB".into()])]);
+        let now = Utc::now();
+        let master = SymbolMaster::build(&listings, &classes, &icb, indices, now);
+
+        assert!(master.contains("vcb") && !master.contains("E1VFVN30"));
+        assert_eq!(
+            master.symbols().collect::<Vec<_>>(),
+            ["ACB", "HPG", "SHS", "VCB"]
+        );
+        assert_eq!(
+            master.listing("HPG").unwrap().exchange(),
+            Some(Exchange::Hose)
+        );
+        assert_eq!(master.listing("SHS").unwrap().sub_industry, "Chứng khoán");
+        let sectors: Vec<(&str, &str, &[String])> = master
+            .sectors()
+            .iter()
+            .map(|s| (s.name.as_str(), s.icb_code.as_str(), s.symbols.as_slice()))
+            .collect();
+        assert_eq!(
+            sectors,
+            [
+                (
+                    "Ngân hàng",
+                    "8350",
+                    &["VCB".to_string(), "ACB".to_string()][..]
+                ),
+                ("Chứng khoán", "8777", &["SHS".to_string()][..]),
+                ("Thép", "", &["HPG".to_string()][..]),
+            ]
+        );
+        assert_eq!(master.constituents("vn30"), ["ACB", "VCB"]);
+        assert!(master.constituents("HNX30").is_empty());
+
+        assert!(!master.is_stale(now, SNAPSHOT_MAX_AGE));
+        assert!(master.is_stale(now + Duration::days(2), SNAPSHOT_MAX_AGE));
+        assert!(SymbolMaster::default().is_stale(now, SNAPSHOT_MAX_AGE));
+
+        // Sectors survive a refresh without classification, minus delisted stocks
+        let mut relisted = SymbolMaster::build(&listings[1..], &[], &[], BTreeMap::new(), now);
+        relisted.keep_sectors(&master);
+        assert_eq!(relisted.sectors()[0].symbols, ["ACB"]);
+        assert_eq!(relisted.listing("HPG").unwrap().industry, "Nguyên vật liệu");
+
+        let path = std::env::temp_dir()
+            .join(format!("aim_symbols_{}", std::process::id()))
+            .join("symbols.json");
+        master.save(&path).unwrap();
+        assert_eq!(SymbolMaster::load(&path).unwrap(), master);
+        fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
@@ -0,0 +1,538 @@
+//! Time & sales of one symbol, fetched incrementally
+//!
+//! VCI only serves the matched orders of the day newest first, up to a limit.
+//! [`fetch_since`] asks for a small page and widens it until it reaches the
+//! last trade already seen, so a steady tape costs a few hundred rows per poll
+//! instead of the whole day. [`Tape`] keeps the latest trades of a symbol in a
+//! bounded ring with running aggregates over the whole session, and
+//! [`TapeStream`] keeps one up to date from the market stream.
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::calendar::{calendar, from_vn_time};
+use crate::explorer::vci::{OrderList, VCIOderBook};
+use crate::source::{DataSource, SourceResult};
+use crate::stream::{Channel, MarketStream, StreamEvent, Tick};
+
+/// Most matched orders VCI returns for one request
+pub const MAX_PAGE: u32 = 30000;
+
+/// Delay before the first fetch of a [`TapeStream`] is tried again
+const SNAPSHOT_RETRY: Duration = Duration::from_secs(1);
+
+/// Side that crossed the spread, from the VCI `match_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Aggressor {
+    Buy,
+    Sell,
+    /// Auction matches (ATO/ATC) have no side
+    Unknown,
+}
+
+impl Aggressor {
+    pub fn from_match_type(match_type: &str) -> Self {
+        match match_type {
+            "b" => Aggressor::Buy,
+            "s" => Aggressor::Sell,
+            _ => Aggressor::Unknown,
+        }
+    }
+}
+
+/// One print of the tape
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub id: u64,
+    /// As given by VCI, time of day in Vietnam
+    pub time: String,
+    pub price: f64,
+    pub volume: i64,
+    pub side: Aggressor,
+    /// At least [`TapeConfig::large_print_multiple`] times the average print
+    pub large: bool,
+}
+
+impl Trade {
+    /// Instant of the print on the trading day `date`, `None` when the time
+    /// is not hh:mm:ss
+    pub fn timestamp(&self, date: NaiveDate) -> Option<DateTime<Utc>> {
+        let time = NaiveTime::parse_from_str(&self.time, "%H:%M:%S").ok()?;
+        Some(from_vn_time(date.and_time(time)))
+    }
+
+    pub fn to_order(&self) -> VCIOderBook {
+        VCIOderBook {
+            id: self.id,
+            timestamp: self.time.clone(),
+            price: self.price,
+            volume: self.volume,
+            match_type: match self.side {
+                Aggressor::Buy => "b".to_string(),
+                Aggressor::Sell => "s".to_string(),
+                Aggressor::Unknown => String::new(),
+            },
+        }
+    }
+}
+
+/// Running aggregates over every trade pushed to a tape
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TapeStats {
+    pub trades: usize,
+    pub volume: i64,
+    pub buy_volume: i64,
+    pub sell_volume: i64,
+    /// Sum of price × volume, in the unit of the prices
+    pub value: f64,
+    pub large_prints: usize,
+}
+
+impl TapeStats {
+    /// Volume weighted average price, `None` before the first trade
+    pub fn vwap(&self) -> Option<f64> {
+        (self.volume > 0).then(|| self.value / self.volume as f64)
+    }
+
+    /// Buy minus sell-initiated volume
+    pub fn delta(&self) -> i64 {
+        self.buy_volume - self.sell_volume
+    }
+
+    /// Average volume of a print, `None` before the first trade
+    pub fn mean_volume(&self) -> Option<f64> {
+        (self.trades > 0).then(|| self.volume as f64 / self.trades as f64)
+    }
+
+    fn add(&mut self, trade: &Trade) {
+        self.trades += 1;
+        self.volume += trade.volume;
+        self.value += trade.price * trade.volume as f64;
+        match trade.side {
+            Aggressor::Buy => self.buy_volume += trade.volume,
+            Aggressor::Sell => self.sell_volume += trade.volume,
+            Aggressor::Unknown => {}
+        }
+        if trade.large {
+            self.large_prints += 1;
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TapeConfig {
+    /// Trades kept in memory, the aggregates cover every trade
+    pub capacity: usize,
+    /// Rows asked first when fetching past the cursor
+    pub page: u32,
+    /// A print this many times the average so far is flagged large
+    pub large_print_multiple: f64,
+    /// Prints needed before the average is trusted
+    pub large_print_min_trades: usize,
+}
+
+impl Default for TapeConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 5000,
+            page: 200,
+            large_print_multiple: 10.0,
+            large_print_min_trades: 50,
+        }
+    }
+}
+
+/// Matched orders of `symbol` newer than `after`, newest first
+///
+/// Without a cursor a single page is asked. With one, the page is widened
+/// until it reaches the cursor or [`MAX_PAGE`], so no trade in between is
+/// skipped.
+pub async fn fetch_since(
+    source: &dyn DataSource,
+    symbol: &str,
+    after: Option<u64>,
+    page: u32,
+) -> SourceResult<OrderList> {
+    let mut limit = page.clamp(1, MAX_PAGE);
+    loop {
+        let mut orders = source.get_order_list(symbol, limit).await?;
+        let Some(after) = after else {
+            return Ok(orders);
+        };
+        let reached = orders.len() < limit as usize || orders.iter().any(|order| order.id <= after);
+        if reached || limit == MAX_PAGE {
+            orders.retain(|order| order.id > after);
+            return Ok(orders);
+        }
+        limit = limit.saturating_mul(4).min(MAX_PAGE);
+    }
+}
+
+/// Latest trades of one symbol, oldest first, with the session aggregates
+#[derive(Debug, Clone)]
+pub struct Tape {
+    symbol: String,
+    config: TapeConfig,
+    trades: VecDeque<Trade>,
+    cursor: Option<u64>,
+    stats: TapeStats,
+    /// Trading day of the trades kept, see [`TradingCalendar::session_date`]
+    ///
+    /// [`TradingCalendar::session_date`]: crate::calendar::TradingCalendar::session_date
+    date: Option<NaiveDate>,
+}
+
+impl Tape {
+    pub fn new(symbol: &str, config: TapeConfig) -> Self {
+        Self {
+            symbol: symbol.to_uppercase(),
+            config,
+            trades: VecDeque::new(),
+            cursor: None,
+            stats: TapeStats::default(),
+            date: None,
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Id of the newest trade seen
+    pub fn cursor(&self) -> Option<u64> {
+        self.cursor
+    }
+
+    pub fn stats(&self) -> &TapeStats {
+        &self.stats
+    }
+
+    /// Trading day the times of the trades belong to, `None` before any
+    /// was added
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.date
+    }
+
+    /// Trades kept, newest first
+    pub fn trades(&self) -> impl Iterator<Item = &Trade> {
+        self.trades.iter().rev()
+    }
+
+    /// Trades kept as the VCI order list, newest first
+    pub fn order_list(&self) -> OrderList {
+        self.trades().map(Trade::to_order).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.trades.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.trades.is_empty()
+    }
+
+    /// Add the orders past the cursor, in any order, returns the new trades
+    /// oldest first
+    ///
+    /// Once a new session opened the tape starts over, its trade ids
+    /// starting over as well.
+    pub fn push_orders(&mut self, orders: &[VCIOderBook]) -> Vec<Trade> {
+        let date = calendar().session_date(Utc::now());
+        if self.date != Some(date) {
+            if self.date.is_some() {
+                self.trades.clear();
+                self.cursor = None;
+                self.stats = TapeStats::default();
+            }
+            self.date = Some(date);
+        }
+        let mut fresh: Vec<&VCIOderBook> = orders
+            .iter()
+            .filter(|order| self.cursor.is_none_or(|cursor| order.id > cursor))
+            .collect();
+        fresh.sort_by_key(|order| order.id);
+        fresh.dedup_by_key(|order| order.id);
+
+        let mut added = Vec::with_capacity(fresh.len());
+        for order in fresh {
+            let large = self.stats.trades >= self.config.large_print_min_trades
+                && self.stats.mean_volume().is_some_and(|mean| {
+                    order.volume as f64 >= mean * self.config.large_print_multiple
+                });
+            let trade = Trade {
+                id: order.id,
+                time: order.timestamp.clone(),
+                price: order.price,
+                volume: order.volume,
+                side: Aggressor::from_match_type(&order.match_type),
+                large,
+            };
+            self.stats.add(&trade);
+            self.cursor = Some(trade.id);
+            self.trades.push_back(trade.clone());
+            added.push(trade);
+        }
+        while self.trades.len() > self.config.capacity {
+            self.trades.pop_front();
+        }
+        added
+    }
+
+    pub fn push_ticks(&mut self, ticks: &[Tick]) -> Vec<Trade> {
+        let orders: Vec<VCIOderBook> = ticks
+            .iter()
+            .filter(|tick| tick.symbol == self.symbol)
+            .map(Tick::to_order)
+            .collect();
+        self.push_orders(&orders)
+    }
+
+    /// Fetch and add the trades past the cursor, the whole day the first time
+    pub async fn refresh(&mut self, source: &dyn DataSource) -> SourceResult<Vec<Trade>> {
+        let page = match self.cursor {
+            Some(_) => self.config.page,
+            None => MAX_PAGE,
+        };
+        let orders = fetch_since(source, &self.symbol, self.cursor, page).await?;
+        Ok(self.push_orders(&orders))
+    }
+}
+
+/// New trades of a [`TapeStream`] and the aggregates once they were added
+#[derive(Debug, Clone, PartialEq)]
+pub struct TapeUpdate {
+    /// Oldest first
+    pub trades: Vec<Trade>,
+    pub stats: TapeStats,
+}
+
+/// A [`Tape`] fed by the ticks of a market stream, stopped when dropped
+///
+/// The first update holds the trades of the day so far. It is also a
+/// [`futures_core::Stream`] of updates.
+pub struct TapeStream {
+    tape: Arc<Mutex<Tape>>,
+    updates: mpsc::Receiver<TapeUpdate>,
+    task: JoinHandle<()>,
+}
+
+impl TapeStream {
+    /// Fetch the day from the fallback of `stream`, then follow its ticks
+    pub fn open(stream: &MarketStream, symbol: &str, config: TapeConfig) -> Self {
+        let tape = Arc::new(Mutex::new(Tape::new(symbol, config)));
+        let (tx, updates) = mpsc::channel(64);
+        let source = stream.fallback();
+        // Subscribe before the snapshot so no tick falls in between
+        let subscription = stream.subscribe(&[symbol], &[Channel::Ticks]);
+        let shared = Arc::clone(&tape);
+        let task = tokio::spawn(async move {
+            let mut subscription = subscription;
+            let mut snapshot = shared.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            let trades = loop {
+                match snapshot.refresh(&*source).await {
+                    Ok(trades) => break trades,
+                    Err(e) => eprintln!("Failed to fetch the tape of {}: {e}", snapshot.symbol),
+                }
+                tokio::time::sleep(SNAPSHOT_RETRY).await;
+            };
+            let update = TapeUpdate {
+                trades,
+                stats: snapshot.stats.clone(),
+            };
+            *shared.lock().unwrap_or_else(|e| e.into_inner()) = snapshot;
+            if tx.send(update).await.is_err() {
+                return;
+            }
+
+            while let Some(event) = subscription.recv().await {
+                let mut ticks = Vec::new();
+                for event in std::iter::once(event).chain(subscription.drain()) {
+                    if let StreamEvent::Tick(tick) = event {
+                        ticks.push(tick);
+                    }
+                }
+                let update = {
+                    let mut tape = shared.lock().unwrap_or_else(|e| e.into_inner());
+                    let trades = tape.push_ticks(&ticks);
+                    TapeUpdate {
+                        trades,
+                        stats: tape.stats.clone(),
+                    }
+                };
+                if !update.trades.is_empty() && tx.send(update).await.is_err() {
+                    return;
+                }
+            }
+        });
+        Self {
+            tape,
+            updates,
+            task,
+        }
+    }
+
+    /// Next update, `None` once the stream stopped
+    pub async fn recv(&mut self) -> Option<TapeUpdate> {
+        self.updates.recv().await
+    }
+
+    /// Every update already received, without waiting
+    pub fn drain(&mut self) -> Vec<TapeUpdate> {
+        let mut updates = Vec::new();
+        while let Ok(update) = self.updates.try_recv() {
+            updates.push(update);
+        }
+        updates
+    }
+
+    /// Copy of the tape as it is now
+    pub fn snapshot(&self) -> Tape {
+        self.tape.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Trading day of the tape as it is now, see [`Tape::date`]
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.tape.lock().unwrap_or_else(|e| e.into_inner()).date()
+    }
+}
+
+impl futures_core::Stream for TapeStream {
+    type Item = TapeUpdate;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<TapeUpdate>> {
+        self.updates.poll_recv(cx)
+    }
+}
+
+impl Drop for TapeStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::BoxFuture;
+    use crate::stream::StreamConfig;
+    use futures_core::Stream;
+
+    fn order(id: u64, volume: i64, match_type: &str) -> VCIOderBook {
+        VCIOderBook {
+            id,
+            timestamp: "09:15:00".to_string(),
+            price: 20.0 + id as f64,
+            volume,
+            match_type: match_type.to_string(),
+        }
+    }
+
+    /// Order book of `total` matches, recording the limits asked
+    struct Book {
+        total: Mutex<u64>,
+        limits: Mutex<Vec<u32>>,
+    }
+
+    impl DataSource for Book {
+        fn name(&self) -> &str {
+            "book"
+        }
+
+        fn get_order_list<'a>(
+            &'a self,
+            _symbol: &'a str,
+            limit: u32,
+        ) -> BoxFuture<'a, SourceResult<OrderList>> {
+            self.limits.lock().unwrap().push(limit);
+            let total = *self.total.lock().unwrap();
+            let orders = (1..=total)
+                .rev()
+                .take(limit as usize)
+                .map(|id| order(id, 100, if id % 2 == 0 { "b" } else { "s" }))
+                .collect();
+            Box::pin(async move { Ok(orders) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tape_fetches_past_cursor() {
+        let book = Book {
+            total: Mutex::new(30),
+            limits: Mutex::new(Vec::new()),
+        };
+        let config = TapeConfig {
+            capacity: 20,
+            page: 4,
+            large_print_min_trades: 10,
+            ..TapeConfig::default()
+        };
+        let mut tape = Tape::new("fpt", config);
+        assert_eq!(tape.refresh(&book).await.unwrap().len(), 30);
+        assert_eq!(tape.len(), 20);
+        assert_eq!(tape.cursor(), Some(30));
+        assert_eq!(tape.stats().trades, 30);
+        assert_eq!(tape.stats().delta(), 0);
+
+        // 10 new matches: pages of 4 then 16 reach the cursor
+        *book.total.lock().unwrap() = 40;
+        let added = tape.refresh(&book).await.unwrap();
+        let ids: Vec<u64> = added.iter().map(|trade| trade.id).collect();
+        assert_eq!(ids, (31..=40).collect::<Vec<_>>());
+        assert_eq!(*book.limits.lock().unwrap(), [MAX_PAGE, 4, 16]);
+        assert_eq!(tape.trades().next().map(|trade| trade.id), Some(40));
+        assert_eq!(tape.order_list().len(), 20);
+
+        // VWAP of prices 21..=60 at equal volume
+        let vwap = tape.stats().vwap().unwrap();
+        assert!((vwap - 40.5).abs() < 1e-9);
+
+        // Ticks already fetched are skipped, a block print is flagged
+        let tick = |id: u64, volume: i64| Tick {
+            symbol: "FPT".to_string(),
+            id,
+            time: "10:00:00".to_string(),
+            price: 50.0,
+            volume,
+            side: "b".to_string(),
+        };
+        let added = tape.push_ticks(&[tick(40, 100), tick(42, 5000), tick(41, 100)]);
+        let flags: Vec<(u64, bool)> = added.iter().map(|t| (t.id, t.large)).collect();
+        assert_eq!(flags, [(41, false), (42, true)]);
+        assert_eq!(tape.stats().large_prints, 1);
+        assert_eq!(tape.stats().buy_volume - tape.stats().sell_volume, 5100);
+    }
+
+    #[tokio::test]
+    async fn test_tape_stream_follows_ticks() {
+        let book = Arc::new(Book {
+            total: Mutex::new(3),
+            limits: Mutex::new(Vec::new()),
+        });
+        let stream = MarketStream::new(None, book.clone()).with_config(StreamConfig {
+            poll_interval: Duration::from_millis(5),
+            pause_when_closed: false,
+            ..StreamConfig::default()
+        });
+        let mut tape = TapeStream::open(&stream, "FPT", TapeConfig::default());
+
+        let first = tape.recv().await.unwrap();
+        assert_eq!(first.trades.len(), 3);
+        *book.total.lock().unwrap() = 5;
+        let mut ids = Vec::new();
+        while ids.len() < 2 {
+            let update = std::future::poll_fn(|cx| Pin::new(&mut tape).poll_next(cx))
+                .await
+                .unwrap();
+            ids.extend(update.trades.iter().map(|trade| trade.id));
+        }
+        assert_eq!(ids, [4, 5]);
+        assert_eq!(tape.snapshot().stats().trades, 5);
+    }
+}
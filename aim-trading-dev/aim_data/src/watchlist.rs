@@ -0,0 +1,461 @@
+use serde::{Deserialize, Serialize, Serializer};
+
+/// Name of the list the user starts with
+pub const DEFAULT_WATCHLIST: &str = "MY LIST";
+
+/// Order the tickers of a list are shown in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WatchlistSort {
+    /// The order the user put them in
+    #[default]
+    None,
+    /// Highest change first
+    HighPrice,
+    /// Lowest change first
+    LowPrice,
+    Alphabet,
+}
+
+/// Optional columns of a list, the symbol and price being always shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchlistColumns {
+    /// Company name
+    pub info: bool,
+    /// Change and change percent
+    pub change: bool,
+    /// Volume matched today
+    pub volume: bool,
+}
+
+impl Default for WatchlistColumns {
+    fn default() -> Self {
+        Self {
+            info: true,
+            change: true,
+            volume: false,
+        }
+    }
+}
+
+/// Edits offered by the list menu, in menu order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchlistAction {
+    Create,
+    Rename,
+    Delete,
+    MoveUp,
+    MoveDown,
+    Import,
+    Export,
+    ToggleInfo,
+    ToggleChange,
+    ToggleVolume,
+}
+
+impl WatchlistAction {
+    pub fn from_index(index: i32) -> Option<Self> {
+        match index {
+            0 => Some(WatchlistAction::Create),
+            1 => Some(WatchlistAction::Rename),
+            2 => Some(WatchlistAction::Delete),
+            3 => Some(WatchlistAction::MoveUp),
+            4 => Some(WatchlistAction::MoveDown),
+            5 => Some(WatchlistAction::Import),
+            6 => Some(WatchlistAction::Export),
+            7 => Some(WatchlistAction::ToggleInfo),
+            8 => Some(WatchlistAction::ToggleChange),
+            9 => Some(WatchlistAction::ToggleVolume),
+            _ => None,
+        }
+    }
+}
+
+/// A named list of tickers with its display preferences
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Watchlist {
+    pub name: String,
+    pub symbols: Vec<String>,
+    #[serde(default)]
+    pub columns: WatchlistColumns,
+    #[serde(default)]
+    pub sort: WatchlistSort,
+    /// Built from the listings on every start, such as VN30 and the ICB
+    /// sectors, and never saved
+    #[serde(skip)]
+    pub system: bool,
+}
+
+impl Watchlist {
+    /// List of `symbols`, upper-cased and without repeats
+    pub fn new<S: AsRef<str>>(name: &str, symbols: &[S]) -> Self {
+        let mut list = Self {
+            name: name.trim().to_string(),
+            symbols: Vec::new(),
+            columns: WatchlistColumns::default(),
+            sort: WatchlistSort::default(),
+            system: false,
+        };
+        for symbol in symbols {
+            list.add(symbol.as_ref());
+        }
+        list
+    }
+
+    /// Append `symbol`, returns whether it was not in the list yet
+    pub fn add(&mut self, symbol: &str) -> bool {
+        let symbol = symbol.trim().to_uppercase();
+        if symbol.is_empty() || self.symbols.contains(&symbol) {
+            return false;
+        }
+        self.symbols.push(symbol);
+        true
+    }
+
+    /// Drop `symbol`, returns whether it was in the list
+    pub fn remove(&mut self, symbol: &str) -> bool {
+        let len = self.symbols.len();
+        self.symbols
+            .retain(|listed| !listed.eq_ignore_ascii_case(symbol.trim()));
+        self.symbols.len() != len
+    }
+
+    /// One ticker per row under a `symbol` header
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("symbol\n");
+        for symbol in &self.symbols {
+            csv.push_str(symbol);
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// List of the first column of `csv`, skipping the header and blank rows
+    pub fn from_csv(name: &str, csv: &str) -> Self {
+        let symbols: Vec<&str> = csv
+            .lines()
+            .filter_map(|line| line.split([',', ';', '\t']).next())
+            .map(|field| field.trim().trim_matches('"'))
+            .filter(|field| {
+                !field.is_empty()
+                    && !field.eq_ignore_ascii_case("symbol")
+                    && field.chars().all(|c| c.is_ascii_alphanumeric())
+            })
+            .collect();
+        Self::new(name, &symbols)
+    }
+}
+
+/// Read lists exported as JSON, one list or several, or a CSV list named
+/// `name`
+pub fn import_watchlists(name: &str, text: &str) -> Option<Vec<Watchlist>> {
+    let trimmed = text.trim_start();
+    if trimmed.starts_with('[') {
+        serde_json::from_str(trimmed).ok()
+    } else if trimmed.starts_with('{') {
+        serde_json::from_str(trimmed).ok().map(|list| vec![list])
+    } else {
+        let list = Watchlist::from_csv(name, text);
+        (!list.symbols.is_empty()).then(|| vec![list])
+    }
+}
+
+/// The lists of the user, in the order they are shown, and the one open
+///
+/// Only the lists the user made are saved, system lists are set again with
+/// [`Watchlists::set_system_lists`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Watchlists {
+    #[serde(serialize_with = "serialize_user_lists")]
+    lists: Vec<Watchlist>,
+    active: String,
+}
+
+fn serialize_user_lists<S: Serializer>(
+    lists: &[Watchlist],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(lists.iter().filter(|list| !list.system))
+}
+
+impl Default for Watchlists {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl Watchlists {
+    /// Lists with the first one open, an empty default list if there are none
+    pub fn new(mut lists: Vec<Watchlist>) -> Self {
+        if lists.is_empty() {
+            lists.push(Watchlist::new::<&str>(DEFAULT_WATCHLIST, &[]));
+        }
+        let active = lists[0].name.clone();
+        Self { lists, active }
+    }
+
+    pub fn lists(&self) -> &[Watchlist] {
+        &self.lists
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Watchlist> {
+        self.lists.iter().find(|list| list.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Watchlist> {
+        self.lists.iter_mut().find(|list| list.name == name)
+    }
+
+    /// The first list of the user, the one rules on "MY LIST" watch
+    ///
+    /// System lists are skipped wherever they were moved.
+    pub fn primary(&self) -> &Watchlist {
+        self.lists
+            .iter()
+            .find(|list| !list.system)
+            .unwrap_or(&self.lists[0])
+    }
+
+    /// Position of the open list
+    pub fn active_index(&self) -> usize {
+        self.lists
+            .iter()
+            .position(|list| list.name == self.active)
+            .unwrap_or(0)
+    }
+
+    pub fn active(&self) -> &Watchlist {
+        &self.lists[self.active_index()]
+    }
+
+    pub fn active_mut(&mut self) -> &mut Watchlist {
+        let index = self.active_index();
+        &mut self.lists[index]
+    }
+
+    /// Open list `name`, returns whether it exists
+    pub fn select(&mut self, name: &str) -> bool {
+        if self.get(name).is_none() {
+            return false;
+        }
+        self.active = name.to_string();
+        true
+    }
+
+    /// Add an empty list and open it, returns whether `name` was free
+    pub fn create(&mut self, name: &str) -> bool {
+        let name = name.trim();
+        if !self.is_free(name) {
+            return false;
+        }
+        self.lists.push(Watchlist::new::<&str>(name, &[]));
+        self.active = name.to_string();
+        true
+    }
+
+    /// Add `list`, renamed `name (2)`, `name (3)`... if its name is taken,
+    /// returns the name it got
+    pub fn insert(&mut self, mut list: Watchlist) -> String {
+        if list.name.trim().is_empty() {
+            list.name = DEFAULT_WATCHLIST.to_string();
+        }
+        let base = list.name.trim().to_string();
+        let mut suffix = 2;
+        list.name = base.clone();
+        while !self.is_free(&list.name) {
+            list.name = format!("{base} ({suffix})");
+            suffix += 1;
+        }
+        let name = list.name.clone();
+        self.lists.push(list);
+        name
+    }
+
+    /// Replace the system lists with `system`, new ones going after the
+    /// last system list or the first list
+    ///
+    /// A list named like one of `system`, such as one saved by older
+    /// versions, is replaced where it stands, keeping its columns and sort.
+    pub fn set_system_lists(&mut self, system: Vec<Watchlist>) {
+        let mut system: Vec<Option<Watchlist>> = system
+            .into_iter()
+            .map(|mut list| {
+                list.system = true;
+                Some(list)
+            })
+            .collect();
+        let mut lists = Vec::with_capacity(self.lists.len() + system.len());
+        let mut at = None;
+        for old in std::mem::take(&mut self.lists) {
+            let same = system
+                .iter_mut()
+                .find_map(|new| new.take_if(|new| new.name.eq_ignore_ascii_case(&old.name)));
+            match same {
+                Some(mut list) => {
+                    list.columns = old.columns;
+                    list.sort = old.sort;
+                    lists.push(list);
+                    at = Some(lists.len());
+                }
+                // Gone from the listings
+                None if old.system => at = Some(lists.len()),
+                None => lists.push(old),
+            }
+        }
+        let at = at.unwrap_or(1).min(lists.len());
+        lists.splice(at..at, system.into_iter().flatten());
+        if lists.iter().all(|list| list.system) {
+            lists.insert(0, Watchlist::new::<&str>(DEFAULT_WATCHLIST, &[]));
+        }
+        self.lists = lists;
+    }
+
+    /// Give list `name` the name `new_name`, returns whether it was renamed
+    ///
+    /// System lists keep their names.
+    pub fn rename(&mut self, name: &str, new_name: &str) -> bool {
+        let new_name = new_name.trim();
+        if !self.is_free(new_name) {
+            return false;
+        }
+        let Some(list) = self.get_mut(name).filter(|list| !list.system) else {
+            return false;
+        };
+        list.name = new_name.to_string();
+        if self.active == name {
+            self.active = new_name.to_string();
+        }
+        true
+    }
+
+    /// Drop list `name`, system lists and the last list of the user being
+    /// kept, returns whether it was dropped
+    pub fn delete(&mut self, name: &str) -> bool {
+        let Some(index) = self.lists.iter().position(|list| list.name == name) else {
+            return false;
+        };
+        let user_lists = self.lists.iter().filter(|list| !list.system).count();
+        if self.lists[index].system || user_lists == 1 {
+            return false;
+        }
+        self.lists.remove(index);
+        if self.active == name {
+            self.active = self.lists[index.min(self.lists.len() - 1)].name.clone();
+        }
+        true
+    }
+
+    /// Move list `name` by `offset` places, returns whether it moved
+    pub fn move_by(&mut self, name: &str, offset: isize) -> bool {
+        let Some(index) = self.lists.iter().position(|list| list.name == name) else {
+            return false;
+        };
+        let target = index as isize + offset;
+        if target < 0 || target as usize >= self.lists.len() || offset == 0 {
+            return false;
+        }
+        let list = self.lists.remove(index);
+        self.lists.insert(target as usize, list);
+        true
+    }
+
+    /// Whether `name` can name a list, names being compared ignoring case
+    fn is_free(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        !name.is_empty()
+            && !self
+                .lists
+                .iter()
+                .any(|list| list.name.to_lowercase() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watchlist_edits_and_round_trips() {
+        let mut lists = Watchlists::new(vec![Watchlist::new(DEFAULT_WATCHLIST, &["fpt", "FPT"])]);
+        assert_eq!(lists.primary().symbols, ["FPT"]);
+        assert!(lists.create("Ngân hàng"));
+        assert!(!lists.create("NGÂN HÀNG"));
+        assert!(!lists.create(" "));
+        assert_eq!(lists.active().name, "Ngân hàng");
+        lists.active_mut().add("vcb");
+        lists.active_mut().add("ACB");
+        assert!(lists.active_mut().remove("vcb"));
+
+        assert!(lists.move_by("Ngân hàng", -1));
+        assert!(!lists.move_by("Ngân hàng", -1));
+        assert_eq!(lists.primary().symbols, ["ACB"]);
+        assert!(lists.rename("Ngân hàng", "Banks"));
+        assert!(!lists.rename("Banks", DEFAULT_WATCHLIST));
+        assert_eq!(lists.active_index(), 0);
+
+        let csv = lists.active().to_csv();
+        assert_eq!(csv, "symbol\nACB\n");
+        let imported = import_watchlists("Banks", "Symbol,Price\n\"TCB\",25\n\nmbb;1\n").unwrap();
+        assert_eq!(imported[0].symbols, ["TCB", "MBB"]);
+        assert_eq!(lists.insert(imported[0].clone()), "Banks (2)");
+        assert_eq!(import_watchlists("x", "symbol\n"), None);
+
+        lists.active_mut().columns.volume = true;
+        lists.active_mut().sort = WatchlistSort::Alphabet;
+        let json = serde_json::to_string(&lists).unwrap();
+        let read: Watchlists = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, lists);
+        let exported = serde_json::to_string(lists.lists()).unwrap();
+        assert_eq!(import_watchlists("", &exported).unwrap(), lists.lists());
+
+        assert!(lists.delete("Banks"));
+        assert_eq!(lists.active().name, DEFAULT_WATCHLIST);
+        assert!(lists.delete("Banks (2)"));
+        assert!(!lists.delete(DEFAULT_WATCHLIST));
+    }
+
+    #[test]
+    fn test_system_lists_are_rebuilt_not_saved() {
+        // Saved by an older version, VN30 among the lists of the user
+        let mut lists = Watchlists::new(vec![
+            Watchlist::new(DEFAULT_WATCHLIST, &["FPT"]),
+            Watchlist::new("VN30", &["ACB"]),
+            Watchlist::new("Banks", &["TCB"]),
+        ]);
+        lists.get_mut("VN30").unwrap().sort = WatchlistSort::Alphabet;
+        lists.set_system_lists(vec![
+            Watchlist::new("VN30", &["ACB", "VCB"]),
+            Watchlist::new("NGÂN HÀNG", &["VCB"]),
+        ]);
+        let names: Vec<&str> = lists
+            .lists()
+            .iter()
+            .map(|list| list.name.as_str())
+            .collect();
+        assert_eq!(names, [DEFAULT_WATCHLIST, "VN30", "NGÂN HÀNG", "Banks"]);
+        assert_eq!(lists.get("VN30").unwrap().symbols, ["ACB", "VCB"]);
+        assert_eq!(lists.get("VN30").unwrap().sort, WatchlistSort::Alphabet);
+        assert!(!lists.rename("VN30", "Rổ"));
+        assert!(!lists.delete("NGÂN HÀNG"));
+        assert!(!lists.create("vn30"));
+
+        // A rebalance reaches the basket, a sector gone from the listings goes
+        lists.select("VN30");
+        lists.set_system_lists(vec![Watchlist::new("VN30", &["ACB", "MBB"])]);
+        assert_eq!(lists.get("VN30").unwrap().symbols, ["ACB", "MBB"]);
+        assert!(lists.get("NGÂN HÀNG").is_none());
+
+        let json = serde_json::to_string(&lists).unwrap();
+        let mut read: Watchlists = serde_json::from_str(&json).unwrap();
+        let names: Vec<&str> = read.lists().iter().map(|list| list.name.as_str()).collect();
+        assert_eq!(names, [DEFAULT_WATCHLIST, "Banks"]);
+        read.set_system_lists(vec![Watchlist::new("VN30", &["ACB"])]);
+        assert_eq!(read.active().name, "VN30");
+        assert_eq!(read.lists()[1].name, "VN30");
+
+        // A system list moved to the top does not become "MY LIST"
+        assert!(read.move_by("VN30", -1));
+        assert_eq!(read.lists()[0].name, "VN30");
+        assert_eq!(read.primary().name, DEFAULT_WATCHLIST);
+        assert_eq!(read.primary().symbols, ["FPT"]);
+    }
+}
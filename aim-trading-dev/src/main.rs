@@ -7,14 +7,25 @@ mod tasks;
 use crate::{
     slint_generatedAppWindow::StockData as SlintStockData,
     tasks::{
-        sort_market_watch, sort_stocks, spawn_cache_storage_task, ChartMetaData, ALL_STOCK_LIST,
+        convert_to_alert_items, convert_to_alert_log_item, describe_data_error, edit_watchlist,
+        import_statement_file, listed_stocks, load_portfolio, load_watchlists, remove_if_unlisted,
+        save_portfolio, save_watchlists, sort_market_watch, spawn_cache_storage_task,
+        spawn_portfolio_task, spawn_symbol_master_task, to_watchlist_sort, update_portfolio_ui,
+        ChartMetaData,
     },
 };
 use aim_chart::Chart;
-use aim_data::{get_company_info, get_market_watch, get_quote};
-use dirs_next::cache_dir;
+use aim_data::symbols::SNAPSHOT_MAX_AGE;
+use aim_data::{
+    get_market_watch, refresh_symbol_master, symbol_master, AimConfig, AlertBook, AlertCondition,
+    CandleStore, FeedRule, OfflineExplorer, TimeFrame, WatchlistAction,
+};
+use dirs_next::{cache_dir, download_dir};
 use slint::{Model, SharedString, VecModel};
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 use tokio::sync::Mutex;
 
 // Import task functions
@@ -57,41 +68,64 @@ async fn main() {
     std::fs::create_dir_all(&app_cache_dir).unwrap();
     let cache_file: PathBuf = app_cache_dir.join("cache.bin");
     let user_list: PathBuf = app_cache_dir.join("user_list.json");
+    let watchlist_file: PathBuf = app_cache_dir.join("watchlists.json");
+    let alert_file: PathBuf = app_cache_dir.join("alerts.json");
+    let symbols_file: PathBuf = app_cache_dir.join("symbols.json");
+    let portfolio_file: PathBuf = app_cache_dir.join("portfolio.json");
+
+    // AIM backend endpoint and credentials come from the config file and AIM_* env vars
+    match AimConfig::load().and_then(aim_data::aim::configure) {
+        Ok(()) => {
+            if aim_data::aim::explorer().config().token.is_empty() {
+                log::error!(
+                    "No AIM backend token configured, set AIM_TOKEN or token in {:?}",
+                    AimConfig::default_path()
+                );
+            }
+        }
+        Err(e) => log::error!("Invalid AIM backend config, using defaults: {e}"),
+    }
 
-    // Fetch initial chart data for default stock (AAA)
-    let chart_data = get_quote(&["AAA"], "ONE_DAY", None, None).await.unwrap();
-    let company_info = get_company_info("AAA").await.unwrap();
-    let company_info = aim_chart::CompanyInfo {
-        roe: company_info.data.company_financial_ratio.ratio[0]
-            .roe
-            .unwrap_or(0.0),
-        roa: company_info.data.company_financial_ratio.ratio[0]
-            .roa
-            .unwrap_or(0.0),
-        pe: company_info.data.company_financial_ratio.ratio[0]
-            .pe
-            .unwrap_or(0.0),
-        pb: company_info.data.company_financial_ratio.ratio[0]
-            .pb
-            .unwrap_or(0.0),
-        eps: company_info.data.company_financial_ratio.ratio[0]
-            .eps
-            .unwrap_or(0.0),
-    };
+    // Replay recorded data instead of hitting the network when requested
+    if let Ok(offline_dir) = std::env::var("AIM_OFFLINE_DIR") {
+        log::info!("Using offline data source at {offline_dir}");
+        aim_data::set_data_source(Arc::new(OfflineExplorer::new(offline_dir)));
+    } else {
+        // Keep candles on disk, only the missing range is fetched from the source
+        aim_data::set_data_source(Arc::new(CandleStore::new(
+            app_cache_dir.join("candles"),
+            aim_data::data_source(),
+        )));
+    }
+
+    // Listings, ICB sectors and index baskets: read the snapshot, fetch again when stale
+    if let Err(e) = refresh_symbol_master(&symbols_file, SNAPSHOT_MAX_AGE).await {
+        log::error!("Failed to refresh symbol master, using the saved snapshot: {e}");
+    }
 
     // Create a thread-safe chart container with initial chart
+    let mut chart_status = String::new();
     let chart_metadata = if std::fs::metadata(&cache_file).is_ok() {
         ChartMetaData::load(&cache_file)
     } else {
-        ChartMetaData::new(vec![Chart::new_default(
-            "AAA".to_string(),
-            chart_data.0[0].clone(),
-            company_info,
-        )])
+        // Fetch initial chart data for default stock (AAA)
+        let time_frame = TimeFrame::default();
+        match Chart::from_source(&*aim_data::data_source(), "AAA", time_frame).await {
+            Ok(chart) => ChartMetaData::new(vec![chart]),
+            Err(e) => {
+                // Offline on a fresh install: start empty, the chart task fetches it later
+                log::error!("Failed to load chart data for AAA: {e}");
+                chart_status = format!("AAA {time_frame}: {}", describe_data_error(&e));
+                ChartMetaData::new(Vec::new())
+            }
+        }
     };
+    // Reopen the chart layout on the panel that was active
+    let active_panel = chart_metadata.layout().active_panel().clone();
+    let layout_kind = chart_metadata.layout().kind();
     let chart = Arc::new(Mutex::new(chart_metadata));
 
-    let init_data = match get_market_watch(&["AAA"]).await {
+    let init_data = match get_market_watch(&[active_panel.stock_name.as_str()]).await {
         Ok(stock_list_data) => {
             let stock_data: Vec<SlintStockData> = stock_list_data
                 .0
@@ -102,7 +136,10 @@ async fn main() {
         }
         Err(e) => {
             log::error!("Failed to fetch stock data: {e}.");
-            SlintStockData::default()
+            SlintStockData {
+                symbol: active_panel.stock_name.clone().into(),
+                ..Default::default()
+            }
         }
     };
 
@@ -113,74 +150,180 @@ async fn main() {
     task_manager::initialize_page_manager(&ui).await;
     log::info!("Page-aware task manager initialized");
 
-    let default_user_list = Arc::new(Mutex::new(
-        MY_STOCK_LIST
-            .iter()
-            .map(|s| s.to_string())
-            .collect::<Vec<String>>(),
-    ));
-    // Initialize the symbol list with initial values
-    let symbol_list = if std::fs::metadata(&user_list).is_ok() {
-        if let Ok(custom_list_data) = std::fs::read_to_string(&user_list) {
-            if let Ok(custom_list) = serde_json::from_str::<Vec<String>>(&custom_list_data) {
-                Arc::new(Mutex::new(custom_list))
-            } else {
-                log::error!("Failed to parse custom_list.json");
-                default_user_list.clone()
-            }
-        } else {
-            default_user_list.clone()
-        }
-    } else {
-        default_user_list
+    // Named watchlists, set up from the single list of older versions on first
+    // start, the system lists being built from the symbol master
+    let default_user_list: Vec<String> = MY_STOCK_LIST.iter().map(|s| s.to_string()).collect();
+    let watchlists = load_watchlists(
+        &watchlist_file,
+        &user_list,
+        &default_user_list,
+        &symbol_master(),
+    );
+    ui.set_watchlist_index(watchlists.active_index() as i32);
+    let symbol_list = Arc::new(Mutex::new(watchlists));
+
+    // Alerts and their log are kept next to the chart cache
+    let alert_book = match std::fs::read_to_string(&alert_file) {
+        Ok(json) => serde_json::from_str::<AlertBook>(&json).unwrap_or_else(|e| {
+            log::error!("Failed to parse alerts.json: {e}");
+            AlertBook::default()
+        }),
+        Err(_) => AlertBook::default(),
     };
+    ui.set_alerts(slint::ModelRc::new(VecModel::from(convert_to_alert_items(
+        &alert_book,
+    ))));
+    ui.set_alert_log(slint::ModelRc::new(VecModel::from(
+        alert_book
+            .log()
+            .iter()
+            .rev()
+            .map(convert_to_alert_log_item)
+            .collect::<Vec<_>>(),
+    )));
+    let alerts = Arc::new(Mutex::new(alert_book));
+
+    // Trades of the user, valued until the first mark at their cost
+    let portfolio = load_portfolio(&portfolio_file);
+    update_portfolio_ui(&ui.as_weak(), &portfolio);
+    let portfolio = Arc::new(Mutex::new(portfolio));
 
     ui.set_current_stock(init_data);
+    ui.set_chart_status(chart_status.into());
+    let mut ui_data = ui.get_ui_data();
+    ui_data.time_frame = active_panel.time_frame.to_string().into();
+    ui_data.layout = layout_kind.label().into();
+    ui.set_ui_data(ui_data);
 
     // Set up callback for adding symbols
     let symbol_list_clone = Arc::clone(&symbol_list);
+    let watchlist_file_clone = watchlist_file.clone();
     let ui_handle: slint::Weak<AppWindow> = ui.as_weak();
     ui.on_add_stock(move |group_name: SharedString, symbol: SharedString| {
-        if group_name == "MY LIST" {
-            let symbol = symbol.to_uppercase();
-            let symbol_list_clone = symbol_list_clone.clone();
-            ui_handle.unwrap().set_is_list_in_update(true);
-            let ui_handle = ui_handle.clone();
-            tokio::spawn(async move {
-                if !ALL_STOCK_LIST.contains(&symbol.as_str()) {
-                    log::error!("Failed to add stock: {symbol} - not found in ALL_STOCK_LIST");
-                } else {
-                    let mut list = symbol_list_clone.lock().await;
-                    if !list.contains(&symbol.to_string()) {
-                        list.push(symbol.to_string());
-                    } else {
-                        log::warn!("Stock {symbol} already exists in the list {list:?}");
+        let symbol = symbol.to_uppercase();
+        let symbol_list_clone = symbol_list_clone.clone();
+        let watchlist_file = watchlist_file_clone.clone();
+        ui_handle.unwrap().set_is_list_in_update(true);
+        let ui_handle = ui_handle.clone();
+        tokio::spawn(async move {
+            // Without the master or the listings, the symbol is taken as typed
+            // and checked once the master loads
+            let listed = listed_stocks().await;
+            if listed.as_ref().is_some_and(|master| !master.contains(&symbol)) {
+                log::error!("Failed to add stock: {symbol} - not a listed stock");
+            } else {
+                let mut lists = symbol_list_clone.lock().await;
+                // System lists follow the listings, they are not edited
+                let list = lists.get_mut(&group_name).filter(|list| !list.system);
+                match list.map(|list| list.add(&symbol)) {
+                    Some(true) => {
+                        save_watchlists(&watchlist_file, &lists);
+                        if listed.is_none() {
+                            tokio::spawn(remove_if_unlisted(
+                                Arc::clone(&symbol_list_clone),
+                                watchlist_file.clone(),
+                                group_name.to_string(),
+                                symbol.clone(),
+                            ));
+                        }
+                    }
+                    Some(false) => {
+                        log::warn!("Stock {symbol} already exists in the list {group_name}")
                     }
+                    None => log::error!("Failed to add stock: no own watchlist {group_name}"),
                 }
-                ui_handle.upgrade_in_event_loop(move |ui| {
-                    ui.set_is_list_in_update(false);
-                })
-            });
-        }
+            }
+            ui_handle.upgrade_in_event_loop(move |ui| {
+                ui.set_is_list_in_update(false);
+            })
+        });
     });
 
     // Set up callback for removing symbols
     let symbol_list_clone = Arc::clone(&symbol_list);
+    let watchlist_file_clone = watchlist_file.clone();
     let ui_handle: slint::Weak<AppWindow> = ui.as_weak();
     ui.on_remove_stock(move |group_name: SharedString, symbol: SharedString| {
-        if group_name == "MY LIST" {
-            let symbol = symbol.to_uppercase();
-            let symbol_list_clone = symbol_list_clone.clone();
-            ui_handle.unwrap().set_is_list_in_update(true);
-            let ui_handle = ui_handle.clone();
-            tokio::spawn(async move {
-                let mut list = symbol_list_clone.lock().await;
-                list.retain(|s| s != &symbol.to_string());
-                ui_handle.upgrade_in_event_loop(move |ui| {
-                    ui.set_is_list_in_update(false);
-                })
+        let symbol_list_clone = symbol_list_clone.clone();
+        let watchlist_file = watchlist_file_clone.clone();
+        ui_handle.unwrap().set_is_list_in_update(true);
+        let ui_handle = ui_handle.clone();
+        tokio::spawn(async move {
+            let mut lists = symbol_list_clone.lock().await;
+            if lists
+                .get_mut(&group_name)
+                .filter(|list| !list.system)
+                .is_some_and(|list| list.remove(&symbol))
+            {
+                save_watchlists(&watchlist_file, &lists);
+            }
+            ui_handle.upgrade_in_event_loop(move |ui| {
+                ui.set_is_list_in_update(false);
+            })
+        });
+    });
+
+    // Set up callbacks for adding and removing alerts
+    let alerts_clone = Arc::clone(&alerts);
+    let symbol_list_clone = Arc::clone(&symbol_list);
+    let ui_handle: slint::Weak<AppWindow> = ui.as_weak();
+    ui.on_add_alert(move |symbol: SharedString, condition: SharedString| {
+        let alerts_clone = alerts_clone.clone();
+        let symbol_list_clone = symbol_list_clone.clone();
+        let ui_handle = ui_handle.clone();
+        tokio::spawn(async move {
+            let watchlist = symbol_list_clone.lock().await.primary().symbols.clone();
+            let items = {
+                let mut book = alerts_clone.lock().await;
+                // Price conditions are set on the symbol, feed rules carry their scope
+                if let Some(condition) = AlertCondition::parse(&condition) {
+                    book.add(&symbol, condition);
+                } else if let Some(rule) = FeedRule::parse(&condition) {
+                    book.add_rule(rule, &watchlist);
+                } else {
+                    log::error!("Invalid alert condition: {condition}");
+                    return;
+                }
+                convert_to_alert_items(&book)
+            };
+            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                ui.set_alerts(slint::ModelRc::new(VecModel::from(items)));
             });
-        }
+        });
+    });
+
+    let alerts_clone = Arc::clone(&alerts);
+    let ui_handle: slint::Weak<AppWindow> = ui.as_weak();
+    ui.on_remove_alert(move |id: i32| {
+        let alerts_clone = alerts_clone.clone();
+        let ui_handle = ui_handle.clone();
+        tokio::spawn(async move {
+            let items = {
+                let mut book = alerts_clone.lock().await;
+                book.remove(id as u32);
+                convert_to_alert_items(&book)
+            };
+            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                ui.set_alerts(slint::ModelRc::new(VecModel::from(items)));
+            });
+        });
+    });
+
+    // Set up callback for importing a broker statement into the portfolio
+    let portfolio_clone = Arc::clone(&portfolio);
+    let portfolio_file_clone = portfolio_file.clone();
+    let ui_handle: slint::Weak<AppWindow> = ui.as_weak();
+    ui.on_import_statement(move |path: SharedString| {
+        let portfolio_clone = portfolio_clone.clone();
+        let portfolio_file = portfolio_file_clone.clone();
+        let ui_handle = ui_handle.clone();
+        tokio::spawn(async move {
+            let mut portfolio = portfolio_clone.lock().await;
+            if import_statement_file(&mut portfolio, Path::new(path.trim())) {
+                save_portfolio(&portfolio_file, &portfolio);
+                update_portfolio_ui(&ui_handle, &portfolio);
+            }
+        });
     });
 
     // Set up callback for toggling group expansion
@@ -190,41 +333,56 @@ async fn main() {
         // In a real implementation, you might want to store group expansion state
     });
 
-    // Set up callback for switching watchlists
-    let ui_handle_clone = ui.as_weak();
+    // Set up callback for switching watchlists, the open list is kept across restarts
+    let symbol_list_clone = Arc::clone(&symbol_list);
+    let watchlist_file_clone = watchlist_file.clone();
     ui.on_switch_list(move |list_name: slint::SharedString| {
         log::info!("Switching to watchlist: {list_name}");
-        let ui_handle = ui_handle_clone.clone();
-
-        // Trigger data update with new category filter
+        let symbol_list_clone = symbol_list_clone.clone();
+        let watchlist_file = watchlist_file_clone.clone();
         tokio::spawn(async move {
-            // You can implement specific logic here to filter stocks by category
-            // For now, we'll just log the switch
-            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
-                ui.set_is_list_in_update(true);
-                // In a real implementation, you would filter the data here
-                ui.set_is_list_in_update(false);
-            });
+            let mut lists = symbol_list_clone.lock().await;
+            if lists.select(&list_name) {
+                save_watchlists(&watchlist_file, &lists);
+            }
         });
     });
 
-    let ui_handle = ui.as_weak();
+    // Set up callback for sorting the open watchlist, the order is kept per list
+    let symbol_list_clone = Arc::clone(&symbol_list);
+    let watchlist_file_clone = watchlist_file.clone();
     ui.on_sort_stocks(move |sort_type| {
-        let ui_handle_clone = ui_handle.clone();
+        let symbol_list_clone = symbol_list_clone.clone();
+        let watchlist_file = watchlist_file_clone.clone();
         tokio::spawn(async move {
-            let _ = ui_handle_clone.upgrade_in_event_loop(move |ui| {
-                let stock_groups = ui.get_stock_groups();
-                let stock_groups_vec: Vec<_> = (0..stock_groups.row_count())
-                    .map(|i| stock_groups.row_data(i).unwrap())
-                    .collect();
-                let updated_groups = sort_stocks(&stock_groups_vec, sort_type);
-
-                // Update the UI with sorted groups
-                ui.set_stock_groups(slint::ModelRc::new(VecModel::from(updated_groups)));
-            });
+            let mut lists = symbol_list_clone.lock().await;
+            lists.active_mut().sort = to_watchlist_sort(sort_type);
+            save_watchlists(&watchlist_file, &lists);
         });
     });
 
+    // Set up callback for the watchlist menu: create, rename, delete, move,
+    // import, export and column toggles
+    let symbol_list_clone = Arc::clone(&symbol_list);
+    let watchlist_file_clone = watchlist_file.clone();
+    let export_dir = download_dir().unwrap_or_else(|| app_cache_dir.clone());
+    ui.on_edit_list(
+        move |action: i32, list_name: SharedString, value: SharedString| {
+            let Some(action) = WatchlistAction::from_index(action) else {
+                return;
+            };
+            let symbol_list_clone = symbol_list_clone.clone();
+            let watchlist_file = watchlist_file_clone.clone();
+            let export_dir = export_dir.clone();
+            tokio::spawn(async move {
+                let mut lists = symbol_list_clone.lock().await;
+                if edit_watchlist(&mut lists, action, &list_name, &value, &export_dir) {
+                    save_watchlists(&watchlist_file, &lists);
+                }
+            });
+        },
+    );
+
     let ui_handle_market_watch = ui.as_weak();
     ui.on_sort_market_watch(move |sort_column| {
         let ui_handle_clone = ui_handle_market_watch.clone();
@@ -320,11 +478,19 @@ async fn main() {
     let _ui_chart_handle = spawn_ui_chart_task(Arc::clone(&chart), &ui).await;
     // If you only want to read the chart data, you can pass a reference to the Arc<Mutex<ChartMetaData>>
     // Spawn cache storage task with task manager
-    let _cache_handle =
-        spawn_cache_storage_task(Arc::clone(&chart), Arc::clone(&symbol_list)).await;
+    let _cache_handle = spawn_cache_storage_task(Arc::clone(&chart), Arc::clone(&alerts)).await;
+    let _symbol_master_handle =
+        spawn_symbol_master_task(symbols_file, Arc::clone(&symbol_list)).await;
+    let _portfolio_handle = spawn_portfolio_task(&ui, Arc::clone(&portfolio), portfolio_file).await;
     let _stock_update_handles = spawn_stock_update_task(Arc::clone(&chart), &ui).await;
     let _chart_update_handle = spawn_chart_update_task(Arc::clone(&chart)).await;
-    let _data_update_handle = spawn_data_update_task(&ui, Arc::clone(&symbol_list)).await;
+    let _data_update_handle = spawn_data_update_task(
+        &ui,
+        Arc::clone(&symbol_list),
+        Arc::clone(&alerts),
+        Arc::clone(&chart),
+    )
+    .await;
     let _balance_sheet_handles = spawn_balance_sheet_task(&ui).await;
     let _company_profile_handles = spawn_company_profile_task(&ui).await;
     let _mini_vnindex_handle = spawn_mini_chart_vnindex_task(&ui).await;
@@ -357,20 +523,3 @@ async fn main() {
     // Run the UI main loop
     ui.run().unwrap();
 }
-
-/// Converts user-friendly interval strings to API interval constants
-///
-/// # Arguments
-/// * `interval` - The interval string (e.g., "1m", "5m", "1H", "1D")
-///
-/// # Returns
-/// The corresponding API interval constant
-fn interval_to_constant(interval: &str) -> &'static str {
-    match interval {
-        "1m" | "5m" | "15m" | "30m" => "ONE_MINUTE",
-        "1H" | "2H" | "4H" => "ONE_HOUR",
-        "1D" | "2D" | "3D" => "ONE_DAY",
-        "1W" | "2W" | "1M" => "ONE_WEEK",
-        _ => "ONE_DAY",
-    }
-}
@@ -1,8 +1,8 @@
-use std::fs::File;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
+use aim_data::{write_atomic, AlertBook};
 use dirs_next::cache_dir;
 use tokio::sync::Mutex;
 
@@ -11,9 +11,11 @@ use crate::tasks::ChartMetaData;
 
 /// Spawns a task to handle cache storage updates
 /// Returns a TaskHandle for controlling the task
+///
+/// Watchlists are saved by the edits themselves, see `save_watchlists`.
 pub async fn spawn_cache_storage_task(
     chart: Arc<Mutex<ChartMetaData>>,
-    custom_list: Arc<Mutex<Vec<String>>>,
+    alerts: Arc<Mutex<AlertBook>>,
 ) -> TaskHandle {
     let (tx, mut rx) = tokio::sync::mpsc::channel(10);
     // Register the task with the task manager
@@ -26,12 +28,12 @@ pub async fn spawn_cache_storage_task(
 
     tokio::spawn(async move {
         let mut pre_md5 = "".to_string();
-        let mut pre_custom_list = Vec::new();
+        let mut pre_alerts = String::new();
         let base_cache = cache_dir().expect("Could not find cache directory");
         let app_cache_dir = base_cache.join("Aim");
         std::fs::create_dir_all(&app_cache_dir).unwrap();
         let cache_file: PathBuf = app_cache_dir.join("cache.bin");
-        let user_list: PathBuf = app_cache_dir.join("user_list.json");
+        let alert_file: PathBuf = app_cache_dir.join("alerts.json");
         let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
         loop {
             if let Ok(status) = rx.try_recv() {
@@ -48,29 +50,26 @@ pub async fn spawn_cache_storage_task(
             let md5 = chart.lock().await.get_md5();
             if md5 != pre_md5 {
                 // If the MD5 hash has changed, update the cache
-                pre_md5 = md5;
-                let file = File::create(&cache_file).unwrap();
-                chart.lock().await.save(file);
+                if let Err(e) = chart.lock().await.save(&cache_file) {
+                    log::error!("Failed to write cache.bin: {e}");
+                } else {
+                    pre_md5 = md5;
+                }
             }
 
-            // check if the user list has changed
-            let custom_list_clone = custom_list.lock().await.clone();
-            if pre_custom_list.len() != custom_list_clone.len() {
-                // If the user list has changed, update the JSON file
-                pre_custom_list = custom_list_clone.clone();
-                // Save to user_list.json
-                match serde_json::to_string(&custom_list_clone) {
-                    Ok(json) => {
-                        if let Err(e) = std::fs::write(&user_list, json) {
-                            log::error!("Failed to write user_list.json: {e}");
-                        } else {
-                            log::info!("user_list.json updated");
-                        }
-                    }
-                    Err(e) => {
-                        log::error!("Failed to serialize user list to JSON: {e}");
+            // Save the alerts whenever one is added, removed or fires
+            match serde_json::to_string(&*alerts.lock().await) {
+                Ok(json) if json != pre_alerts => {
+                    if let Err(e) = write_atomic(&alert_file, json.as_bytes()) {
+                        log::error!("Failed to write alerts.json: {e}");
+                    } else {
+                        pre_alerts = json;
                     }
                 }
+                Ok(_) => {}
+                Err(e) => {
+                    log::error!("Failed to serialize alerts to JSON: {e}");
+                }
             }
             tokio::time::sleep(Duration::from_millis(100)).await; // Update every 100 milliseconds
         }
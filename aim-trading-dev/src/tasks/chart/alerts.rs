@@ -0,0 +1,62 @@
+use crate::slint_generatedAppWindow::{
+    AlertItem as SlintAlertItem, AlertLogItem as SlintAlertLogItem,
+};
+use crate::tasks::ChartMetaData;
+use aim_data::calendar::to_vn_time;
+use aim_data::explorer::vci::market_watch::VCIMarketWatch;
+use aim_data::{AlertBook, AlertEvent, Quote};
+use chrono::Utc;
+
+/// Quote of a market-watch row, prices in thousands like the charts
+pub fn convert_to_quote(market_watch: &VCIMarketWatch) -> Quote {
+    Quote {
+        symbol: market_watch.listing_info.symbol.clone(),
+        price: market_watch.match_price.match_price / 1000.0,
+        ref_price: market_watch.listing_info.ref_price / 1000.0,
+        ceiling: market_watch.listing_info.ceiling / 1000.0,
+        floor: market_watch.listing_info.floor / 1000.0,
+        volume: market_watch.match_price.accumulated_volume as f64,
+        time: Utc::now(),
+    }
+}
+
+/// Run the alerts of every polled row, returns the ones that fired
+pub fn evaluate_alerts(
+    book: &mut AlertBook,
+    charts: &ChartMetaData,
+    rows: &[VCIMarketWatch],
+) -> Vec<AlertEvent> {
+    let symbols = book.symbols();
+    rows.iter()
+        .filter(|row| symbols.contains(&row.listing_info.symbol))
+        .flat_map(|row| {
+            let quote = convert_to_quote(row);
+            let context = charts.alert_context(&quote.symbol, quote.time);
+            book.evaluate(&quote, &context)
+        })
+        .collect()
+}
+
+/// Rows of the alert list, feed rules after the price alerts under their scope
+pub fn convert_to_alert_items(book: &AlertBook) -> Vec<SlintAlertItem> {
+    let alerts = book.alerts().iter().map(|alert| SlintAlertItem {
+        id: alert.id as i32,
+        symbol: alert.symbol.clone().into(),
+        condition: alert.condition.to_string().into(),
+    });
+    let rules = book.rules().iter().map(|alert| SlintAlertItem {
+        id: alert.id as i32,
+        symbol: alert.rule.scope().to_string().into(),
+        condition: alert.rule.to_string().into(),
+    });
+    alerts.chain(rules).collect()
+}
+
+/// Row of the alert log, with the time of day in Vietnam
+pub fn convert_to_alert_log_item(event: &AlertEvent) -> SlintAlertLogItem {
+    SlintAlertLogItem {
+        time: to_vn_time(event.time).format("%H:%M:%S").to_string().into(),
+        symbol: event.symbol.clone().into(),
+        message: event.message.clone().into(),
+    }
+}
@@ -1,9 +1,16 @@
 use crate::tasks::task_manager::{register_task, TaskHandle};
 use crate::tasks::{chart::is_trading_hours, ChartMetaData};
+use aim_chart::ORDER_FLOW_CAPACITY;
 use aim_data::get_quote;
-use std::{sync::Arc, time::Duration};
+use aim_data::{market_stream, subscribe, Channel, Subscription, TapeConfig, TapeStream, Trade};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
+/// A chart is downloaded again at most this often, however often its bars change
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Spawns a task to handle real-time stock data updates
 pub async fn spawn_chart_update_task(chart: Arc<Mutex<ChartMetaData>>) -> TaskHandle {
     let chart_clone = Arc::clone(&chart);
@@ -16,8 +23,12 @@ pub async fn spawn_chart_update_task(chart: Arc<Mutex<ChartMetaData>>) -> TaskHa
     .await;
 
     tokio::spawn(async move {
+        let mut subscription: Option<Subscription> = None;
+        let mut subscribed: Vec<(String, &'static str)> = Vec::new();
+        let mut pending: BTreeSet<String> = BTreeSet::new();
+        let mut last_refresh: Option<Instant> = None;
+        let mut tapes: BTreeMap<String, TapeStream> = BTreeMap::new();
         let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
-        let mut stock_names: Vec<String> = vec!["AAA".to_string()];
         loop {
             if let Ok(status) = rx.try_recv() {
                 if task_status != status {
@@ -26,40 +37,149 @@ pub async fn spawn_chart_update_task(chart: Arc<Mutex<ChartMetaData>>) -> TaskHa
                 }
             }
             if task_status != crate::tasks::task_manager::TaskStatus::Running {
+                subscription = None;
+                subscribed.clear();
+                tapes.clear();
                 tokio::time::sleep(std::time::Duration::from_millis(100)).await;
                 continue;
             }
+            // The tape holds the whole session, so the order flow is fed after the close too
+            feed_order_flow(&chart_clone, &mut tapes).await;
             if !is_trading_hours() {
-                log::info!("Outside trading hours (9:00-15:00 Vietnam time), skipping data update");
+                log::info!("Market is not matching orders (break, closed or holiday), skipping data update");
                 tokio::time::sleep(Duration::from_millis(100)).await;
                 continue;
             }
-            // Fetch updated chart data for all tracked stocks
-            let stock_name_slices: Vec<&str> = stock_names.iter().map(|s| s.as_str()).collect();
-            if let Ok(chart_data_vec) = get_quote(&stock_name_slices, "ONE_DAY", None, None).await {
-                let mut charts = chart_clone.lock().await;
-                // Update list of tracked stocks
-                let new_stock_names: Vec<String> = charts
+            // Follow the bars of the charted stocks, refreshing all of them
+            // when a chart changes stock or time frame
+            let tracked: Vec<(String, &'static str)> = {
+                let charts = chart_clone.lock().await;
+                let mut tracked: Vec<(String, &'static str)> = charts
                     .data
                     .iter()
-                    .map(|chart| chart.stock_name.clone())
+                    .map(|chart| (chart.stock_name.clone(), chart.time_frame.source_interval()))
                     .collect();
-                stock_names = new_stock_names.clone();
-
-                // Update chart data for each tracked stock
-                for chart in charts.data.iter_mut() {
-                    if let Some(updated_data) = chart_data_vec
-                        .0
+                tracked.sort();
+                tracked.dedup();
+                tracked
+            };
+            if tracked != subscribed {
+                let mut symbols: Vec<&str> =
+                    tracked.iter().map(|(name, _)| name.as_str()).collect();
+                symbols.dedup();
+                subscription = Some(subscribe(&symbols, &[Channel::Bars]));
+                pending.extend(symbols.iter().map(|symbol| symbol.to_string()));
+                subscribed = tracked;
+            }
+            if let Some(subscription) = subscription.as_mut() {
+                pending.extend(
+                    subscription
+                        .drain()
                         .iter()
-                        .find(|data| data.symbol == chart.stock_name)
+                        .map(|event| event.symbol().to_string()),
+                );
+            }
+            if pending.is_empty()
+                || last_refresh.is_some_and(|at| at.elapsed() < MIN_REFRESH_INTERVAL)
+            {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+            last_refresh = Some(Instant::now());
+
+            // Group the updated stocks by the interval their time frame is resampled from
+            let mut stock_groups: BTreeMap<&'static str, Vec<String>> = BTreeMap::new();
+            {
+                let charts = chart_clone.lock().await;
+                for chart in charts
+                    .data
+                    .iter()
+                    .filter(|chart| pending.contains(&chart.stock_name))
+                {
+                    stock_groups
+                        .entry(chart.time_frame.source_interval())
+                        .or_default()
+                        .push(chart.stock_name.clone());
+                }
+            }
+            pending.clear();
+
+            // Fetch updated chart data for these stocks. Reads go through the
+            // candle store, so only bars after the last stored one are downloaded.
+            for (interval, stock_names) in stock_groups {
+                let stock_name_slices: Vec<&str> = stock_names.iter().map(|s| s.as_str()).collect();
+                if let Ok(chart_data_vec) =
+                    get_quote(&stock_name_slices, interval, None, None).await
+                {
+                    let mut charts = chart_clone.lock().await;
+
+                    // Update chart data for each tracked stock
+                    for chart in charts
+                        .data
+                        .iter_mut()
+                        .filter(|chart| chart.time_frame.source_interval() == interval)
                     {
-                        chart.update_candle_data(updated_data.clone());
+                        if let Some(updated_data) = chart_data_vec
+                            .0
+                            .iter()
+                            .find(|data| data.symbol == chart.stock_name)
+                        {
+                            chart.update_candle_data(updated_data.clone());
+                        }
                     }
                 }
             }
-            tokio::time::sleep(Duration::from_millis(5000)).await;
+            tokio::time::sleep(Duration::from_millis(100)).await;
         }
     });
 
     task_handle
 }
+
+/// Keep a tape of every stock charted with its order flow shown and hand the
+/// new prints to those charts
+async fn feed_order_flow(chart: &Mutex<ChartMetaData>, tapes: &mut BTreeMap<String, TapeStream>) {
+    let mut charts = chart.lock().await;
+    let wanted: BTreeSet<String> = charts
+        .data
+        .iter()
+        .filter(|chart| chart.order_flow.is_visible())
+        .map(|chart| chart.stock_name.clone())
+        .collect();
+    tapes.retain(|symbol, _| wanted.contains(symbol));
+    for symbol in wanted {
+        tapes.entry(symbol).or_insert_with_key(|symbol| {
+            log::info!("Following the tape of {symbol} for its order flow");
+            let config = TapeConfig {
+                capacity: ORDER_FLOW_CAPACITY,
+                ..Default::default()
+            };
+            TapeStream::open(&market_stream(), symbol, config)
+        });
+    }
+
+    for (symbol, tape) in tapes.iter_mut() {
+        let trades: Vec<Trade> = tape
+            .drain()
+            .into_iter()
+            .flat_map(|update| update.trades)
+            .collect();
+        // Times on the tape are times of day of its session
+        let Some(date) = tape.date() else {
+            continue;
+        };
+        for chart in charts
+            .data
+            .iter_mut()
+            .filter(|chart| &chart.stock_name == symbol && chart.order_flow.is_visible())
+        {
+            // A chart that just turned its order flow on starts from the whole tape
+            if chart.order_flow.is_empty() {
+                let snapshot: Vec<Trade> = tape.snapshot().trades().cloned().collect();
+                chart.push_trades(&snapshot, date);
+            } else if !trades.is_empty() {
+                chart.push_trades(&trades, date);
+            }
+        }
+    }
+}
@@ -1,63 +1,78 @@
 use super::convert_to_market_data;
 use super::convert_to_stock_data;
 use crate::slint_generatedAppWindow::{
+    AlertLogItem as SlintAlertLogItem, DomLevel as SlintDomLevel,
     MarketWatchData as SlintMarketWatchData, OrderList as SlintOrderList,
     StockData as SlintStockData,
 };
-use crate::tasks::chart::create_sector_groups;
+use crate::tasks::chart::create_watchlist_groups;
 use crate::tasks::chart::is_trading_hours;
 use crate::tasks::chart::sort_market_watch;
-use crate::tasks::chart::ALL_STOCK_LIST;
 use crate::tasks::task_manager::TaskStatus;
 use crate::tasks::task_manager::{register_task, TaskHandle};
-use crate::tasks::DataUpdate;
-use aim_data::explorer::vci::VCIOderBook;
-use aim_data::get_market_watch;
-use aim_data::get_order_list;
-use chrono::Timelike;
+use crate::tasks::{listed_stocks, ChartMetaData, DataUpdate};
+use aim_chart::depth::{depth_chart_render, dom_ladder};
+use aim_data::aim::{fetch_abnormal_trade_data, fetch_insider_transactions_data};
+use aim_data::explorer::vci::market_watch::VCIMarketWatch;
+use aim_data::symbol_master;
+use aim_data::{
+    subscribe, watch_tape, AlertBook, Channel, DepthHistory, OrderBook, StreamEvent, Subscription,
+    TapeStream, Watchlists, ALERT_LOG_CAPACITY,
+};
+use chrono::{Timelike, Utc};
 use slint::Weak;
-use slint::{ComponentHandle, ModelRc};
+use slint::{ComponentHandle, Model, ModelRc};
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio::sync::Mutex;
 
-use super::VN30_LIST;
-
 /// Spawns a task to handle market watch data updates
 pub async fn spawn_data_update_task(
     ui: &crate::slint_generatedAppWindow::AppWindow,
-    custom_list: Arc<Mutex<Vec<String>>>,
+    watchlists: Arc<Mutex<Watchlists>>,
+    alerts: Arc<Mutex<AlertBook>>,
+    chart: Arc<Mutex<ChartMetaData>>,
 ) -> Vec<TaskHandle> {
     let mut handles = Vec::new();
     let (tx_data_update, rx_data_update) = mpsc::channel::<DataUpdate>(10);
     let ui_handle = ui.as_weak();
 
     // Create individual task handles for each sub-task
-    handles.push(spawn_custom_list_polling_task(tx_data_update.clone(), custom_list).await);
+    handles.push(
+        spawn_feed_alert_polling_task(
+            tx_data_update.clone(),
+            Arc::clone(&watchlists),
+            Arc::clone(&alerts),
+        )
+        .await,
+    );
+    handles.push(spawn_watchlist_polling_task(tx_data_update.clone(), watchlists).await);
     handles.push(spawn_stock_data_polling_task(tx_data_update.clone()).await);
-    handles.push(spawn_market_watch_polling_task(tx_data_update.clone()).await);
+    handles.push(spawn_market_watch_polling_task(tx_data_update.clone(), alerts, chart).await);
     handles.push(spawn_order_list_polling_task(tx_data_update.clone(), ui_handle.clone()).await);
+    handles.push(spawn_order_book_polling_task(tx_data_update.clone(), ui_handle.clone()).await);
     handles.push(spawn_ui_update_task(ui_handle, rx_data_update).await);
 
     handles
 }
 
 // Individual task spawning functions with task manager integration
-async fn spawn_custom_list_polling_task(
+async fn spawn_watchlist_polling_task(
     tx_data: mpsc::Sender<DataUpdate>,
-    custom_list: Arc<Mutex<Vec<String>>>,
+    watchlists: Arc<Mutex<Watchlists>>,
 ) -> TaskHandle {
     let (tx, rx) = tokio::sync::mpsc::channel(10);
     let task_handle = register_task(
-        "chart.data_update.custom_list".to_string(),
+        "chart.data_update.watchlists".to_string(),
         tx,
-        "Custom List Polling Task".to_string(),
+        "Watchlist Polling Task".to_string(),
     )
     .await;
 
     tokio::spawn(async move {
-        polling_custom_list(tx_data, rx, custom_list).await;
+        polling_watchlists(tx_data, rx, watchlists).await;
     });
 
     task_handle
@@ -79,7 +94,11 @@ async fn spawn_stock_data_polling_task(tx: mpsc::Sender<DataUpdate>) -> TaskHand
     task_handle
 }
 
-async fn spawn_market_watch_polling_task(tx: mpsc::Sender<DataUpdate>) -> TaskHandle {
+async fn spawn_market_watch_polling_task(
+    tx: mpsc::Sender<DataUpdate>,
+    alerts: Arc<Mutex<AlertBook>>,
+    chart: Arc<Mutex<ChartMetaData>>,
+) -> TaskHandle {
     let (tx_status, rx) = tokio::sync::mpsc::channel(10);
     let task_handle = register_task(
         "chart.data_update.market_watch".to_string(),
@@ -89,7 +108,27 @@ async fn spawn_market_watch_polling_task(tx: mpsc::Sender<DataUpdate>) -> TaskHa
     .await;
 
     tokio::spawn(async move {
-        polling_market_watch(tx, rx).await;
+        polling_market_watch(tx, rx, alerts, chart).await;
+    });
+
+    task_handle
+}
+
+async fn spawn_feed_alert_polling_task(
+    tx: mpsc::Sender<DataUpdate>,
+    watchlists: Arc<Mutex<Watchlists>>,
+    alerts: Arc<Mutex<AlertBook>>,
+) -> TaskHandle {
+    let (tx_status, rx) = tokio::sync::mpsc::channel(10);
+    let task_handle = register_task(
+        "chart.data_update.feed_alert".to_string(),
+        tx_status,
+        "Feed Alert Polling Task".to_string(),
+    )
+    .await;
+
+    tokio::spawn(async move {
+        polling_feed_alerts(tx, rx, watchlists, alerts).await;
     });
 
     task_handle
@@ -114,6 +153,25 @@ async fn spawn_order_list_polling_task(
     task_handle
 }
 
+async fn spawn_order_book_polling_task(
+    tx: mpsc::Sender<DataUpdate>,
+    ui_handle: Weak<crate::slint_generatedAppWindow::AppWindow>,
+) -> TaskHandle {
+    let (tx_task, rx) = tokio::sync::mpsc::channel(10);
+    let task_handle = register_task(
+        "chart.data_update.order_book".to_string(),
+        tx_task,
+        "Order Book Polling Task".to_string(),
+    )
+    .await;
+
+    tokio::spawn(async move {
+        polling_order_book(tx, rx, ui_handle).await;
+    });
+
+    task_handle
+}
+
 async fn spawn_ui_update_task(
     ui_handle: Weak<crate::slint_generatedAppWindow::AppWindow>,
     rx: mpsc::Receiver<DataUpdate>,
@@ -133,17 +191,17 @@ async fn spawn_ui_update_task(
     task_handle
 }
 
-async fn polling_custom_list(
+async fn polling_watchlists(
     tx: mpsc::Sender<DataUpdate>,
     mut rx: mpsc::Receiver<TaskStatus>,
-    custom_list: Arc<Mutex<Vec<String>>>,
+    watchlists: Arc<Mutex<Watchlists>>,
 ) {
-    let mut previous_custom_list: Option<Vec<String>> = None;
+    let mut previous_watchlists: Option<Watchlists> = None;
     let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
     loop {
         if let Ok(status) = rx.try_recv() {
             if task_status != status {
-                log::info!("Cache storage task status changed to: {:?}", status);
+                log::info!("Watchlist task status changed to: {:?}", status);
                 task_status = status;
             }
         }
@@ -151,45 +209,47 @@ async fn polling_custom_list(
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
             continue;
         }
-        let custom_symbols = custom_list.lock().await;
-        let custom_symbol_slices: Vec<String> = custom_symbols.iter().cloned().collect();
-        drop(custom_symbols);
-        // Check if custom list changed (this should always be checked regardless of trading hours)
-        let custom_list_changed = {
-            if let Some(ref previous) = previous_custom_list {
-                // Compare current custom symbols with previous ones
-                // Check if length is different or if any symbol is missing
-                previous.len() != custom_symbol_slices.len()
-                    || !previous
-                        .iter()
-                        .all(|symbol| custom_symbol_slices.contains(symbol))
-                    || !custom_symbol_slices
-                        .iter()
-                        .all(|symbol| previous.contains(symbol))
-            } else {
-                // First time - consider it changed if we have any custom symbols
-                !custom_symbol_slices.is_empty()
-            }
-        };
-
-        if custom_list_changed {
-            previous_custom_list = Some(custom_symbol_slices.clone());
-            tx.send(DataUpdate::CustomList(custom_symbol_slices))
-                .await
-                .ok();
+        // Any edit, including renames, moves and preferences, regroups the list
+        // (this should always be checked regardless of trading hours)
+        let lists = watchlists.lock().await.clone();
+        if previous_watchlists.as_ref() != Some(&lists) {
+            previous_watchlists = Some(lists.clone());
+            tx.send(DataUpdate::Watchlists(lists)).await.ok();
         }
 
         tokio::time::sleep(Duration::from_millis(50)).await;
     }
 }
 
+/// Prices shown on the depth-of-market ladder
+const DOM_LADDER_ROWS: usize = 14;
+/// Pixels of the depth chart, drawn under the ladder
+const DEPTH_CHART_SIZE: (u32, u32) = (300, 120);
+
+/// Rows the UI is refreshed with at most this often, the feed pushes every change
+const QUOTE_REFRESH_INTERVAL: Duration = Duration::from_millis(500);
+/// Wait before fetching the listings again, while there is no symbol master
+const LISTINGS_RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Quote rows among the pushed `events`
+fn pushed_quotes(events: Vec<StreamEvent>) -> Vec<VCIMarketWatch> {
+    events
+        .into_iter()
+        .filter_map(|event| match event {
+            StreamEvent::Quote(row) => Some(*row),
+            _ => None,
+        })
+        .collect()
+}
+
 async fn polling_order_list(
     tx: mpsc::Sender<DataUpdate>,
     mut rx: mpsc::Receiver<TaskStatus>,
     ui_handle: Weak<crate::slint_generatedAppWindow::AppWindow>,
 ) {
     let current_stock = Arc::new(Mutex::new(String::from("AAA")));
-    let mut previous_order_list: Option<Vec<VCIOderBook>> = None;
+    let mut tape: Option<TapeStream> = None;
+    let mut subscribed = String::new();
     let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
     loop {
         if let Ok(status) = rx.try_recv() {
@@ -199,6 +259,9 @@ async fn polling_order_list(
             }
         }
         if task_status != crate::tasks::task_manager::TaskStatus::Running {
+            // Stop the feed while paused, the tape is fetched again on resume
+            tape = None;
+            subscribed.clear();
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
             continue;
         }
@@ -220,33 +283,89 @@ async fn polling_order_list(
             });
         });
 
-        log::info!("Polling order list for stock: {current_stock_str}");
-        let (order_changed, order_list) = match get_order_list(&current_stock_str).await {
-            Ok(order_list) => {
-                if let Some(previous) = &previous_order_list {
-                    if previous.is_empty()
-                        || order_list.is_empty()
-                        || previous[0].id != order_list[0].id
-                    {
-                        previous_order_list = Some(order_list.clone());
-                        (true, order_list)
-                    } else {
-                        (false, previous.clone())
-                    }
-                } else {
-                    previous_order_list = Some(order_list.clone());
-                    (true, order_list)
-                }
+        // The tape starts with the day's trades, then follows the pushed ticks
+        if current_stock_str != subscribed {
+            log::info!("Following the trade tape of stock: {current_stock_str}");
+            tape = Some(watch_tape(&current_stock_str));
+            subscribed = current_stock_str;
+        }
+
+        if let Some(tape) = tape.as_mut() {
+            if let Some(update) = tape.drain().pop() {
+                let stats = update.stats;
+                log::debug!(
+                    "{subscribed}: {} trades, VWAP {:?}, delta {}, {} large prints",
+                    stats.trades,
+                    stats.vwap(),
+                    stats.delta(),
+                    stats.large_prints
+                );
+                let order_list = tape.snapshot().order_list();
+                tx.send(DataUpdate::OrdList(order_list)).await.ok();
             }
-            Err(e) => {
-                log::error!("Failed to fetch order list: {e}. try again ...");
-                continue;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+async fn polling_order_book(
+    tx: mpsc::Sender<DataUpdate>,
+    mut rx: mpsc::Receiver<TaskStatus>,
+    ui_handle: Weak<crate::slint_generatedAppWindow::AppWindow>,
+) {
+    let current_stock = Arc::new(Mutex::new(String::from("AAA")));
+    let mut subscription: Option<Subscription> = None;
+    let mut subscribed = String::new();
+    let mut history = DepthHistory::default();
+    let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
+    loop {
+        if let Ok(status) = rx.try_recv() {
+            if task_status != status {
+                log::info!("Order book task status changed to: {:?}", status);
+                task_status = status;
             }
+        }
+        if task_status != crate::tasks::task_manager::TaskStatus::Running {
+            subscription = None;
+            subscribed.clear();
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            continue;
+        }
+        let current_stock_str = {
+            let current = current_stock.lock().await;
+            current.clone()
         };
 
-        if order_changed {
-            previous_order_list = Some(order_list.clone());
-            tx.send(DataUpdate::OrdList(order_list)).await.ok();
+        let current_stock_clone = Arc::clone(&current_stock);
+        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+            let symbol = ui.get_current_stock().symbol.to_string();
+            tokio::spawn(async move {
+                *current_stock_clone.lock().await = symbol;
+            });
+        });
+
+        if current_stock_str != subscribed {
+            log::info!("Subscribing to the depth of stock: {current_stock_str}");
+            subscription = Some(subscribe(&[&current_stock_str], &[Channel::Quotes]));
+            subscribed = current_stock_str;
+        }
+
+        // Every pushed row is a snapshot, the UI gets the last one with
+        // everything that moved since the previous update
+        let rows = match subscription.as_mut() {
+            Some(subscription) => pushed_quotes(subscription.drain()),
+            None => Vec::new(),
+        };
+        let mut latest = None;
+        let mut changes = Vec::new();
+        for row in &rows {
+            let book = OrderBook::from_market_watch(row, Utc::now());
+            changes.extend(history.push(book.clone()));
+            latest = Some(book);
+        }
+        if let Some(book) = latest.filter(|_| !changes.is_empty()) {
+            tx.send(DataUpdate::OrderBook(book, changes)).await.ok();
         }
 
         tokio::time::sleep(Duration::from_millis(100)).await;
@@ -255,7 +374,12 @@ async fn polling_order_list(
 
 async fn polling_all_stock_data(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Receiver<TaskStatus>) {
     let mut previous_stock_data: Option<Vec<SlintStockData>> = None;
-    let mut is_first_update = true; // Track if this is the first update
+    let mut subscription: Option<Subscription> = None;
+    let mut subscribed_at = None;
+    let mut failed_polls = 0;
+    let mut rows: HashMap<String, VCIMarketWatch> = HashMap::new();
+    let mut last_sent: Option<Instant> = None;
+    let mut listed = None;
     let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
     loop {
         if let Ok(status) = rx.try_recv() {
@@ -265,31 +389,55 @@ async fn polling_all_stock_data(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Rece
             }
         }
         if task_status != crate::tasks::task_manager::TaskStatus::Running {
+            subscription = None;
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
             continue;
         }
-        // Check if we're in trading hours (9:00 AM - 3:00 PM Vietnam time)
-        let is_trading_hours = is_trading_hours();
 
-        // If not in trading hours and we've already done the first update,
-        // only continue if custom list changed, otherwise skip data fetching
-        if !is_trading_hours && !is_first_update {
-            log::info!("Outside trading hours (9:00-15:00 Vietnam time), skipping data update");
+        // Quotes of every stock of the symbol master, the feed pauses itself
+        // while no exchange is matching orders
+        let mut master = symbol_master();
+        if master.is_empty() {
+            // No snapshot saved yet: the listings stand in until the first refresh
+            if listed.is_none() {
+                listed = listed_stocks().await;
+            }
+            let Some(stocks) = listed.clone() else {
+                log::warn!("Symbol master not loaded yet, waiting for listings");
+                tokio::time::sleep(LISTINGS_RETRY_INTERVAL).await;
+                continue;
+            };
+            master = stocks;
+        }
+        let all_symbols: Vec<&str> = master.symbols().collect();
+        if subscription.is_none() || subscribed_at != master.updated_at() {
+            log::info!("Subscribing to the quotes of {} stocks", all_symbols.len());
+            subscription = Some(subscribe(&all_symbols, &[Channel::Quotes]));
+            subscribed_at = master.updated_at();
+            failed_polls = 0;
+        }
+        if let Some(subscription) = subscription.as_mut() {
+            for row in pushed_quotes(subscription.drain()) {
+                rows.insert(row.listing_info.symbol.clone(), row);
+            }
+            let (failed, last) = subscription.failed_polls();
+            if failed > failed_polls {
+                log::warn!(
+                    "Failed to poll the quote board: {}. try again ...",
+                    last.unwrap_or_default()
+                );
+                failed_polls = failed;
+            }
+        }
+        if last_sent.is_some_and(|at| at.elapsed() < QUOTE_REFRESH_INTERVAL) {
             tokio::time::sleep(Duration::from_millis(100)).await;
             continue;
         }
 
-        // Fetch market data
-        let market_watch_data = match get_market_watch(&ALL_STOCK_LIST).await {
-            Ok(data) => data,
-            Err(e) => {
-                log::error!("Failed to fetch market watch data: {e}. try again ...");
-                continue;
-            }
-        };
-        let mut all_stock_data: Vec<SlintStockData> = market_watch_data
-            .0
+        // Rows in the order of the symbol master, so they compare one to one
+        let mut all_stock_data: Vec<SlintStockData> = all_symbols
             .iter()
+            .filter_map(|symbol| rows.get(*symbol))
             .map(convert_to_stock_data)
             .collect();
 
@@ -297,8 +445,8 @@ async fn polling_all_stock_data(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Rece
         let market_watch_changed =
             has_stock_data_changed(&previous_stock_data, &mut all_stock_data);
 
-        if market_watch_changed {
-            is_first_update = false;
+        if market_watch_changed && !all_stock_data.is_empty() {
+            last_sent = Some(Instant::now());
             previous_stock_data = Some(all_stock_data.clone());
             tx.send(DataUpdate::StockData(all_stock_data)).await.ok();
         }
@@ -306,9 +454,16 @@ async fn polling_all_stock_data(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Rece
     }
 }
 
-async fn polling_market_watch(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Receiver<TaskStatus>) {
+async fn polling_market_watch(
+    tx: mpsc::Sender<DataUpdate>,
+    mut rx: mpsc::Receiver<TaskStatus>,
+    alerts: Arc<Mutex<AlertBook>>,
+    chart: Arc<Mutex<ChartMetaData>>,
+) {
     let mut previous_market_watch_data: Option<Vec<SlintMarketWatchData>> = None;
-    let mut is_first_update = true; // Track if this is the first update
+    let mut subscription: Option<Subscription> = None;
+    let mut subscribed: Vec<String> = Vec::new();
+    let mut rows: HashMap<String, VCIMarketWatch> = HashMap::new();
     let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
     loop {
         if let Ok(status) = rx.try_recv() {
@@ -318,31 +473,58 @@ async fn polling_market_watch(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Receiv
             }
         }
         if task_status != crate::tasks::task_manager::TaskStatus::Running {
+            subscription = None;
+            subscribed.clear();
             tokio::time::sleep(std::time::Duration::from_millis(100)).await;
             continue;
         }
-        // Check if we're in trading hours (9:00 AM - 3:00 PM Vietnam time)
-        let is_trading_hours = super::is_trading_hours();
 
-        // If not in trading hours and we've already done the first update,
-        // only continue if custom list changed, otherwise skip data fetching
-        if !is_trading_hours && !is_first_update {
-            log::info!("Outside trading hours (9:00-15:00 Vietnam time), skipping data update");
+        // Follow the VN30 basket, with the symbols alerts are set on
+        let master = symbol_master();
+        let vn30 = master.constituents("VN30");
+        let alert_symbols = alerts.lock().await.symbols();
+        let mut symbols: Vec<String> = vn30.to_vec();
+        symbols.extend(
+            alert_symbols
+                .iter()
+                .filter(|symbol| !vn30.contains(*symbol))
+                .cloned(),
+        );
+        if symbols.is_empty() {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            continue;
+        }
+        if symbols != subscribed {
+            let symbol_slices: Vec<&str> = symbols.iter().map(String::as_str).collect();
+            subscription = Some(subscribe(&symbol_slices, &[Channel::Quotes]));
+            rows.retain(|symbol, _| symbols.contains(symbol));
+            subscribed = symbols;
+        }
+        let updated = match subscription.as_mut() {
+            Some(subscription) => pushed_quotes(subscription.drain()),
+            None => Vec::new(),
+        };
+        if updated.is_empty() {
             tokio::time::sleep(Duration::from_millis(100)).await;
             continue;
         }
 
-        // Fetch market data
-        let market_watch_data = match get_market_watch(&VN30_LIST).await {
-            Ok(data) => data,
-            Err(e) => {
-                log::error!("Failed to fetch market watch data: {e}. try again ...");
-                continue;
+        // Alerts are checked on the rows that just changed
+        if !alert_symbols.is_empty() {
+            let events = {
+                let charts = chart.lock().await;
+                super::evaluate_alerts(&mut *alerts.lock().await, &charts, &updated)
+            };
+            if !events.is_empty() {
+                tx.send(DataUpdate::Alerts(events)).await.ok();
             }
-        };
-        let market_watch_stock_data: Vec<SlintMarketWatchData> = market_watch_data
-            .0
+        }
+        for row in updated {
+            rows.insert(row.listing_info.symbol.clone(), row);
+        }
+        let market_watch_stock_data: Vec<SlintMarketWatchData> = vn30
             .iter()
+            .filter_map(|symbol| rows.get(symbol))
             .map(convert_to_market_data)
             .collect();
 
@@ -351,7 +533,6 @@ async fn polling_market_watch(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Receiv
             has_market_watch_changed(&previous_market_watch_data, &market_watch_stock_data);
 
         if market_watch_changed {
-            is_first_update = false;
             previous_market_watch_data = Some(market_watch_stock_data.clone());
             tx.send(DataUpdate::MarketWatchData(market_watch_stock_data))
                 .await
@@ -361,13 +542,95 @@ async fn polling_market_watch(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Receiv
     }
 }
 
+/// Abnormal trades come as one feed and are checked often, insider
+/// transactions are fetched ticker by ticker so much less often
+const ABNORMAL_TRADE_INTERVAL: Duration = Duration::from_secs(10);
+const INSIDER_INTERVAL: Duration = Duration::from_secs(600);
+
+async fn polling_feed_alerts(
+    tx: mpsc::Sender<DataUpdate>,
+    mut rx: mpsc::Receiver<TaskStatus>,
+    watchlists: Arc<Mutex<Watchlists>>,
+    alerts: Arc<Mutex<AlertBook>>,
+) {
+    let mut previous_insider_symbols: Vec<String> = Vec::new();
+    let mut next_insider_fetch = Instant::now();
+    let mut was_trading = false;
+    let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
+    loop {
+        if let Ok(status) = rx.try_recv() {
+            if task_status != status {
+                log::info!("Feed alert task status changed to: {:?}", status);
+                task_status = status;
+            }
+        }
+        if task_status != crate::tasks::task_manager::TaskStatus::Running {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            continue;
+        }
+
+        // Rules scoped to MY LIST follow the first list of the user as it is edited
+        let watchlist = watchlists.lock().await.primary().symbols.clone();
+        let (watches_trades, insider_symbols) = {
+            let book = alerts.lock().await;
+            (
+                book.watches_abnormal_trades(),
+                book.insider_symbols(&watchlist),
+            )
+        };
+
+        // Abnormal trades only happen while orders are matched, one more
+        // fetch after the close picks up the last ones
+        let trading = is_trading_hours();
+        let mut events = Vec::new();
+        if watches_trades && (trading || was_trading) {
+            match fetch_abnormal_trade_data().await {
+                Ok(trades) => events.extend(
+                    alerts
+                        .lock()
+                        .await
+                        .check_abnormal_trades(&trades, &watchlist),
+                ),
+                Err(e) => log::error!("Failed to fetch abnormal trades: {e}. try again ..."),
+            }
+        }
+        // A new rule or ticker is fetched right away
+        if !insider_symbols.is_empty()
+            && (Instant::now() >= next_insider_fetch || insider_symbols != previous_insider_symbols)
+        {
+            next_insider_fetch = Instant::now() + INSIDER_INTERVAL;
+            for symbol in &insider_symbols {
+                match fetch_insider_transactions_data(symbol).await {
+                    Ok(transactions) => {
+                        events.extend(alerts.lock().await.check_insider_transactions(
+                            symbol,
+                            &transactions,
+                            &watchlist,
+                        ))
+                    }
+                    Err(e) => {
+                        log::error!("Failed to fetch insider transactions of {symbol}: {e}")
+                    }
+                }
+            }
+        }
+        previous_insider_symbols = insider_symbols;
+        was_trading = trading;
+
+        if !events.is_empty() {
+            tx.send(DataUpdate::Alerts(events)).await.ok();
+        }
+        tokio::time::sleep(ABNORMAL_TRADE_INTERVAL).await;
+    }
+}
+
 async fn update_ui_with_data(
     ui_handle: Weak<crate::slint_generatedAppWindow::AppWindow>,
     mut rx: mpsc::Receiver<DataUpdate>,
     mut rx_status: mpsc::Receiver<TaskStatus>,
 ) {
     let mut previous_stock_data: Option<Vec<SlintStockData>> = None;
-    let mut previous_custom_list: Option<Vec<String>> = None;
+    let mut previous_watchlists: Option<Watchlists> = None;
     let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
     loop {
         if let Ok(status) = rx_status.try_recv() {
@@ -402,15 +665,9 @@ async fn update_ui_with_data(
                 }
                 DataUpdate::StockData(data) => {
                     previous_stock_data = Some(data.clone());
-                    let previous_custom_list_clone = previous_custom_list.clone();
+                    let lists = previous_watchlists.clone().unwrap_or_default();
                     let _ = ui_handle.upgrade_in_event_loop(move |ui| {
-                        let sort_type = ui.get_sort_type();
-                        let grouped_stock_data = if let Some(previous) = &previous_custom_list_clone
-                        {
-                            create_sector_groups(sort_type, data, previous.clone())
-                        } else {
-                            create_sector_groups(sort_type, data, vec![])
-                        };
+                        let grouped_stock_data = create_watchlist_groups(&data, &lists);
                         ui.set_stock_groups(ModelRc::new(slint::VecModel::from(
                             grouped_stock_data,
                         )));
@@ -445,20 +702,65 @@ async fn update_ui_with_data(
                         ui.set_order_list(ModelRc::new(slint::VecModel::from(slint_order_list)));
                     });
                 }
-                DataUpdate::CustomList(items) => {
-                    previous_custom_list = Some(items.clone());
+                DataUpdate::OrderBook(book, changes) => {
+                    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                        let ladder: Vec<SlintDomLevel> =
+                            dom_ladder(&book, &changes, DOM_LADDER_ROWS)
+                                .into_iter()
+                                .map(|row| SlintDomLevel {
+                                    price: (row.price / 1000.0) as f32,
+                                    bid_vol: row.bid_volume as i32,
+                                    ask_vol: row.ask_volume as i32,
+                                    bid_change: row.bid_change as i32,
+                                    ask_change: row.ask_change as i32,
+                                    is_last: row.is_last,
+                                })
+                                .collect();
+                        // Create the image inside the event loop, like the mini charts
+                        let image =
+                            depth_chart_render(&book, DEPTH_CHART_SIZE.0, DEPTH_CHART_SIZE.1);
+                        let summary = match (book.spread(), book.imbalance()) {
+                            (Some(spread), Some(imbalance)) => format!(
+                                "Chênh lệch {:.2} | Mua/Bán {:+.0}%",
+                                spread / 1000.0,
+                                imbalance * 100.0
+                            ),
+                            _ => String::new(),
+                        };
+                        ui.set_dom_ladder(ModelRc::new(slint::VecModel::from(ladder)));
+                        ui.set_depth_chart(image);
+                        ui.set_depth_summary(summary.into());
+                    });
+                }
+                DataUpdate::Alerts(events) => {
+                    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                        // Newest first, as many as the alert book keeps
+                        let log = ui.get_alert_log();
+                        let mut rows: Vec<SlintAlertLogItem> = events
+                            .iter()
+                            .rev()
+                            .map(super::convert_to_alert_log_item)
+                            .collect();
+                        rows.extend(log.iter());
+                        rows.truncate(ALERT_LOG_CAPACITY);
+                        if let Some(latest) = rows.first() {
+                            ui.set_alert_message(latest.message.clone());
+                        }
+                        ui.set_alert_log(ModelRc::new(slint::VecModel::from(rows)));
+                    });
+                }
+                DataUpdate::Watchlists(lists) => {
+                    previous_watchlists = Some(lists.clone());
                     let previous_stock_data_clone = previous_stock_data.clone();
                     let _ = ui_handle.upgrade_in_event_loop(move |ui| {
                         if let Some(previous) = previous_stock_data_clone {
-                            let sort_type = ui.get_sort_type();
-                            let grouped_stock_data =
-                                super::create_sector_groups(sort_type, previous, items.clone());
+                            let grouped_stock_data = create_watchlist_groups(&previous, &lists);
                             ui.set_stock_groups(ModelRc::new(slint::VecModel::from(
                                 grouped_stock_data,
                             )));
-                        } else {
-                            // no thing update
                         }
+                        // Follow the list that was opened, created or moved
+                        ui.set_watchlist_index(lists.active_index() as i32);
                     });
                 }
             }
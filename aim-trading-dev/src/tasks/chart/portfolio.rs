@@ -0,0 +1,178 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::slint_generatedAppWindow::{AppWindow, PositionItem as SlintPositionItem};
+use crate::tasks::task_manager::{register_task, TaskHandle, TaskStatus};
+use aim_data::calendar::{calendar, to_vn_time};
+use aim_data::{get_market_watch, parse_statement, write_atomic, Book, Portfolio};
+use chrono::{NaiveDate, Utc};
+use slint::{ModelRc, VecModel, Weak};
+use tokio::sync::Mutex;
+
+/// How often the held symbols are marked to the market
+const MARK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Trading day the portfolio is valued on, the last one on weekends and holidays
+fn valuation_date() -> NaiveDate {
+    calendar().latest_trading_day(to_vn_time(Utc::now()).date())
+}
+
+/// Read the portfolio saved at `path`, an empty one on a first start
+pub fn load_portfolio(path: &Path) -> Portfolio {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+            log::error!("Failed to parse portfolio.json: {e}");
+            Portfolio::default()
+        }),
+        Err(_) => Portfolio::default(),
+    }
+}
+
+/// Write the portfolio to `path`, replacing the previous file in one step
+pub fn save_portfolio(path: &Path, portfolio: &Portfolio) {
+    match serde_json::to_vec_pretty(portfolio) {
+        Ok(json) => {
+            if let Err(e) = write_atomic(path, &json) {
+                log::error!("Failed to write portfolio.json: {e}");
+            }
+        }
+        Err(e) => log::error!("Failed to serialize portfolio to JSON: {e}"),
+    }
+}
+
+/// Record the trades of the broker statement at `path`, returns whether any
+/// was added
+pub fn import_statement_file(portfolio: &mut Portfolio, path: &Path) -> bool {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            log::error!("Failed to read statement {path:?}: {e}");
+            return false;
+        }
+    };
+    let transactions = match parse_statement(&text) {
+        Ok(transactions) => transactions,
+        Err(e) => {
+            log::error!("Failed to read statement {path:?}: {e}");
+            return false;
+        }
+    };
+    let (added, errors) = portfolio.import(&transactions);
+    for e in errors {
+        log::error!("Skipped a trade of {path:?}: {e}");
+    }
+    log::info!(
+        "Imported {added} of {} trades from {path:?}",
+        transactions.len()
+    );
+    added > 0
+}
+
+/// Rows of the position list, prices in thousands like the charts
+pub fn convert_to_position_items(book: &Book, date: NaiveDate) -> Vec<SlintPositionItem> {
+    book.open_positions()
+        .map(|position| SlintPositionItem {
+            symbol: position.symbol.clone().into(),
+            quantity: position.quantity() as i32,
+            pending: position.pending_quantity(date) as i32,
+            avg_cost: (position.average_cost() / 1000.0) as f32,
+            price: (position.market_price.unwrap_or(position.average_cost()) / 1000.0) as f32,
+            pnl_percent: if position.cost > 0.0 {
+                (position.unrealised() / position.cost * 100.0) as f32
+            } else {
+                0.0
+            },
+        })
+        .collect()
+}
+
+/// NAV and P&L line of the panel header, in millions of VND
+pub fn portfolio_summary(book: &Book) -> String {
+    format!(
+        "NAV {:.1}tr | Đã chốt {:+.1}tr | Tạm tính {:+.1}tr",
+        book.nav() / 1e6,
+        book.realised() / 1e6,
+        book.unrealised() / 1e6
+    )
+}
+
+/// Show the positions and NAV of `portfolio` on the chart page
+pub fn update_portfolio_ui(ui_handle: &Weak<AppWindow>, portfolio: &Portfolio) {
+    let date = valuation_date();
+    let book = portfolio.book(date);
+    let items = convert_to_position_items(&book, date);
+    let summary = portfolio_summary(&book);
+    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+        ui.set_positions(ModelRc::new(VecModel::from(items)));
+        ui.set_portfolio_summary(summary.into());
+    });
+}
+
+/// Spawns a task marking the held symbols to the market watch, recording the
+/// NAV of the day and saving the portfolio to `path`
+pub async fn spawn_portfolio_task(
+    ui: &AppWindow,
+    portfolio: Arc<Mutex<Portfolio>>,
+    path: PathBuf,
+) -> TaskHandle {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+    let task_handle = register_task(
+        "chart.portfolio".to_string(),
+        tx,
+        "Portfolio Marking Task".to_string(),
+    )
+    .await;
+    let ui_handle = ui.as_weak();
+
+    tokio::spawn(async move {
+        let mut task_status = TaskStatus::Running;
+        let mut next_mark = Instant::now();
+        loop {
+            if let Ok(status) = rx.try_recv() {
+                if task_status != status {
+                    log::info!("Portfolio task status changed to: {:?}", status);
+                    task_status = status;
+                }
+            }
+            if task_status != TaskStatus::Running || Instant::now() < next_mark {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+            next_mark = Instant::now() + MARK_INTERVAL;
+
+            let date = valuation_date();
+            let symbols = portfolio.lock().await.held_symbols(date);
+            let rows = if symbols.is_empty() {
+                Vec::new()
+            } else {
+                let symbol_slices: Vec<&str> = symbols.iter().map(String::as_str).collect();
+                match get_market_watch(&symbol_slices).await {
+                    Ok(response) => response.0,
+                    Err(e) => {
+                        log::error!("Failed to fetch prices of the portfolio: {e}. try again ...");
+                        continue;
+                    }
+                }
+            };
+
+            let mut ledger = portfolio.lock().await;
+            for row in &rows {
+                // Before the first match of the day the reference price stands in
+                let price = if row.match_price.match_price > 0.0 {
+                    row.match_price.match_price
+                } else {
+                    row.listing_info.ref_price
+                };
+                ledger.mark(&row.listing_info.symbol, price);
+            }
+            if !ledger.transactions().is_empty() || !ledger.cash_flows().is_empty() {
+                ledger.record_nav(date);
+                save_portfolio(&path, &ledger);
+            }
+            update_portfolio_ui(&ui_handle, &ledger);
+        }
+    });
+
+    task_handle
+}
@@ -1,11 +1,10 @@
-use crate::interval_to_constant;
 use crate::slint_generatedAppWindow::AppWindow;
 use crate::tasks::task_manager::{register_task, TaskHandle};
-use crate::tasks::ChartMetaData;
+use crate::tasks::{describe_data_error, ChartMetaData};
 use aim_chart::Chart;
 use aim_chart::CompanyInfo;
 use aim_data::get_company_info;
-use aim_data::get_quote;
+use aim_data::{get_quote, TimeFrame};
 use slint::ComponentHandle;
 use std::{sync::Arc, time::Duration};
 use tokio::sync::Mutex;
@@ -39,6 +38,8 @@ pub async fn spawn_new_stock_data_task(
 
     tokio::spawn(async move {
         let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
+        // Symbols and time frames of the panels when their charts were last checked
+        let mut checked_panels = Vec::new();
         loop {
             if let Ok(status) = rx.try_recv() {
                 if task_status != status {
@@ -62,78 +63,46 @@ pub async fn spawn_new_stock_data_task(
                     ui_data.is_new_time_frame = false;
                     let time_frame = ui_data.clone().time_frame;
                     ui.set_ui_data(ui_data);
-                    let stock = ui.get_current_stock().symbol;
+                    let stock = ui.get_current_stock().symbol.to_uppercase();
 
-                    // Fetch and update chart data for new selection
-                    let chart_clone = Arc::clone(&chart_clone);
+                    // Fetch the charts the active panel and its linked panels now show
                     let ui_handle = ui.as_weak();
                     tokio::spawn(async move {
-                        // Check if chart with the same stock name already exists first
-                        let chart_exists = {
-                            let charts = chart_clone.lock().await;
-                            charts
-                                .data
-                                .iter()
-                                .any(|chart| chart.stock_name == stock.to_uppercase())
-                        };
-
-                        // If chart exists, just return without creating a new one
-                        if chart_exists {
-                            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
-                                ui.set_is_chart_in_update(false);
-                            });
-                            return;
-                        }
-
-                        let stock_data = if let Ok(data) =
-                            get_quote(&[&stock], interval_to_constant(&time_frame), None, None)
-                                .await
-                        {
-                            data
-                        } else {
-                            log::error!("Failed to fetch stock data for {stock}");
-                            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
-                                ui.set_is_chart_in_update(false);
-                            });
-                            return;
-                        };
-
-                        if !stock_data.0.is_empty() {
-                            let company_info =
-                                if let Ok(stock_info) = get_company_info(&stock).await {
-                                    CompanyInfo {
-                                        roe: stock_info.data.company_financial_ratio.ratio[0]
-                                            .roe
-                                            .unwrap_or(0.0),
-                                        roa: stock_info.data.company_financial_ratio.ratio[0]
-                                            .roa
-                                            .unwrap_or(0.0),
-                                        pe: stock_info.data.company_financial_ratio.ratio[0]
-                                            .pe
-                                            .unwrap_or(0.0),
-                                        pb: stock_info.data.company_financial_ratio.ratio[0]
-                                            .pb
-                                            .unwrap_or(0.0),
-                                        eps: stock_info.data.company_financial_ratio.ratio[0]
-                                            .eps
-                                            .unwrap_or(0.0),
-                                    }
-                                } else {
-                                    CompanyInfo::default()
-                                };
+                        let time_frame = TimeFrame::parse(&time_frame).unwrap_or_default();
+                        let missing = {
                             let mut charts = chart_clone.lock().await;
-                            charts.data.push(Chart::new_default(
-                                stock.to_uppercase(),
-                                stock_data.0[0].clone(),
-                                company_info,
-                            ));
-                        }
+                            charts.layout.select(&stock, time_frame);
+                            charts.layout.missing_charts(&charts.data)
+                        };
+                        let status = fetch_missing_charts(&chart_clone, missing).await;
                         let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                            ui.set_chart_status(status.into());
                             ui.set_is_chart_in_update(false);
                         });
                     });
                 }
             });
+
+            // A new layout or link may show charts that were never fetched
+            let (panels, missing) = {
+                let charts = chart.lock().await;
+                let panels: Vec<_> = charts
+                    .layout
+                    .panels()
+                    .iter()
+                    .map(|panel| (panel.stock_name.clone(), panel.time_frame))
+                    .collect();
+                (panels, charts.layout.missing_charts(&charts.data))
+            };
+            if panels != checked_panels {
+                checked_panels = panels;
+                if !missing.is_empty() {
+                    let status = fetch_missing_charts(&chart, missing).await;
+                    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                        ui.set_chart_status(status.into());
+                    });
+                }
+            }
             tokio::time::sleep(Duration::from_millis(50)).await;
         }
     });
@@ -141,6 +110,46 @@ pub async fn spawn_new_stock_data_task(
     task_handle
 }
 
+/// Fetch the charts of `missing` symbols and time frames, returns the status
+/// line describing the ones that failed
+async fn fetch_missing_charts(
+    chart: &Arc<Mutex<ChartMetaData>>,
+    missing: Vec<(String, TimeFrame)>,
+) -> String {
+    let source = aim_data::data_source();
+    let mut errors = Vec::new();
+    for (stock, time_frame) in missing {
+        match Chart::from_source(&*source, &stock, time_frame).await {
+            Ok(new_chart) => {
+                let mut charts = chart.lock().await;
+                // The other fetch path may have added it meanwhile
+                if !charts.data.iter().any(|chart| {
+                    chart.stock_name == new_chart.stock_name && chart.time_frame == time_frame
+                }) {
+                    // Drawings made at another time frame show up on the new chart
+                    let stock_name = new_chart.stock_name.clone();
+                    let drawn = charts
+                        .data
+                        .iter()
+                        .find(|chart| {
+                            chart.stock_name == stock_name && !chart.all_draw_data.is_empty()
+                        })
+                        .map(|chart| chart.time_frame);
+                    charts.data.push(new_chart);
+                    if let Some(from) = drawn {
+                        charts.carry_drawings(&stock_name, from, time_frame);
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to fetch {time_frame} data for {stock}: {e}");
+                errors.push(format!("{stock} {time_frame}: {}", describe_data_error(&e)));
+            }
+        }
+    }
+    errors.join("; ")
+}
+
 /// Spawns a separate task to handle chart data updates for existing charts
 pub async fn spawn_current_stock_data_task(
     chart: Arc<Mutex<ChartMetaData>>,
@@ -182,23 +191,27 @@ pub async fn spawn_current_stock_data_task(
 
                 tokio::spawn(async move {
                     // Check if chart with the same stock name already exists
-                    let chart_exists = {
+                    let interval = {
                         let charts = chart_clone.lock().await;
                         charts
                             .data
                             .iter()
-                            .any(|chart| chart.stock_name == stock.to_uppercase())
+                            .find(|chart| chart.stock_name == stock.to_uppercase())
+                            .map(|chart| chart.time_frame.source_interval())
                     };
 
                     // If chart exists, update it with latest data
-                    if chart_exists {
-                        if let Ok(chart_data_vec) =
-                            get_quote(&[&stock], "ONE_DAY", None, None).await
+                    if let Some(interval) = interval {
+                        if let Ok(chart_data_vec) = get_quote(&[&stock], interval, None, None).await
                         {
                             let mut charts = chart_clone.lock().await;
 
                             // Update chart data for each tracked stock
-                            for chart in charts.data.iter_mut() {
+                            for chart in charts
+                                .data
+                                .iter_mut()
+                                .filter(|chart| chart.time_frame.source_interval() == interval)
+                            {
                                 if let Some(updated_data) = chart_data_vec
                                     .0
                                     .iter()
@@ -209,23 +222,7 @@ pub async fn spawn_current_stock_data_task(
                             }
                         }
                         if let Ok(stock_info) = get_company_info(&stock).await {
-                            let company_info = CompanyInfo {
-                                roe: stock_info.data.company_financial_ratio.ratio[0]
-                                    .roe
-                                    .unwrap_or(0.0),
-                                roa: stock_info.data.company_financial_ratio.ratio[0]
-                                    .roa
-                                    .unwrap_or(0.0),
-                                pe: stock_info.data.company_financial_ratio.ratio[0]
-                                    .pe
-                                    .unwrap_or(0.0),
-                                pb: stock_info.data.company_financial_ratio.ratio[0]
-                                    .pb
-                                    .unwrap_or(0.0),
-                                eps: stock_info.data.company_financial_ratio.ratio[0]
-                                    .eps
-                                    .unwrap_or(0.0),
-                            };
+                            let company_info = CompanyInfo::from_ratios(&stock_info);
 
                             let mut charts = chart_clone.lock().await;
 
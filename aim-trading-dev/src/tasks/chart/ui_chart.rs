@@ -1,7 +1,9 @@
 use crate::slint_generatedAppWindow::AppWindow;
 use crate::tasks::task_manager::{register_task, TaskHandle};
 use crate::tasks::ChartMetaData;
-use aim_chart::UiData;
+use aim_chart::{DrawingStyle, UiData};
+use aim_data::TimeFrame;
+use plotters::style::RGBColor;
 use slint::ComponentHandle;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -24,6 +26,15 @@ fn convert_ui_data_to_chart_data(ui_data: crate::slint_generatedAppWindow::UiDat
         crate::slint_generatedAppWindow::MouseType::HorizontalLine => {
             aim_chart::MouseType::HorizontalLine
         }
+        crate::slint_generatedAppWindow::MouseType::FibRetracement => {
+            aim_chart::MouseType::FibRetracement
+        }
+        crate::slint_generatedAppWindow::MouseType::FibExtension => {
+            aim_chart::MouseType::FibExtension
+        }
+        crate::slint_generatedAppWindow::MouseType::Channel => aim_chart::MouseType::Channel,
+        crate::slint_generatedAppWindow::MouseType::Pitchfork => aim_chart::MouseType::Pitchfork,
+        crate::slint_generatedAppWindow::MouseType::Range => aim_chart::MouseType::Range,
     };
 
     UiData {
@@ -34,6 +45,9 @@ fn convert_ui_data_to_chart_data(ui_data: crate::slint_generatedAppWindow::UiDat
         is_in_object: ui_data.is_in_object,
         is_clean: ui_data.is_clean,
         is_undo: ui_data.is_undo,
+        is_redo: ui_data.is_redo,
+        is_delete: ui_data.is_delete,
+        is_restyle: ui_data.is_restyle,
         is_release: ui_data.is_release,
         move_x: ui_data.move_x,
         move_y: ui_data.move_y,
@@ -47,6 +61,20 @@ fn convert_ui_data_to_chart_data(ui_data: crate::slint_generatedAppWindow::UiDat
         is_new_time_frame: ui_data.is_new_time_frame,
         is_new_stock: ui_data.is_new_stock,
         color: ui_data.color,
+        indicator: ui_data.indicator.to_string(),
+        is_toggle_indicator: ui_data.is_toggle_indicator,
+        layout: ui_data.layout.to_string(),
+        is_new_layout: ui_data.is_new_layout,
+        link: ui_data.link,
+        is_toggle_link: ui_data.is_toggle_link,
+        hide_cursor: false,
+        text: ui_data.text.to_string(),
+        text_size: ui_data.text_size,
+        fib_levels: ui_data.fib_levels.to_string(),
+        layer: ui_data.layer.to_string(),
+        is_select_layer: ui_data.is_select_layer,
+        layer_action: ui_data.layer_action,
+        is_layer_action: ui_data.is_layer_action,
     }
 }
 
@@ -94,24 +122,52 @@ pub async fn spawn_ui_chart_task(chart: Arc<Mutex<ChartMetaData>>, ui: &AppWindo
                     ui_data.width = width as i32;
 
                     // Render the chart plot
-                    let (image, is_in_object) = {
+                    let (image, is_in_object, active, layout, layer, style) = {
                         ui_data.is_in_update = false;
                         log::info!("Rendering chart for {stock_name}");
                         tokio::task::block_in_place(|| {
                             let mut charts = chart_clone.blocking_lock();
-                            if let Some(chart) = charts
-                                .data
-                                .iter_mut()
-                                .find(|chart| stock_name == chart.stock_name)
+                            let charts = &mut *charts;
+                            // The search bar and time frame picker drive the active panel
+                            let time_frame =
+                                TimeFrame::parse(&ui_data.time_frame).unwrap_or_default();
+                            let previous = charts.layout.active_panel().clone();
+                            charts.layout.select(&stock_name, time_frame);
+                            if previous.stock_name == stock_name.as_str()
+                                && previous.time_frame != time_frame
                             {
-                                log::info!("Found existing chart for {stock_name}, rendering...");
-                                ui_data.ticker = stock_name.clone();
-                                let chart_ui_data = convert_ui_data_to_chart_data(ui_data.clone());
-                                chart.render_plot(chart_ui_data)
-                            } else {
-                                log::warn!("No chart found for {stock_name}, using default");
-                                (slint::Image::default(), false)
+                                charts.carry_drawings(&stock_name, previous.time_frame, time_frame);
                             }
+                            let template = ui_data.template.trim();
+                            if ui_data.is_save_template && !template.is_empty() {
+                                let style = DrawingStyle {
+                                    color: RGBColor(
+                                        ui_data.color.red(),
+                                        ui_data.color.green(),
+                                        ui_data.color.blue(),
+                                    ),
+                                    text_size: ui_data.text_size.max(0) as u32,
+                                    fib_levels: ui_data.fib_levels.to_string(),
+                                };
+                                charts.save_template(template, style);
+                            }
+                            let style = if ui_data.is_apply_template && !template.is_empty() {
+                                charts.apply_template(template)
+                            } else {
+                                None
+                            };
+                            ui_data.ticker = stock_name.clone();
+                            let chart_ui_data = convert_ui_data_to_chart_data(ui_data.clone());
+                            let (image, is_in_object) =
+                                charts.layout.render(&mut charts.data, chart_ui_data);
+                            (
+                                image,
+                                is_in_object,
+                                charts.layout.active_panel().clone(),
+                                charts.layout.kind(),
+                                charts.active_layer(),
+                                style,
+                            )
                         })
                     };
 
@@ -119,6 +175,26 @@ pub async fn spawn_ui_chart_task(chart: Arc<Mutex<ChartMetaData>>, ui: &AppWindo
                     ui.set_candle_stick_image(image);
                     ui_data.is_in_object = is_in_object;
 
+                    // Pressing in another panel makes the toolbar follow it
+                    if active.stock_name != stock_name.as_str() {
+                        let mut stock = ui.get_current_stock();
+                        stock.symbol = active.stock_name.clone().into();
+                        ui.set_current_stock(stock);
+                    }
+                    ui_data.ticker = active.stock_name.into();
+                    ui_data.time_frame = active.time_frame.to_string().into();
+                    ui_data.layout = layout.label().into();
+                    if let Some(layer) = layer {
+                        ui_data.layer = layer.into();
+                    }
+                    // An applied template sets up the drawing tools too
+                    if let Some(style) = style {
+                        let RGBColor(r, g, b) = style.color;
+                        ui_data.color = slint::Color::from_rgb_u8(r, g, b);
+                        ui_data.text_size = style.text_size as i32;
+                        ui_data.fib_levels = style.fib_levels.into();
+                    }
+
                     // Reset UI state flags
                     if ui_data.is_clean {
                         ui_data.is_clean = false;
@@ -126,6 +202,36 @@ pub async fn spawn_ui_chart_task(chart: Arc<Mutex<ChartMetaData>>, ui: &AppWindo
                     if ui_data.is_undo {
                         ui_data.is_undo = false;
                     }
+                    if ui_data.is_redo {
+                        ui_data.is_redo = false;
+                    }
+                    if ui_data.is_delete {
+                        ui_data.is_delete = false;
+                    }
+                    if ui_data.is_restyle {
+                        ui_data.is_restyle = false;
+                    }
+                    if ui_data.is_select_layer {
+                        ui_data.is_select_layer = false;
+                    }
+                    if ui_data.is_layer_action {
+                        ui_data.is_layer_action = false;
+                    }
+                    if ui_data.is_save_template {
+                        ui_data.is_save_template = false;
+                    }
+                    if ui_data.is_apply_template {
+                        ui_data.is_apply_template = false;
+                    }
+                    if ui_data.is_toggle_indicator {
+                        ui_data.is_toggle_indicator = false;
+                    }
+                    if ui_data.is_new_layout {
+                        ui_data.is_new_layout = false;
+                    }
+                    if ui_data.is_toggle_link {
+                        ui_data.is_toggle_link = false;
+                    }
                     if ui_data.is_release {
                         ui_data.move_x = 0;
                         ui_data.move_y = 0;
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::slint_generatedAppWindow::{
+    ShortType, StockData as SlintStockData, StockGroup as SlintStockGroup,
+};
+use aim_data::{
+    import_watchlists, write_atomic, SymbolMaster, Watchlist, WatchlistAction, WatchlistSort,
+    Watchlists, DEFAULT_WATCHLIST,
+};
+use slint::{ModelRc, VecModel};
+
+use super::sort_stock_data;
+
+/// Lists built from `master`: the VN30 basket, then one list per ICB sector
+pub fn system_watchlists(master: &SymbolMaster) -> Vec<Watchlist> {
+    let mut lists = Vec::new();
+    let vn30 = master.constituents("VN30");
+    if !vn30.is_empty() {
+        lists.push(Watchlist::new("VN30", vn30));
+    }
+    lists.extend(
+        master
+            .sectors()
+            .iter()
+            .map(|sector| Watchlist::new(&sector.name.to_uppercase(), &sector.symbols)),
+    );
+    lists
+}
+
+/// Read the lists of the user saved at `path`, or the single list older
+/// versions kept in `legacy_user_list`, with the system lists of `master`
+pub fn load_watchlists(
+    path: &Path,
+    legacy_user_list: &Path,
+    my_list: &[String],
+    master: &SymbolMaster,
+) -> Watchlists {
+    let saved =
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| match serde_json::from_str(&json) {
+                Ok(lists) => Some(lists),
+                Err(e) => {
+                    log::error!("Failed to parse watchlists.json: {e}");
+                    None
+                }
+            });
+    let mut lists = saved.unwrap_or_else(|| {
+        let my_list = std::fs::read_to_string(legacy_user_list)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Vec<String>>(&json).ok())
+            .unwrap_or_else(|| my_list.to_vec());
+        Watchlists::new(vec![Watchlist::new(DEFAULT_WATCHLIST, &my_list)])
+    });
+    lists.set_system_lists(system_watchlists(master));
+    lists
+}
+
+/// Write the lists of the user to `path`, replacing the previous file in one step
+pub fn save_watchlists(path: &Path, lists: &Watchlists) {
+    match serde_json::to_vec_pretty(lists) {
+        Ok(json) => {
+            if let Err(e) = write_atomic(path, &json) {
+                log::error!("Failed to write watchlists.json: {e}");
+            }
+        }
+        Err(e) => log::error!("Failed to serialize watchlists to JSON: {e}"),
+    }
+}
+
+pub fn to_short_type(sort: WatchlistSort) -> ShortType {
+    match sort {
+        WatchlistSort::None => ShortType::None,
+        WatchlistSort::HighPrice => ShortType::HighPrice,
+        WatchlistSort::LowPrice => ShortType::LowPrice,
+        WatchlistSort::Alphabet => ShortType::Alphabet,
+    }
+}
+
+pub fn to_watchlist_sort(sort_type: ShortType) -> WatchlistSort {
+    match sort_type {
+        ShortType::None => WatchlistSort::None,
+        ShortType::HighPrice => WatchlistSort::HighPrice,
+        ShortType::LowPrice => WatchlistSort::LowPrice,
+        ShortType::Alphabet => WatchlistSort::Alphabet,
+    }
+}
+
+/// One group per list with its column and sort preferences, tickers in the
+/// order of the list unless it is sorted
+pub fn create_watchlist_groups(
+    stock_data: &[SlintStockData],
+    lists: &Watchlists,
+) -> Vec<SlintStockGroup> {
+    let by_symbol: HashMap<&str, &SlintStockData> = stock_data
+        .iter()
+        .map(|stock| (stock.symbol.as_str(), stock))
+        .collect();
+    lists
+        .lists()
+        .iter()
+        .map(|list| {
+            let mut stocks: Vec<SlintStockData> = list
+                .symbols
+                .iter()
+                .filter_map(|symbol| by_symbol.get(symbol.as_str()).map(|&stock| stock.clone()))
+                .collect();
+            let sort = to_short_type(list.sort);
+            sort_stock_data(&mut stocks, sort);
+            SlintStockGroup {
+                group_name: list.name.clone().into(),
+                stocks: ModelRc::new(VecModel::from(stocks)),
+                is_expanded: true,
+                sort,
+                show_info: list.columns.info,
+                show_change: list.columns.change,
+                show_volume: list.columns.volume,
+            }
+        })
+        .collect()
+}
+
+/// Apply list menu entry `action` to list `name`, `value` being the new name
+/// or the file to import or export, returns whether the lists changed
+///
+/// A list exported without a path goes to `export_dir` as CSV.
+pub fn edit_watchlist(
+    lists: &mut Watchlists,
+    action: WatchlistAction,
+    name: &str,
+    value: &str,
+    export_dir: &Path,
+) -> bool {
+    let value = value.trim();
+    let changed = match action {
+        WatchlistAction::Create => lists.create(value),
+        WatchlistAction::Rename => lists.rename(name, value),
+        WatchlistAction::Delete => lists.delete(name),
+        WatchlistAction::MoveUp => lists.move_by(name, -1),
+        WatchlistAction::MoveDown => lists.move_by(name, 1),
+        WatchlistAction::Import => import_watchlist_file(lists, Path::new(value)),
+        WatchlistAction::Export => {
+            let path = if value.is_empty() {
+                export_dir.join(format!("{name}.csv"))
+            } else {
+                PathBuf::from(value)
+            };
+            export_watchlist_file(lists, name, &path);
+            false
+        }
+        WatchlistAction::ToggleInfo
+        | WatchlistAction::ToggleChange
+        | WatchlistAction::ToggleVolume => match lists.get_mut(name) {
+            Some(list) => {
+                let column = match action {
+                    WatchlistAction::ToggleInfo => &mut list.columns.info,
+                    WatchlistAction::ToggleChange => &mut list.columns.change,
+                    _ => &mut list.columns.volume,
+                };
+                *column = !*column;
+                true
+            }
+            None => false,
+        },
+    };
+    if !changed && action != WatchlistAction::Export {
+        log::warn!("Watchlist {action:?} on {name} with {value:?} did nothing");
+    }
+    changed
+}
+
+/// Add the lists of a CSV or JSON file and open the last one
+fn import_watchlist_file(lists: &mut Watchlists, path: &Path) -> bool {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            log::error!("Failed to read watchlist file {path:?}: {e}");
+            return false;
+        }
+    };
+    let name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let Some(imported) = import_watchlists(&name, &text) else {
+        log::error!("No watchlist found in {path:?}");
+        return false;
+    };
+    for list in imported {
+        let name = lists.insert(list);
+        lists.select(&name);
+    }
+    true
+}
+
+/// Write list `name` as JSON if `path` ends in `.json`, as CSV otherwise
+fn export_watchlist_file(lists: &Watchlists, name: &str, path: &Path) {
+    let Some(list) = lists.get(name) else {
+        return;
+    };
+    let is_json = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+    let contents = if is_json {
+        match serde_json::to_string_pretty(list) {
+            Ok(json) => json,
+            Err(e) => {
+                log::error!("Failed to serialize watchlist {name}: {e}");
+                return;
+            }
+        }
+    } else {
+        list.to_csv()
+    };
+    match write_atomic(path, contents.as_bytes()) {
+        Ok(()) => log::info!("Watchlist {name} exported to {path:?}"),
+        Err(e) => log::error!("Failed to export watchlist {name} to {path:?}: {e}"),
+    }
+}
@@ -2,7 +2,8 @@ use crate::slint_generatedAppWindow::AppWindow;
 use aim_chart::convert_candlesticks;
 use aim_chart::mini_chart::mini_chart_render;
 use aim_data::get_quote;
-use chrono::{Datelike, Duration, Local, NaiveDate, Utc, Weekday};
+use aim_data::Exchange;
+use chrono::{NaiveDate, Utc};
 use slint::{ComponentHandle, Model};
 
 /// Mini chart task pattern macro for market watch mini charts
@@ -37,63 +38,59 @@ macro_rules! create_mini_chart_task {
 }
 // Unused imports removed
 
+/// Exchange whose sessions an index follows
+fn index_exchange(symbol: &str) -> Exchange {
+    if symbol.starts_with("HNX") {
+        Exchange::Hnx
+    } else {
+        Exchange::Hose
+    }
+}
+
 async fn get_reference_price(symbol: &str, current_date: NaiveDate) -> f32 {
     // Try to get the previous trading day's close price
-    let mut prev_date = current_date - Duration::days(1);
+    let calendar = aim_data::calendar();
+    let mut prev_date = calendar.previous_trading_day(current_date);
     let mut attempts = 0;
 
-    // Try up to 7 days back to find the previous trading day
+    // Try up to 7 trading days back in case a day has no data
     while attempts < 7 {
-        // Skip weekends
-        if prev_date.weekday() != Weekday::Sat && prev_date.weekday() != Weekday::Sun {
-            let day_start = prev_date
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_local_timezone(Local)
-                .unwrap()
-                .with_timezone(&Utc);
-            let day_end = prev_date
-                .and_hms_opt(23, 59, 59)
-                .unwrap()
-                .and_local_timezone(Local)
-                .unwrap()
-                .with_timezone(&Utc);
+        let (day_start, day_end) = calendar.session_bounds(index_exchange(symbol), prev_date);
 
-            println!(
-                "Mini chart {}: Fetching reference price from {}...",
-                symbol,
-                prev_date.format("%Y-%m-%d")
-            );
-            match get_quote(&[symbol], "ONE_MINUTE", Some(day_start), Some(day_end)).await {
-                Ok(resp) => {
-                    if !resp.0.is_empty() {
-                        if let Some(ohlc) = resp.0.first() {
-                            let candlesticks = ohlc.to_candlesticks();
-                            if !candlesticks.is_empty() {
-                                let last_close = candlesticks.last().unwrap().close as f32; // Convert to display format
-                                println!(
-                                    "Mini chart {}: Got reference price {:.2} from {}",
-                                    symbol,
-                                    last_close,
-                                    prev_date.format("%Y-%m-%d")
-                                );
-                                return last_close;
-                            }
+        println!(
+            "Mini chart {}: Fetching reference price from {}...",
+            symbol,
+            prev_date.format("%Y-%m-%d")
+        );
+        match get_quote(&[symbol], "ONE_MINUTE", Some(day_start), Some(day_end)).await {
+            Ok(resp) => {
+                if !resp.0.is_empty() {
+                    if let Some(ohlc) = resp.0.first() {
+                        let candlesticks = ohlc.to_candlesticks();
+                        if !candlesticks.is_empty() {
+                            let last_close = candlesticks.last().unwrap().close as f32; // Convert to display format
+                            println!(
+                                "Mini chart {}: Got reference price {:.2} from {}",
+                                symbol,
+                                last_close,
+                                prev_date.format("%Y-%m-%d")
+                            );
+                            return last_close;
                         }
                     }
                 }
-                Err(e) => {
-                    println!(
-                        "Mini chart {}: Failed to get reference price from {}: {}",
-                        symbol,
-                        prev_date.format("%Y-%m-%d"),
-                        e
-                    );
-                }
+            }
+            Err(e) => {
+                println!(
+                    "Mini chart {}: Failed to get reference price from {}: {}",
+                    symbol,
+                    prev_date.format("%Y-%m-%d"),
+                    e
+                );
             }
         }
 
-        prev_date -= Duration::days(1);
+        prev_date = calendar.previous_trading_day(prev_date);
         attempts += 1;
     }
 
@@ -161,140 +158,128 @@ pub async fn spawn_chart_loop(
             continue;
         }
         // get the latest data for the given symbol (latest trading day only)
-        // Get today's date for filtering, with fallback to previous trading days
-        let now = Local::now();
+        // Start from today in Vietnam time, falling back to previous trading days
+        let calendar = aim_data::calendar();
+        let today = aim_data::calendar::to_vn_time(Utc::now()).date();
 
-        let mut attempt_date = now.date_naive();
+        let mut attempt_date = calendar.latest_trading_day(today);
         let mut data_found = false;
         let mut attempts = 0;
 
-        // Try up to 7 days back to find trading data (skip weekends)
+        // Try up to 7 trading days back to find trading data
         while !data_found && attempts < 7 {
-            // Skip weekends for Vietnamese stock market
-            if attempt_date.weekday() != Weekday::Sat && attempt_date.weekday() != Weekday::Sun {
-                let day_start = attempt_date
-                    .and_hms_opt(0, 0, 0)
-                    .unwrap()
-                    .and_local_timezone(Local)
-                    .unwrap()
-                    .with_timezone(&Utc);
-                let day_end = attempt_date
-                    .and_hms_opt(23, 59, 59)
-                    .unwrap()
-                    .and_local_timezone(Local)
-                    .unwrap()
-                    .with_timezone(&Utc);
+            let (day_start, day_end) =
+                calendar.session_bounds(index_exchange(symbol), attempt_date);
 
-                println!(
-                    "Mini chart: Fetching {} data for {}...",
-                    symbol,
-                    attempt_date.format("%Y-%m-%d")
-                );
-                match get_quote(&[symbol], "ONE_MINUTE", Some(day_start), Some(day_end)).await {
-                    Ok(resp) => {
-                        println!(
-                            "Mini chart: Got response for {} with timeframe ONE_MINUTE - {} items",
-                            symbol,
-                            resp.0.len()
-                        );
-                        if !resp.0.is_empty() {
-                            if let Some(ohlc) = resp.0.first() {
-                                let candlesticks = ohlc.to_candlesticks();
-                                println!(
-                                    "Mini chart: Got {} candlesticks for {}",
-                                    candlesticks.len(),
-                                    symbol
-                                );
-                                if !candlesticks.is_empty() {
-                                    let candle_data = convert_candlesticks(false, candlesticks);
+            println!(
+                "Mini chart: Fetching {} data for {}...",
+                symbol,
+                attempt_date.format("%Y-%m-%d")
+            );
+            match get_quote(&[symbol], "ONE_MINUTE", Some(day_start), Some(day_end)).await {
+                Ok(resp) => {
+                    println!(
+                        "Mini chart: Got response for {} with timeframe ONE_MINUTE - {} items",
+                        symbol,
+                        resp.0.len()
+                    );
+                    if !resp.0.is_empty() {
+                        if let Some(ohlc) = resp.0.first() {
+                            let candlesticks = ohlc.to_candlesticks();
+                            println!(
+                                "Mini chart: Got {} candlesticks for {}",
+                                candlesticks.len(),
+                                symbol
+                            );
+                            if !candlesticks.is_empty() {
+                                let candle_data = convert_candlesticks(false, candlesticks);
 
-                                    // Get reference price (previous day's close price)
-                                    let ref_price = get_reference_price(symbol, attempt_date).await;
+                                // Get reference price (previous day's close price)
+                                let ref_price = get_reference_price(symbol, attempt_date).await;
 
-                                    let symbol_clone = symbol.to_string();
-                                    let ui_type_clone = ui_type.to_string();
-                                    let candle_data_clone = candle_data.clone();
-                                    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
-                                        // Create the image inside the event loop to avoid thread safety issues
-                                        let image = mini_chart_render(ref_price, candle_data_clone.clone());
+                                let symbol_clone = symbol.to_string();
+                                let ui_type_clone = ui_type.to_string();
+                                let candle_data_clone = candle_data.clone();
+                                let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                                    // Create the image inside the event loop to avoid thread safety issues
+                                    let image = mini_chart_render(ref_price, candle_data_clone.clone());
 
-                                        // Calculate market data from candle data
-                                        let current_price = candle_data_clone.last().map(|c| c.close()).unwrap_or(0.0);
-                                        let change = current_price - ref_price;
-                                        let percentage = if ref_price > 0.0 { (change / ref_price) * 100.0 } else { 0.0 };
+                                    // Calculate market data from candle data
+                                    let current_price = candle_data_clone.last().map(|c| c.close()).unwrap_or(0.0);
+                                    let change = current_price - ref_price;
+                                    let percentage = if ref_price > 0.0 { (change / ref_price) * 100.0 } else { 0.0 };
 
-                                        // Calculate total volume from all candle data
-                                        let total_volume: i64 = candle_data_clone.iter()
-                                            .map(|c| c.volume() as i64)
-                                            .sum();
+                                    // Calculate total volume from all candle data
+                                    let total_volume: i64 = candle_data_clone.iter()
+                                        .map(|c| c.volume() as i64)
+                                        .sum();
 
-                                        // Format number (total volume count) with commas - this goes to the number field
-                                        let number_str = {
-                                            let num_str = total_volume.to_string();
-                                            let mut result = String::new();
-                                            let chars: Vec<char> = num_str.chars().rev().collect();
-                                            for (i, c) in chars.iter().enumerate() {
-                                                if i > 0 && i % 3 == 0 {
-                                                    result.push(',');
-                                                }
-                                                result.push(*c);
+                                    // Format number (total volume count) with commas - this goes to the number field
+                                    let number_str = {
+                                        let num_str = total_volume.to_string();
+                                        let mut result = String::new();
+                                        let chars: Vec<char> = num_str.chars().rev().collect();
+                                        for (i, c) in chars.iter().enumerate() {
+                                            if i > 0 && i % 3 == 0 {
+                                                result.push(',');
                                             }
-                                            result.chars().rev().collect::<String>()
-                                        };
+                                            result.push(*c);
+                                        }
+                                        result.chars().rev().collect::<String>()
+                                    };
 
-                                        // Format volume string (for display) - this goes to the volume field
-                                        let volume_str = if total_volume > 1_000_000 {
-                                            format!("{:.1} TỶ", total_volume as f64 / 1_000_000.0)
-                                        } else if total_volume > 1_000 {
-                                            format!("{:.1}K", total_volume as f64 / 1_000.0)
-                                        } else {
-                                            total_volume.to_string()
-                                        };
+                                    // Format volume string (for display) - this goes to the volume field
+                                    let volume_str = if total_volume > 1_000_000 {
+                                        format!("{:.1} TỶ", total_volume as f64 / 1_000_000.0)
+                                    } else if total_volume > 1_000 {
+                                        format!("{:.1}K", total_volume as f64 / 1_000.0)
+                                    } else {
+                                        total_volume.to_string()
+                                    };
 
-                                        println!("Mini chart: Updated {symbol_clone} - Price: {current_price:.2}, Change: {change:.2} ({percentage:.2}%), Total Volume: {number_str}, Volume Display: {volume_str}");
+                                    println!("Mini chart: Updated {symbol_clone} - Price: {current_price:.2}, Change: {change:.2} ({percentage:.2}%), Total Volume: {number_str}, Volume Display: {volume_str}");
 
-                                        // Update the index_data array with complete market data
-                                        let index_data = ui.get_index_data();
-                                        for i in 0..index_data.row_count() {
-                                            if let Some(mut row) = index_data.row_data(i) {
-                                                if row.symbol.as_str().to_lowercase() == ui_type_clone.as_str() {
-                                                    row.image = image;
-                                                    row.number = number_str.into(); // Total volume count
-                                                    row.volume = volume_str.into();  // Formatted volume display
-                                                    row.price = current_price;
-                                                    row.change = change;
-                                                    row.percentage = percentage;
-                                                    index_data.set_row_data(i, row);
-                                                    break;
-                                                }
+                                    // Update the index_data array with complete market data
+                                    let index_data = ui.get_index_data();
+                                    for i in 0..index_data.row_count() {
+                                        if let Some(mut row) = index_data.row_data(i) {
+                                            if row.symbol.as_str().to_lowercase() == ui_type_clone.as_str() {
+                                                row.image = image;
+                                                row.number = number_str.into(); // Total volume count
+                                                row.volume = volume_str.into();  // Formatted volume display
+                                                row.price = current_price;
+                                                row.change = change;
+                                                row.percentage = percentage;
+                                                index_data.set_row_data(i, row);
+                                                break;
                                             }
                                         }
-                                        ui.set_index_data(index_data);
-                                    });
-                                    data_found = true;
-                                    break;
-                                }
+                                    }
+                                    ui.set_index_data(index_data);
+                                });
+                                data_found = true;
+                                break;
                             }
                         }
                     }
-                    Err(e) => {
-                        println!(
-                            "Mini chart: Failed to get quote data for {} on {}: {}",
-                            symbol,
-                            attempt_date.format("%Y-%m-%d"),
-                            e
-                        );
-                    }
+                }
+                Err(e) => {
+                    println!(
+                        "Mini chart: Failed to get quote data for {} on {}: {}",
+                        symbol,
+                        attempt_date.format("%Y-%m-%d"),
+                        e
+                    );
                 }
             }
 
-            // Move to previous day
-            attempt_date -= Duration::days(1);
+            // Move to previous trading day
+            attempt_date = calendar.previous_trading_day(attempt_date);
             attempts += 1;
         }
 
         if !data_found {
-            println!("Mini chart {symbol}: No trading data found in the last 7 days");
+            println!("Mini chart {symbol}: No trading data found in the last 7 trading days");
         }
 
         tokio::time::sleep(std::time::Duration::from_secs(60)).await; // Adjust the interval as needed
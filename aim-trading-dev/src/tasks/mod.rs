@@ -1,23 +1,45 @@
 use crate::slint_generatedAppWindow;
-use aim_chart::Chart;
+use aim_chart::{
+    read_record, write_record, CacheRecord, Chart, ChartLayout, ChartTemplate, DrawingStyle,
+    CACHE_MAGIC, CACHE_VERSION, RECORD_CHART, RECORD_LAYOUT, RECORD_TEMPLATE,
+};
 use aim_data::explorer::vci::OrderList;
+use aim_data::{
+    AimDataError, AlertContext, AlertEvent, LevelChange, OrderBook, TimeFrame, Watchlists,
+};
 pub use cache_storage::spawn_cache_storage_task;
 pub use chart::*;
+use chrono::{DateTime, Utc};
 pub use dashboard::*;
 pub use market_watch::*;
 use slint_generatedAppWindow::{
     MarketWatchData as SlintMarketWatchData, StockData as SlintStockData,
 };
-use std::{fs::File, io::Write, path::PathBuf};
+use std::path::{Path, PathBuf};
+pub use symbol_master::{listed_stocks, remove_if_unlisted, spawn_symbol_master_task};
 
 pub mod backend;
 pub mod cache_storage;
 pub mod chart;
 pub mod dashboard;
 pub mod market_watch;
+pub mod symbol_master;
 pub mod task_manager;
 pub mod world_index;
 
+/// Short message shown in the UI when a data request fails
+pub fn describe_data_error(err: &AimDataError) -> String {
+    match err {
+        _ if err.is_unauthorized() => "Máy chủ từ chối truy cập, kiểm tra lại token".to_string(),
+        _ if err.is_not_found() => "Không có dữ liệu cho mã này".to_string(),
+        AimDataError::Transport(_) => "Mất kết nối tới máy chủ dữ liệu".to_string(),
+        AimDataError::HttpStatus { status, .. } => format!("Máy chủ dữ liệu lỗi (HTTP {status})"),
+        AimDataError::Decode { .. } => "Dữ liệu trả về không đúng định dạng".to_string(),
+        AimDataError::Io { .. } => "Không đọc/ghi được dữ liệu trên máy".to_string(),
+        _ => err.to_string(),
+    }
+}
+
 /// Simplified macro for non-stock-specific tasks (dashboard, market watch, etc.)
 #[macro_export]
 macro_rules! create_simple_task {
@@ -89,111 +111,292 @@ macro_rules! create_simple_task {
 #[repr(C)]
 pub struct ChartMetaData {
     data: Vec<Chart>,
+    layout: ChartLayout,
+    templates: Vec<ChartTemplate>,
 }
 
+/// Positional cache file written before the tagged format: the version,
+/// the chart count, then the charts
+///
+/// Since v2 the file is `CACHE_MAGIC`, the version, then checksummed records:
+/// one per chart, one for the layout and one per chart template.
+const LEGACY_CACHE_VERSION: u32 = 1;
+
 impl ChartMetaData {
     pub fn new(data: Vec<Chart>) -> Self {
-        Self { data }
+        let layout = Self::default_layout(&data);
+        Self {
+            data,
+            layout,
+            templates: Vec::new(),
+        }
     }
 
-    pub fn save(&self, mut file: File) {
-        const VERSION: u32 = 1;
+    /// A single panel on the first chart, for caches saved before layouts existed
+    fn default_layout(data: &[Chart]) -> ChartLayout {
+        match data.first() {
+            Some(chart) => ChartLayout::new(chart.stock_name.clone(), chart.time_frame),
+            // Same stock a fresh install opens on
+            None => ChartLayout::new("AAA".to_string(), TimeFrame::default()),
+        }
+    }
 
+    pub fn layout(&self) -> &ChartLayout {
+        &self.layout
+    }
+
+    /// Put the drawings of `stock_name` at `from` on its `to` chart, so they
+    /// follow the stock across time frame switches
+    pub fn carry_drawings(&mut self, stock_name: &str, from: TimeFrame, to: TimeFrame) {
+        let shows = |chart: &Chart, time_frame: TimeFrame| {
+            chart.stock_name == stock_name && chart.time_frame == time_frame
+        };
+        let Some(source) = self.data.iter().position(|chart| shows(chart, from)) else {
+            return;
+        };
+        let Some(target) = self.data.iter().position(|chart| shows(chart, to)) else {
+            return;
+        };
+        if source != target {
+            let source = self.data[source].clone();
+            self.data[target].adopt_drawings(&source);
+        }
+    }
+
+    /// The chart shown in the active panel
+    fn active_chart_mut(&mut self) -> Option<&mut Chart> {
+        let panel = self.layout.active_panel();
+        self.data.iter_mut().find(|chart| panel.shows(chart))
+    }
+
+    /// Name of the layer new drawings go to on the active panel
+    pub fn active_layer(&self) -> Option<String> {
+        let panel = self.layout.active_panel();
+        self.data
+            .iter()
+            .find(|chart| panel.shows(chart))
+            .map(|chart| chart.layers.active().name.clone())
+    }
+
+    /// Save the indicators and layers of the active chart with `style` as
+    /// template `name`, replacing a template of that name
+    pub fn save_template(&mut self, name: &str, style: DrawingStyle) {
+        let panel = self.layout.active_panel();
+        let Some(chart) = self.data.iter().find(|chart| panel.shows(chart)) else {
+            return;
+        };
+        let template = ChartTemplate::from_chart(name, chart, style);
+        match self.templates.iter_mut().find(|saved| saved.name == name) {
+            Some(saved) => *saved = template,
+            None => self.templates.push(template),
+        }
+        log::info!("Chart template {name} saved");
+    }
+
+    /// Apply template `name` to the active chart, returns its drawing style
+    /// for the toolbar
+    pub fn apply_template(&mut self, name: &str) -> Option<DrawingStyle> {
+        let Some(template) = self
+            .templates
+            .iter()
+            .find(|saved| saved.name == name)
+            .cloned()
+        else {
+            log::warn!("No chart template named {name}");
+            return None;
+        };
+        self.active_chart_mut()?.apply_template(&template);
+        Some(template.style)
+    }
+
+    /// Session volumes and drawn lines of `symbol` at `time`, for its alerts
+    ///
+    /// Volumes come from the daily chart of the symbol, lines from all of
+    /// its charts; both are empty when no chart of the symbol is open.
+    pub fn alert_context(&self, symbol: &str, time: DateTime<Utc>) -> AlertContext {
+        let charts = || self.data.iter().filter(|chart| chart.stock_name == symbol);
+        let session_volumes = charts()
+            .find(|chart| chart.time_frame == TimeFrame::Day(1))
+            .map(|chart| chart.volumes_before(time))
+            .unwrap_or_default();
+        // Drawings are carried between time frames, count each line once
+        let mut line_prices: Vec<f64> = charts()
+            .flat_map(|chart| chart.drawn_line_prices(time))
+            .collect();
+        line_prices.sort_by(f64::total_cmp);
+        line_prices.dedup_by(|a, b| (*a - *b).abs() < 1e-3);
+        AlertContext {
+            session_volumes,
+            line_prices,
+        }
+    }
+
+    /// The whole cache file, see [`LEGACY_CACHE_VERSION`] for the layout
+    fn to_cache_bytes(&self) -> Vec<u8> {
         let mut bytes = Vec::new();
-        // Write version header
-        bytes.extend_from_slice(&VERSION.to_le_bytes());
-        let count = self.data.len() as u32;
-        bytes.extend_from_slice(&count.to_le_bytes());
-        for (i, chart) in self.data.iter().enumerate() {
-            let start_len = bytes.len();
-            chart.write_to_bytes(&mut bytes);
-            let written = bytes.len() - start_len;
-            if written == 0 {
-                log::error!("Chart #{} failed to serialize: {}", i, chart.stock_name);
-            }
+        bytes.extend_from_slice(&CACHE_MAGIC);
+        bytes.extend_from_slice(&CACHE_VERSION.to_le_bytes());
+        for chart in &self.data {
+            write_record(&mut bytes, RECORD_CHART, &chart.to_tagged_bytes());
         }
-        if let Err(e) = file.write_all(&bytes) {
-            log::error!("Failed to write chart data to cache file: {e}");
+        let mut layout = Vec::new();
+        self.layout.write_to_bytes(&mut layout);
+        write_record(&mut bytes, RECORD_LAYOUT, &layout);
+        for template in &self.templates {
+            let mut payload = Vec::new();
+            template.write_to_bytes(&mut payload);
+            write_record(&mut bytes, RECORD_TEMPLATE, &payload);
         }
+        bytes
+    }
+
+    /// Write the cache file atomically, a crash mid-save keeps the previous one
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        aim_data::write_atomic(path, &self.to_cache_bytes())
     }
 
     // Load charts from a file (manual deserialization, no external crate)
     pub fn load(path: &PathBuf) -> Self {
-        let mut data = Vec::new();
-        match std::fs::read(path) {
-            Ok(bytes) => {
-                let mut pos = 0;
-                if bytes.len() < 8 {
-                    log::error!("File too small to contain version and chart count: {path:?}");
-                    return Self { data };
-                }
-                let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-                pos += 4;
-                if version != 1 {
-                    log::error!("Unsupported chart cache version {version} in {path:?}");
-                    return Self { data };
-                }
-                let count = u32::from_le_bytes([
-                    bytes[pos],
-                    bytes[pos + 1],
-                    bytes[pos + 2],
-                    bytes[pos + 3],
-                ]) as usize;
-                pos += 4;
-                for i in 0..count {
-                    let chart_bytes = &bytes[pos..];
-                    match Chart::read_from_bytes(chart_bytes) {
-                        Some((chart, used)) => {
-                            if used == 0 {
-                                log::error!(
-                                    "Chart #{i} deserialized 0 bytes at pos {pos} in {path:?}"
-                                );
-                                break;
-                            }
-                            data.push(chart);
-                            pos += used;
-                        }
-                        None => {
-                            log::error!(
-                                "Failed to deserialize chart #{i} at pos {pos} in {path:?}"
-                            );
-                            break;
-                        }
-                    }
-                }
-                log::info!("Loaded {} charts from {}", data.len(), path.display());
-                for chart in &data {
-                    log::info!("Chart loaded: {}", chart.stock_name);
-                }
+        let mut templates = Vec::new();
+        let (data, layout) = match std::fs::read(path) {
+            Ok(bytes) if bytes.starts_with(&CACHE_MAGIC) => {
+                Self::read_tagged(&bytes, path, &mut templates)
             }
+            // Version 1 had no layout, the default one is built for its charts
+            Ok(bytes) => (Self::migrate_legacy(&bytes, path), None),
             Err(e) => {
                 log::error!("Failed to read chart file {path:?}: {e}");
+                (Vec::new(), None)
             }
-        }
+        };
 
-        log::error!(
+        log::info!(
             "Chart data loaded from {}: {} charts",
             path.display(),
             data.len()
         );
         for chart in &data {
-            log::info!("Chart loaded: {}", chart.stock_name);
+            log::info!("Chart loaded: {} {}", chart.stock_name, chart.time_frame);
+        }
+        let layout = layout.unwrap_or_else(|| Self::default_layout(&data));
+        Self {
+            data,
+            layout,
+            templates,
         }
-        Self { data }
     }
 
-    // Get a simple hash of the chart data (no external crate)
-    pub fn get_md5(&self) -> String {
-        // Use a simple FNV-1a hash for demonstration
-        let mut hash: u64 = 0xcbf29ce484222325;
-        for chart in &self.data {
-            let bytes = chart.to_bytes();
-            for b in bytes {
-                hash ^= b as u64;
-                hash = hash.wrapping_mul(0x100000001b3);
+    /// Read the records of a tagged cache, skipping the corrupt ones
+    fn read_tagged(
+        bytes: &[u8],
+        path: &PathBuf,
+        templates: &mut Vec<ChartTemplate>,
+    ) -> (Vec<Chart>, Option<ChartLayout>) {
+        let mut data = Vec::new();
+        let mut layout = None;
+        let Some(version) = bytes
+            .get(4..8)
+            .map(|version| u32::from_le_bytes(version.try_into().unwrap()))
+        else {
+            log::error!("Chart cache too small to contain its version: {path:?}");
+            return (data, layout);
+        };
+        if version > CACHE_VERSION {
+            // Records this build does not know are skipped below
+            log::warn!("Chart cache {path:?} is version {version}, reading what version {CACHE_VERSION} knows");
+        }
+        let mut pos = 8;
+        while pos < bytes.len() {
+            let Some((record, used)) = read_record(&bytes[pos..]) else {
+                log::error!("Chart cache truncated at pos {pos} in {path:?}");
+                break;
+            };
+            match record {
+                CacheRecord::Valid {
+                    tag: RECORD_CHART,
+                    payload,
+                } => match Chart::from_tagged_bytes(payload) {
+                    Some(chart) => data.push(chart),
+                    None => log::error!("Skipping unreadable chart at pos {pos} in {path:?}"),
+                },
+                CacheRecord::Valid {
+                    tag: RECORD_LAYOUT,
+                    payload,
+                } => {
+                    layout = ChartLayout::read_from_bytes(payload).map(|(layout, _)| layout);
+                    if layout.is_none() {
+                        log::error!("Failed to read the chart layout at pos {pos} in {path:?}");
+                    }
+                }
+                CacheRecord::Valid {
+                    tag: RECORD_TEMPLATE,
+                    payload,
+                } => match ChartTemplate::read_from_bytes(payload) {
+                    Some((template, _)) => templates.push(template),
+                    None => {
+                        log::error!("Skipping unreadable chart template at pos {pos} in {path:?}")
+                    }
+                },
+                CacheRecord::Valid { tag, .. } => {
+                    log::warn!("Skipping unknown cache record {tag} at pos {pos} in {path:?}");
+                }
+                CacheRecord::Corrupt { tag } => {
+                    log::error!("Skipping corrupt cache record {tag} at pos {pos} in {path:?}");
+                }
+            }
+            pos += used;
+        }
+        (data, layout)
+    }
+
+    /// Read a positional version 1 cache, its charts are all daily
+    ///
+    /// The old file is kept next to the cache, the next save writes the
+    /// tagged format. Charts cannot be told apart in these files, so reading
+    /// stops at the first one that fails.
+    fn migrate_legacy(bytes: &[u8], path: &PathBuf) -> Vec<Chart> {
+        let mut data = Vec::new();
+        let mut pos = 0;
+        if bytes.len() < 8 {
+            log::error!("File too small to contain version and chart count: {path:?}");
+            return data;
+        }
+        let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        pos += 4;
+        if version != LEGACY_CACHE_VERSION {
+            log::error!("Unsupported chart cache version {version} in {path:?}");
+            return data;
+        }
+        log::info!("Migrating chart cache {path:?} from version {version}");
+        let backup = path.with_extension(format!("v{version}.bak"));
+        if let Err(e) = std::fs::copy(path, &backup) {
+            log::error!("Failed to back up chart cache to {backup:?}: {e}");
+        }
+        let count = u32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+            as usize;
+        pos += 4;
+        for i in 0..count {
+            match Chart::read_from_bytes(&bytes[pos..]) {
+                Some((chart, used)) => {
+                    if used == 0 {
+                        log::error!("Chart #{i} deserialized 0 bytes at pos {pos} in {path:?}");
+                        break;
+                    }
+                    pos += used;
+                    data.push(chart);
+                }
+                None => {
+                    log::error!("Failed to deserialize chart #{i} at pos {pos} in {path:?}");
+                    break;
+                }
             }
         }
-        format!("{hash:016x}")
+        data
+    }
+
+    // Get a simple hash of the chart data (no external crate)
+    pub fn get_md5(&self) -> String {
+        format!("{:016x}", aim_chart::fnv1a(&self.to_cache_bytes()))
     }
 }
 
@@ -201,5 +404,10 @@ pub enum DataUpdate {
     MarketWatchData(Vec<SlintMarketWatchData>),
     StockData(Vec<SlintStockData>),
     OrdList(OrderList),
-    CustomList(Vec<String>),
+    /// Depth of the current stock and the levels that changed since the last one
+    OrderBook(OrderBook, Vec<LevelChange>),
+    /// The watchlists after an edit
+    Watchlists(Watchlists),
+    /// Alerts that just fired
+    Alerts(Vec<AlertEvent>),
 }
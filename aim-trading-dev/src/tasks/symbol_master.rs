@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use aim_data::symbols::SNAPSHOT_MAX_AGE;
+use aim_data::{refresh_symbol_master, symbol_master, SymbolMaster, Watchlists};
+use chrono::Utc;
+use tokio::sync::Mutex;
+
+use crate::tasks::chart::{save_watchlists, system_watchlists};
+use crate::tasks::task_manager::{register_task, TaskHandle, TaskStatus};
+
+/// How often the snapshot age is checked, a failed refresh is retried as often
+const CHECK_INTERVAL: Duration = Duration::from_secs(600);
+
+/// How often a task waiting for the first symbol master checks for it
+const LOAD_WAIT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The symbol master, or one built from the listings alone while none is loaded
+///
+/// The saved snapshot is read at startup, so an empty master means there is
+/// none and the listings are fetched on the spot. `None` when that fails too.
+pub async fn listed_stocks() -> Option<Arc<SymbolMaster>> {
+    let master = symbol_master();
+    if !master.is_empty() {
+        return Some(master);
+    }
+    match aim_data::data_source().get_listings().await {
+        Ok(listings) => {
+            let master = SymbolMaster::build(&listings, &[], &[], BTreeMap::new(), Utc::now());
+            (!master.is_empty()).then(|| Arc::new(master))
+        }
+        Err(e) => {
+            log::error!("Failed to fetch listings: {e}");
+            None
+        }
+    }
+}
+
+/// Wait for the symbol master, then take `symbol` out of the watchlist `name`
+/// saved at `path` if it is not a listed stock
+///
+/// For symbols added while neither the master nor the listings could be loaded.
+pub async fn remove_if_unlisted(
+    watchlists: Arc<Mutex<Watchlists>>,
+    path: PathBuf,
+    name: String,
+    symbol: String,
+) {
+    let master = loop {
+        let master = symbol_master();
+        if !master.is_empty() {
+            break master;
+        }
+        tokio::time::sleep(LOAD_WAIT_INTERVAL).await;
+    };
+    if master.contains(&symbol) {
+        return;
+    }
+    let mut lists = watchlists.lock().await;
+    if lists
+        .get_mut(&name)
+        .is_some_and(|list| list.remove(&symbol))
+    {
+        log::error!("Removed {symbol} from the list {name}: not a listed stock");
+        save_watchlists(&path, &lists);
+    }
+}
+
+/// Spawns a task keeping the symbol master at `path` no older than a day,
+/// rebuilding the system lists of `watchlists` from every new snapshot
+pub async fn spawn_symbol_master_task(
+    path: PathBuf,
+    watchlists: Arc<Mutex<Watchlists>>,
+) -> TaskHandle {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+    let task_handle = register_task(
+        "system.symbol_master".to_string(),
+        tx,
+        "Symbol Master Refresh".to_string(),
+    )
+    .await;
+
+    tokio::spawn(async move {
+        let mut task_status = TaskStatus::Running;
+        loop {
+            if let Ok(status) = rx.try_recv() {
+                if task_status != status {
+                    log::info!("Symbol master task status changed to: {:?}", status);
+                    task_status = status;
+                }
+            }
+            if task_status != TaskStatus::Running {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+            match refresh_symbol_master(&path, SNAPSHOT_MAX_AGE).await {
+                Ok(true) => {
+                    log::info!("Symbol master refreshed");
+                    let system = system_watchlists(&symbol_master());
+                    watchlists.lock().await.set_system_lists(system);
+                }
+                Ok(false) => {}
+                Err(e) => log::error!("Failed to refresh symbol master: {e}"),
+            }
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+
+    task_handle
+}